@@ -1,5 +1,5 @@
 import maplibregl, { GeoJSONSource, Marker, Map } from "maplibre-gl";
-import initIbre, { PMTilesMVTRouter, RoutingError, Point, init_hooks } from "ibre";
+import initIbre, { PMTilesMVTRouter, RoutingErrorKind, Point, init_hooks } from "ibre";
 
 const map = new Map({
     container: 'map',
@@ -88,6 +88,9 @@ const stopMarker = new Marker({
                 );
             }
         } catch (error) {
+            if (error.kind === RoutingErrorKind.TileFetchingError) {
+                console.log("error fetching tile", error.tile, error.status);
+            }
             console.log("error finding route", error);
         }
     };
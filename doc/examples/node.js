@@ -0,0 +1,15 @@
+// Finds a route in Node.js using a local PMTiles archive.
+//
+// Build the package for Node.js first:
+//   wasm-pack build --target nodejs --out-dir pkg-node
+const { readFile } = require("fs/promises");
+const { PMTilesMVTRouter, Point } = require("../../pkg-node");
+
+(async () => {
+    const router = PMTilesMVTRouter.fromBuffer(await readFile(process.argv[2]));
+    const route = await router.findRoute(
+        new Point(8.68, 50.11),
+        new Point(8.692, 50.117),
+    );
+    console.log(route.get_segments_as_geojson());
+})();
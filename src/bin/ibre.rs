@@ -0,0 +1,19 @@
+//! Queries routing networks from the command line, see `ibre::cli`.
+
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    #[cfg(feature = "tracing")]
+    ibre::init_tracing();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match ibre::cli::run(&args) {
+        Ok(output) => {
+            println!("{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
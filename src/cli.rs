@@ -0,0 +1,235 @@
+//! Command line interface to query networks without a browser.
+//!
+//! Only available on native targets with the `cli` feature, which builds the
+//! `ibre` binary:
+//!
+//! ```text
+//! ibre route <network> <lon,lat> <lon,lat> [--zoom <z>]
+//! ibre nearest <network> <lon,lat> [--zoom <z>]
+//! ibre isochrone <network> <lon,lat> <max length> [--zoom <z>]
+//! ```
+//!
+//! Networks are PMTiles archives of vector tiles, of which all tiles at the
+//! given zoom level (the highest by default) are loaded, or GeoJSON files as
+//! read by [`crate::native::write_pmtiles`]. Results are printed as GeoJSON.
+
+use crate::geo_types::Point;
+use crate::routing::{Router, RoutingError};
+use crate::tile::backend::mvt_tile::parse_mvt_buffer;
+use crate::tile::backend::pmtiles_file_backend::PMTilesFileBackend;
+use crate::tile::pmtiles::PMTilesError;
+use crate::tile::writer::{parse_network, WriterError};
+use std::collections::HashSet;
+use std::path::Path;
+use thiserror::Error;
+
+/// Usage of the command line interface.
+pub const USAGE: &str = "Usage:
+  ibre route <network> <lon,lat> <lon,lat> [--zoom <z>]
+  ibre nearest <network> <lon,lat> [--zoom <z>]
+  ibre isochrone <network> <lon,lat> <max length> [--zoom <z>]
+
+<network> is a PMTiles archive (.pmtiles) or a GeoJSON network.";
+
+/// Errors of a command.
+#[derive(Error, Debug)]
+pub enum CliError {
+    /// The arguments are invalid.
+    #[error("{0}\n\n{USAGE}")]
+    Usage(String),
+    /// The network file could not be read.
+    #[error("Could not read network: {0}")]
+    Io(#[from] std::io::Error),
+    /// The PMTiles archive could not be read.
+    #[error("Could not read archive: {0}")]
+    Archive(#[from] PMTilesError),
+    /// The GeoJSON network is invalid.
+    #[error("{0}")]
+    Network(#[from] WriterError),
+    /// The query failed.
+    #[error("{0}")]
+    Routing(#[from] RoutingError),
+}
+
+/// Runs the command given by the arguments (without the program name) and
+/// returns its output.
+pub fn run(args: &[String]) -> Result<String, CliError> {
+    let mut positional = Vec::new();
+    let mut zoom = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--zoom" {
+            let value = args
+                .next()
+                .ok_or_else(|| CliError::Usage("Missing zoom level".into()))?;
+            zoom = Some(
+                value
+                    .parse()
+                    .map_err(|_| CliError::Usage(format!("Invalid zoom level `{}`", value)))?,
+            );
+        } else {
+            positional.push(arg.as_str());
+        }
+    }
+    match positional.as_slice() {
+        ["route", network, start, stop] => {
+            let (start, stop) = (point(start)?, point(stop)?);
+            let router = load_network(Path::new(network), zoom)?;
+            let route = router.find_route(&start, &stop)?;
+            Ok(route.get_segments_as_geojson())
+        }
+        ["nearest", network, location] => {
+            let location = point(location)?;
+            let router = load_network(Path::new(network), zoom)?;
+            let nearest = router
+                .find_nearest(&location)
+                .ok_or(RoutingError::MissingSegments)?;
+            Ok(serde_json::json!({
+                "type": "Feature",
+                "id": nearest.get_segment_id(),
+                "geometry": nearest.get_position_as_point(),
+                "properties": {
+                    "position": nearest.get_position(),
+                    "distance": nearest.get_distance(),
+                },
+            })
+            .to_string())
+        }
+        ["isochrone", network, start, max_length] => {
+            let start = point(start)?;
+            let max_length = max_length
+                .parse()
+                .map_err(|_| CliError::Usage(format!("Invalid length `{}`", max_length)))?;
+            let router = load_network(Path::new(network), zoom)?;
+            Ok(router.isochrone(&start, max_length)?)
+        }
+        [] => Err(CliError::Usage("Missing command".into())),
+        [command, ..] => Err(CliError::Usage(format!(
+            "Unknown command or wrong arguments for `{}`",
+            command
+        ))),
+    }
+}
+
+/// Parses a point given as `lon,lat`.
+fn point(value: &str) -> Result<Point, CliError> {
+    let invalid = || CliError::Usage(format!("Invalid point `{}`, expected lon,lat", value));
+    let (x, y) = value.split_once(',').ok_or_else(invalid)?;
+    Ok(Point::new(
+        x.trim().parse().map_err(|_| invalid())?,
+        y.trim().parse().map_err(|_| invalid())?,
+    ))
+}
+
+/// Loads the whole network of the PMTiles archive or GeoJSON file.
+fn load_network(path: &Path, zoom: Option<u8>) -> Result<Router, CliError> {
+    let mut router = Router::new();
+    if path
+        .extension()
+        .is_some_and(|extension| extension == "pmtiles")
+    {
+        let archive = PMTilesFileBackend::open(path)?;
+        let zoom = zoom.unwrap_or_else(|| archive.get_max_zoom());
+        // Segments may be contained in several tiles.
+        let mut known_segments = HashSet::new();
+        let mut known_connectors = HashSet::new();
+        for coord in archive.tile_coords()? {
+            if coord.z != zoom {
+                continue;
+            }
+            let data = match archive.read_tile(&coord)? {
+                Some(data) => data,
+                None => continue,
+            };
+            let tile =
+                parse_mvt_buffer(&data, &coord, false).or(Err(RoutingError::TileParsingError))?;
+            for segment in tile.get_segments() {
+                if known_segments.insert(segment.get_id()) {
+                    router.push_segment(segment.clone());
+                }
+            }
+            for connector in tile.get_connectors() {
+                if known_connectors.insert(connector.get_id()) {
+                    router.push_connector(connector.clone());
+                }
+            }
+        }
+    } else {
+        let (connectors, segments) = parse_network(&std::fs::read_to_string(path)?)?;
+        for connector in connectors {
+            router.push_connector(connector);
+        }
+        for segment in segments {
+            router.push_segment(segment);
+        }
+    }
+    Ok(router)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile::writer::write_pmtiles;
+
+    const NETWORK: &str = r#"{"type": "FeatureCollection", "features": [
+        {"type": "Feature", "properties": {"id": "a"},
+         "geometry": {"type": "Point", "coordinates": [8.68, 50.11]}},
+        {"type": "Feature", "properties": {"id": "b"},
+         "geometry": {"type": "Point", "coordinates": [8.71, 50.12]}},
+        {"type": "Feature", "properties": {"id": "ab", "connector_ids": ["a", "b"]},
+         "geometry": {"type": "LineString", "coordinates": [[8.68, 50.11], [8.71, 50.12]]}}
+    ]}"#;
+
+    fn run(args: &[&str]) -> Result<serde_json::Value, CliError> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        Ok(serde_json::from_str(&super::run(&args)?).unwrap())
+    }
+
+    #[test]
+    fn commands() {
+        let directory = std::env::temp_dir().join(format!("ibre-cli-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let geojson = directory.join("network.geojson");
+        std::fs::write(&geojson, NETWORK).unwrap();
+        let pmtiles = directory.join("network.pmtiles");
+        write_pmtiles(NETWORK, 14, &mut std::fs::File::create(&pmtiles).unwrap()).unwrap();
+
+        for network in [geojson.to_str().unwrap(), pmtiles.to_str().unwrap()] {
+            let route = run(&["route", network, "8.68,50.11", "8.71,50.12"]).unwrap();
+            assert_eq!(route["features"][0]["id"], "ab");
+            let nearest = run(&["nearest", network, "8.69,50.2", "--zoom", "14"]).unwrap();
+            assert_eq!(nearest["id"], "ab");
+            let isochrone = run(&["isochrone", network, "8.68,50.11", "1"]).unwrap();
+            assert_eq!(isochrone["features"].as_array().unwrap().len(), 2);
+        }
+        assert!(matches!(
+            run(&[
+                "nearest",
+                pmtiles.to_str().unwrap(),
+                "8.69,50.2",
+                "--zoom",
+                "13"
+            ]),
+            Err(CliError::Routing(RoutingError::MissingSegments))
+        ));
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn usage() {
+        assert!(matches!(run(&[]), Err(CliError::Usage(_))));
+        assert!(matches!(run(&["route", "x"]), Err(CliError::Usage(_))));
+        assert!(matches!(
+            run(&["nearest", "x", "8.69"]),
+            Err(CliError::Usage(_))
+        ));
+        assert!(matches!(
+            run(&["nearest", "x", "1,2", "--zoom", "z"]),
+            Err(CliError::Usage(_))
+        ));
+        assert!(matches!(
+            run(&["nearest", "missing.geojson", "1,2"]),
+            Err(CliError::Io(_))
+        ));
+    }
+}
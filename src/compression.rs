@@ -0,0 +1,93 @@
+// Decompression of zlib (RFC 1950) and gzip (RFC 1952) data with bounded
+// output, e.g. of PMTiles directories and OSM PBF blocks.
+use flate2::read::{GzDecoder, ZlibDecoder};
+use std::io::Read;
+use thiserror::Error;
+
+/// Maximum size of decompressed data.
+pub const MAX_DECOMPRESSED_SIZE: u64 = 64 * 1024 * 1024;
+
+#[derive(Error, Debug)]
+pub enum DecompressionError {
+    #[error("Invalid compressed data: {0}")]
+    InvalidData(#[from] std::io::Error),
+    #[error("Decompressed data exceeds {0} bytes")]
+    TooLarge(u64),
+}
+
+/// Reads the decompressed data, failing if it exceeds `limit` bytes.
+fn read_bounded(reader: impl Read, limit: u64) -> Result<Vec<u8>, DecompressionError> {
+    let mut output = Vec::new();
+    reader.take(limit + 1).read_to_end(&mut output)?;
+    if output.len() as u64 > limit {
+        return Err(DecompressionError::TooLarge(limit));
+    }
+    Ok(output)
+}
+
+/// Decompresses data in the zlib format and verifies its checksum.
+#[cfg_attr(not(feature = "osm"), allow(dead_code))]
+pub fn zlib_decompress(data: &[u8]) -> Result<Vec<u8>, DecompressionError> {
+    read_bounded(ZlibDecoder::new(data), MAX_DECOMPRESSED_SIZE)
+}
+
+/// Decompresses gzip data, e.g. PMTiles directories, and verifies its
+/// checksum.
+///
+/// Only the first member is decompressed.
+pub fn gzip_decompress(data: &[u8]) -> Result<Vec<u8>, DecompressionError> {
+    read_bounded(GzDecoder::new(data), MAX_DECOMPRESSED_SIZE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &[u8] = b"ibre ibre ibre - in browser routing engine, in browser routing engine!";
+
+    #[test]
+    fn zlib() {
+        // zlib.compress(TEXT, 9)
+        let compressed = [
+            0x78, 0xda, 0xcb, 0x4c, 0x2a, 0x4a, 0x55, 0xc8, 0x84, 0x13, 0xba, 0x0a, 0x99, 0x79,
+            0x0a, 0x49, 0x45, 0xf9, 0xe5, 0xc5, 0xa9, 0x45, 0x0a, 0x45, 0xf9, 0xa5, 0x25, 0x99,
+            0x79, 0xe9, 0x0a, 0xa9, 0x79, 0xe9, 0x99, 0x79, 0xa9, 0x3a, 0xb8, 0xa5, 0x14, 0x01,
+            0x7c, 0x17, 0x19, 0x73,
+        ];
+        assert_eq!(zlib_decompress(&compressed).unwrap(), TEXT);
+    }
+
+    #[test]
+    fn gzip() {
+        // gzip.compress(TEXT, 9, mtime=0)
+        let compressed = [
+            0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xcb, 0x4c, 0x2a, 0x4a,
+            0x55, 0xc8, 0x84, 0x13, 0xba, 0x0a, 0x99, 0x79, 0x0a, 0x49, 0x45, 0xf9, 0xe5, 0xc5,
+            0xa9, 0x45, 0x0a, 0x45, 0xf9, 0xa5, 0x25, 0x99, 0x79, 0xe9, 0x0a, 0xa9, 0x79, 0xe9,
+            0x99, 0x79, 0xa9, 0x3a, 0xb8, 0xa5, 0x14, 0x01, 0x5c, 0xb5, 0x0b, 0x2d, 0x46, 0x00,
+            0x00, 0x00,
+        ];
+        assert_eq!(gzip_decompress(&compressed).unwrap(), TEXT);
+        assert!(gzip_decompress(&compressed[2..]).is_err());
+        let mut corrupt = compressed;
+        corrupt[compressed.len() - 8] ^= 1;
+        assert!(gzip_decompress(&corrupt).is_err());
+    }
+
+    #[test]
+    fn invalid() {
+        // zlib header and stored block with "abc".
+        let mut stored = vec![0x78, 0x01, 0x01, 0x03, 0x00, 0xfc, 0xff, b'a', b'b', b'c'];
+        assert!(zlib_decompress(&stored).is_err());
+        // Adler-32 checksum of "abc".
+        stored.extend([0x02, 0x4d, 0x01, 0x27]);
+        assert_eq!(zlib_decompress(&stored).unwrap(), b"abc");
+        assert!(matches!(
+            read_bounded(ZlibDecoder::new(&stored[..]), 2),
+            Err(DecompressionError::TooLarge(2))
+        ));
+        stored[13] ^= 1;
+        assert!(zlib_decompress(&stored).is_err());
+        assert!(zlib_decompress(&[0x00, 0x00]).is_err());
+    }
+}
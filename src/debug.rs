@@ -1,14 +1,297 @@
-// Writes to `console.log` or stderr (depending on target architecture), using
-// the same arguments as [`format!`].
+//! Logging to the console or stderr, filtered by a log level set at runtime.
+//!
+//! With the `tracing` feature, messages and spans are emitted with `tracing`
+//! instead, to be collected by the subscriber set with `initTracing`.
+
+use crate::time;
+#[cfg(feature = "tracing")]
+use std::future::Future;
+use std::sync::atomic::{AtomicU8, Ordering};
+use wasm_bindgen::prelude::*;
+
+#[cfg(all(target_arch = "wasm32", not(feature = "tracing")))]
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(catch, js_namespace = performance, js_name = measure)]
+    fn performance_measure(name: &str, options: &JsValue) -> Result<JsValue, JsValue>;
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// Verbosity of the log messages.
+pub enum LogLevel {
+    /// No messages at all.
+    Off = 0,
+    /// Failures the library cannot recover from.
+    Error = 1,
+    /// Problems the library works around, e.g. failed tile requests.
+    Warn = 2,
+    /// Notable events, e.g. cleared caches.
+    Info = 3,
+    /// Details about routing and tile loading.
+    Debug = 4,
+}
+
+/// The log level without a call to `setLogLevel`; `Debug` with the `debug`
+/// feature, `Warn` otherwise.
+const DEFAULT_LOG_LEVEL: LogLevel = if cfg!(feature = "debug") {
+    LogLevel::Debug
+} else {
+    LogLevel::Warn
+};
+
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(DEFAULT_LOG_LEVEL as u8);
+
+#[wasm_bindgen(js_name = setLogLevel)]
+/// Sets the most verbose level of messages to log.
+pub fn set_log_level(level: LogLevel) {
+    LOG_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+#[wasm_bindgen(js_name = getLogLevel)]
+/// Returns the most verbose level of messages to log.
+pub fn get_log_level() -> LogLevel {
+    match LOG_LEVEL.load(Ordering::Relaxed) {
+        0 => LogLevel::Off,
+        1 => LogLevel::Error,
+        2 => LogLevel::Warn,
+        3 => LogLevel::Info,
+        _ => LogLevel::Debug,
+    }
+}
+
+/// Returns whether messages of the level are logged, never without the
+/// `logging` feature.
+pub(crate) fn enabled(level: LogLevel) -> bool {
+    cfg!(feature = "logging") && level != LogLevel::Off && level <= get_log_level()
+}
+
+/// Writes the message to the console method matching the level.
+#[cfg(all(target_arch = "wasm32", not(feature = "tracing")))]
+pub(crate) fn write(level: LogLevel, message: &str) {
+    let message = JsValue::from_str(message);
+    match level {
+        LogLevel::Off => {}
+        LogLevel::Error => web_sys::console::error_1(&message),
+        LogLevel::Warn => web_sys::console::warn_1(&message),
+        LogLevel::Info => web_sys::console::info_1(&message),
+        LogLevel::Debug => web_sys::console::log_1(&message),
+    }
+}
+
+/// Writes the message to stderr, prefixed with the level.
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "tracing")))]
+pub(crate) fn write(level: LogLevel, message: &str) {
+    eprintln!("[{:?}] {}", level, message);
+}
+
+/// Emits the message as `tracing` event of the level.
+#[cfg(feature = "tracing")]
+pub(crate) fn write(level: LogLevel, message: &str) {
+    match level {
+        LogLevel::Off => {}
+        LogLevel::Error => tracing::error!("{}", message),
+        LogLevel::Warn => tracing::warn!("{}", message),
+        LogLevel::Info => tracing::info!("{}", message),
+        LogLevel::Debug => tracing::debug!("{}", message),
+    }
+}
+
+#[cfg(feature = "tracing")]
+#[wasm_bindgen(js_name = initTracing)]
+/// Sets the global `tracing` subscriber, which reports spans and events to
+/// the performance profiler and console of the browser, or writes them to
+/// stderr on native targets. Calls after the first have no effect.
+///
+/// Only available with the `tracing` feature.
+pub fn init_tracing() {
+    #[cfg(target_arch = "wasm32")]
+    let _ = tracing_wasm::try_set_as_global_default();
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+        .with_writer(std::io::stderr)
+        .try_init();
+}
+
+// Logs a message at the given level, using the same arguments as
+// [`format!`]. The message is only formatted if the level is enabled.
+macro_rules! log {
+    ($level:expr, $($arg:tt)*) => {
+        if $crate::debug::enabled($level) {
+            $crate::debug::write($level, &format!($($arg)*));
+        }
+    };
+}
+pub(crate) use log;
+
+// Logs a message at warn level, using the same arguments as [`format!`].
+macro_rules! warn_log {
+    ($($arg:tt)*) => {
+        $crate::debug::log!($crate::debug::LogLevel::Warn, $($arg)*)
+    };
+}
+pub(crate) use warn_log;
+
+// Logs a message at info level, using the same arguments as [`format!`].
+macro_rules! info_log {
+    ($($arg:tt)*) => {
+        $crate::debug::log!($crate::debug::LogLevel::Info, $($arg)*)
+    };
+}
+pub(crate) use info_log;
+
+// Logs a message at debug level, using the same arguments as [`format!`].
 macro_rules! debug_log {
     ($($arg:tt)*) => {
-        #[cfg(feature = "debug")] {
-            let formatted_message = format!($($arg)*);
-            #[cfg(target_arch = "wasm32")]
-            web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(&formatted_message));
-            #[cfg(not(target_arch = "wasm32"))]
-            eprintln!("{}", formatted_message);
-        }
+        $crate::debug::log!($crate::debug::LogLevel::Debug, $($arg)*)
     };
 }
 pub(crate) use debug_log;
+
+/// A timed section of work, e.g. a route search or a tile load.
+///
+/// When dropped, the duration is logged at debug level and, on the web,
+/// recorded with `performance.measure`, so that spans show up in the
+/// performance profiler of the browser's developer tools.
+///
+/// With the `tracing` feature, the span is a `tracing` span at debug level
+/// instead, which subscribers measure.
+pub(crate) struct Span {
+    name: String,
+    start: f64,
+    #[cfg(feature = "tracing")]
+    span: TracingSpan,
+}
+
+/// The `tracing` span of a [`Span`], entered for synchronous work only.
+#[cfg(feature = "tracing")]
+enum TracingSpan {
+    Entered(tracing::span::EnteredSpan),
+    Idle(tracing::Span),
+}
+
+impl Span {
+    /// Starts the span, see [`span!`] and [`async_span!`].
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn new(name: String, entered: bool) -> Span {
+        #[cfg(feature = "tracing")]
+        let span = {
+            let span = tracing::debug_span!("span", name = %name);
+            if entered {
+                TracingSpan::Entered(span.entered())
+            } else {
+                TracingSpan::Idle(span)
+            }
+        };
+        Span {
+            name,
+            start: time::monotonic(),
+            #[cfg(feature = "tracing")]
+            span,
+        }
+    }
+
+    /// Runs the future within the span, for spans of asynchronous work which
+    /// must not stay entered across `.await`.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn instrument<F: Future>(&self, future: F) -> impl Future<Output = F::Output> {
+        use tracing::Instrument;
+        let span = match &self.span {
+            TracingSpan::Entered(span) => (**span).clone(),
+            TracingSpan::Idle(span) => span.clone(),
+        };
+        future.instrument(span)
+    }
+
+    /// Runs the future within the span, for spans of asynchronous work which
+    /// must not stay entered across `.await`.
+    #[cfg(not(feature = "tracing"))]
+    pub(crate) fn instrument<F>(&self, future: F) -> F {
+        future
+    }
+
+    /// Runs the synchronous work within the span.
+    pub(crate) fn in_scope<T>(&self, work: impl FnOnce() -> T) -> T {
+        #[cfg(feature = "tracing")]
+        if let TracingSpan::Idle(span) = &self.span {
+            return span.in_scope(work);
+        }
+        work()
+    }
+
+    /// Ends the span and returns its duration in milliseconds.
+    pub(crate) fn finish(self) -> f64 {
+        time::monotonic() - self.start
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        debug_log!(
+            "{} took {:.1} ms",
+            self.name,
+            time::monotonic() - self.start
+        );
+        #[cfg(all(target_arch = "wasm32", not(feature = "tracing")))]
+        if enabled(LogLevel::Debug) {
+            let options = js_sys::Object::new();
+            let _ = js_sys::Reflect::set(&options, &"start".into(), &self.start.into());
+            let _ = performance_measure(&self.name, &options);
+        }
+    }
+}
+
+// Starts a [`Span`] of synchronous work named with the same arguments as
+// [`format!`]. The span ends when the returned guard is dropped.
+macro_rules! span {
+    ($($arg:tt)*) => {
+        $crate::debug::Span::new(format!($($arg)*), true)
+    };
+}
+pub(crate) use span;
+
+// Starts a [`Span`] of asynchronous work like [`span!`]. The futures awaited
+// within it are passed through `Span::instrument`.
+macro_rules! async_span {
+    ($($arg:tt)*) => {
+        $crate::debug::Span::new(format!($($arg)*), false)
+    };
+}
+pub(crate) use async_span;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "logging")]
+    fn log_level() {
+        assert_eq!(get_log_level(), DEFAULT_LOG_LEVEL);
+        set_log_level(LogLevel::Info);
+        assert!(enabled(LogLevel::Error));
+        assert!(enabled(LogLevel::Info));
+        assert!(!enabled(LogLevel::Debug));
+        set_log_level(LogLevel::Off);
+        assert!(!enabled(LogLevel::Error));
+        assert!(!enabled(LogLevel::Off));
+        set_log_level(DEFAULT_LOG_LEVEL);
+    }
+
+    #[test]
+    fn span() {
+        let span = span!("load tile {}", 1);
+        assert_eq!(span.name, "load tile 1");
+        assert!(span.start <= time::monotonic());
+        assert!(span.finish() >= 0.0);
+    }
+
+    #[test]
+    fn async_span() {
+        let span = async_span!("find route");
+        assert_eq!(span.in_scope(|| 1), 1);
+        assert_eq!(futures::executor::block_on(span.instrument(async { 2 })), 2);
+        assert_eq!(span.name, "find route");
+    }
+}
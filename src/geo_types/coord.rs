@@ -1,22 +1,96 @@
+use super::Point;
 use geo::geometry as geo;
 use wasm_bindgen::prelude::*;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 #[wasm_bindgen]
-/// Two dimensional coordinate.
-pub struct Coord(geo::Coord<f64>);
+/// Two dimensional coordinate with an optional z value, e.g. the elevation.
+pub struct Coord(geo::Coord<f64>, Option<f64>);
 
 #[wasm_bindgen]
 impl Coord {
     #[wasm_bindgen(constructor)]
+    /// Creates a coordinate, e.g. from longitude and latitude.
     pub fn new(x: f64, y: f64) -> Coord {
-        Coord(geo::Coord { x, y })
+        Coord(geo::Coord { x, y }, None)
+    }
+
+    #[wasm_bindgen(js_name = withZ)]
+    /// Creates a coordinate with a z value, e.g. longitude, latitude and
+    /// elevation.
+    pub fn with_z(x: f64, y: f64, z: f64) -> Coord {
+        Coord::from_xyz(x, y, Some(z))
+    }
+
+    /// Returns the x coordinate (longitude).
+    pub fn x(&self) -> f64 {
+        self.0.x
+    }
+
+    /// Returns the y coordinate (latitude).
+    pub fn y(&self) -> f64 {
+        self.0.y
+    }
+
+    /// Returns the z value (elevation), `undefined` if the coordinate has
+    /// none.
+    pub fn z(&self) -> Option<f64> {
+        self.1
+    }
+
+    #[wasm_bindgen(js_name = fromPoint)]
+    /// Creates a coordinate at the position of the point.
+    pub fn from_point(point: &Point) -> Coord {
+        Coord::from_xyz(point.x(), point.y(), point.z())
+    }
+
+    #[wasm_bindgen(js_name = toPoint)]
+    /// Returns a point at this coordinate.
+    pub fn to_point(&self) -> Point {
+        Point::from_xyz(self.x(), self.y(), self.z())
+    }
+
+    /// Returns whether both coordinates are exactly equal, including z.
+    pub fn equals(&self, other: &Coord) -> bool {
+        self == other
+    }
+
+    #[wasm_bindgen(js_name = approxEquals)]
+    /// Returns whether the coordinates differ by at most `epsilon` in x, y and
+    /// z. Coordinates with and without z value are never equal.
+    pub fn approx_equals(&self, other: &Coord, epsilon: f64) -> bool {
+        let close = |a: f64, b: f64| (a - b).abs() <= epsilon;
+        close(self.x(), other.x())
+            && close(self.y(), other.y())
+            && match (self.z(), other.z()) {
+                (Some(a), Some(b)) => close(a, b),
+                (a, b) => a.is_none() && b.is_none(),
+            }
+    }
+}
+
+impl Coord {
+    /// Creates a coordinate with an optional z value.
+    pub(crate) fn from_xyz(x: f64, y: f64, z: Option<f64>) -> Coord {
+        Coord(geo::Coord { x, y }, z)
+    }
+}
+
+impl From<Coord> for Point {
+    fn from(value: Coord) -> Point {
+        value.to_point()
+    }
+}
+
+impl From<Point> for Coord {
+    fn from(value: Point) -> Coord {
+        Coord::from_point(&value)
     }
 }
 
 impl From<geo::Coord<f64>> for Coord {
     fn from(value: geo::Coord<f64>) -> Coord {
-        Coord(value)
+        Coord(value, None)
     }
 }
 
@@ -36,3 +110,32 @@ macro_rules! coord {
 
 #[cfg(test)]
 pub(crate) use coord;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_interop() {
+        let coord = Coord::new(8.68, 50.11);
+        assert_eq!((coord.x(), coord.y()), (8.68, 50.11));
+        let point = coord.to_point();
+        assert_eq!((point.x(), point.y()), (8.68, 50.11));
+        assert!(Coord::from(point).equals(&coord));
+        assert!(!coord.equals(&Coord::new(8.68, 50.110001)));
+        assert!(coord.approx_equals(&Coord::new(8.68, 50.110001), 1e-5));
+        assert!(!coord.approx_equals(&Coord::new(8.68, 50.12), 1e-5));
+    }
+
+    #[test]
+    fn z() {
+        let coord = Coord::with_z(8.68, 50.11, 100.0);
+        assert_eq!(coord.z(), Some(100.0));
+        assert_eq!(coord.to_point().z(), Some(100.0));
+        assert_eq!(Coord::from(coord.to_point()), coord);
+        assert!(!coord.equals(&Coord::new(8.68, 50.11)));
+        assert!(!coord.approx_equals(&Coord::new(8.68, 50.11), 1.0));
+        assert!(coord.approx_equals(&Coord::with_z(8.68, 50.11, 100.5), 1.0));
+        assert_eq!(Coord::new(8.68, 50.11).z(), None);
+    }
+}
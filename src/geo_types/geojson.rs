@@ -0,0 +1,346 @@
+use super::{Coord, LineString, Point, Rect};
+use serde::de::{Deserialize, Deserializer, Error as _};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_json::Value;
+use std::sync::atomic::{AtomicU8, Ordering};
+use thiserror::Error;
+use wasm_bindgen::prelude::*;
+
+/// Decimals of coordinates in GeoJSON output without a call to
+/// `setGeoJSONDecimals`, about 10 cm for longitudes and latitudes.
+const DEFAULT_DECIMALS: u8 = 6;
+
+/// Value of [`DECIMALS`] for full precision.
+const FULL_PRECISION: u8 = u8::MAX;
+
+static DECIMALS: AtomicU8 = AtomicU8::new(DEFAULT_DECIMALS);
+
+#[wasm_bindgen(js_name = setGeoJSONDecimals)]
+/// Sets the number of decimals of coordinates in GeoJSON output,
+/// `undefined` for full precision. Defaults to 6.
+pub fn set_geojson_decimals(decimals: Option<u8>) {
+    DECIMALS.store(decimals.unwrap_or(FULL_PRECISION), Ordering::Relaxed);
+}
+
+#[wasm_bindgen(js_name = getGeoJSONDecimals)]
+/// Returns the number of decimals of coordinates in GeoJSON output,
+/// `undefined` for full precision.
+pub fn get_geojson_decimals() -> Option<u8> {
+    match DECIMALS.load(Ordering::Relaxed) {
+        FULL_PRECISION => None,
+        decimals => Some(decimals),
+    }
+}
+
+/// Rounds the value to the decimals, if any.
+fn round(value: f64, decimals: Option<u8>) -> f64 {
+    match decimals {
+        Some(decimals) => {
+            let factor = 10f64.powi(i32::from(decimals));
+            (value * factor).round() / factor
+        }
+        None => value,
+    }
+}
+
+/// Returns the GeoJSON position of x and y rounded to the decimals set with
+/// [`set_geojson_decimals`].
+pub(crate) fn round_position(x: f64, y: f64) -> [f64; 2] {
+    let decimals = get_geojson_decimals();
+    [round(x, decimals), round(y, decimals)]
+}
+
+/// Returns the GeoJSON bbox `[west, south, east, north]` of the rectangle,
+/// rounded like positions.
+pub(crate) fn round_bbox(rect: &Rect) -> [f64; 4] {
+    let [west, south] = round_position(rect.west(), rect.south());
+    let [east, north] = round_position(rect.east(), rect.north());
+    [west, south, east, north]
+}
+
+/// Returns the bbox member of the rectangle to insert into a GeoJSON object
+/// as text, nothing without rectangle.
+pub(crate) fn bbox_member(rect: Option<Rect>) -> String {
+    rect.map_or_else(String::new, |rect| {
+        format!(r#""bbox": {}, "#, serde_json::json!(round_bbox(&rect)))
+    })
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+/// Errors while parsing GeoJSON geometries.
+pub enum GeoJsonError {
+    /// The input is not valid JSON.
+    #[error("Invalid JSON")]
+    InvalidJson,
+    /// The geometry is malformed, e.g. it lacks coordinates.
+    #[error("Invalid GeoJSON geometry: {0}")]
+    InvalidGeometry(&'static str),
+    /// The geometry has another type than expected.
+    #[error("Unsupported geometry type {0}")]
+    UnsupportedType(String),
+}
+
+/// A geometry parsed from GeoJSON.
+#[derive(Debug, Clone)]
+pub enum Geometry {
+    /// A GeoJSON Point.
+    Point(Point),
+    /// A GeoJSON LineString.
+    LineString(LineString),
+}
+
+impl Geometry {
+    /// Parses a GeoJSON geometry or the geometry of a GeoJSON feature.
+    pub fn from_geojson(json: &str) -> Result<Geometry, GeoJsonError> {
+        let value: Value = serde_json::from_str(json).or(Err(GeoJsonError::InvalidJson))?;
+        Geometry::from_value(&value)
+    }
+
+    pub(crate) fn from_value(value: &Value) -> Result<Geometry, GeoJsonError> {
+        let geometry = match value.get("type").and_then(Value::as_str) {
+            Some("Feature") => value
+                .get("geometry")
+                .ok_or(GeoJsonError::InvalidGeometry("feature without geometry"))?,
+            _ => value,
+        };
+        let coordinates = geometry.get("coordinates");
+        match geometry.get("type").and_then(Value::as_str) {
+            Some("Point") => Ok(Geometry::Point(
+                coordinates
+                    .and_then(position)
+                    .ok_or(GeoJsonError::InvalidGeometry("invalid point coordinates"))?
+                    .to_point(),
+            )),
+            Some("LineString") => Ok(Geometry::LineString(LineString::new(
+                coordinates
+                    .and_then(Value::as_array)
+                    .and_then(|positions| positions.iter().map(position).collect())
+                    .ok_or(GeoJsonError::InvalidGeometry(
+                        "invalid line string coordinates",
+                    ))?,
+            ))),
+            Some(other) => Err(GeoJsonError::UnsupportedType(other.into())),
+            None => Err(GeoJsonError::InvalidGeometry("missing type")),
+        }
+    }
+}
+
+/// Returns the coordinate of a GeoJSON position, including the altitude if
+/// present.
+fn position(value: &Value) -> Option<Coord> {
+    Some(Coord::from_xyz(
+        value.get(0)?.as_f64()?,
+        value.get(1)?.as_f64()?,
+        value.get(2).and_then(Value::as_f64),
+    ))
+}
+
+impl From<GeoJsonError> for JsValue {
+    fn from(err: GeoJsonError) -> JsValue {
+        JsError::new(&err.to_string()).into()
+    }
+}
+
+#[wasm_bindgen]
+impl Point {
+    #[wasm_bindgen(js_name = fromGeoJSON)]
+    /// Parses a GeoJSON Point geometry or a feature with a Point geometry.
+    pub fn from_geojson(json: &str) -> Result<Point, GeoJsonError> {
+        match Geometry::from_geojson(json)? {
+            Geometry::Point(point) => Ok(point),
+            Geometry::LineString(_) => Err(GeoJsonError::UnsupportedType("LineString".into())),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl LineString {
+    #[wasm_bindgen(js_name = fromGeoJSON)]
+    /// Parses a GeoJSON LineString geometry or a feature with a LineString
+    /// geometry.
+    pub fn from_geojson(json: &str) -> Result<LineString, GeoJsonError> {
+        match Geometry::from_geojson(json)? {
+            Geometry::LineString(line) => Ok(line),
+            Geometry::Point(_) => Err(GeoJsonError::UnsupportedType("Point".into())),
+        }
+    }
+}
+
+impl Serialize for Coord {
+    /// Serializes the coordinate as GeoJSON position `[x, y]` or `[x, y, z]`,
+    /// with x and y rounded to the decimals set with `setGeoJSONDecimals`.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let [x, y] = round_position(self.x(), self.y());
+        match self.z() {
+            Some(z) => [x, y, z].serialize(serializer),
+            None => [x, y].serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Coord {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Coord, D::Error> {
+        position(&Value::deserialize(deserializer)?)
+            .ok_or_else(|| D::Error::custom("invalid GeoJSON position"))
+    }
+}
+
+impl Serialize for Point {
+    /// Serializes the point as GeoJSON Point geometry.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut geometry = serializer.serialize_struct("Point", 2)?;
+        geometry.serialize_field("type", "Point")?;
+        geometry.serialize_field("coordinates", &Coord::from_point(self))?;
+        geometry.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Point {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Point, D::Error> {
+        match Geometry::from_value(&Value::deserialize(deserializer)?) {
+            Ok(Geometry::Point(point)) => Ok(point),
+            Ok(Geometry::LineString(_)) => Err(D::Error::custom(GeoJsonError::UnsupportedType(
+                "LineString".into(),
+            ))),
+            Err(err) => Err(D::Error::custom(err)),
+        }
+    }
+}
+
+impl Serialize for LineString {
+    /// Serializes the line string as GeoJSON LineString geometry.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let coordinates: Vec<Coord> = (0..self.coordinate_count())
+            .filter_map(|index| self.get_coord(index))
+            .collect();
+        let mut geometry = serializer.serialize_struct("LineString", 2)?;
+        geometry.serialize_field("type", "LineString")?;
+        geometry.serialize_field("coordinates", &coordinates)?;
+        geometry.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for LineString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<LineString, D::Error> {
+        match Geometry::from_value(&Value::deserialize(deserializer)?) {
+            Ok(Geometry::LineString(line)) => Ok(line),
+            Ok(Geometry::Point(_)) => Err(D::Error::custom(GeoJsonError::UnsupportedType(
+                "Point".into(),
+            ))),
+            Err(err) => Err(D::Error::custom(err)),
+        }
+    }
+}
+
+#[wasm_bindgen(js_name = parseGeoJSON)]
+/// Parses a GeoJSON geometry or feature into a `Point` or `LineString`.
+///
+/// Use `instanceof` to distinguish the returned geometries.
+pub fn parse_geojson(json: &str) -> Result<JsValue, GeoJsonError> {
+    Ok(match Geometry::from_geojson(json)? {
+        Geometry::Point(point) => point.into(),
+        Geometry::LineString(line) => line.into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round() {
+        assert_eq!(get_geojson_decimals(), Some(DEFAULT_DECIMALS));
+        assert_eq!(super::round(8.123_456_789, Some(6)), 8.123_457);
+        assert_eq!(super::round(-50.115, Some(1)), -50.1);
+        assert_eq!(super::round(8.123_456_789, None), 8.123_456_789);
+        assert_eq!(
+            serde_json::to_string(&Coord::from_xyz(8.123_456_789, 50.0, Some(1.234_567_89)))
+                .unwrap(),
+            "[8.123457,50.0,1.23456789]"
+        );
+    }
+
+    #[test]
+    fn bbox_member() {
+        assert_eq!(super::bbox_member(None), "");
+        assert_eq!(
+            super::bbox_member(Some(Rect::new(0.123_456_78, -1.0, 2.0, 3.5))),
+            r#""bbox": [0.123457,-1.0,2.0,3.5], "#
+        );
+    }
+
+    #[test]
+    fn parse_geometries() {
+        let point =
+            Point::from_geojson(r#"{"type": "Point", "coordinates": [8.68, 50.11]}"#).unwrap();
+        assert_eq!((point.x(), point.y()), (8.68, 50.11));
+        let line = LineString::from_geojson(
+            r#"{
+                "type": "Feature",
+                "properties": {},
+                "geometry": {"type": "LineString", "coordinates": [[0, 1], [2, 3, 100]]}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(line.to_array(), vec![0.0, 1.0, 2.0, 3.0]);
+        assert!(!line.has_z());
+        let point = Point::from_geojson(r#"{"type": "Point", "coordinates": [1, 2, 3]}"#).unwrap();
+        assert_eq!(point.z(), Some(3.0));
+        assert!(matches!(
+            Geometry::from_geojson(r#"{"type": "Point", "coordinates": [1, 2]}"#),
+            Ok(Geometry::Point(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_geometries() {
+        assert_eq!(
+            Point::from_geojson("{").err(),
+            Some(GeoJsonError::InvalidJson)
+        );
+        assert_eq!(
+            Point::from_geojson(r#"{"type": "LineString", "coordinates": []}"#).err(),
+            Some(GeoJsonError::UnsupportedType("LineString".into()))
+        );
+        assert_eq!(
+            Point::from_geojson(r#"{"type": "Polygon", "coordinates": []}"#).err(),
+            Some(GeoJsonError::UnsupportedType("Polygon".into()))
+        );
+        assert!(matches!(
+            LineString::from_geojson(r#"{"type": "LineString", "coordinates": [[0]]}"#),
+            Err(GeoJsonError::InvalidGeometry(_))
+        ));
+    }
+
+    #[test]
+    fn serde() {
+        let point = Point::new(8.68, 50.11);
+        let json = serde_json::to_string(&point).unwrap();
+        assert_eq!(json, r#"{"type":"Point","coordinates":[8.68,50.11]}"#);
+        let point: Point = serde_json::from_str(&json).unwrap();
+        assert_eq!((point.x(), point.y()), (8.68, 50.11));
+
+        let coord: Coord = serde_json::from_str("[1, 2]").unwrap();
+        assert!(coord.equals(&Coord::new(1.0, 2.0)));
+        assert_eq!(serde_json::to_string(&coord).unwrap(), "[1.0,2.0]");
+        let coord: Coord = serde_json::from_str("[1, 2, 3]").unwrap();
+        assert!(coord.equals(&Coord::with_z(1.0, 2.0, 3.0)));
+        assert_eq!(serde_json::to_string(&coord).unwrap(), "[1.0,2.0,3.0]");
+        let point = Point::with_z(1.0, 2.0, 3.0);
+        assert_eq!(
+            serde_json::to_string(&point).unwrap(),
+            r#"{"type":"Point","coordinates":[1.0,2.0,3.0]}"#
+        );
+
+        let line = LineString::new(vec![Coord::new(0.0, 1.0), Coord::new(2.0, 3.5)]);
+        let json = serde_json::to_string(&line).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"LineString","coordinates":[[0.0,1.0],[2.0,3.5]]}"#
+        );
+        let line: LineString = serde_json::from_str(&json).unwrap();
+        assert_eq!(line.to_array(), vec![0.0, 1.0, 2.0, 3.5]);
+        assert!(serde_json::from_str::<Point>(&json).is_err());
+        assert!(serde_json::from_str::<Coord>("[1]").is_err());
+    }
+}
@@ -1,17 +1,222 @@
-use super::Coord;
+use super::{Coord, Point, Rect};
+use geo::{
+    BoundingRect, Centroid, EuclideanLength, HaversineIntermediate, HaversineLength,
+    LineInterpolatePoint, LineLocatePoint, SimplifyIdx,
+};
+use thiserror::Error;
 use wasm_bindgen::prelude::*;
 
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Length {0} of the coordinate array is odd")]
+/// The flat coordinate array contains an incomplete coordinate.
+pub struct OddArrayLengthError(usize);
+
+impl From<OddArrayLengthError> for JsValue {
+    fn from(err: OddArrayLengthError) -> JsValue {
+        JsError::new(&err.to_string()).into()
+    }
+}
+
 #[derive(Debug, Clone)]
 #[wasm_bindgen]
 /// A series of contiguous line segments represented by two or more Coords.
-pub struct LineString(geo::LineString<f64>);
+///
+/// The z values are kept only if all coordinates have one.
+pub struct LineString(geo::LineString<f64>, Option<Vec<f64>>);
 
 #[wasm_bindgen]
 impl LineString {
     #[wasm_bindgen(constructor)]
+    /// Creates the line string from its coordinates.
     pub fn new(coords: Vec<Coord>) -> LineString {
+        let z = coords
+            .iter()
+            .map(Coord::z)
+            .collect::<Option<Vec<f64>>>()
+            .filter(|z| !z.is_empty());
         let converted = geo::LineString::new(coords.into_iter().map(|x| x.into()).collect());
-        LineString(converted)
+        LineString(converted, z)
+    }
+
+    #[wasm_bindgen(js_name = fromArray)]
+    /// Creates the line string from a flat array [x0, y0, x1, y1, ...], e.g.
+    /// a `Float64Array`, without creating a `Coord` per vertex.
+    pub fn from_array(coords: &[f64]) -> Result<LineString, OddArrayLengthError> {
+        if !coords.len().is_multiple_of(2) {
+            return Err(OddArrayLengthError(coords.len()));
+        }
+        Ok(LineString(
+            coords
+                .chunks_exact(2)
+                .map(|coord| geo::coord! { x: coord[0], y: coord[1] })
+                .collect(),
+            None,
+        ))
+    }
+
+    #[wasm_bindgen(js_name = hasZ)]
+    /// Returns whether the coordinates have z values.
+    pub fn has_z(&self) -> bool {
+        self.1.is_some()
+    }
+
+    #[wasm_bindgen(js_name = coordinateCount)]
+    /// Returns the number of coordinates.
+    pub fn coordinate_count(&self) -> usize {
+        self.0 .0.len()
+    }
+
+    #[wasm_bindgen(js_name = getCoord)]
+    /// Returns the coordinate at the index, `undefined` if out of range.
+    pub fn get_coord(&self, index: usize) -> Option<Coord> {
+        let coord = self.0 .0.get(index)?;
+        let z = self.1.as_ref().and_then(|z| z.get(index).copied());
+        Some(Coord::from_xyz(coord.x, coord.y, z))
+    }
+
+    #[wasm_bindgen(js_name = toArray)]
+    /// Returns the coordinates as flat array [x0, y0, x1, y1, ...], without z
+    /// values.
+    pub fn to_array(&self) -> Vec<f64> {
+        self.0
+            .coords()
+            .flat_map(|coord| [coord.x, coord.y])
+            .collect()
+    }
+
+    /// Returns the bounding box, `undefined` for empty line strings.
+    pub fn bbox(&self) -> Option<Rect> {
+        self.0.bounding_rect().map(Rect::from)
+    }
+
+    #[wasm_bindgen(js_name = lengthEuclidean)]
+    /// Returns the length in coordinate units, as used by the router.
+    pub fn length_euclidean(&self) -> f64 {
+        self.0.euclidean_length()
+    }
+
+    #[wasm_bindgen(js_name = lengthMeters)]
+    /// Returns the length in meters of a line string given in longitude and
+    /// latitude, using the haversine formula.
+    pub fn length_meters(&self) -> f64 {
+        self.0.haversine_length()
+    }
+
+    #[wasm_bindgen(js_name = pushCoord)]
+    /// Appends the coordinate to the end of the line string.
+    ///
+    /// Appending a coordinate without z value drops the z values of the line.
+    pub fn push_coord(&mut self, coord: &Coord) {
+        match (&mut self.1, coord.z()) {
+            (Some(z), Some(value)) => z.push(value),
+            (None, Some(value)) if self.0 .0.is_empty() => self.1 = Some(vec![value]),
+            _ => self.1 = None,
+        }
+        self.0 .0.push(coord.clone().into());
+    }
+
+    /// Returns the line string simplified with the Douglas–Peucker algorithm.
+    ///
+    /// Coordinates closer than `tolerance` (in coordinate units) to the
+    /// simplified line are removed. The first and last coordinates are kept.
+    pub fn simplify(&self, tolerance: f64) -> LineString {
+        LineString::new(
+            self.0
+                .simplify_idx(&tolerance)
+                .into_iter()
+                .filter_map(|index| self.get_coord(index))
+                .collect(),
+        )
+    }
+
+    /// Returns the line string with additional vertices, so that no segment
+    /// of a line string in longitude and latitude is longer than
+    /// `max_segment_length` meters.
+    ///
+    /// Every segment is split into parts of equal length along the great
+    /// circle. Z values are interpolated linearly. A non-positive maximum
+    /// length returns an unchanged copy.
+    pub fn densify(&self, max_segment_length: f64) -> LineString {
+        if max_segment_length.is_nan() || max_segment_length <= 0.0 {
+            return self.clone();
+        }
+        let coords: Vec<Coord> = (0..self.coordinate_count())
+            .filter_map(|index| self.get_coord(index))
+            .collect();
+        let mut densified = Vec::with_capacity(coords.len());
+        for pair in coords.windows(2) {
+            let (start, end) = (pair[0].to_point(), pair[1].to_point());
+            let parts = (start.distance_meters(&end) / max_segment_length).ceil() as usize;
+            densified.push(pair[0].clone());
+            for part in 1..parts {
+                let fraction = part as f64 / parts as f64;
+                let point = geo::Point::from(start.clone())
+                    .haversine_intermediate(&end.clone().into(), fraction);
+                let z = pair[0]
+                    .z()
+                    .zip(pair[1].z())
+                    .map(|(start, end)| start + (end - start) * fraction);
+                densified.push(Coord::from_xyz(point.x(), point.y(), z));
+            }
+        }
+        densified.extend(coords.last().cloned());
+        LineString::new(densified)
+    }
+
+    #[wasm_bindgen(js_name = interpolatePoint)]
+    /// Returns the point at the fraction (between 0 and 1) of the Euclidean
+    /// length along the line, as the router positions points on segments.
+    ///
+    /// Fractions outside of [0, 1] are clamped. The point has no z value.
+    /// Returns `undefined` for empty line strings.
+    pub fn interpolate_point(&self, fraction: f64) -> Option<Point> {
+        self.0.line_interpolate_point(fraction).map(Point::from)
+    }
+
+    #[wasm_bindgen(js_name = pointAtFraction)]
+    /// Returns the point at the fraction (between 0 and 1) of the length in
+    /// meters along a line string in longitude and latitude, e.g. 0.5 for a
+    /// label in the middle.
+    ///
+    /// Unlike `interpolatePoint`, this follows great circles and interpolates
+    /// z values. Fractions outside of [0, 1] are clamped. Returns `undefined`
+    /// for empty line strings.
+    pub fn point_at_fraction(&self, fraction: f64) -> Option<Point> {
+        let coords: Vec<Coord> = (0..self.coordinate_count())
+            .filter_map(|index| self.get_coord(index))
+            .collect();
+        let mut remaining = fraction.clamp(0.0, 1.0) * self.length_meters();
+        for pair in coords.windows(2) {
+            let (start, end) = (pair[0].to_point(), pair[1].to_point());
+            let length = start.distance_meters(&end);
+            if remaining <= length && length > 0.0 {
+                let fraction = remaining / length;
+                let point = geo::Point::from(start.clone())
+                    .haversine_intermediate(&end.clone().into(), fraction);
+                let z = start
+                    .z()
+                    .zip(end.z())
+                    .map(|(start, end)| start + (end - start) * fraction);
+                return Some(Point::from_xyz(point.x(), point.y(), z));
+            }
+            remaining -= length;
+        }
+        coords.last().map(Coord::to_point)
+    }
+
+    /// Returns the centroid, i.e. the mean of the segment midpoints weighted
+    /// by their Euclidean length. Returns `undefined` for empty line strings.
+    pub fn centroid(&self) -> Option<Point> {
+        self.0.centroid().map(Point::from)
+    }
+
+    #[wasm_bindgen(js_name = locatePoint)]
+    /// Returns the fraction of the Euclidean length along the line of the
+    /// position closest to the point.
+    ///
+    /// Returns `undefined` for empty line strings.
+    pub fn locate_point(&self, point: &Point) -> Option<f64> {
+        self.0.line_locate_point(&point.clone().into())
     }
 }
 
@@ -23,6 +228,142 @@ impl From<LineString> for geo::LineString<f64> {
 
 impl From<geo::LineString<f64>> for LineString {
     fn from(value: geo::LineString<f64>) -> LineString {
-        LineString(value.into())
+        LineString(value, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accessors() {
+        let mut line = LineString::new(vec![Coord::new(1.0, 2.0), Coord::new(3.0, 4.0)]);
+        line.push_coord(&Coord::new(5.0, 6.0));
+        assert_eq!(line.coordinate_count(), 3);
+        assert_eq!(line.get_coord(1), Some(Coord::new(3.0, 4.0)));
+        assert_eq!(line.get_coord(3), None);
+        assert_eq!(line.to_array(), vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn from_array() {
+        let line = LineString::from_array(&[1.0, 2.0, 3.0, 4.0]).unwrap();
+        assert_eq!(line.get_coord(1), Some(Coord::new(3.0, 4.0)));
+        assert_eq!(line.to_array(), vec![1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(
+            LineString::from_array(&[1.0, 2.0, 3.0]).err(),
+            Some(OddArrayLengthError(3))
+        );
+    }
+
+    #[test]
+    fn bbox() {
+        let line = LineString::from_array(&[1.0, 5.0, -2.0, 3.0, 4.0, 4.0]).unwrap();
+        assert_eq!(line.bbox().unwrap().to_array(), vec![-2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(LineString::new(vec![]).bbox(), None);
+    }
+
+    #[test]
+    fn length() {
+        let line = LineString::new(vec![Coord::new(0.0, 0.0), Coord::new(3.0, 4.0)]);
+        assert_eq!(line.length_euclidean(), 5.0);
+        let equator = LineString::new(vec![Coord::new(0.0, 0.0), Coord::new(1.0, 0.0)]);
+        assert!((equator.length_meters() - 111_195.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn simplify() {
+        let line = LineString::new(vec![
+            Coord::new(0.0, 0.0),
+            Coord::new(1.0, 0.05),
+            Coord::new(2.0, 0.0),
+            Coord::new(2.0, 2.0),
+        ]);
+        assert_eq!(
+            line.simplify(0.1).to_array(),
+            vec![0.0, 0.0, 2.0, 0.0, 2.0, 2.0]
+        );
+        assert_eq!(line.simplify(0.01).coordinate_count(), 4);
+    }
+
+    #[test]
+    fn z() {
+        let mut line = LineString::new(vec![
+            Coord::with_z(0.0, 0.0, 10.0),
+            Coord::with_z(1.0, 0.05, 20.0),
+            Coord::with_z(2.0, 0.0, 30.0),
+        ]);
+        assert!(line.has_z());
+        assert_eq!(line.get_coord(1), Some(Coord::with_z(1.0, 0.05, 20.0)));
+        assert_eq!(line.get_coord(3), None);
+        let simplified = line.simplify(0.1);
+        assert_eq!(simplified.get_coord(1), Some(Coord::with_z(2.0, 0.0, 30.0)));
+        line.push_coord(&Coord::new(3.0, 0.0));
+        assert!(!line.has_z());
+        assert_eq!(line.get_coord(0), Some(Coord::new(0.0, 0.0)));
+
+        let mut line = LineString::new(vec![]);
+        assert!(!line.has_z());
+        line.push_coord(&Coord::with_z(0.0, 0.0, 10.0));
+        assert!(line.has_z());
+        assert!(!LineString::new(vec![Coord::with_z(0.0, 0.0, 1.0), Coord::new(1.0, 1.0)]).has_z());
+    }
+
+    #[test]
+    fn densify() {
+        let line = LineString::new(vec![
+            Coord::with_z(0.0, 0.0, 0.0),
+            Coord::with_z(1.0, 0.0, 100.0),
+            Coord::with_z(1.0, 0.0, 100.0),
+        ]);
+        let densified = line.densify(30_000.0);
+        assert_eq!(densified.coordinate_count(), 6);
+        let coord = densified.get_coord(1).unwrap();
+        assert!((coord.x() - 0.25).abs() < 1e-9 && coord.y().abs() < 1e-9);
+        assert_eq!(coord.z(), Some(25.0));
+        assert_eq!(densified.get_coord(4), line.get_coord(1));
+        assert_eq!(densified.get_coord(5), line.get_coord(2));
+        assert_eq!(line.densify(0.0).coordinate_count(), 3);
+        assert_eq!(line.densify(200_000.0).coordinate_count(), 3);
+    }
+
+    #[test]
+    fn centroid_and_fraction() {
+        let line = LineString::new(vec![
+            Coord::with_z(0.0, 0.0, 0.0),
+            Coord::with_z(3.0, 0.0, 30.0),
+            Coord::with_z(3.0, 0.0, 30.0),
+            Coord::with_z(4.0, 0.0, 40.0),
+        ]);
+        let point = line.point_at_fraction(0.5).unwrap();
+        assert!((point.x() - 2.0).abs() < 1e-9 && point.y().abs() < 1e-9);
+        assert!((point.z().unwrap() - 20.0).abs() < 1e-9);
+        let point = line.point_at_fraction(2.0).unwrap();
+        assert_eq!((point.x(), point.z()), (4.0, Some(40.0)));
+        let point = line.point_at_fraction(0.0).unwrap();
+        assert_eq!((point.x(), point.z()), (0.0, Some(0.0)));
+        assert!(LineString::new(vec![]).point_at_fraction(0.5).is_none());
+
+        let line = LineString::from_array(&[0.0, 0.0, 2.0, 0.0, 2.0, 2.0]).unwrap();
+        let centroid = line.centroid().unwrap();
+        assert_eq!((centroid.x(), centroid.y()), (1.5, 0.5));
+        assert!(LineString::new(vec![]).centroid().is_none());
+    }
+
+    #[test]
+    fn linear_referencing() {
+        let line = LineString::new(vec![
+            Coord::new(0.0, 0.0),
+            Coord::new(2.0, 0.0),
+            Coord::new(2.0, 2.0),
+        ]);
+        let point = line.interpolate_point(0.75).unwrap();
+        assert_eq!((point.x(), point.y()), (2.0, 1.0));
+        let point = line.interpolate_point(2.0).unwrap();
+        assert_eq!((point.x(), point.y()), (2.0, 2.0));
+        assert_eq!(line.locate_point(&Point::new(1.0, -1.0)), Some(0.25));
+        assert_eq!(line.locate_point(&Point::new(3.0, 1.0)), Some(0.75));
+        assert!(LineString::new(vec![]).interpolate_point(0.5).is_none());
     }
 }
@@ -1,8 +1,19 @@
 pub mod coord;
 pub use coord::Coord;
 
+pub mod geojson;
+
 pub mod line_string;
 pub use line_string::LineString;
 
 pub mod point;
 pub use point::Point;
+
+pub mod polygon;
+
+pub mod projection;
+
+pub mod rect;
+pub use rect::Rect;
+
+pub mod wkt;
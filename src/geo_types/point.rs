@@ -1,30 +1,78 @@
+use geo::{EuclideanDistance, HaversineDistance, HaversineIntermediate};
 use wasm_bindgen::prelude::*;
 
 #[derive(Debug, Clone)]
 #[wasm_bindgen]
-/// A single point in 2D space.
-pub struct Point(geo::Point<f64>);
+/// A single point in 2D space with an optional z value, e.g. the elevation.
+///
+/// The z value is carried along but ignored by all computations.
+pub struct Point(geo::Point<f64>, Option<f64>);
 
 #[wasm_bindgen]
 impl Point {
     #[wasm_bindgen(constructor)]
+    /// Creates a point from its coordinates, e.g. longitude and latitude.
     pub fn new(x: f64, y: f64) -> Point {
         let point: geo::Point<f64> = (x, y).into();
         Point::from(point)
     }
 
+    #[wasm_bindgen(js_name = withZ)]
+    /// Creates a point with a z value, e.g. longitude, latitude and elevation.
+    pub fn with_z(x: f64, y: f64, z: f64) -> Point {
+        Point::from_xyz(x, y, Some(z))
+    }
+
+    /// Returns the x coordinate (longitude).
     pub fn x(&self) -> f64 {
         self.0.x()
     }
 
+    /// Returns the y coordinate (latitude).
     pub fn y(&self) -> f64 {
         self.0.y()
     }
+
+    /// Returns the z value (elevation), `undefined` if the point has none.
+    pub fn z(&self) -> Option<f64> {
+        self.1
+    }
+
+    #[wasm_bindgen(js_name = distanceMeters)]
+    /// Returns the distance in meters to the other point, both given in
+    /// longitude and latitude, using the haversine formula.
+    pub fn distance_meters(&self, other: &Point) -> f64 {
+        self.0.haversine_distance(&other.0)
+    }
+
+    #[wasm_bindgen(js_name = distanceEuclidean)]
+    /// Returns the distance in coordinate units, as used by the router to
+    /// snap points to segments.
+    pub fn distance_euclidean(&self, other: &Point) -> f64 {
+        self.0.euclidean_distance(&other.0)
+    }
+
+    /// Returns the point halfway to the other point on the great circle,
+    /// both given in longitude and latitude.
+    ///
+    /// The z value is the mean of both z values, if both have one.
+    pub fn midpoint(&self, other: &Point) -> Point {
+        let midpoint = self.0.haversine_intermediate(&other.0, 0.5);
+        let z = self.z().zip(other.z()).map(|(a, b)| (a + b) / 2.0);
+        Point::from_xyz(midpoint.x(), midpoint.y(), z)
+    }
+}
+
+impl Point {
+    /// Creates a point with an optional z value.
+    pub(crate) fn from_xyz(x: f64, y: f64, z: Option<f64>) -> Point {
+        Point((x, y).into(), z)
+    }
 }
 
 impl From<geo::Point<f64>> for Point {
     fn from(value: geo::Point<f64>) -> Point {
-        Point(value)
+        Point(value, None)
     }
 }
 
@@ -33,3 +81,28 @@ impl From<Point> for geo::Point<f64> {
         value.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distances() {
+        let a = Point::new(0.0, 0.0);
+        assert_eq!(a.distance_euclidean(&Point::new(3.0, 4.0)), 5.0);
+        assert!((a.distance_meters(&Point::new(1.0, 0.0)) - 111_195.0).abs() < 1.0);
+        assert_eq!(a.distance_meters(&a), 0.0);
+        assert_eq!(Point::with_z(0.0, 0.0, 100.0).distance_euclidean(&a), 0.0);
+    }
+
+    #[test]
+    fn midpoint() {
+        let midpoint = Point::with_z(0.0, 0.0, 10.0).midpoint(&Point::with_z(2.0, 0.0, 20.0));
+        assert!((midpoint.x() - 1.0).abs() < 1e-9 && midpoint.y().abs() < 1e-9);
+        assert_eq!(midpoint.z(), Some(15.0));
+        // The great circle bends towards the pole.
+        let midpoint = Point::new(0.0, 60.0).midpoint(&Point::new(20.0, 60.0));
+        assert!(midpoint.y() > 60.0);
+        assert_eq!(midpoint.z(), None);
+    }
+}
@@ -0,0 +1,110 @@
+use super::line_string::OddArrayLengthError;
+use super::{LineString, Point, Rect};
+use geo::{BoundingRect, Contains, Intersects};
+use wasm_bindgen::prelude::*;
+
+#[derive(Debug, Clone, PartialEq)]
+#[wasm_bindgen]
+/// A polygon with an exterior ring and optional holes, e.g. an area to avoid.
+///
+/// Rings are closed automatically.
+pub struct Polygon(geo::Polygon<f64>);
+
+#[wasm_bindgen]
+impl Polygon {
+    #[wasm_bindgen(constructor)]
+    /// Creates the polygon from its exterior ring and its holes.
+    pub fn new(exterior: LineString, interiors: Vec<LineString>) -> Polygon {
+        Polygon(geo::Polygon::new(
+            exterior.into(),
+            interiors.into_iter().map(|ring| ring.into()).collect(),
+        ))
+    }
+
+    #[wasm_bindgen(js_name = fromArray)]
+    /// Creates a polygon without holes from its exterior ring given as flat
+    /// array [x0, y0, x1, y1, ...].
+    pub fn from_array(exterior: &[f64]) -> Result<Polygon, OddArrayLengthError> {
+        Ok(Polygon::new(LineString::from_array(exterior)?, vec![]))
+    }
+
+    /// Returns the exterior ring.
+    pub fn exterior(&self) -> LineString {
+        self.0.exterior().clone().into()
+    }
+
+    /// Returns the holes.
+    pub fn interiors(&self) -> Vec<LineString> {
+        self.0
+            .interiors()
+            .iter()
+            .map(|ring| ring.clone().into())
+            .collect()
+    }
+
+    /// Returns whether the point lies inside the polygon.
+    ///
+    /// Points on the boundary, including the boundary of holes, are not
+    /// contained.
+    pub fn contains(&self, point: &Point) -> bool {
+        self.0.contains(&geo::Point::from(point.clone()))
+    }
+
+    /// Returns whether the line string touches or crosses the polygon.
+    pub fn intersects(&self, line: &LineString) -> bool {
+        self.0.intersects(&geo::LineString::from(line.clone()))
+    }
+
+    /// Returns the bounding box, `undefined` for empty polygons.
+    pub fn bbox(&self) -> Option<Rect> {
+        self.0.bounding_rect().map(Rect::from)
+    }
+}
+
+impl From<geo::Polygon<f64>> for Polygon {
+    fn from(value: geo::Polygon<f64>) -> Polygon {
+        Polygon(value)
+    }
+}
+
+impl From<Polygon> for geo::Polygon<f64> {
+    fn from(value: Polygon) -> geo::Polygon<f64> {
+        value.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a 4x4 square with a 2x2 hole in the middle.
+    fn square_with_hole() -> Polygon {
+        Polygon::new(
+            LineString::from_array(&[0.0, 0.0, 4.0, 0.0, 4.0, 4.0, 0.0, 4.0]).unwrap(),
+            vec![LineString::from_array(&[1.0, 1.0, 3.0, 1.0, 3.0, 3.0, 1.0, 3.0]).unwrap()],
+        )
+    }
+
+    #[test]
+    fn contains() {
+        let polygon = square_with_hole();
+        assert!(polygon.contains(&Point::new(0.5, 0.5)));
+        assert!(!polygon.contains(&Point::new(2.0, 2.0)));
+        assert!(!polygon.contains(&Point::new(0.0, 2.0)));
+        assert!(!polygon.contains(&Point::new(5.0, 2.0)));
+        assert_eq!(polygon.exterior().coordinate_count(), 5);
+        assert_eq!(polygon.interiors().len(), 1);
+        assert_eq!(polygon.bbox().unwrap().to_array(), vec![0.0, 0.0, 4.0, 4.0]);
+    }
+
+    #[test]
+    fn intersects() {
+        let polygon = square_with_hole();
+        let line = |coords: &[f64]| LineString::from_array(coords).unwrap();
+        assert!(polygon.intersects(&line(&[-1.0, 0.5, 5.0, 0.5])));
+        assert!(polygon.intersects(&line(&[-1.0, 4.0, 5.0, 4.0])));
+        assert!(!polygon.intersects(&line(&[1.5, 1.5, 2.5, 2.5])));
+        assert!(!polygon.intersects(&line(&[5.0, 0.0, 5.0, 4.0])));
+        assert!(Polygon::from_array(&[0.0, 0.0, 1.0]).is_err());
+    }
+}
@@ -0,0 +1,87 @@
+//! Conversion between WGS84 longitude and latitude and Web Mercator
+//! (EPSG:3857) coordinates in meters.
+
+use super::Point;
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+use wasm_bindgen::prelude::*;
+
+/// Radius of the sphere used by Web Mercator in meters.
+pub(crate) const EARTH_RADIUS: f64 = 6_378_137.0;
+
+/// Maximum latitude covered by Web Mercator, where the map is square.
+pub(crate) const MAX_LATITUDE: f64 = 85.051_128_779_806_59;
+
+/// Half the circumference of the earth, the largest Web Mercator coordinate.
+pub(crate) const HALF_CIRCUMFERENCE: f64 = PI * EARTH_RADIUS;
+
+/// Projects longitude and latitude to Web Mercator.
+///
+/// Latitudes beyond ±[`MAX_LATITUDE`] are clamped.
+pub(crate) fn lon_lat_to_mercator(lon: f64, lat: f64) -> (f64, f64) {
+    let lat = lat.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+    (
+        EARTH_RADIUS * lon.to_radians(),
+        EARTH_RADIUS * (FRAC_PI_4 + lat / 2.0).tan().ln(),
+    )
+}
+
+/// Returns the longitude and latitude of Web Mercator coordinates.
+pub(crate) fn mercator_to_lon_lat(x: f64, y: f64) -> (f64, f64) {
+    (
+        (x / EARTH_RADIUS).to_degrees(),
+        (2.0 * (y / EARTH_RADIUS).exp().atan() - FRAC_PI_2).to_degrees(),
+    )
+}
+
+#[wasm_bindgen]
+impl Point {
+    #[wasm_bindgen(js_name = toWebMercator)]
+    /// Projects the point given in longitude and latitude to Web Mercator,
+    /// keeping the z value.
+    pub fn to_web_mercator(&self) -> Point {
+        let (x, y) = lon_lat_to_mercator(self.x(), self.y());
+        Point::from_xyz(x, y, self.z())
+    }
+
+    #[wasm_bindgen(js_name = fromWebMercator)]
+    /// Creates a point in longitude and latitude from Web Mercator
+    /// coordinates.
+    pub fn from_web_mercator(x: f64, y: f64) -> Point {
+        let (lon, lat) = mercator_to_lon_lat(x, y);
+        Point::new(lon, lat)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: (f64, f64), expected: (f64, f64)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-6 && (actual.1 - expected.1).abs() < 1e-6,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn projection() {
+        assert_close(lon_lat_to_mercator(0.0, 0.0), (0.0, 0.0));
+        assert_close(
+            lon_lat_to_mercator(180.0, MAX_LATITUDE),
+            (HALF_CIRCUMFERENCE, HALF_CIRCUMFERENCE),
+        );
+        assert_close(
+            lon_lat_to_mercator(-180.0, -90.0),
+            (-HALF_CIRCUMFERENCE, -HALF_CIRCUMFERENCE),
+        );
+        assert_close(
+            lon_lat_to_mercator(8.68, 50.11),
+            (966_253.180_086, 6_465_347.741_613),
+        );
+        let point = Point::new(8.68, 50.11).to_web_mercator();
+        let point = Point::from_web_mercator(point.x(), point.y());
+        assert_close((point.x(), point.y()), (8.68, 50.11));
+    }
+}
@@ -0,0 +1,129 @@
+use super::Point;
+use wasm_bindgen::prelude::*;
+
+#[derive(Debug, Clone, PartialEq)]
+#[wasm_bindgen]
+/// An axis-aligned bounding box, e.g. in longitude and latitude.
+pub struct Rect(geo::Rect<f64>);
+
+#[wasm_bindgen]
+impl Rect {
+    #[wasm_bindgen(constructor)]
+    /// Creates the box from its edges; swapped edges are reordered.
+    pub fn new(west: f64, south: f64, east: f64, north: f64) -> Rect {
+        Rect(geo::Rect::new(
+            geo::coord! { x: west, y: south },
+            geo::coord! { x: east, y: north },
+        ))
+    }
+
+    /// Returns the minimum x coordinate.
+    pub fn west(&self) -> f64 {
+        self.0.min().x
+    }
+
+    /// Returns the minimum y coordinate.
+    pub fn south(&self) -> f64 {
+        self.0.min().y
+    }
+
+    /// Returns the maximum x coordinate.
+    pub fn east(&self) -> f64 {
+        self.0.max().x
+    }
+
+    /// Returns the maximum y coordinate.
+    pub fn north(&self) -> f64 {
+        self.0.max().y
+    }
+
+    /// Returns whether the point lies inside or on the border of the box.
+    pub fn contains(&self, point: &Point) -> bool {
+        (self.west()..=self.east()).contains(&point.x())
+            && (self.south()..=self.north()).contains(&point.y())
+    }
+
+    #[wasm_bindgen(js_name = containsRect)]
+    /// Returns whether the other box lies completely inside this box.
+    pub fn contains_rect(&self, other: &Rect) -> bool {
+        self.west() <= other.west()
+            && other.east() <= self.east()
+            && self.south() <= other.south()
+            && other.north() <= self.north()
+    }
+
+    /// Returns whether the boxes overlap or touch.
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.west() <= other.east()
+            && other.west() <= self.east()
+            && self.south() <= other.north()
+            && other.south() <= self.north()
+    }
+
+    /// Returns the box grown by the margin on every side.
+    pub fn expand(&self, margin: f64) -> Rect {
+        Rect::new(
+            self.west() - margin,
+            self.south() - margin,
+            self.east() + margin,
+            self.north() + margin,
+        )
+    }
+
+    /// Returns the smallest box containing both boxes.
+    pub fn union(&self, other: &Rect) -> Rect {
+        Rect::new(
+            self.west().min(other.west()),
+            self.south().min(other.south()),
+            self.east().max(other.east()),
+            self.north().max(other.north()),
+        )
+    }
+
+    #[wasm_bindgen(js_name = toArray)]
+    /// Returns the box as [west, south, east, north].
+    pub fn to_array(&self) -> Vec<f64> {
+        vec![self.west(), self.south(), self.east(), self.north()]
+    }
+}
+
+impl From<geo::Rect<f64>> for Rect {
+    fn from(value: geo::Rect<f64>) -> Rect {
+        Rect(value)
+    }
+}
+
+impl From<Rect> for geo::Rect<f64> {
+    fn from(value: Rect) -> geo::Rect<f64> {
+        value.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn predicates() {
+        let rect = Rect::new(10.0, 50.0, 8.0, 51.0);
+        assert_eq!(rect.to_array(), vec![8.0, 50.0, 10.0, 51.0]);
+        assert!(rect.contains(&Point::new(8.0, 50.5)));
+        assert!(!rect.contains(&Point::new(7.9, 50.5)));
+        assert!(rect.intersects(&Rect::new(9.0, 49.0, 12.0, 50.0)));
+        assert!(!rect.intersects(&Rect::new(10.1, 50.0, 12.0, 51.0)));
+        assert!(rect.contains_rect(&Rect::new(9.0, 50.2, 9.5, 50.8)));
+        assert!(!rect.contains_rect(&Rect::new(9.0, 50.2, 11.0, 50.8)));
+    }
+
+    #[test]
+    fn expand_and_union() {
+        let rect = Rect::new(8.0, 50.0, 10.0, 51.0);
+        assert_eq!(rect.expand(0.5).to_array(), vec![7.5, 49.5, 10.5, 51.5]);
+        assert_eq!(
+            rect.union(&Rect::new(9.0, 49.0, 9.5, 49.5)).to_array(),
+            vec![8.0, 49.0, 10.0, 51.0]
+        );
+        let rect: geo::Rect<f64> = rect.into();
+        assert_eq!(rect.min(), geo::coord! { x: 8.0, y: 50.0 });
+    }
+}
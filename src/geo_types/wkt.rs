@@ -0,0 +1,186 @@
+use super::geojson::Geometry;
+use super::{Coord, LineString, Point};
+use thiserror::Error;
+use wasm_bindgen::prelude::*;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+/// Errors while parsing Well-Known Text geometries.
+pub enum WktError {
+    /// The text is not a valid WKT geometry.
+    #[error("Invalid WKT: {0}")]
+    InvalidWkt(&'static str),
+    /// The geometry has another type than expected.
+    #[error("Unsupported geometry type {0}")]
+    UnsupportedType(String),
+}
+
+impl From<WktError> for JsValue {
+    fn from(err: WktError) -> JsValue {
+        JsError::new(&err.to_string()).into()
+    }
+}
+
+impl Geometry {
+    /// Parses a POINT or LINESTRING in Well-Known Text.
+    ///
+    /// Z values are kept, M values are ignored.
+    pub fn from_wkt(wkt: &str) -> Result<Geometry, WktError> {
+        let wkt = wkt.trim();
+        let start = wkt
+            .find('(')
+            .ok_or(WktError::InvalidWkt("missing coordinates"))?;
+        let mut tag = wkt[..start].split_whitespace();
+        let kind = tag
+            .next()
+            .ok_or(WktError::InvalidWkt("missing type"))?
+            .to_ascii_uppercase();
+        let dimension = tag.next().map(str::to_ascii_uppercase);
+        if tag.next().is_some() || !matches!(dimension.as_deref(), None | Some("Z" | "M" | "ZM")) {
+            return Err(WktError::InvalidWkt("invalid dimension"));
+        }
+        let measured = dimension.as_deref() == Some("M");
+        let body = wkt[start + 1..]
+            .strip_suffix(')')
+            .ok_or(WktError::InvalidWkt("missing closing parenthesis"))?;
+        let coords = body
+            .split(',')
+            .map(|text| position(text, measured))
+            .collect::<Result<Vec<Coord>, WktError>>()?;
+        match kind.as_str() {
+            "POINT" if coords.len() == 1 => Ok(Geometry::Point(coords[0].to_point())),
+            "POINT" => Err(WktError::InvalidWkt("point with several coordinates")),
+            "LINESTRING" => Ok(Geometry::LineString(LineString::new(coords))),
+            _ => Err(WktError::UnsupportedType(kind)),
+        }
+    }
+}
+
+/// Parses a position of whitespace separated numbers.
+///
+/// The third number is the z value, unless the geometry is `measured` only.
+fn position(text: &str, measured: bool) -> Result<Coord, WktError> {
+    let mut numbers = text.split_whitespace().map(str::parse::<f64>);
+    match (numbers.next(), numbers.next(), numbers.next()) {
+        (Some(Ok(x)), Some(Ok(y)), None) => Ok(Coord::new(x, y)),
+        (Some(Ok(x)), Some(Ok(y)), Some(_)) if measured => Ok(Coord::new(x, y)),
+        (Some(Ok(x)), Some(Ok(y)), Some(Ok(z))) => Ok(Coord::with_z(x, y, z)),
+        _ => Err(WktError::InvalidWkt("invalid coordinate")),
+    }
+}
+
+/// Formats the coordinate as WKT position.
+fn format_position(coord: &Coord) -> String {
+    match coord.z() {
+        Some(z) => format!("{} {} {}", coord.x(), coord.y(), z),
+        None => format!("{} {}", coord.x(), coord.y()),
+    }
+}
+
+#[wasm_bindgen]
+impl Point {
+    #[wasm_bindgen(js_name = toWKT)]
+    /// Returns the point in Well-Known Text, e.g. `POINT (8.68 50.11)` or
+    /// `POINT Z (8.68 50.11 100)`.
+    pub fn to_wkt(&self) -> String {
+        let tag = if self.z().is_some() {
+            "POINT Z"
+        } else {
+            "POINT"
+        };
+        format!("{} ({})", tag, format_position(&Coord::from_point(self)))
+    }
+
+    #[wasm_bindgen(js_name = fromWKT)]
+    /// Parses a point in Well-Known Text.
+    pub fn from_wkt(wkt: &str) -> Result<Point, WktError> {
+        match Geometry::from_wkt(wkt)? {
+            Geometry::Point(point) => Ok(point),
+            Geometry::LineString(_) => Err(WktError::UnsupportedType("LINESTRING".into())),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl LineString {
+    #[wasm_bindgen(js_name = toWKT)]
+    /// Returns the line string in Well-Known Text, e.g.
+    /// `LINESTRING (8.68 50.11, 8.69 50.12)`.
+    pub fn to_wkt(&self) -> String {
+        let coords: Vec<String> = (0..self.coordinate_count())
+            .filter_map(|index| self.get_coord(index))
+            .map(|coord| format_position(&coord))
+            .collect();
+        let tag = if self.has_z() {
+            "LINESTRING Z"
+        } else {
+            "LINESTRING"
+        };
+        format!("{} ({})", tag, coords.join(", "))
+    }
+
+    #[wasm_bindgen(js_name = fromWKT)]
+    /// Parses a line string in Well-Known Text.
+    pub fn from_wkt(wkt: &str) -> Result<LineString, WktError> {
+        match Geometry::from_wkt(wkt)? {
+            Geometry::LineString(line) => Ok(line),
+            Geometry::Point(_) => Err(WktError::UnsupportedType("POINT".into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let point = Point::new(8.68, 50.11);
+        assert_eq!(point.to_wkt(), "POINT (8.68 50.11)");
+        let parsed = Point::from_wkt(&point.to_wkt()).unwrap();
+        assert_eq!((parsed.x(), parsed.y()), (8.68, 50.11));
+
+        let line = LineString::new(vec![Coord::new(1.0, 2.5), Coord::new(-3.0, 4.0)]);
+        assert_eq!(line.to_wkt(), "LINESTRING (1 2.5, -3 4)");
+        let line = LineString::from_wkt("linestring z(1 2.5 7,-3 4 8)").unwrap();
+        assert_eq!(line.to_array(), vec![1.0, 2.5, -3.0, 4.0]);
+        assert_eq!(line.to_wkt(), "LINESTRING Z (1 2.5 7, -3 4 8)");
+    }
+
+    #[test]
+    fn z_and_m() {
+        let point = Point::with_z(8.68, 50.11, 100.0);
+        assert_eq!(point.to_wkt(), "POINT Z (8.68 50.11 100)");
+        assert_eq!(
+            Point::from_wkt("POINT Z (8.68 50.11 100)").unwrap().z(),
+            Some(100.0)
+        );
+        assert_eq!(
+            Point::from_wkt("POINT (8.68 50.11 100)").unwrap().z(),
+            Some(100.0)
+        );
+        assert_eq!(
+            Point::from_wkt("POINT M (8.68 50.11 100)").unwrap().z(),
+            None
+        );
+        assert_eq!(
+            Point::from_wkt("POINT ZM (8.68 50.11 100 5)").unwrap().z(),
+            Some(100.0)
+        );
+        assert!(Point::from_wkt("POINT Q (8.68 50.11)").is_err());
+    }
+
+    #[test]
+    fn invalid_wkt() {
+        assert!(Point::from_wkt("POINT 1 2").is_err());
+        assert!(Point::from_wkt("POINT (1)").is_err());
+        assert!(Point::from_wkt("POINT (1 2, 3 4)").is_err());
+        assert_eq!(
+            Point::from_wkt("POLYGON ((0 0, 1 0, 0 1, 0 0))").err(),
+            Some(WktError::InvalidWkt("invalid coordinate"))
+        );
+        assert_eq!(
+            LineString::from_wkt("POINT (1 2)").err(),
+            Some(WktError::UnsupportedType("POINT".into()))
+        );
+    }
+}
@@ -1,11 +1,27 @@
 #![warn(missing_docs)]
 //! IBRE - In Browser Routing Engine
 
+#[cfg(all(feature = "cli", not(target_arch = "wasm32")))]
+pub mod cli;
+#[cfg(not(target_arch = "wasm32"))]
+mod compression;
 mod debug;
 mod geo_types;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod native;
+#[cfg(all(feature = "osm", not(target_arch = "wasm32")))]
+pub mod osm;
 mod routing;
 mod tile;
+mod transit;
 
+#[cfg(feature = "tracing")]
+pub use debug::init_tracing;
+#[cfg(all(feature = "osm", not(target_arch = "wasm32")))]
+pub use routing::Router;
+mod time;
+
+#[cfg(feature = "console_error_panic_hook")]
 extern crate console_error_panic_hook;
 use wasm_bindgen::prelude::*;
 
@@ -13,6 +29,8 @@ use wasm_bindgen::prelude::*;
 /// Sets up hooks so that panics are forwarded to console.error.
 ///
 /// If you want this behaviour, call the function one time in your code.
+/// Without the `console_error_panic_hook` feature, this does nothing.
 pub fn init_hooks() {
+    #[cfg(feature = "console_error_panic_hook")]
     std::panic::set_hook(Box::new(console_error_panic_hook::hook));
 }
@@ -0,0 +1,44 @@
+//! Routing on native targets, e.g. in HTTP services or batch jobs.
+//!
+//! The futures of the network are not `Send`. Run them with a single
+//! threaded executor like `futures::executor::block_on` or inside a
+//! `tokio::task::LocalSet`. Networks fetching tiles via HTTP require the
+//! `native` feature and a tokio runtime.
+
+pub use crate::debug::{get_log_level, set_log_level, LogLevel};
+pub use crate::geo_types::geojson::{get_geojson_decimals, set_geojson_decimals};
+pub use crate::geo_types::Point;
+pub use crate::routing::{
+    BreakdownEntry, Connector, Direction, DistanceMode, Maneuver, NetworkDelta, Objective,
+    ProfileError, QueryTimings, Route, RouteMetrics, RouteOptions, RouteSegment, RoutingError,
+    SearchStats, Segment, SegmentAttributes, SegmentWithPosition, TileSelection, TraceDeviation,
+    TurnRestriction,
+};
+pub use crate::tile::backend::mvt_tile::{parse_mvt, ParseOptions, ParsingError};
+#[cfg(feature = "native")]
+pub use crate::tile::backend::native_http::{
+    HttpClient, HttpFuture, HttpResponse, NativeXYZMVTBackend, ReqwestClient,
+};
+pub use crate::tile::backend::pmtiles_file_backend::PMTilesFileBackend;
+#[cfg(feature = "native")]
+pub use crate::tile::backend::pmtiles_http_backend::PMTilesHttpBackend;
+pub use crate::tile::backend::{Backend, CachedTileNetwork, FetchingError};
+pub use crate::tile::pmtiles::PMTilesError;
+#[cfg(feature = "writer")]
+pub use crate::tile::writer::{write_pmtiles, write_routing_tiles, WriterError};
+pub use crate::tile::{Coord as TileCoord, ParseIssue, ParsedTile};
+pub use crate::transit::{Journey, JourneyLeg, LegMode, TransitError, TransitNetwork};
+
+/// A network of Mapbox Vector Tiles fetched from an XYZ URL template.
+#[cfg(feature = "native")]
+pub type XYZMVTNetwork<C = ReqwestClient> =
+    CachedTileNetwork<NativeXYZMVTBackend<C>, crate::tile::backend::xyz_mvt_backend::Tile>;
+
+/// A network of Mapbox Vector Tiles read from a local PMTiles archive.
+pub type PMTilesFileNetwork =
+    CachedTileNetwork<PMTilesFileBackend, crate::tile::backend::mvt_tile::Tile>;
+
+/// A network of Mapbox Vector Tiles read from a remote PMTiles archive.
+#[cfg(feature = "native")]
+pub type PMTilesHttpNetwork<C = ReqwestClient> =
+    CachedTileNetwork<PMTilesHttpBackend<C>, crate::tile::backend::mvt_tile::Tile>;
@@ -0,0 +1,498 @@
+//! Loading of the road network from OpenStreetMap PBF extracts.
+//!
+//! Only available on native targets with the `osm` feature. Ways with a
+//! `highway` tag become segments which are split at nodes shared with other
+//! ways or tagged as traffic control (see [`NODE_HIGHWAYS`]). These nodes and
+//! the ends of the ways become connectors. The tags of the ways and nodes
+//! relevant for profiles (see [`ROUTING_TAGS`] and [`NODE_TAGS`]) are kept.
+
+mod pbf;
+
+use crate::compression::{zlib_decompress, DecompressionError};
+use crate::geo_types::{LineString, Point};
+use crate::routing::{Connector, Router, Segment};
+use prost::Message;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Read};
+use std::path::Path;
+use thiserror::Error;
+
+/// Values of the `highway` tag which are not part of the road network.
+const EXCLUDED_HIGHWAYS: [&str; 4] = ["proposed", "construction", "abandoned", "razed"];
+
+/// Tags of ways kept as tags of the segments, e.g. for profiles.
+pub const ROUTING_TAGS: [&str; 15] = [
+    "highway",
+    "access",
+    "surface",
+    "maxspeed",
+    "oneway",
+    "foot",
+    "bicycle",
+    "motor_vehicle",
+    "access:conditional",
+    "foot:conditional",
+    "bicycle:conditional",
+    "motor_vehicle:conditional",
+    "layer",
+    "bridge",
+    "tunnel",
+];
+
+/// Values of the `highway` tag of nodes which become connectors, e.g. for
+/// delays of profiles.
+pub const NODE_HIGHWAYS: [&str; 4] = ["traffic_signals", "crossing", "stop", "give_way"];
+
+/// Tags of these nodes kept as tags of the connectors.
+pub const NODE_TAGS: [&str; 2] = ["highway", "crossing"];
+
+/// Maximum size of a blob header according to the PBF specification.
+const MAX_BLOB_HEADER_SIZE: u32 = 64 * 1024;
+
+/// Maximum size of a blob according to the PBF specification.
+const MAX_BLOB_SIZE: usize = 32 * 1024 * 1024;
+
+/// Errors while loading an OSM PBF extract.
+#[derive(Error, Debug)]
+pub enum LoadingError {
+    /// The file could not be read.
+    #[error("Could not read file: {0}")]
+    Io(#[from] std::io::Error),
+    /// A block could not be decoded.
+    #[error("Invalid PBF block: {0}")]
+    Decoding(#[from] prost::DecodeError),
+    /// A compressed block could not be decompressed.
+    #[error("Invalid compressed block: {0}")]
+    Decompression(#[from] DecompressionError),
+    /// A block uses a compression other than zlib.
+    #[error("Unsupported block compression")]
+    UnsupportedCompression,
+    /// The file structure is invalid.
+    #[error("Invalid PBF file: {0}")]
+    InvalidFile(&'static str),
+}
+
+/// A road read from an extract.
+struct Way {
+    id: i64,
+    /// IDs of the nodes.
+    refs: Vec<i64>,
+    /// Tags of [`ROUTING_TAGS`].
+    tags: Vec<(String, String)>,
+}
+
+/// Ways and node locations read from an extract.
+#[derive(Default)]
+struct Extract {
+    nodes: HashMap<i64, (f64, f64)>,
+    /// Tags of [`NODE_TAGS`] of nodes with a highway of [`NODE_HIGHWAYS`].
+    node_tags: HashMap<i64, Vec<(String, String)>>,
+    ways: Vec<Way>,
+}
+
+/// Returns the tags of [`NODE_TAGS`] if the node has a highway of
+/// [`NODE_HIGHWAYS`].
+fn node_tags<'a>(
+    tags: impl Iterator<Item = (&'a [u8], &'a [u8])>,
+) -> Option<Vec<(String, String)>> {
+    let tags: Vec<_> = tags
+        .filter(|(key, _)| NODE_TAGS.iter().any(|tag| tag.as_bytes() == *key))
+        .map(|(key, value)| {
+            (
+                String::from_utf8_lossy(key).into_owned(),
+                String::from_utf8_lossy(value).into_owned(),
+            )
+        })
+        .collect();
+    tags.iter()
+        .any(|(key, value)| key == "highway" && NODE_HIGHWAYS.contains(&value.as_str()))
+        .then_some(tags)
+}
+
+/// Builds a router from the OSM PBF extract at the given path.
+pub fn load_pbf_file<P: AsRef<Path>>(path: P) -> Result<Router, LoadingError> {
+    load_pbf(BufReader::new(File::open(path)?))
+}
+
+/// Builds a router from an OSM PBF extract.
+pub fn load_pbf<R: Read>(mut reader: R) -> Result<Router, LoadingError> {
+    let mut extract = Extract::default();
+    while let Some(block) = read_block(&mut reader)? {
+        extract.read_block(&block)?;
+    }
+    Ok(extract.into_router())
+}
+
+/// Reads the next data block, skipping header blocks.
+fn read_block<R: Read>(reader: &mut R) -> Result<Option<pbf::PrimitiveBlock>, LoadingError> {
+    loop {
+        let mut size = [0; 4];
+        match reader.read_exact(&mut size) {
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            result => result?,
+        }
+        let size = u32::from_be_bytes(size);
+        if size > MAX_BLOB_HEADER_SIZE {
+            return Err(LoadingError::InvalidFile("Blob header too large"));
+        }
+        let header = pbf::BlobHeader::decode(read_bytes(reader, size as usize)?.as_slice())?;
+        let size =
+            usize::try_from(header.datasize).or(Err(LoadingError::InvalidFile("Negative size")))?;
+        if size > MAX_BLOB_SIZE {
+            return Err(LoadingError::InvalidFile("Blob too large"));
+        }
+        let blob = pbf::Blob::decode(read_bytes(reader, size)?.as_slice())?;
+        if header.r#type != "OSMData" {
+            continue;
+        }
+        let data = match (blob.raw, blob.zlib_data) {
+            (Some(raw), _) => raw,
+            (None, Some(compressed)) => zlib_decompress(&compressed)?,
+            (None, None) => return Err(LoadingError::UnsupportedCompression),
+        };
+        return Ok(Some(pbf::PrimitiveBlock::decode(data.as_slice())?));
+    }
+}
+
+fn read_bytes<R: Read>(reader: &mut R, size: usize) -> Result<Vec<u8>, LoadingError> {
+    let mut bytes = vec![0; size];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+impl Extract {
+    fn read_block(&mut self, block: &pbf::PrimitiveBlock) -> Result<(), LoadingError> {
+        let granularity = block.granularity.unwrap_or(100) as f64;
+        let lat_offset = block.lat_offset.unwrap_or(0) as f64;
+        let lon_offset = block.lon_offset.unwrap_or(0) as f64;
+        let location = |lat: i64, lon: i64| {
+            (
+                1e-9 * (lon_offset + granularity * lon as f64),
+                1e-9 * (lat_offset + granularity * lat as f64),
+            )
+        };
+        let strings = &block.stringtable.s;
+        let string = |index: u32| {
+            strings
+                .get(index as usize)
+                .map(|string| string.as_slice())
+                .ok_or(LoadingError::InvalidFile("Invalid string reference"))
+        };
+        let add = |value: i64, delta: i64| {
+            value
+                .checked_add(delta)
+                .ok_or(LoadingError::InvalidFile("Delta out of range"))
+        };
+        for group in &block.primitivegroup {
+            for node in &group.nodes {
+                self.nodes.insert(node.id, location(node.lat, node.lon));
+                let tags = node
+                    .keys
+                    .iter()
+                    .zip(&node.vals)
+                    .map(|(key, value)| Ok((string(*key)?, string(*value)?)))
+                    .collect::<Result<Vec<_>, LoadingError>>()?;
+                if let Some(tags) = node_tags(tags.into_iter()) {
+                    self.node_tags.insert(node.id, tags);
+                }
+            }
+            if let Some(dense) = &group.dense {
+                let (mut id, mut lat, mut lon) = (0, 0, 0);
+                let mut keys_vals = dense.keys_vals.iter();
+                for ((id_delta, lat_delta), lon_delta) in
+                    dense.id.iter().zip(&dense.lat).zip(&dense.lon)
+                {
+                    id = add(id, *id_delta)?;
+                    lat = add(lat, *lat_delta)?;
+                    lon = add(lon, *lon_delta)?;
+                    self.nodes.insert(id, location(lat, lon));
+                    let mut tags = Vec::new();
+                    while let Some(&key) = keys_vals.next().filter(|key| **key != 0) {
+                        let value = keys_vals
+                            .next()
+                            .ok_or(LoadingError::InvalidFile("Missing tag value"))?;
+                        tags.push((string(key as u32)?, string(*value as u32)?));
+                    }
+                    if let Some(tags) = node_tags(tags.into_iter()) {
+                        self.node_tags.insert(id, tags);
+                    }
+                }
+            }
+            for way in &group.ways {
+                let mut is_road = false;
+                let mut tags = Vec::new();
+                for (key, value) in way.keys.iter().zip(&way.vals) {
+                    let (key, value) = (string(*key)?, string(*value)?);
+                    if key == b"highway" {
+                        is_road = !EXCLUDED_HIGHWAYS
+                            .iter()
+                            .any(|excluded| excluded.as_bytes() == value);
+                    }
+                    if ROUTING_TAGS.iter().any(|tag| tag.as_bytes() == key) {
+                        tags.push((
+                            String::from_utf8_lossy(key).into_owned(),
+                            String::from_utf8_lossy(value).into_owned(),
+                        ));
+                    }
+                }
+                if is_road {
+                    let mut id = 0;
+                    let refs = way
+                        .refs
+                        .iter()
+                        .map(|delta| {
+                            id = add(id, *delta)?;
+                            Ok(id)
+                        })
+                        .collect::<Result<_, LoadingError>>()?;
+                    self.ways.push(Way {
+                        id: way.id,
+                        refs,
+                        tags,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn into_router(self) -> Router {
+        let mut usage: HashMap<i64, u32> = HashMap::new();
+        for way in &self.ways {
+            for node in &way.refs {
+                *usage.entry(*node).or_default() += 1;
+            }
+        }
+        let mut router = Router::new();
+        let mut connectors = HashSet::new();
+        for Way {
+            id: way_id,
+            refs,
+            tags,
+        } in &self.ways
+        {
+            // Ways crossing the border of the extract miss some nodes.
+            let coords: Option<Vec<(f64, f64)>> = refs
+                .iter()
+                .map(|node| self.nodes.get(node).copied())
+                .collect();
+            let coords = match coords {
+                Some(coords) if coords.len() > 1 => coords,
+                _ => continue,
+            };
+            let mut start = 0;
+            for end in 1..refs.len() {
+                if end != refs.len() - 1
+                    && usage[&refs[end]] < 2
+                    && !self.node_tags.contains_key(&refs[end])
+                {
+                    continue;
+                }
+                let geometry: geo::LineString<f64> = coords[start..=end].to_vec().into();
+                let connector_ids = vec![refs[start].to_string(), refs[end].to_string()];
+                for (node, connector_id) in [refs[start], refs[end]].iter().zip(&connector_ids) {
+                    if connectors.insert(*node) {
+                        let (x, y) = self.nodes[node];
+                        let mut connector = Connector::new(connector_id, &Point::new(x, y));
+                        for (key, value) in self.node_tags.get(node).into_iter().flatten() {
+                            connector.set_tag(key, value);
+                        }
+                        router.push_connector(connector);
+                    }
+                }
+                let mut segment = Segment::new(
+                    format!("{}-{}", way_id, start),
+                    LineString::from(geometry),
+                    connector_ids,
+                );
+                for (key, value) in tags {
+                    segment.set_tag(key, value);
+                }
+                router.push_segment(segment);
+                start = end;
+            }
+        }
+        router
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routing::RouteOptions;
+
+    fn string_table(strings: &[&str]) -> pbf::StringTable {
+        pbf::StringTable {
+            s: strings.iter().map(|s| s.as_bytes().to_vec()).collect(),
+        }
+    }
+
+    fn write_blob(file: &mut Vec<u8>, r#type: &str, data: Vec<u8>) {
+        let blob = pbf::Blob {
+            raw: Some(data),
+            raw_size: None,
+            zlib_data: None,
+        }
+        .encode_to_vec();
+        let header = pbf::BlobHeader {
+            r#type: r#type.into(),
+            datasize: blob.len() as i32,
+        }
+        .encode_to_vec();
+        file.extend_from_slice(&(header.len() as u32).to_be_bytes());
+        file.extend(header);
+        file.extend(blob);
+    }
+
+    fn way(id: i64, highway: u32, refs: &[i64]) -> pbf::Way {
+        let mut last = 0;
+        pbf::Way {
+            id,
+            keys: vec![1],
+            vals: vec![highway],
+            refs: refs
+                .iter()
+                .map(|node| {
+                    let delta = node - last;
+                    last = *node;
+                    delta
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn load_pbf() {
+        // 1 - 2 - 3 crossed by 4 - 2 - 5, plus a proposed road 3 - 5.
+        let nodes = pbf::DenseNodes {
+            id: vec![1, 1, 1, 1, 1],
+            lat: vec![0, 0, 0, 10_000_000, -20_000_000],
+            lon: vec![0, 10_000_000, 10_000_000, -10_000_000, 0],
+            keys_vals: vec![],
+        };
+        let block = pbf::PrimitiveBlock {
+            stringtable: string_table(&["", "highway", "primary", "proposed"]),
+            primitivegroup: vec![
+                pbf::PrimitiveGroup {
+                    nodes: vec![],
+                    dense: Some(nodes),
+                    ways: vec![],
+                },
+                pbf::PrimitiveGroup {
+                    nodes: vec![],
+                    dense: None,
+                    ways: vec![
+                        way(10, 2, &[1, 2, 3]),
+                        way(11, 2, &[4, 2, 5]),
+                        way(12, 3, &[3, 5]),
+                    ],
+                },
+            ],
+            granularity: None,
+            lat_offset: None,
+            lon_offset: None,
+        };
+        let mut file = Vec::new();
+        write_blob(&mut file, "OSMHeader", vec![]);
+        write_blob(&mut file, "OSMData", block.encode_to_vec());
+
+        let router = super::load_pbf(file.as_slice()).unwrap();
+        assert_eq!(router.segments_len(), 4);
+        assert_eq!(router.connectors_len(), 5);
+        let route = router
+            .find_route(&Point::new(0.0, 0.0), &Point::new(2.0, 0.1))
+            .unwrap();
+        assert_eq!(route.get_segments().len(), 2);
+        assert_eq!(
+            route.get_segments()[0].get_segment().get_tag("highway"),
+            Some("primary".into())
+        );
+    }
+
+    #[test]
+    fn load_pbf_node_tags() {
+        // 1 - 2 - 3 with traffic signals at 2.
+        let nodes = pbf::DenseNodes {
+            id: vec![1, 1, 1],
+            lat: vec![0, 0, 0],
+            lon: vec![0, 10_000_000, 10_000_000],
+            keys_vals: vec![0, 1, 3, 4, 5, 0, 0],
+        };
+        let block = pbf::PrimitiveBlock {
+            stringtable: string_table(&["", "highway", "primary", "traffic_signals", "ref", "1"]),
+            primitivegroup: vec![pbf::PrimitiveGroup {
+                nodes: vec![],
+                dense: Some(nodes),
+                ways: vec![way(10, 2, &[1, 2, 3])],
+            }],
+            granularity: None,
+            lat_offset: None,
+            lon_offset: None,
+        };
+        let mut file = Vec::new();
+        write_blob(&mut file, "OSMData", block.encode_to_vec());
+
+        let mut router = super::load_pbf(file.as_slice()).unwrap();
+        assert_eq!(router.segments_len(), 2);
+        assert_eq!(router.connectors_len(), 3);
+        // The signals delay a route passing them.
+        router
+            .register_profile(
+                "car",
+                r#"{"default_speed": 36, "delays": {"highway=traffic_signals": 20}}"#,
+            )
+            .unwrap();
+        let mut options = RouteOptions::new();
+        options.set_profile(Some("car".into()));
+        let route = router
+            .find_route_with_options(&Point::new(0.0, 0.0), &Point::new(2.0, 0.0), &options)
+            .unwrap();
+        let delay = route.get_duration().unwrap() - route.get_distance() / 10.0;
+        assert!((delay - 20.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn invalid_file() {
+        assert!(super::load_pbf([0u8, 0, 0, 3, 1, 2].as_slice()).is_err());
+        assert_eq!(super::load_pbf([].as_slice()).unwrap().segments_len(), 0);
+
+        // Rejected before reading the blob.
+        let header = pbf::BlobHeader {
+            r#type: "OSMData".into(),
+            datasize: i32::MAX,
+        }
+        .encode_to_vec();
+        let mut file = (header.len() as u32).to_be_bytes().to_vec();
+        file.extend(header);
+        assert!(matches!(
+            super::load_pbf(file.as_slice()),
+            Err(LoadingError::InvalidFile("Blob too large"))
+        ));
+
+        let nodes = pbf::DenseNodes {
+            id: vec![i64::MAX, 1],
+            lat: vec![0, 0],
+            lon: vec![0, 0],
+            keys_vals: vec![],
+        };
+        let block = pbf::PrimitiveBlock {
+            stringtable: string_table(&[""]),
+            primitivegroup: vec![pbf::PrimitiveGroup {
+                nodes: vec![],
+                dense: Some(nodes),
+                ways: vec![],
+            }],
+            granularity: None,
+            lat_offset: None,
+            lon_offset: None,
+        };
+        let mut file = Vec::new();
+        write_blob(&mut file, "OSMData", block.encode_to_vec());
+        assert!(matches!(
+            super::load_pbf(file.as_slice()),
+            Err(LoadingError::InvalidFile("Delta out of range"))
+        ));
+    }
+}
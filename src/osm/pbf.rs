@@ -0,0 +1,94 @@
+// Messages of the OSM PBF format which are needed to read the road network.
+// See https://wiki.openstreetmap.org/wiki/PBF_Format. Fields which are not
+// declared here are skipped while decoding.
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(super) struct BlobHeader {
+    #[prost(string, required, tag = "1")]
+    pub r#type: String,
+    #[prost(int32, required, tag = "3")]
+    pub datasize: i32,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(super) struct Blob {
+    #[prost(bytes = "vec", optional, tag = "1")]
+    pub raw: Option<Vec<u8>>,
+    #[prost(int32, optional, tag = "2")]
+    pub raw_size: Option<i32>,
+    #[prost(bytes = "vec", optional, tag = "3")]
+    pub zlib_data: Option<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(super) struct PrimitiveBlock {
+    #[prost(message, required, tag = "1")]
+    pub stringtable: StringTable,
+    #[prost(message, repeated, tag = "2")]
+    pub primitivegroup: Vec<PrimitiveGroup>,
+    #[prost(int32, optional, tag = "17")]
+    pub granularity: Option<i32>,
+    #[prost(int64, optional, tag = "19")]
+    pub lat_offset: Option<i64>,
+    #[prost(int64, optional, tag = "20")]
+    pub lon_offset: Option<i64>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(super) struct StringTable {
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    pub s: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(super) struct PrimitiveGroup {
+    #[prost(message, repeated, tag = "1")]
+    pub nodes: Vec<Node>,
+    #[prost(message, optional, tag = "2")]
+    pub dense: Option<DenseNodes>,
+    #[prost(message, repeated, tag = "3")]
+    pub ways: Vec<Way>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(super) struct Node {
+    #[prost(sint64, required, tag = "1")]
+    pub id: i64,
+    #[prost(uint32, repeated, packed = "true", tag = "2")]
+    pub keys: Vec<u32>,
+    #[prost(uint32, repeated, packed = "true", tag = "3")]
+    pub vals: Vec<u32>,
+    #[prost(sint64, required, tag = "8")]
+    pub lat: i64,
+    #[prost(sint64, required, tag = "9")]
+    pub lon: i64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(super) struct DenseNodes {
+    /// Delta encoded IDs.
+    #[prost(sint64, repeated, packed = "true", tag = "1")]
+    pub id: Vec<i64>,
+    /// Delta encoded latitudes.
+    #[prost(sint64, repeated, packed = "true", tag = "8")]
+    pub lat: Vec<i64>,
+    /// Delta encoded longitudes.
+    #[prost(sint64, repeated, packed = "true", tag = "9")]
+    pub lon: Vec<i64>,
+    /// Keys and values of the tags of each node, each node's terminated by 0.
+    #[prost(int32, repeated, packed = "true", tag = "10")]
+    pub keys_vals: Vec<i32>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub(super) struct Way {
+    #[prost(int64, required, tag = "1")]
+    pub id: i64,
+    #[prost(uint32, repeated, packed = "true", tag = "2")]
+    pub keys: Vec<u32>,
+    #[prost(uint32, repeated, packed = "true", tag = "3")]
+    pub vals: Vec<u32>,
+    /// Delta encoded node IDs.
+    #[prost(sint64, repeated, packed = "true", tag = "8")]
+    pub refs: Vec<i64>,
+}
@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+
+/// Kilometers per mile, to convert `maxspeed` values in mph.
+const KILOMETERS_PER_MILE: f64 = 1.609_344;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// Direction in which a segment can be travelled, relative to its geometry.
+pub enum Direction {
+    /// In both directions.
+    #[default]
+    Both,
+    /// Only from the first to the last coordinate of the geometry.
+    Forward,
+    /// Only from the last to the first coordinate of the geometry.
+    Backward,
+}
+
+impl Direction {
+    /// Parses the value of a `oneway` like tag. `yes`, `true`, `1` and
+    /// `forward` are forward, `-1`, `reverse` and `backward` are backward,
+    /// anything else is both directions.
+    pub(crate) fn from_tag(value: &str) -> Direction {
+        match value {
+            "yes" | "true" | "1" | "forward" => Direction::Forward,
+            "-1" | "reverse" | "backward" => Direction::Backward,
+            _ => Direction::Both,
+        }
+    }
+
+    /// Returns whether the segment can be travelled from the position
+    /// `from` to the position `to` along its geometry.
+    pub(crate) fn allows(self, from: f64, to: f64) -> bool {
+        match self {
+            Direction::Both => true,
+            Direction::Forward => from <= to,
+            Direction::Backward => from >= to,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Default, PartialEq)]
+/// Typed attributes of a segment parsed from its tags, e.g. for profiles,
+/// filters and instructions.
+pub struct SegmentAttributes {
+    class: Option<String>,
+    direction: Direction,
+    speed: Option<f64>,
+    surface: Option<String>,
+    access: Option<String>,
+    extras: BTreeMap<String, String>,
+}
+
+#[wasm_bindgen]
+impl SegmentAttributes {
+    /// Returns the road class from the `highway` tag or, without it, from
+    /// the `class` tag of e.g. OpenMapTiles.
+    pub fn get_class(&self) -> Option<String> {
+        self.class.clone()
+    }
+
+    /// Returns whether the segment can only be travelled in one direction,
+    /// see `get_direction`.
+    pub fn is_oneway(&self) -> bool {
+        self.direction != Direction::Both
+    }
+
+    /// Returns the direction in which the segment can be travelled, from the
+    /// `oneway` tag unless set otherwise.
+    pub fn get_direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Returns the speed limit in km/h from the `maxspeed` tag, `undefined`
+    /// without a numeric limit. Values in mph are converted.
+    pub fn get_speed(&self) -> Option<f64> {
+        self.speed
+    }
+
+    /// Returns the value of the `surface` tag.
+    pub fn get_surface(&self) -> Option<String> {
+        self.surface.clone()
+    }
+
+    /// Returns the value of the `access` tag.
+    pub fn get_access(&self) -> Option<String> {
+        self.access.clone()
+    }
+
+    /// Returns the value of a tag without typed attribute, `undefined` if
+    /// the segment lacks it.
+    pub fn get_extra(&self, key: &str) -> Option<String> {
+        self.extras.get(key).cloned()
+    }
+
+    /// Returns the keys of the tags without typed attribute, sorted.
+    pub fn get_extra_keys(&self) -> Vec<String> {
+        self.extras.keys().cloned().collect()
+    }
+}
+
+impl SegmentAttributes {
+    /// Updates the attributes for the tag of the segment.
+    pub(crate) fn set(&mut self, key: &str, value: &str) {
+        match key {
+            "highway" => self.class = Some(value.into()),
+            // The `highway` tag takes precedence.
+            "class" if self.class.is_none() => self.class = Some(value.into()),
+            "oneway" => self.direction = Direction::from_tag(value),
+            "maxspeed" => self.speed = parse_speed(value),
+            "surface" => self.surface = Some(value.into()),
+            "access" => self.access = Some(value.into()),
+            _ => {
+                self.extras.insert(key.into(), value.into());
+            }
+        }
+    }
+
+    /// Sets the direction, e.g. read from another property than `oneway`.
+    pub(crate) fn set_direction(&mut self, direction: Direction) {
+        self.direction = direction;
+    }
+
+    /// Returns the road class without copying it.
+    pub(crate) fn class(&self) -> Option<&str> {
+        self.class.as_deref()
+    }
+}
+
+/// Parses a `maxspeed` value like `50` or `30 mph` into km/h.
+fn parse_speed(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let (number, factor) = match value.strip_suffix("mph") {
+        Some(number) => (number.trim(), KILOMETERS_PER_MILE),
+        None => (value, 1.0),
+    };
+    number
+        .parse::<f64>()
+        .ok()
+        .filter(|speed| speed.is_finite() && *speed > 0.0)
+        .map(|speed| speed * factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set() {
+        let mut attributes = SegmentAttributes::default();
+        for (key, value) in [
+            ("class", "track"),
+            ("highway", "residential"),
+            ("oneway", "-1"),
+            ("maxspeed", "30 mph"),
+            ("surface", "asphalt"),
+            ("access", "destination"),
+            ("lanes", "2"),
+        ] {
+            attributes.set(key, value);
+        }
+        assert_eq!(attributes.get_class(), Some("residential".into()));
+        assert!(attributes.is_oneway());
+        assert!((attributes.get_speed().unwrap() - 48.28).abs() < 0.01);
+        assert_eq!(attributes.get_surface(), Some("asphalt".into()));
+        assert_eq!(attributes.get_access(), Some("destination".into()));
+        assert_eq!(attributes.get_extra("lanes"), Some("2".into()));
+        assert_eq!(attributes.get_extra_keys(), vec!["lanes".to_string()]);
+
+        attributes.set("oneway", "no");
+        attributes.set("maxspeed", "none");
+        attributes.set("class", "track");
+        assert!(!attributes.is_oneway());
+        attributes.set("oneway", "reverse");
+        assert!(attributes.is_oneway());
+        assert_eq!(attributes.get_direction(), Direction::Backward);
+        assert_eq!(attributes.get_speed(), None);
+        assert_eq!(attributes.class(), Some("residential"));
+    }
+
+    #[test]
+    fn direction() {
+        assert_eq!(Direction::from_tag("yes"), Direction::Forward);
+        assert_eq!(Direction::from_tag("-1"), Direction::Backward);
+        assert_eq!(Direction::from_tag("no"), Direction::Both);
+        assert!(Direction::Forward.allows(0.2, 0.8));
+        assert!(!Direction::Forward.allows(0.8, 0.2));
+        assert!(Direction::Backward.allows(0.8, 0.2));
+        assert!(Direction::Both.allows(0.8, 0.2));
+    }
+}
@@ -0,0 +1,233 @@
+//! Conditional tags, which change the value of a tag in time windows.
+//!
+//! The values follow the OpenStreetMap syntax of conditional restrictions,
+//! e.g. `access:conditional=no @ (Mo-Fr 22:00-06:00); destination @ (Sa,Su)`.
+//! Conditions are weekdays (`Mo`, `Tu`, `We`, `Th`, `Fr`, `Sa`, `Su`) as
+//! ranges or lists, followed by times of day as ranges or lists, both
+//! optional. Several conditions of a rule are separated by `;` inside its
+//! parentheses, e.g. `no @ (Mo-Fr 07:00-09:00; Sa 10:00-12:00)`. Time ranges
+//! ending before they start extend into the next day. Other conditions, e.g.
+//! on the weather or on public holidays, are ignored.
+
+/// Names of the weekdays starting on Monday.
+const WEEKDAYS: [&str; 7] = ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"];
+
+/// Seconds of a day.
+const DAY: u32 = 24 * 60 * 60;
+
+/// A point in a week, e.g. the departure of a route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Departure {
+    /// Day of the week, 0 for Monday to 6 for Sunday.
+    pub(crate) weekday: u8,
+    /// Seconds after midnight.
+    pub(crate) seconds: u32,
+}
+
+/// A time window of a conditional value.
+#[derive(Debug, Clone, PartialEq)]
+struct TimeCondition {
+    /// Bits of the weekdays, bit 0 for Monday.
+    weekdays: u8,
+    /// Ranges of seconds after midnight, all day if empty.
+    times: Vec<(u32, u32)>,
+}
+
+impl TimeCondition {
+    /// Parses a condition like `Mo-Fr 07:00-09:00,16:00-18:00`, `None` if it
+    /// is not a time window.
+    fn parse(condition: &str) -> Option<TimeCondition> {
+        let mut parts = condition.split_whitespace().peekable();
+        let weekdays = match parts.peek().and_then(|part| parse_weekdays(part)) {
+            Some(weekdays) => {
+                parts.next();
+                weekdays
+            }
+            None => 0b111_1111,
+        };
+        let times = match parts.next() {
+            Some(part) => part
+                .split(',')
+                .map(parse_time_range)
+                .collect::<Option<_>>()?,
+            None => Vec::new(),
+        };
+        match parts.next() {
+            Some(_) => None,
+            None => Some(TimeCondition { weekdays, times }),
+        }
+    }
+
+    /// Returns whether the departure is inside the window.
+    fn contains(&self, departure: Departure) -> bool {
+        let on = |weekday: u8| self.weekdays & (1 << weekday) != 0;
+        let previous = (departure.weekday + 6) % 7;
+        if self.times.is_empty() {
+            return on(departure.weekday);
+        }
+        self.times.iter().any(|&(start, stop)| {
+            if start < stop {
+                on(departure.weekday) && start <= departure.seconds && departure.seconds < stop
+            } else {
+                (on(departure.weekday) && departure.seconds >= start)
+                    || (on(previous) && departure.seconds < stop)
+            }
+        })
+    }
+}
+
+/// Returns the bits of weekdays like `Mo-Fr` or `Sa,Su`.
+fn parse_weekdays(weekdays: &str) -> Option<u8> {
+    let day = |name: &str| WEEKDAYS.iter().position(|day| *day == name);
+    weekdays.split(',').try_fold(0u8, |bits, part| {
+        let (first, last) = match part.split_once('-') {
+            Some((first, last)) => (day(first)?, day(last)?),
+            None => (day(part)?, day(part)?),
+        };
+        // Ranges like Fr-Mo wrap around the week.
+        let days = (last + 7 - first) % 7;
+        Some((0..=days).fold(bits, |bits, offset| bits | 1 << ((first + offset) % 7)))
+    })
+}
+
+/// Returns the seconds after midnight of a range like `22:00-06:00`.
+fn parse_time_range(range: &str) -> Option<(u32, u32)> {
+    let (start, stop) = range.split_once('-')?;
+    Some((parse_time(start)?, parse_time(stop)?))
+}
+
+/// Returns the seconds after midnight of a time like `06:30`, up to `24:00`.
+fn parse_time(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.split_once(':')?;
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    if hours > 24 || minutes >= 60 {
+        return None;
+    }
+    let seconds = hours * 3600 + minutes * 60;
+    (seconds <= DAY).then_some(seconds)
+}
+
+/// Splits the value into its rules at `;` outside of parentheses.
+fn split_rules(value: &str) -> Vec<&str> {
+    let mut rules = Vec::new();
+    let (mut depth, mut start) = (0usize, 0);
+    for (index, character) in value.char_indices() {
+        match character {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ';' if depth == 0 => {
+                rules.push(&value[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    rules.push(&value[start..]);
+    rules
+}
+
+/// The parsed value of a conditional tag.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Conditional {
+    /// Values with the time windows in which they apply.
+    rules: Vec<(String, Vec<TimeCondition>)>,
+}
+
+impl Conditional {
+    /// Parses a value like `no @ (Mo-Fr 22:00-06:00); destination @ (Sa,Su)`.
+    /// Rules with other conditions only are skipped.
+    pub(crate) fn parse(value: &str) -> Conditional {
+        let rules = split_rules(value)
+            .into_iter()
+            .filter_map(|rule| {
+                let (value, condition) = rule.split_once('@')?;
+                let condition = condition.trim();
+                let condition = condition
+                    .strip_prefix('(')
+                    .and_then(|condition| condition.strip_suffix(')'))
+                    .unwrap_or(condition);
+                let conditions: Vec<_> = condition
+                    .split(';')
+                    .filter_map(TimeCondition::parse)
+                    .collect();
+                (!conditions.is_empty()).then(|| (value.trim().into(), conditions))
+            })
+            .collect();
+        Conditional { rules }
+    }
+
+    /// Returns the value at the departure, `None` if no rule applies. The
+    /// last matching rule wins.
+    pub(crate) fn value_at(&self, departure: Departure) -> Option<&str> {
+        self.rules
+            .iter()
+            .rev()
+            .find(|(_, conditions)| {
+                conditions
+                    .iter()
+                    .any(|condition| condition.contains(departure))
+            })
+            .map(|(value, _)| value.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(weekday: u8, hours: u32) -> Departure {
+        Departure {
+            weekday,
+            seconds: hours * 3600,
+        }
+    }
+
+    #[test]
+    fn value_at() {
+        let conditional =
+            Conditional::parse("no @ (Mo-Fr 22:00-06:00); destination @ (Sa,Su); no @ wet");
+        assert_eq!(conditional.value_at(at(0, 23)), Some("no"));
+        // Monday night extends into Tuesday.
+        assert_eq!(conditional.value_at(at(1, 5)), Some("no"));
+        assert_eq!(conditional.value_at(at(0, 5)), None);
+        assert_eq!(conditional.value_at(at(2, 12)), None);
+        // Friday night extends into Saturday, but the later rule wins.
+        assert_eq!(conditional.value_at(at(5, 5)), Some("destination"));
+        assert_eq!(conditional.value_at(at(6, 23)), Some("destination"));
+
+        let conditional = Conditional::parse("no @ 07:00-09:00,16:00-18:00");
+        assert_eq!(conditional.value_at(at(6, 8)), Some("no"));
+        assert_eq!(conditional.value_at(at(6, 12)), None);
+        assert_eq!(
+            Conditional::parse("no @ (Fr-Mo)").value_at(at(6, 0)),
+            Some("no")
+        );
+        assert_eq!(Conditional::parse("no @ (Fr-Mo)").value_at(at(2, 0)), None);
+        assert_eq!(Conditional::parse("no").value_at(at(0, 0)), None);
+    }
+
+    #[test]
+    fn several_conditions() {
+        let conditional =
+            Conditional::parse("no @ (Mo-Fr 07:00-09:00; Sa 10:00-12:00); destination @ (Su)");
+        assert_eq!(conditional.value_at(at(1, 8)), Some("no"));
+        assert_eq!(conditional.value_at(at(5, 11)), Some("no"));
+        assert_eq!(conditional.value_at(at(5, 8)), None);
+        assert_eq!(conditional.value_at(at(6, 8)), Some("destination"));
+        // Unknown conditions of a rule are ignored.
+        let conditional = Conditional::parse("no @ (Mo 07:00-09:00; wet)");
+        assert_eq!(conditional.value_at(at(0, 8)), Some("no"));
+    }
+
+    #[test]
+    fn invalid_times() {
+        assert_eq!(parse_time("4000000:00"), None);
+        assert_eq!(parse_time("24:00"), Some(DAY));
+        assert_eq!(parse_time("24:30"), None);
+        assert_eq!(parse_time("12:60"), None);
+        assert_eq!(
+            Conditional::parse("no @ (Mo 4000000:00-06:00)").value_at(at(0, 5)),
+            None
+        );
+    }
+}
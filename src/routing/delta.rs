@@ -0,0 +1,127 @@
+use crate::routing::{Connector, Segment};
+use std::collections::{BTreeMap, HashSet};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Default)]
+/// Changes of a transport network, e.g. from a live editing session, which
+/// are applied to a loaded network without reloading tiles.
+///
+/// Added and modified features are given as a whole and replace all features
+/// with the same ID. Later changes of a delta replace earlier ones of the same
+/// ID.
+pub struct NetworkDelta {
+    segments: BTreeMap<String, Segment>,
+    connectors: BTreeMap<String, Connector>,
+    removed_segments: HashSet<String>,
+    removed_connectors: HashSet<String>,
+}
+
+#[wasm_bindgen]
+impl NetworkDelta {
+    #[wasm_bindgen(constructor)]
+    /// Creates an empty delta.
+    pub fn new() -> NetworkDelta {
+        NetworkDelta::default()
+    }
+
+    /// Adds the segment or replaces the segments with the same ID.
+    pub fn put_segment(&mut self, segment: Segment) {
+        self.removed_segments.remove(&segment.get_id());
+        self.segments.insert(segment.get_id(), segment);
+    }
+
+    /// Adds the connector or replaces the connectors with the same ID.
+    pub fn put_connector(&mut self, connector: Connector) {
+        self.removed_connectors.remove(&connector.get_id());
+        self.connectors.insert(connector.get_id(), connector);
+    }
+
+    /// Removes the segments with the ID.
+    pub fn remove_segment(&mut self, id: &str) {
+        self.segments.remove(id);
+        self.removed_segments.insert(id.into());
+    }
+
+    /// Removes the connectors with the ID.
+    pub fn remove_connector(&mut self, id: &str) {
+        self.connectors.remove(id);
+        self.removed_connectors.insert(id.into());
+    }
+
+    /// Returns whether the delta has no changes.
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+            && self.connectors.is_empty()
+            && self.removed_segments.is_empty()
+            && self.removed_connectors.is_empty()
+    }
+
+    /// Adds the changes of the other delta, which replace changes of the same
+    /// IDs.
+    pub fn merge(&mut self, other: &NetworkDelta) {
+        for id in &other.removed_segments {
+            self.remove_segment(id);
+        }
+        for id in &other.removed_connectors {
+            self.remove_connector(id);
+        }
+        for segment in other.segments.values() {
+            self.put_segment(segment.clone());
+        }
+        for connector in other.connectors.values() {
+            self.put_connector(connector.clone());
+        }
+    }
+}
+
+impl NetworkDelta {
+    /// Returns whether the segment ID is removed or replaced.
+    pub(crate) fn replaces_segment(&self, id: &str) -> bool {
+        self.removed_segments.contains(id) || self.segments.contains_key(id)
+    }
+
+    /// Returns whether the connector ID is removed or replaced.
+    pub(crate) fn replaces_connector(&self, id: &str) -> bool {
+        self.removed_connectors.contains(id) || self.connectors.contains_key(id)
+    }
+
+    /// Returns the added and modified segments ordered by ID.
+    pub(crate) fn segments(&self) -> impl Iterator<Item = &Segment> {
+        self.segments.values()
+    }
+
+    /// Returns the added and modified connectors ordered by ID.
+    pub(crate) fn connectors(&self) -> impl Iterator<Item = &Connector> {
+        self.connectors.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo_types::Point;
+
+    #[test]
+    fn merge() {
+        let mut delta = NetworkDelta::new();
+        assert!(delta.is_empty());
+        delta.put_connector(Connector::new("a", &Point::new(0.0, 0.0)));
+        delta.remove_connector("b");
+        let mut other = NetworkDelta::new();
+        other.remove_connector("a");
+        other.put_connector(Connector::new("b", &Point::new(1.0, 0.0)));
+        delta.merge(&other);
+        assert!(!delta.is_empty());
+        assert_eq!(
+            delta
+                .connectors()
+                .map(Connector::get_id)
+                .collect::<Vec<_>>(),
+            vec!["b"]
+        );
+        assert!(delta.replaces_connector("a"));
+        assert!(delta.replaces_connector("b"));
+        assert!(!delta.replaces_connector("c"));
+    }
+}
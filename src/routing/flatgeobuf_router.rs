@@ -0,0 +1,24 @@
+use wasm_bindgen::prelude::*;
+
+use crate::routing::tile_router::tile_router_methods;
+use crate::tile::backend::flatgeobuf_backend::{FlatGeobufBackend, Tile};
+use crate::tile::backend::CachedTileNetwork;
+
+#[wasm_bindgen]
+/// A router reading the network from FlatGeobuf files.
+pub struct FlatGeobufRouter {
+    network: CachedTileNetwork<FlatGeobufBackend, Tile>,
+}
+
+#[wasm_bindgen]
+impl FlatGeobufRouter {
+    #[wasm_bindgen(constructor)]
+    /// Create the router using the URLs of the segment and connector files.
+    pub fn new(segments_url: &str, connectors_url: &str) -> FlatGeobufRouter {
+        FlatGeobufRouter {
+            network: CachedTileNetwork::new(FlatGeobufBackend::new(segments_url, connectors_url)),
+        }
+    }
+}
+
+tile_router_methods!(FlatGeobufRouter);
@@ -0,0 +1,688 @@
+//! Compact graph of a network for route searches.
+//!
+//! Connectors are numbered in the order of the router, followed by the
+//! connectors of level crossings and the endpoints of the search. The edges
+//! of all connectors are stored in one array ordered by connector (compressed
+//! sparse rows), so searches index contiguous arrays instead of maps keyed by
+//! connector IDs.
+//!
+//! Edges span the part of a segment between neighbouring connectors on it and
+//! have the length of that part only. Edges against the direction of one-way
+//! segments are stored as well but skipped by searches.
+//!
+//! The graph of the network is prepared once and reused by all searches until
+//! the network changes. Each search only adds the edges of its endpoints.
+//! Connectors and segments added to the network are appended to the graph,
+//! numbering new connectors after those of level crossings; changes to the
+//! existing network, like replaced connectors or new level crossings, need a
+//! new graph.
+
+use crate::geo_types::Point;
+use crate::routing::router::{Connector, LevelCrossings, Position, Segment};
+use crate::routing::DistanceMode;
+use geo::{EuclideanLength, HaversineLength};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// A way between neighbouring connectors along a segment.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Edge {
+    /// Index of the connector the edge leads to.
+    pub(crate) target: u32,
+    /// Index of the segment in the router.
+    pub(crate) segment: u32,
+    /// Length of the part of the segment between the connectors.
+    pub(crate) length: f64,
+    /// Positions of the connector and of the target on the segment.
+    pub(crate) positions: (Position, Position),
+}
+
+/// Connectors of a network with the edges between them, with edge lengths
+/// measured in a distance mode.
+#[derive(Debug)]
+pub(crate) struct PreparedGraph {
+    mode: DistanceMode,
+    /// Number of connectors of the router when the graph was built, numbered
+    /// before the connectors of level crossings. Connectors appended later
+    /// are numbered after them.
+    router_connectors: usize,
+    /// Connectors of level crossings, one for each level.
+    crossings: Vec<Connector>,
+    /// IDs of the connectors which are level crossings.
+    crossing_ids: HashSet<String>,
+    /// IDs of connectors of segments which are not in the graph.
+    unknown_ids: HashSet<String>,
+    /// Level of the segments passing each connector, not counting segments
+    /// ending at it, and whether any segment ends at it.
+    passing: Vec<(Option<i32>, bool)>,
+    /// Coordinates of the connectors.
+    points: Vec<[f64; 2]>,
+    /// Indices of the connectors by ID. Connectors replace earlier ones with
+    /// the same ID, which are left without edges.
+    index: HashMap<String, u32>,
+    /// Indices of the segments by ID.
+    segments_by_id: HashMap<String, Vec<u32>>,
+    /// Positions of the connectors on each segment, in order.
+    on_segments: Vec<Vec<(Position, u32)>>,
+    /// Start of the edges of each connector, followed by the number of edges.
+    offsets: Vec<u32>,
+    edges: Vec<Edge>,
+    /// Edges of the appended segments by connector.
+    appended: HashMap<u32, Vec<Edge>>,
+}
+
+impl PreparedGraph {
+    /// Builds the graph of the segments and connectors with edge lengths
+    /// measured in the distance mode.
+    ///
+    /// Segments use the connector of their level at level crossings.
+    pub(crate) fn new(
+        segments: &[Segment],
+        connectors: &[Connector],
+        crossings: &LevelCrossings,
+        mode: DistanceMode,
+    ) -> PreparedGraph {
+        let crossing_connectors: Vec<Connector> = crossings
+            .values()
+            .flat_map(HashMap::values)
+            .cloned()
+            .collect();
+        let all: Vec<&Connector> = connectors.iter().chain(&crossing_connectors).collect();
+        let points = all
+            .iter()
+            .map(|connector| {
+                let point = connector.get_point();
+                [point.x(), point.y()]
+            })
+            .collect();
+        let index: HashMap<String, u32> = all
+            .iter()
+            .enumerate()
+            .map(|(node, connector)| (connector.id().to_string(), node as u32))
+            .collect();
+
+        let mut unknown_ids = HashSet::new();
+        let mut passing = vec![(None, false); all.len()];
+        let mut segments_by_id: HashMap<String, Vec<u32>> = HashMap::new();
+        let mut on_segments = Vec::with_capacity(segments.len());
+        let mut edges: Vec<(u32, Edge)> = Vec::new();
+        for (segment_index, segment) in (0..).zip(segments) {
+            segments_by_id
+                .entry(segment.id().to_string())
+                .or_default()
+                .push(segment_index);
+            // The segment is split at its connectors, so only connectors
+            // next to each other along the geometry are neighbours.
+            let mut positions: Vec<(Position, u32)> = segment
+                .get_connectors()
+                .iter()
+                // Ignore unknown connectors.
+                .filter_map(|id| match crossings.get(id.as_str()) {
+                    Some(levels) => levels
+                        .get(&segment.get_level())
+                        .map(|connector| index[connector.id()]),
+                    None => {
+                        let node = index.get(id).copied();
+                        if node.is_none() {
+                            unknown_ids.insert(id.clone());
+                        }
+                        node
+                    }
+                })
+                .filter_map(|node| {
+                    segment
+                        .get_point_position(&all[node as usize].get_point())
+                        .map(|position| (position, node))
+                })
+                .collect();
+            positions.sort_by(|a, b| a.0.total_cmp(&b.0));
+            for (position, node) in &positions {
+                pass(&mut passing[*node as usize], segment, *position);
+            }
+            push_edges(
+                &mut edges,
+                segment,
+                segment_index,
+                &positions,
+                mode,
+                |_, _| true,
+            );
+            on_segments.push(positions);
+        }
+        // The sort is stable, so the edges of a connector keep the order of
+        // the segments.
+        edges.sort_by_key(|(node, _)| *node);
+        let mut offsets = vec![0; all.len() + 1];
+        for (node, _) in &edges {
+            offsets[*node as usize + 1] += 1;
+        }
+        for node in 0..all.len() {
+            offsets[node + 1] += offsets[node];
+        }
+        PreparedGraph {
+            mode,
+            router_connectors: connectors.len(),
+            crossings: crossing_connectors,
+            crossing_ids: crossings.keys().map(|id| id.to_string()).collect(),
+            unknown_ids,
+            passing,
+            points,
+            index,
+            segments_by_id,
+            on_segments,
+            offsets,
+            edges: edges.into_iter().map(|(_, edge)| edge).collect(),
+            appended: HashMap::new(),
+        }
+    }
+
+    /// Appends a connector added to the router.
+    ///
+    /// Returns `false` if the graph has to be built anew, because the
+    /// connector replaces one with the same ID or is on segments of the
+    /// graph.
+    pub(crate) fn push_connector(&mut self, connector: &Connector) -> bool {
+        let id = connector.id();
+        if self.index.contains_key(id) || self.unknown_ids.contains(id) {
+            return false;
+        }
+        let point = connector.get_point();
+        self.index.insert(id.to_string(), self.len() as u32);
+        self.points.push([point.x(), point.y()]);
+        self.passing.push((None, false));
+        true
+    }
+
+    /// Appends a segment added to the router with the index.
+    ///
+    /// Returns `false` if the graph has to be built anew, because the
+    /// segment changes level crossings.
+    pub(crate) fn push_segment(&mut self, segment: &Segment, segment_index: u32) -> bool {
+        let mut positions = Vec::new();
+        for id in segment.get_connectors() {
+            if self.crossing_ids.contains(id) {
+                return false;
+            }
+            let node = match self.index.get(id) {
+                Some(node) => *node,
+                None => {
+                    self.unknown_ids.insert(id.clone());
+                    continue;
+                }
+            };
+            let [x, y] = self.points[node as usize];
+            if let Some(position) = segment.get_point_position(&Point::new(x, y)) {
+                // Segments at other levels passing the connector would cross
+                // there.
+                let (level, end) = self.passing[node as usize];
+                let inside = position > 0.0 && position < 1.0;
+                if inside && !end && level.is_some_and(|level| level != segment.get_level()) {
+                    return false;
+                }
+                positions.push((position, node));
+            }
+        }
+        positions.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for (position, node) in &positions {
+            pass(&mut self.passing[*node as usize], segment, *position);
+        }
+        let mut edges = Vec::new();
+        push_edges(
+            &mut edges,
+            segment,
+            segment_index,
+            &positions,
+            self.mode,
+            |_, _| true,
+        );
+        for (node, edge) in edges {
+            self.appended.entry(node).or_default().push(edge);
+        }
+        self.segments_by_id
+            .entry(segment.id().to_string())
+            .or_default()
+            .push(segment_index);
+        self.on_segments.push(positions);
+        true
+    }
+
+    /// Returns the number of connectors.
+    fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Returns the edges leaving the connector with the index.
+    fn edges(&self, node: u32) -> impl Iterator<Item = &Edge> {
+        let built = match node as usize {
+            node if node + 1 < self.offsets.len() => {
+                &self.edges[self.offsets[node] as usize..self.offsets[node + 1] as usize]
+            }
+            _ => &[],
+        };
+        built
+            .iter()
+            .chain(self.appended.get(&node).into_iter().flatten())
+    }
+}
+
+/// Records that the segment passes or ends at the connector at the
+/// position.
+fn pass(passing: &mut (Option<i32>, bool), segment: &Segment, position: Position) {
+    if position > 0.0 && position < 1.0 {
+        passing.0.get_or_insert(segment.get_level());
+    } else {
+        passing.1 = true;
+    }
+}
+
+/// Pushes the edges between the connectors next to each other at the
+/// positions on the segment, if they are wanted.
+fn push_edges(
+    edges: &mut Vec<(u32, Edge)>,
+    segment: &Segment,
+    segment_index: u32,
+    positions: &[(Position, u32)],
+    mode: DistanceMode,
+    wanted: impl Fn(u32, u32) -> bool,
+) {
+    let line: Option<geo::LineString<f64>> =
+        (mode == DistanceMode::Geodesic).then(|| segment.get_geometry().into());
+    for pair in positions.windows(2) {
+        let ((from_position, from), (to_position, to)) = (pair[0], pair[1]);
+        if !wanted(from, to) {
+            continue;
+        }
+        let length = match &line {
+            Some(line) => geodesic_length(line, from_position, to_position),
+            None => (to_position - from_position) * segment.get_length(),
+        };
+        for (node, target, positions) in [
+            (from, to, (from_position, to_position)),
+            (to, from, (to_position, from_position)),
+        ] {
+            edges.push((
+                node,
+                Edge {
+                    target,
+                    segment: segment_index,
+                    length,
+                    positions,
+                },
+            ));
+        }
+    }
+}
+
+/// The prepared graph of a network with the endpoints of a search.
+pub(crate) struct Graph<'a> {
+    prepared: &'a PreparedGraph,
+    connectors: &'a [Connector],
+    segments: &'a [Segment],
+    /// Connectors placed onto the segments for the search, numbered after
+    /// the connectors of the prepared graph.
+    endpoints: Vec<&'a Connector>,
+    /// Coordinates of the endpoints.
+    endpoint_points: Vec<[f64; 2]>,
+    /// Edges from and to the endpoints by connector.
+    extra_edges: HashMap<u32, Vec<Edge>>,
+}
+
+impl<'a> Graph<'a> {
+    /// Adds the endpoints to the prepared graph of the segments and
+    /// connectors.
+    ///
+    /// The endpoints are connectors placed onto the segments with the given
+    /// IDs, e.g. at the start and stop. They split the segments like other
+    /// connectors on them.
+    pub(crate) fn new(
+        prepared: &'a PreparedGraph,
+        segments: &'a [Segment],
+        connectors: &'a [Connector],
+        endpoints: &[(&str, &'a Connector)],
+    ) -> Graph<'a> {
+        let first = prepared.len() as u32;
+        let mut on_segments: BTreeMap<u32, Vec<(Position, u32)>> = BTreeMap::new();
+        for (node, (segment_id, connector)) in (first..).zip(endpoints) {
+            for segment_index in prepared
+                .segments_by_id
+                .get(*segment_id)
+                .into_iter()
+                .flatten()
+            {
+                if let Some(position) =
+                    segments[*segment_index as usize].get_point_position(&connector.get_point())
+                {
+                    on_segments
+                        .entry(*segment_index)
+                        .or_default()
+                        .push((position, node));
+                }
+            }
+        }
+        let mut edges = Vec::new();
+        for (segment_index, endpoint_positions) in on_segments {
+            let mut positions = prepared.on_segments[segment_index as usize].clone();
+            positions.extend(endpoint_positions);
+            positions.sort_by(|a, b| a.0.total_cmp(&b.0));
+            push_edges(
+                &mut edges,
+                &segments[segment_index as usize],
+                segment_index,
+                &positions,
+                prepared.mode,
+                |from, to| from >= first || to >= first,
+            );
+        }
+        let mut extra_edges: HashMap<u32, Vec<Edge>> = HashMap::new();
+        for (node, edge) in edges {
+            extra_edges.entry(node).or_default().push(edge);
+        }
+        Graph {
+            prepared,
+            connectors,
+            segments,
+            endpoints: endpoints.iter().map(|(_, connector)| *connector).collect(),
+            endpoint_points: endpoints
+                .iter()
+                .map(|(_, connector)| {
+                    let point = connector.get_point();
+                    [point.x(), point.y()]
+                })
+                .collect(),
+            extra_edges,
+        }
+    }
+
+    /// Returns the number of connectors.
+    pub(crate) fn len(&self) -> usize {
+        self.prepared.len() + self.endpoints.len()
+    }
+
+    /// Returns the index of the connector with the ID.
+    pub(crate) fn node(&self, id: &str) -> Option<u32> {
+        match self
+            .endpoints
+            .iter()
+            .rposition(|endpoint| endpoint.id() == id)
+        {
+            Some(endpoint) => Some((self.prepared.len() + endpoint) as u32),
+            None => self.prepared.index.get(id).copied(),
+        }
+    }
+
+    /// Returns the connector with the index.
+    pub(crate) fn connector(&self, node: u32) -> &'a Connector {
+        let node = node as usize;
+        let crossings = self.prepared.router_connectors;
+        let appended = crossings + self.prepared.crossings.len();
+        if node < crossings {
+            &self.connectors[node]
+        } else if node < appended {
+            &self.prepared.crossings[node - crossings]
+        } else if node < self.prepared.len() {
+            &self.connectors[node - self.prepared.crossings.len()]
+        } else {
+            self.endpoints[node - self.prepared.len()]
+        }
+    }
+
+    /// Returns the coordinates of the connector with the index.
+    pub(crate) fn point(&self, node: u32) -> [f64; 2] {
+        match node as usize {
+            node if node < self.prepared.len() => self.prepared.points[node],
+            node => self.endpoint_points[node - self.prepared.len()],
+        }
+    }
+
+    /// Returns the edges leaving the connector with the index in the
+    /// direction of their segments.
+    pub(crate) fn edges(&self, node: u32) -> impl Iterator<Item = &Edge> {
+        self.all_edges(node).filter(move |edge| {
+            self.segment(edge)
+                .get_direction()
+                .allows(edge.positions.0, edge.positions.1)
+        })
+    }
+
+    /// Returns the edges leaving the connector with the index, including
+    /// those against the direction of one-way segments.
+    fn all_edges(&self, node: u32) -> impl Iterator<Item = &Edge> {
+        self.prepared
+            .edges(node)
+            .chain(self.extra_edges.get(&node).into_iter().flatten())
+    }
+
+    /// Returns the component of each connector, the index of its first
+    /// connector. Connectors are in the same component if there is a way
+    /// between them along the usable edges, ignoring one-way segments.
+    pub(crate) fn components(&self, usable: impl Fn(&Edge) -> bool) -> Vec<u32> {
+        let mut components = vec![u32::MAX; self.len()];
+        let mut to_visit = Vec::new();
+        for first in 0..self.len() as u32 {
+            if components[first as usize] != u32::MAX {
+                continue;
+            }
+            components[first as usize] = first;
+            to_visit.push(first);
+            while let Some(node) = to_visit.pop() {
+                for edge in self.all_edges(node) {
+                    if usable(edge) && components[edge.target as usize] == u32::MAX {
+                        components[edge.target as usize] = first;
+                        to_visit.push(edge.target);
+                    }
+                }
+            }
+        }
+        components
+    }
+
+    /// Returns the segments of the router, indexed by the edges.
+    pub(crate) fn segments(&self) -> &'a [Segment] {
+        self.segments
+    }
+
+    /// Returns the segment of the edge.
+    pub(crate) fn segment(&self, edge: &Edge) -> &'a Segment {
+        &self.segments[edge.segment as usize]
+    }
+}
+
+/// Returns the geodesic length of the part of the line between the linear
+/// positions.
+///
+/// Positions are fractions of the planar length, so pieces of the line
+/// running east and west take a smaller share of the geodesic length than
+/// pieces running north and south at the same planar length.
+fn geodesic_length(line: &geo::LineString<f64>, from: Position, to: Position) -> f64 {
+    let total = line.euclidean_length();
+    if total == 0.0 {
+        return 0.0;
+    }
+    let (from, to) = (from * total, to * total);
+    let mut start = 0.0;
+    let mut length = 0.0;
+    for piece in line.lines() {
+        let piece_length = piece.euclidean_length();
+        let end = start + piece_length;
+        let overlap = end.min(to) - start.max(from);
+        if overlap > 0.0 {
+            length += geo::LineString::from(piece).haversine_length() * overlap / piece_length;
+        }
+        start = end;
+    }
+    length
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo_types::coord::{coord, Coord};
+    use crate::geo_types::{LineString, Point};
+
+    #[test]
+    fn new() {
+        let segments = vec![
+            Segment::new(
+                "1".into(),
+                LineString::new(vec![coord!( x: 0.0, y: 0.0 ), coord!( x: 2.0, y: 0.0 )]),
+                vec!["a".into(), "b".into(), "c".into()],
+            ),
+            Segment::new(
+                "2".into(),
+                LineString::new(vec![coord!( x: 2.0, y: 0.0 ), coord!( x: 2.0, y: 1.0 )]),
+                vec!["c".into(), "unknown".into()],
+            ),
+        ];
+        let connectors = vec![
+            Connector::new("a", &Point::new(0.0, 0.0)),
+            Connector::new("c", &Point::new(2.0, 0.0)),
+            Connector::new("b", &Point::new(1.0, 0.0)),
+        ];
+        let start = Connector::new("#start", &Point::new(2.0, 0.5));
+        let crossings = HashMap::new();
+        let prepared = PreparedGraph::new(&segments, &connectors, &crossings, DistanceMode::Planar);
+        let graph = Graph::new(&prepared, &segments, &connectors, &[("2", &start)]);
+        assert_eq!(graph.len(), 4);
+        assert_eq!(graph.node("#start"), Some(3));
+        assert_eq!(graph.point(2), [1.0, 0.0]);
+
+        // Only connectors next to each other along a segment are neighbours.
+        assert_eq!(neighbours_in(&graph, "a"), vec![("b".to_string(), 0)]);
+        assert_eq!(
+            neighbours_in(&graph, "c"),
+            vec![("b".to_string(), 0), ("#start".to_string(), 1)]
+        );
+        assert_eq!(neighbours_in(&graph, "#start"), vec![("c".to_string(), 1)]);
+        let edge = *graph.edges(3).next().unwrap();
+        assert_eq!(graph.segment(&edge).get_id(), "2");
+        assert_eq!(edge.positions, (0.5, 0.0));
+
+        assert_eq!(graph.components(|_| true), vec![0, 0, 0, 0]);
+        assert_eq!(graph.components(|edge| edge.segment == 0), vec![0, 0, 0, 3]);
+
+        // Endpoints between connectors are added to the neighbours of both,
+        // the prepared graph is kept as it is.
+        let stop = Connector::new("#stop", &Point::new(0.5, 0.0));
+        let graph = Graph::new(&prepared, &segments, &connectors, &[("1", &stop)]);
+        assert_eq!(graph.node("#stop"), Some(3));
+        assert_eq!(
+            neighbours_in(&graph, "a"),
+            vec![("b".to_string(), 0), ("#stop".to_string(), 0)]
+        );
+        assert_eq!(
+            neighbours_in(&graph, "#stop"),
+            vec![("a".to_string(), 0), ("b".to_string(), 0)]
+        );
+        assert_eq!(graph.edges(3).map(|edge| edge.length).sum::<f64>(), 1.0);
+    }
+
+    #[test]
+    fn directions() {
+        let mut segments = vec![Segment::new(
+            "1".into(),
+            LineString::new(vec![coord!( x: 0.0, y: 0.0 ), coord!( x: 2.0, y: 0.0 )]),
+            vec!["a".into(), "b".into()],
+        )];
+        segments[0].set_tag("oneway", "-1");
+        let connectors = vec![
+            Connector::new("a", &Point::new(0.0, 0.0)),
+            Connector::new("b", &Point::new(2.0, 0.0)),
+        ];
+        let stop = Connector::new("#stop", &Point::new(1.0, 0.0));
+        let crossings = HashMap::new();
+        let prepared = PreparedGraph::new(&segments, &connectors, &crossings, DistanceMode::Planar);
+        let graph = Graph::new(&prepared, &segments, &connectors, &[("1", &stop)]);
+        assert_eq!(neighbours_in(&graph, "a"), vec![]);
+        assert_eq!(
+            neighbours_in(&graph, "b"),
+            vec![("a".to_string(), 0), ("#stop".to_string(), 0)]
+        );
+        assert_eq!(neighbours_in(&graph, "#stop"), vec![("a".to_string(), 0)]);
+        // Components ignore the direction.
+        assert_eq!(graph.components(|_| true), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn append() {
+        let segments = vec![
+            Segment::new(
+                "1".into(),
+                LineString::new(vec![coord!( x: 0.0, y: 0.0 ), coord!( x: 2.0, y: 0.0 )]),
+                vec!["a".into(), "b".into(), "c".into()],
+            ),
+            Segment::new(
+                "2".into(),
+                LineString::new(vec![coord!( x: 2.0, y: 0.0 ), coord!( x: 2.0, y: 1.0 )]),
+                vec!["c".into(), "d".into(), "unknown".into()],
+            ),
+        ];
+        let connectors = vec![
+            Connector::new("a", &Point::new(0.0, 0.0)),
+            Connector::new("c", &Point::new(2.0, 0.0)),
+            Connector::new("b", &Point::new(1.0, 0.0)),
+            Connector::new("d", &Point::new(2.0, 1.0)),
+        ];
+        let crossings = HashMap::new();
+        let mut appended = PreparedGraph::new(
+            &segments[..1],
+            &connectors[..3],
+            &crossings,
+            DistanceMode::Planar,
+        );
+        assert!(appended.push_connector(&connectors[3]));
+        assert!(appended.push_segment(&segments[1], 1));
+        let built = PreparedGraph::new(&segments, &connectors, &crossings, DistanceMode::Planar);
+        let appended_graph = Graph::new(&appended, &segments, &connectors, &[]);
+        let built_graph = Graph::new(&built, &segments, &connectors, &[]);
+        for id in ["a", "b", "c", "d"] {
+            assert_eq!(
+                neighbours_in(&appended_graph, id),
+                neighbours_in(&built_graph, id)
+            );
+        }
+
+        // Connectors on segments of the graph or replacing others need a new
+        // graph.
+        assert!(!appended.push_connector(&Connector::new("unknown", &Point::new(2.0, 2.0))));
+        assert!(!appended.push_connector(&connectors[0]));
+    }
+
+    /// Returns the IDs of the neighbours of the connector with their segments.
+    fn neighbours_in(graph: &Graph, id: &str) -> Vec<(String, u32)> {
+        graph
+            .edges(graph.node(id).unwrap())
+            .map(|edge| (graph.connector(edge.target).get_id(), edge.segment))
+            .collect()
+    }
+
+    #[test]
+    fn geodesic_lengths() {
+        // At 60° north, the part to the east is half as long as the part to
+        // the north, though both are half of the planar length.
+        let segments = vec![Segment::new(
+            "1".into(),
+            LineString::new(vec![
+                coord!( x: 0.0, y: 60.0 ),
+                coord!( x: 0.01, y: 60.0 ),
+                coord!( x: 0.01, y: 60.01 ),
+            ]),
+            vec!["a".into(), "b".into(), "c".into()],
+        )];
+        let connectors = vec![
+            Connector::new("a", &Point::new(0.0, 60.0)),
+            Connector::new("b", &Point::new(0.01, 60.0)),
+            Connector::new("c", &Point::new(0.01, 60.01)),
+        ];
+        let crossings = HashMap::new();
+        let length = |mode, node| {
+            PreparedGraph::new(&segments, &connectors, &crossings, mode)
+                .edges(node)
+                .next()
+                .unwrap()
+                .length
+        };
+        assert!((length(DistanceMode::Planar, 0) - 0.01).abs() < 1e-12);
+        assert!((length(DistanceMode::Planar, 2) - 0.01).abs() < 1e-12);
+        assert!((length(DistanceMode::Geodesic, 0) - 556.0).abs() < 1.0);
+        assert!((length(DistanceMode::Geodesic, 2) - 1112.0).abs() < 1.0);
+    }
+}
@@ -0,0 +1,38 @@
+use wasm_bindgen::prelude::*;
+
+use crate::routing::tile_router::tile_router_methods;
+use crate::tile::backend::js_backend::{JsBackend, Tile, TileFormat};
+use crate::tile::backend::CachedTileNetwork;
+
+#[wasm_bindgen]
+/// A router getting its tiles from JS functions.
+///
+/// This allows to plug in any tile source, e.g. tiles bundled with an app or
+/// read from IndexedDB.
+pub struct JsRouter {
+    network: CachedTileNetwork<JsBackend, Tile>,
+}
+
+#[wasm_bindgen]
+impl JsRouter {
+    #[wasm_bindgen(constructor)]
+    /// Create the router using the given async `getTile(z, x, y)` function.
+    ///
+    /// The function must resolve to the tile data in the given format, or to
+    /// `null` or `undefined` if there is no such tile.
+    pub fn new(get_tile: js_sys::Function, format: TileFormat) -> JsRouter {
+        JsRouter {
+            network: CachedTileNetwork::new(JsBackend::new(get_tile, format)),
+        }
+    }
+
+    #[wasm_bindgen(js_name = addSource)]
+    /// Adds another tile function whose network is merged with the others.
+    ///
+    /// Sources added earlier take priority if features share the same ID.
+    pub fn add_source(&mut self, get_tile: js_sys::Function, format: TileFormat) {
+        self.network.add_backend(JsBackend::new(get_tile, format));
+    }
+}
+
+tile_router_methods!(JsRouter);
@@ -1,10 +1,52 @@
 #![allow(unused_imports)]
 
+mod attributes;
+pub use attributes::{Direction, SegmentAttributes};
+
 mod router;
-pub use router::{Connector, Router, RoutingError, Segment};
+pub use router::{Connector, Router, RoutingError, RoutingErrorKind, Segment, SegmentWithPosition};
+
+mod restriction;
+pub use restriction::TurnRestriction;
 
 mod route;
-pub use route::{Route, RouteSegment};
+pub use route::{BreakdownEntry, Maneuver, Route, RouteMetrics, RouteSegment, TraceDeviation};
+
+mod conditional;
+
+mod graph;
+pub(crate) use conditional::Departure;
+
+mod delta;
+pub use delta::NetworkDelta;
+
+mod options;
+pub use options::{DistanceMode, Objective, RouteOptions, TileSelection};
+
+pub mod profile;
+pub use profile::ProfileError;
 
+mod stats;
+pub use stats::SearchStats;
+
+mod timings;
+pub use timings::QueryTimings;
+
+mod tile_router;
+
+#[cfg(feature = "pmtiles")]
 pub mod pmtiles_mvt_router;
+#[cfg(feature = "pmtiles")]
 pub use pmtiles_mvt_router::PMTilesMVTRouter;
+
+pub mod xyz_mvt_router;
+pub use xyz_mvt_router::XYZMVTRouter;
+
+pub mod routing_tile_router;
+pub use routing_tile_router::RoutingTileRouter;
+
+pub mod flatgeobuf_router;
+pub use flatgeobuf_router::FlatGeobufRouter;
+
+pub mod js_router;
+pub use js_router::JsRouter;
@@ -0,0 +1,349 @@
+use crate::routing::{Departure, RoutingError};
+use crate::tile::region::{bounds_to_region, ring_to_region};
+use geo::Contains;
+use std::collections::HashSet;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// What a route search minimizes with a profile, see
+/// `RouteOptions.set_objective`.
+pub enum Objective {
+    /// The lengths weighed by speeds and penalties of the profile.
+    #[default]
+    Cost,
+    /// The lengths only.
+    Distance,
+    /// The travel time by the speeds of the profile.
+    Time,
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// How lengths and distances are measured in a route search, see
+/// `RouteOptions.set_distance_mode`.
+pub enum DistanceMode {
+    /// In the units of the coordinates, as if they were planar.
+    #[default]
+    Planar,
+    /// In meters along the surface of the earth (haversine), for coordinates
+    /// in longitude and latitude.
+    Geodesic,
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// Which tiles are loaded for a route search, see
+/// `RouteOptions.set_tile_selection`.
+pub enum TileSelection {
+    /// The tiles around the start.
+    #[default]
+    Around,
+    /// The tiles along the straight line from start to stop.
+    Corridor,
+    /// The tiles around start and stop, growing until a route is found.
+    Expanding,
+}
+
+/// A segment or connector a route has to pass, see
+/// `RouteOptions.set_via_segment`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Via {
+    Segment(String),
+    Connector(String),
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq)]
+/// Options of a route search, see `findRouteWithOptions`.
+pub struct RouteOptions {
+    pub(crate) snap_candidates: usize,
+    pub(crate) revisit_penalty: f64,
+    /// Region the route has to stay inside.
+    pub(crate) region: Option<geo::Polygon<f64>>,
+    /// Name of the registered profile to use.
+    pub(crate) profile: Option<String>,
+    /// IDs of the segments the route must not use.
+    pub(crate) blocked_segments: HashSet<String>,
+    /// Whether a partial route is returned if the stop is unreachable.
+    pub(crate) allow_partial: bool,
+    pub(crate) objective: Objective,
+    pub(crate) distance_mode: DistanceMode,
+    pub(crate) turn_penalty: f64,
+    /// Departure at which conditional tags apply.
+    pub(crate) departure: Option<Departure>,
+    /// Segment or connector the route has to pass.
+    pub(crate) via: Option<Via>,
+    pub(crate) tile_selection: TileSelection,
+    /// Radius in tiles of the tile selection.
+    pub(crate) tile_radius: u32,
+}
+
+impl Default for RouteOptions {
+    fn default() -> Self {
+        RouteOptions {
+            snap_candidates: 1,
+            revisit_penalty: 1.0,
+            region: None,
+            profile: None,
+            blocked_segments: HashSet::new(),
+            allow_partial: false,
+            objective: Objective::default(),
+            distance_mode: DistanceMode::default(),
+            turn_penalty: 0.0,
+            departure: None,
+            via: None,
+            tile_selection: TileSelection::default(),
+            tile_radius: 1,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl RouteOptions {
+    #[wasm_bindgen(constructor)]
+    /// Creates the default options.
+    pub fn new() -> RouteOptions {
+        RouteOptions::default()
+    }
+
+    /// Returns the number of nearest segments tried for start and stop.
+    pub fn get_snap_candidates(&self) -> usize {
+        self.snap_candidates
+    }
+
+    /// Sets the number of nearest segments tried for start and stop (at
+    /// least 1, the default).
+    ///
+    /// If the nearest segment is ambiguous, e.g. for two parallel roads, the
+    /// cheapest route of all combinations of candidates is returned instead
+    /// of committing to the nearest segment. Each combination is searched,
+    /// so keep the number small.
+    pub fn set_snap_candidates(&mut self, candidates: usize) {
+        self.snap_candidates = candidates.max(1);
+    }
+
+    /// Returns the factor for the length of segments traversed again.
+    pub fn get_revisit_penalty(&self) -> f64 {
+        self.revisit_penalty
+    }
+
+    /// Sets the factor by which the length of a segment is multiplied when
+    /// the route traverses it again (at least 1, the default).
+    ///
+    /// This discourages routes going back and forth on the same segment when
+    /// costs are nearly tied. An infinite factor forbids traversing a segment
+    /// twice.
+    pub fn set_revisit_penalty(&mut self, factor: f64) {
+        self.revisit_penalty = factor.max(1.0);
+    }
+
+    /// Returns the cost added for turning around.
+    pub fn get_turn_penalty(&self) -> f64 {
+        self.turn_penalty
+    }
+
+    /// Sets the cost, in units of the segment lengths, added for turning
+    /// around at a connector, 0 by default. Smaller turns add proportionally
+    /// less, so a small penalty prefers going straight when costs are
+    /// similar.
+    ///
+    /// The penalty depends on the best way to each connector only, so a
+    /// slightly longer way with fewer turns may still be missed.
+    pub fn set_turn_penalty(&mut self, penalty: f64) {
+        self.turn_penalty = penalty.max(0.0);
+    }
+
+    /// Restricts the route to segments inside the bounds [west, south, east,
+    /// north], e.g. to stay inside a park.
+    pub fn set_bounds(&mut self, bounds: Vec<f64>) -> Result<(), RoutingError> {
+        self.region = Some(bounds_to_region(&bounds)?);
+        Ok(())
+    }
+
+    /// Restricts the route to segments inside the polygon given as ring
+    /// [x0, y0, x1, y1, ...].
+    pub fn set_polygon(&mut self, ring: Vec<f64>) -> Result<(), RoutingError> {
+        self.region = Some(ring_to_region(&ring)?);
+        Ok(())
+    }
+
+    /// Removes the restriction of the bounds or polygon.
+    pub fn clear_region(&mut self) {
+        self.region = None;
+    }
+
+    /// Returns the name of the profile to use.
+    pub fn get_profile(&self) -> Option<String> {
+        self.profile.clone()
+    }
+
+    /// Sets the name of the profile registered with `registerProfile` to
+    /// use, `undefined` to route by length.
+    pub fn set_profile(&mut self, name: Option<String>) {
+        self.profile = name;
+    }
+
+    /// Returns what the search minimizes.
+    pub fn get_objective(&self) -> Objective {
+        self.objective
+    }
+
+    /// Sets what the search minimizes with a profile: its cost (the
+    /// default), the distance or the travel time. Excluded segments are
+    /// never used. Without a profile, routes are always the shortest.
+    pub fn set_objective(&mut self, objective: Objective) {
+        self.objective = objective;
+    }
+
+    /// Returns how lengths and distances are measured.
+    pub fn get_distance_mode(&self) -> DistanceMode {
+        self.distance_mode
+    }
+
+    /// Sets how segment lengths, the estimate of the remaining distance and
+    /// the distances to the nearest segments are measured: planar in the
+    /// units of the coordinates (the default) or geodesic in meters, which
+    /// avoids distortions of longitude and latitude away from the equator.
+    ///
+    /// Revisit and turn penalties are in the units of this mode.
+    pub fn set_distance_mode(&mut self, mode: DistanceMode) {
+        self.distance_mode = mode;
+    }
+
+    /// Returns the IDs of the blocked segments.
+    pub fn get_blocked_segments(&self) -> Vec<String> {
+        let mut ids: Vec<_> = self.blocked_segments.iter().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Blocks the segments with the IDs for searches with these options
+    /// only, e.g. to compare routes as if a street were closed. The router
+    /// is not changed, so other queries are not affected.
+    pub fn set_blocked_segments(&mut self, ids: Vec<String>) {
+        self.blocked_segments = ids.into_iter().collect();
+    }
+
+    /// Returns the weekday of the departure, 0 for Monday to 6 for Sunday.
+    pub fn get_departure_weekday(&self) -> Option<u8> {
+        self.departure.map(|departure| departure.weekday)
+    }
+
+    /// Returns the time of the departure in seconds after midnight.
+    pub fn get_departure_time(&self) -> Option<u32> {
+        self.departure.map(|departure| departure.seconds)
+    }
+
+    /// Sets the departure in local time by the weekday, 0 for Monday to 6 for
+    /// Sunday, and the seconds after midnight.
+    ///
+    /// Conditional tags of segments, e.g. `access:conditional=no @ (Mo-Fr
+    /// 22:00-06:00)`, then change their tags for profiles if the departure
+    /// is in their time windows. They apply at the departure for the whole
+    /// route, not when a segment is reached.
+    pub fn set_departure(&mut self, weekday: u8, seconds: u32) {
+        self.departure = Some(Departure {
+            weekday: weekday % 7,
+            seconds: seconds % (24 * 60 * 60),
+        });
+    }
+
+    /// Removes the departure, so conditional tags are ignored.
+    pub fn clear_departure(&mut self) {
+        self.departure = None;
+    }
+
+    /// Returns the ID of the segment the route has to pass.
+    pub fn get_via_segment(&self) -> Option<String> {
+        match &self.via {
+            Some(Via::Segment(id)) => Some(id.clone()),
+            _ => None,
+        }
+    }
+
+    /// Sets the ID of a segment the route has to pass, e.g. a ferry or a
+    /// scenic road, `undefined` to remove it. Replaces a via connector.
+    ///
+    /// The route is searched in two legs, from the start to the middle of
+    /// the segment and on from there to the stop, see `Route.get_legs`.
+    pub fn set_via_segment(&mut self, id: Option<String>) {
+        match id {
+            Some(id) => self.via = Some(Via::Segment(id)),
+            None if self.get_via_segment().is_some() => self.via = None,
+            None => {}
+        }
+    }
+
+    /// Returns the ID of the connector the route has to pass.
+    pub fn get_via_connector(&self) -> Option<String> {
+        match &self.via {
+            Some(Via::Connector(id)) => Some(id.clone()),
+            _ => None,
+        }
+    }
+
+    /// Sets the ID of a connector the route has to pass, `undefined` to
+    /// remove it. Replaces a via segment.
+    ///
+    /// The route is searched in two legs, from the start to the connector
+    /// and on from there to the stop, see `Route.get_legs`.
+    pub fn set_via_connector(&mut self, id: Option<String>) {
+        match id {
+            Some(id) => self.via = Some(Via::Connector(id)),
+            None if self.get_via_connector().is_some() => self.via = None,
+            None => {}
+        }
+    }
+
+    /// Returns which tiles are loaded for a route search.
+    pub fn get_tile_selection(&self) -> TileSelection {
+        self.tile_selection
+    }
+
+    /// Sets which tiles routers with tile sources load for a route search:
+    /// the tiles around the start (the default), the tiles along the
+    /// straight line to the stop, or the tiles around start and stop,
+    /// searched again with one more tile around them until a route is found
+    /// (up to three times).
+    ///
+    /// Longer routes need more tiles, at the cost of loading more data.
+    /// Searches from one start to several stops always use the tiles around
+    /// the start.
+    pub fn set_tile_selection(&mut self, selection: TileSelection) {
+        self.tile_selection = selection;
+    }
+
+    /// Returns the radius in tiles of the tile selection.
+    pub fn get_tile_radius(&self) -> u32 {
+        self.tile_radius
+    }
+
+    /// Sets how many tiles around the start, the line or the endpoints are
+    /// loaded, 1 by default, i.e. the neighbouring tiles.
+    pub fn set_tile_radius(&mut self, radius: u32) {
+        self.tile_radius = radius;
+    }
+
+    /// Returns whether partial routes are returned.
+    pub fn get_allow_partial(&self) -> bool {
+        self.allow_partial
+    }
+
+    /// Sets whether the route to the reachable connector closest to the stop
+    /// is returned instead of an error if the stop is unreachable, e.g. for
+    /// networks with known gaps. The remaining distance is given by
+    /// `Route.get_gap`.
+    pub fn set_allow_partial(&mut self, allow: bool) {
+        self.allow_partial = allow;
+    }
+}
+
+impl RouteOptions {
+    /// Returns whether the geometry is inside the region, if any.
+    pub(crate) fn is_inside(&self, geometry: &geo::LineString<f64>) -> bool {
+        self.region
+            .as_ref()
+            .is_none_or(|region| region.contains(geometry))
+    }
+}
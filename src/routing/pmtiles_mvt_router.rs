@@ -1,15 +1,16 @@
 use wasm_bindgen::prelude::*;
 
-use crate::debug::debug_log;
-use crate::geo_types::Point;
-use crate::routing::{Route, RoutingError};
-use crate::tile::backend::pmtiles_mvt_backend::{PMTilesMVTBackend, Tile};
-use crate::tile::backend::CachedTileNetwork;
+use crate::debug::warn_log;
+use crate::routing::tile_router::tile_router_methods;
+use crate::routing::RoutingError;
+use crate::tile::backend::mvt_tile::Tile;
+use crate::tile::backend::pmtiles_mvt_backend::{ArchiveInfo, PMTilesMVTBackend};
+use crate::tile::backend::{CachedTileNetwork, FallbackBackend};
 
 #[wasm_bindgen]
 /// A router using Mapbox Vector Tiles insiden an PMTiles container.
 pub struct PMTilesMVTRouter {
-    network: CachedTileNetwork<PMTilesMVTBackend, Tile>,
+    network: CachedTileNetwork<FallbackBackend<PMTilesMVTBackend>, Tile>,
 }
 
 #[wasm_bindgen]
@@ -17,16 +18,157 @@ impl PMTilesMVTRouter {
     #[wasm_bindgen(constructor)]
     /// Create the router using the given PMTiles URL.
     pub fn new(url: &str) -> PMTilesMVTRouter {
-        let backend = PMTilesMVTBackend::new(url);
+        let backend = FallbackBackend::new(PMTilesMVTBackend::new(url));
         PMTilesMVTRouter {
             network: CachedTileNetwork::new(backend),
         }
     }
 
-    #[wasm_bindgen(js_name = findRoute)]
-    /// Find a route for the given start and stop points.
-    pub async fn find_route(&mut self, start: &Point, stop: &Point) -> Result<Route, RoutingError> {
-        debug_log!("PMTilesMVTRouter::find_route {:?}, {:?}", start, stop);
-        self.network.find_route(start, stop).await
+    #[wasm_bindgen(js_name = fromBuffer)]
+    /// Create the router using a PMTiles archive held in memory.
+    ///
+    /// Accepts a Uint8Array or a Node.js Buffer, e.g. from `fs.readFile`, so
+    /// no web server is needed.
+    pub fn from_buffer(data: &js_sys::Uint8Array) -> PMTilesMVTRouter {
+        let backend = FallbackBackend::new(PMTilesMVTBackend::from_buffer("buffer:0", data));
+        PMTilesMVTRouter {
+            network: CachedTileNetwork::new(backend),
+        }
+    }
+
+    #[wasm_bindgen(js_name = addSourceBuffer)]
+    /// Adds another PMTiles archive held in memory, see `addSource`.
+    pub fn add_source_buffer(&mut self, data: &js_sys::Uint8Array) {
+        let key = format!("buffer:{}", self.network.get_backends_mut().len());
+        self.network
+            .add_backend(FallbackBackend::new(PMTilesMVTBackend::from_buffer(
+                &key, data,
+            )));
+    }
+
+    #[wasm_bindgen(js_name = addSource)]
+    /// Adds another PMTiles archive whose network is merged with the others.
+    ///
+    /// Sources added earlier take priority if features share the same ID.
+    pub fn add_source(&mut self, url: &str) {
+        self.network
+            .add_backend(FallbackBackend::new(PMTilesMVTBackend::new(url)));
+    }
+
+    #[wasm_bindgen(js_name = addFallback)]
+    /// Adds a fallback PMTiles archive for the most recently added source.
+    ///
+    /// Tiles are fetched from the fallback if the previous archives fail to
+    /// deliver them, e.g. because a CDN is unavailable.
+    pub fn add_fallback(&mut self, url: &str) {
+        if let Some(source) = self.network.get_backends_mut().last_mut() {
+            source.add_fallback(PMTilesMVTBackend::new(url));
+        }
+    }
+
+    #[wasm_bindgen(js_name = preload)]
+    /// Fetches all archives which are not larger than `max_bytes` entirely.
+    ///
+    /// Tiles of preloaded archives are served from memory, which avoids a
+    /// range request per tile on slow connections. Returns the number of
+    /// preloaded archives.
+    pub async fn preload(&mut self, max_bytes: f64) -> usize {
+        let mut preloaded = 0;
+        for source in self.network.get_backends_mut() {
+            for backend in source.get_backends_mut() {
+                match backend.preload(max_bytes).await {
+                    Ok(true) => preloaded += 1,
+                    Ok(false) => {}
+                    Err(err) => {
+                        warn_log!("Could not preload archive: {:?}", err);
+                    }
+                }
+            }
+        }
+        preloaded
+    }
+
+    #[wasm_bindgen(js_name = getArchiveInfo)]
+    /// Returns zoom range, bounds and layers of the PMTiles archive.
+    pub async fn get_archive_info(&self) -> Result<ArchiveInfo, RoutingError> {
+        self.network
+            .get_backend()
+            .get_primary()
+            .get_archive_info()
+            .await
+            .or(Err(RoutingError::MetadataFetchingError))
+    }
+
+    #[wasm_bindgen(js_name = checkDataVersion)]
+    /// Reads the data version of the archive and invalidates cached tiles of
+    /// other versions.
+    ///
+    /// Call this after setting the offline store, so outdated offline tiles
+    /// are removed as well. Returns whether cached tiles were invalidated.
+    pub async fn check_data_version(&mut self) -> Result<bool, RoutingError> {
+        let info = self.get_archive_info().await?;
+        self.network.set_data_version(info.get_version()).await
+    }
+
+    #[wasm_bindgen(js_name = validateZoom)]
+    /// Checks that the archive can serve tiles for the configured routing zoom.
+    ///
+    /// Zoom levels above the maximum zoom of the archive are supported by
+    /// using the parent tiles.
+    pub async fn validate_zoom(&self) -> Result<(), RoutingError> {
+        let info = self.get_archive_info().await?;
+        if info.get_min_zoom() <= self.get_zoom() {
+            Ok(())
+        } else {
+            Err(RoutingError::UnsupportedZoom)
+        }
+    }
+
+    #[wasm_bindgen(js_name = setParser)]
+    /// Delegates tile parsing to the given JS function.
+    ///
+    /// The function receives the MVT data, the z, x, y tile coordinates and the
+    /// direction property and must resolve to the bytes returned by
+    /// `parseMVTTile`. This allows to parse tiles inside a Web Worker instead
+    /// of the main thread.
+    pub fn set_parser(&mut self, parser: js_sys::Function) {
+        for source in self.network.get_backends_mut() {
+            for backend in source.get_backends_mut() {
+                backend.set_parser(parser.clone());
+            }
+        }
+    }
+
+    #[wasm_bindgen(js_name = setDirectionProperty)]
+    /// Reads the direction in which segments can be travelled from the given
+    /// property instead of `oneway`, e.g. one derived from the access rules
+    /// of Overture data.
+    ///
+    /// Values like `yes` or `forward` allow only the direction of the
+    /// geometry, `-1` or `backward` only the opposite one. Tiles which were
+    /// already loaded keep their directions.
+    pub fn set_direction_property(&mut self, property: Option<String>) {
+        for source in self.network.get_backends_mut() {
+            for backend in source.get_backends_mut() {
+                backend.set_direction_property(property.clone());
+            }
+        }
+    }
+}
+
+tile_router_methods!(PMTilesMVTRouter, before_search: read_max_source_zoom);
+
+impl PMTilesMVTRouter {
+    /// Reads the highest zoom level of the archive once, so that parent tiles
+    /// are used if the archive lacks the routing zoom.
+    async fn read_max_source_zoom(&mut self) {
+        if self.network.get_max_source_zoom().is_none() {
+            match self.get_archive_info().await {
+                Ok(info) => self.network.set_max_source_zoom(Some(info.get_max_zoom())),
+                Err(err) => {
+                    warn_log!("Could not read archive info: {:?}", err);
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,389 @@
+//! Routing profiles defined at runtime.
+//!
+//! A profile is given as JSON object, e.g.
+//!
+//! ```json
+//! {
+//!   "default_speed": 30,
+//!   "speeds": {"highway=primary": 80, "highway=track": 10},
+//!   "class_speeds": {"residential": 30, "service": 15},
+//!   "penalties": {"surface=gravel": 2},
+//!   "exclude": ["highway=motorway", "access"],
+//!   "delays": {"highway=traffic_signals": 20, "highway=crossing": 5}
+//! }
+//! ```
+//!
+//! Tag conditions are either `key=value` or `key` (any value). For the speed,
+//! conditions with value take precedence. Segments matching no condition get
+//! the speed of their road class (see `SegmentAttributes.get_class`) from
+//! `class_speeds`, otherwise the default speed. Speeds are given in km/h,
+//! which is used for the estimated duration of routes and their segments.
+//! Segments matching an `exclude` condition are not used and the lengths of
+//! segments are multiplied by all matching penalties (at least 1). Delays are
+//! given in seconds for passing connectors with matching tags, e.g. waiting at
+//! traffic signals; they add to the duration of routes and to the cost except
+//! for the distance objective. Conditional tags, e.g. `access:conditional`,
+//! change their tags at the departure of a route if it is set.
+
+use crate::routing::{Connector, Departure, DistanceMode, Objective, Segment};
+use geo::HaversineLength;
+use serde_json::Value;
+use std::collections::HashMap;
+use thiserror::Error;
+use wasm_bindgen::prelude::*;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+/// Errors of profile definitions.
+pub enum ProfileError {
+    /// The definition is not valid JSON.
+    #[error("Invalid JSON")]
+    InvalidJson,
+    /// The definition is malformed, e.g. a speed is not a positive number.
+    #[error("Invalid profile definition: {0}")]
+    InvalidDefinition(&'static str),
+}
+
+impl From<ProfileError> for JsValue {
+    fn from(err: ProfileError) -> JsValue {
+        JsError::new(&err.to_string()).into()
+    }
+}
+
+/// A condition on the tags of a segment.
+#[derive(Debug, Clone, PartialEq)]
+struct TagCondition {
+    key: String,
+    value: Option<String>,
+}
+
+impl TagCondition {
+    fn parse(condition: &str) -> TagCondition {
+        match condition.split_once('=') {
+            Some((key, value)) => TagCondition {
+                key: key.into(),
+                value: Some(value.into()),
+            },
+            None => TagCondition {
+                key: condition.into(),
+                value: None,
+            },
+        }
+    }
+
+    /// Returns whether the tags of the segment at the departure, if any,
+    /// match.
+    fn matches(&self, segment: &Segment, departure: Option<Departure>) -> bool {
+        self.matches_tag(segment.tag_at(&self.key, departure))
+    }
+
+    /// Returns whether the tags of the connector match.
+    fn matches_connector(&self, connector: &Connector) -> bool {
+        self.matches_tag(connector.tag(&self.key))
+    }
+
+    /// Returns whether the value of the tag with the key matches.
+    fn matches_tag(&self, tag: Option<&str>) -> bool {
+        match (tag, &self.value) {
+            (Some(tag), Some(value)) => tag == value,
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+/// A parsed profile definition.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Profile {
+    default_speed: f64,
+    speeds: Vec<(TagCondition, f64)>,
+    /// Speeds by road class of segments without matching speed condition.
+    class_speeds: HashMap<String, f64>,
+    penalties: Vec<(TagCondition, f64)>,
+    exclude: Vec<TagCondition>,
+    /// Delays in seconds at connectors.
+    delays: Vec<(TagCondition, f64)>,
+    /// Highest speed of the profile, to keep costs at least the length.
+    max_speed: f64,
+}
+
+impl Profile {
+    /// Parses the JSON definition of a profile.
+    pub(crate) fn from_json(definition: &str) -> Result<Profile, ProfileError> {
+        let value: Value = serde_json::from_str(definition).or(Err(ProfileError::InvalidJson))?;
+        if !value.is_object() {
+            return Err(ProfileError::InvalidDefinition("not an object"));
+        }
+        let default_speed = match value.get("default_speed") {
+            Some(speed) => positive(speed, "invalid default_speed")?,
+            None => 1.0,
+        };
+        let conditions = |key: &str, context| -> Result<Vec<(TagCondition, f64)>, ProfileError> {
+            match value.get(key) {
+                Some(Value::Object(entries)) => entries
+                    .iter()
+                    .map(|(condition, value)| {
+                        Ok((TagCondition::parse(condition), positive(value, context)?))
+                    })
+                    .collect(),
+                Some(_) => Err(ProfileError::InvalidDefinition(context)),
+                None => Ok(Vec::new()),
+            }
+        };
+        let mut speeds = conditions("speeds", "invalid speeds")?;
+        // Conditions with value take precedence.
+        speeds.sort_by_key(|(condition, _)| condition.value.is_none());
+        let class_speeds: HashMap<_, _> = conditions("class_speeds", "invalid class_speeds")?
+            .into_iter()
+            .map(|(condition, speed)| (condition.key, speed))
+            .collect();
+        let penalties = conditions("penalties", "invalid penalties")?
+            .into_iter()
+            .map(|(condition, penalty)| (condition, penalty.max(1.0)))
+            .collect();
+        let exclude = match value.get("exclude") {
+            Some(Value::Array(conditions)) => conditions
+                .iter()
+                .map(|condition| condition.as_str().map(TagCondition::parse))
+                .collect::<Option<_>>()
+                .ok_or(ProfileError::InvalidDefinition("invalid exclude"))?,
+            Some(_) => return Err(ProfileError::InvalidDefinition("invalid exclude")),
+            None => Vec::new(),
+        };
+        let delays = conditions("delays", "invalid delays")?;
+        let max_speed = speeds
+            .iter()
+            .map(|(_, speed)| *speed)
+            .chain(class_speeds.values().copied())
+            .fold(default_speed, f64::max);
+        Ok(Profile {
+            default_speed,
+            speeds,
+            class_speeds,
+            penalties,
+            exclude,
+            delays,
+            max_speed,
+        })
+    }
+
+    /// Returns the cost of traversing the segment for the objective, `None`
+    /// if it is excluded.
+    ///
+    /// The cost is the length scaled by the speed relative to the highest
+    /// speed of the profile and, for [`Objective::Cost`], the penalties, so
+    /// it is never lower than the length. Conditional tags apply at the
+    /// departure, if any.
+    pub(crate) fn cost(
+        &self,
+        segment: &Segment,
+        objective: Objective,
+        departure: Option<Departure>,
+    ) -> Option<f64> {
+        if self.is_excluded(segment, departure) {
+            return None;
+        }
+        let length = segment.get_length();
+        Some(match objective {
+            Objective::Distance => length,
+            Objective::Time => length * self.max_speed / self.speed(segment, departure),
+            Objective::Cost => {
+                let penalty: f64 = self
+                    .penalties
+                    .iter()
+                    .filter(|(condition, _)| condition.matches(segment, departure))
+                    .map(|(_, penalty)| penalty)
+                    .product();
+                length * self.max_speed / self.speed(segment, departure) * penalty
+            }
+        })
+    }
+
+    /// Returns whether the segment must not be used at the departure, if any.
+    pub(crate) fn is_excluded(&self, segment: &Segment, departure: Option<Departure>) -> bool {
+        self.exclude
+            .iter()
+            .any(|condition| condition.matches(segment, departure))
+    }
+
+    /// Returns the delay in seconds for passing the connector.
+    pub(crate) fn delay(&self, connector: &Connector) -> f64 {
+        self.delays
+            .iter()
+            .filter(|(condition, _)| condition.matches_connector(connector))
+            .map(|(_, delay)| delay)
+            .sum()
+    }
+
+    /// Returns the cost of the delay at the connector reached on the segment
+    /// for the objective, 0 for [`Objective::Distance`].
+    ///
+    /// The cost is the length of the segment, measured in the distance mode,
+    /// that could be travelled at the highest speed of the profile during the
+    /// delay.
+    pub(crate) fn delay_cost(
+        &self,
+        connector: &Connector,
+        segment: &Segment,
+        objective: Objective,
+        mode: DistanceMode,
+    ) -> f64 {
+        let delay = match objective {
+            Objective::Distance => return 0.0,
+            _ => self.delay(connector),
+        };
+        if delay == 0.0 {
+            return 0.0;
+        }
+        let meters = delay * self.max_speed / 3.6;
+        if mode == DistanceMode::Geodesic {
+            return meters;
+        }
+        match segment.length(DistanceMode::Geodesic) {
+            length if length > 0.0 => meters * segment.get_length() / length,
+            _ => 0.0,
+        }
+    }
+
+    /// Returns the speed on the segment in km/h at the departure, if any.
+    pub(crate) fn speed(&self, segment: &Segment, departure: Option<Departure>) -> f64 {
+        self.speeds
+            .iter()
+            .find(|(condition, _)| condition.matches(segment, departure))
+            .map(|(_, speed)| *speed)
+            .or_else(|| {
+                let class = segment.attributes().class()?;
+                self.class_speeds.get(class).copied()
+            })
+            .unwrap_or(self.default_speed)
+    }
+}
+
+/// Returns the positive number of the value.
+fn positive(value: &Value, context: &'static str) -> Result<f64, ProfileError> {
+    value
+        .as_f64()
+        .filter(|value| *value > 0.0)
+        .ok_or(ProfileError::InvalidDefinition(context))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo_types::coord::{coord, Coord};
+    use crate::geo_types::LineString;
+
+    fn segment(tags: &[(&str, &str)]) -> Segment {
+        let mut segment = Segment::new(
+            "a".into(),
+            LineString::new(vec![coord!( x: 0.0, y: 0.0 ), coord!( x: 2.0, y: 0.0 )]),
+            vec![],
+        );
+        for (key, value) in tags {
+            segment.set_tag(key, value);
+        }
+        segment
+    }
+
+    #[test]
+    fn cost() {
+        let profile = Profile::from_json(
+            r#"{
+                "default_speed": 10,
+                "speeds": {"highway": 20, "highway=primary": 40},
+                "penalties": {"surface=gravel": 2, "lit=no": 0.5},
+                "exclude": ["highway=motorway", "access"]
+            }"#,
+        )
+        .unwrap();
+        let cost = |tags| profile.cost(&segment(tags), Objective::Cost, None);
+        assert_eq!(cost(&[]), Some(8.0));
+        assert_eq!(cost(&[("highway", "primary")]), Some(2.0));
+        assert_eq!(cost(&[("highway", "track")]), Some(4.0));
+        let gravel = segment(&[("highway", "track"), ("surface", "gravel"), ("lit", "no")]);
+        assert_eq!(profile.cost(&gravel, Objective::Cost, None), Some(8.0));
+        assert_eq!(profile.cost(&gravel, Objective::Time, None), Some(4.0));
+        assert_eq!(profile.cost(&gravel, Objective::Distance, None), Some(2.0));
+        assert_eq!(profile.speed(&gravel, None), 20.0);
+        assert_eq!(cost(&[("highway", "motorway")]), None);
+        assert_eq!(cost(&[("access", "private")]), None);
+    }
+
+    #[test]
+    fn class_speeds() {
+        let profile = Profile::from_json(
+            r#"{
+                "default_speed": 10,
+                "speeds": {"surface=gravel": 15},
+                "class_speeds": {"primary": 60, "track": 20}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            profile.speed(&segment(&[("highway", "primary")]), None),
+            60.0
+        );
+        // OpenMapTiles classes work as well.
+        assert_eq!(profile.speed(&segment(&[("class", "track")]), None), 20.0);
+        let gravel = segment(&[("highway", "track"), ("surface", "gravel")]);
+        assert_eq!(profile.speed(&gravel, None), 15.0);
+        assert_eq!(profile.speed(&segment(&[("highway", "path")]), None), 10.0);
+        assert_eq!(profile.cost(&gravel, Objective::Time, None), Some(8.0));
+        assert_eq!(
+            Profile::from_json(r#"{"class_speeds": {"primary": "fast"}}"#),
+            Err(ProfileError::InvalidDefinition("invalid class_speeds"))
+        );
+    }
+
+    #[test]
+    fn delays() {
+        let profile = Profile::from_json(
+            r#"{
+                "default_speed": 36,
+                "delays": {"highway=traffic_signals": 20, "crossing": 5}
+            }"#,
+        )
+        .unwrap();
+        let mut connector = Connector::new("a", &crate::geo_types::Point::new(0.0, 0.0));
+        assert_eq!(profile.delay(&connector), 0.0);
+        connector.set_tag("highway", "traffic_signals");
+        connector.set_tag("crossing", "traffic_signals");
+        assert_eq!(profile.delay(&connector), 25.0);
+        // 250 m at 10 m/s, in units of the segment length.
+        let segment = segment(&[]);
+        let meters = Into::<geo::LineString<f64>>::into(segment.get_geometry()).haversine_length();
+        let cost = profile.delay_cost(&connector, &segment, Objective::Time, DistanceMode::Planar);
+        assert!((cost - 250.0 * segment.get_length() / meters).abs() < 1e-9);
+        assert_eq!(
+            profile.delay_cost(
+                &connector,
+                &segment,
+                Objective::Time,
+                DistanceMode::Geodesic
+            ),
+            250.0
+        );
+        assert_eq!(
+            profile.delay_cost(
+                &connector,
+                &segment,
+                Objective::Distance,
+                DistanceMode::Planar
+            ),
+            0.0
+        );
+    }
+
+    #[test]
+    fn invalid_definitions() {
+        assert_eq!(Profile::from_json("{"), Err(ProfileError::InvalidJson));
+        assert_eq!(
+            Profile::from_json(r#"{"speeds": {"highway": 0}}"#),
+            Err(ProfileError::InvalidDefinition("invalid speeds"))
+        );
+        assert_eq!(
+            Profile::from_json(r#"{"exclude": "highway"}"#),
+            Err(ProfileError::InvalidDefinition("invalid exclude"))
+        );
+        assert!(Profile::from_json("{}").is_ok());
+    }
+}
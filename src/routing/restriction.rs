@@ -0,0 +1,98 @@
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A restriction of the turns from a segment onto other segments at a
+/// connector, e.g. a prohibited left turn.
+pub struct TurnRestriction {
+    from: String,
+    via: String,
+    to: String,
+    /// Whether the turn is the only one allowed instead of prohibited.
+    only: bool,
+}
+
+#[wasm_bindgen]
+impl TurnRestriction {
+    #[wasm_bindgen(constructor)]
+    /// Prohibits turning from the segment onto the other segment at the
+    /// connector.
+    pub fn new(from: String, via: String, to: String) -> TurnRestriction {
+        TurnRestriction {
+            from,
+            via,
+            to,
+            only: false,
+        }
+    }
+
+    /// Allows only turning from the segment onto the other segment at the
+    /// connector, e.g. for `only_straight_on`.
+    pub fn only(from: String, via: String, to: String) -> TurnRestriction {
+        TurnRestriction {
+            only: true,
+            ..TurnRestriction::new(from, via, to)
+        }
+    }
+
+    /// Returns the ID of the segment the turn starts on.
+    pub fn get_from(&self) -> String {
+        self.from.clone()
+    }
+
+    /// Returns the ID of the connector of the turn.
+    pub fn get_via(&self) -> String {
+        self.via.clone()
+    }
+
+    /// Returns the ID of the segment the turn ends on.
+    pub fn get_to(&self) -> String {
+        self.to.clone()
+    }
+
+    /// Returns whether the turn is the only one allowed instead of
+    /// prohibited.
+    pub fn is_only(&self) -> bool {
+        self.only
+    }
+}
+
+impl TurnRestriction {
+    /// Returns the ID of the segment the turn starts on without copying it.
+    pub(crate) fn from(&self) -> &str {
+        &self.from
+    }
+
+    /// Returns the connector ID without copying it.
+    pub(crate) fn via(&self) -> &str {
+        &self.via
+    }
+
+    /// Returns the ID of the segment the turn ends on without copying it.
+    pub(crate) fn to(&self) -> &str {
+        &self.to
+    }
+
+    /// Returns whether the restriction prohibits turning from the segment
+    /// onto the other segment at its connector.
+    pub(crate) fn prohibits(&self, from: &str, to: &str) -> bool {
+        self.from == from && (self.to == to) != self.only
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prohibits() {
+        let no = TurnRestriction::new("a".into(), "x".into(), "b".into());
+        assert!(no.prohibits("a", "b"));
+        assert!(!no.prohibits("a", "c"));
+        assert!(!no.prohibits("c", "b"));
+        let only = TurnRestriction::only("a".into(), "x".into(), "b".into());
+        assert!(!only.prohibits("a", "b"));
+        assert!(only.prohibits("a", "c"));
+        assert!(!only.prohibits("c", "a"));
+    }
+}
@@ -1,7 +1,15 @@
 use crate::debug::debug_log;
-use crate::geo_types::Point;
+use crate::geo_types::geojson::{bbox_member, round_bbox, round_position, GeoJsonError, Geometry};
+use crate::geo_types::{LineString, Point, Rect};
 use crate::routing::router::Segment;
-use ::geo::{LineInterpolatePoint, LineLocatePoint};
+use crate::routing::SearchStats;
+use ::geo::{
+    BoundingRect, ChaikinSmoothing, ClosestPoint, HaversineBearing, HaversineDistance,
+    HaversineLength, LineInterpolatePoint, LineLocatePoint,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
 use wasm_bindgen::prelude::*;
 
 #[derive(Debug, Clone)]
@@ -16,31 +24,72 @@ pub struct RouteSegment {
     start: f64,
     /// The end position on this segment (0..1).
     stop: f64,
+    /// Estimated duration in seconds of travelling the part.
+    duration: Option<f64>,
+    /// Number of the exit the route takes if the part enters a roundabout.
+    roundabout_exit: Option<u32>,
 }
 
 #[wasm_bindgen]
 impl RouteSegment {
     #[wasm_bindgen(constructor)]
+    /// Creates a part of the segment from start to stop position.
     pub fn new(segment: &Segment, start: f64, stop: f64) -> RouteSegment {
         RouteSegment {
             segment: (*segment).clone(),
             start,
             stop,
+            duration: None,
+            roundabout_exit: None,
         }
     }
 
+    /// Returns the whole segment.
     pub fn get_segment(&self) -> Segment {
         self.segment.clone()
     }
 
+    /// Returns the start position on the segment (0..1).
     pub fn get_start(&self) -> f64 {
         self.start
     }
 
+    /// Returns the end position on the segment (0..1).
     pub fn get_stop(&self) -> f64 {
         self.stop
     }
 
+    /// Returns the length in meters of the travelled part of the segment.
+    pub fn get_length(&self) -> f64 {
+        self.get_travelled_geometry().haversine_length()
+    }
+
+    /// Returns the estimated duration in seconds of travelling the part of
+    /// the segment by the speed of the profile used, `undefined` if no
+    /// profile was used. Delays at connectors are only part of the duration
+    /// of the route.
+    pub fn get_duration_seconds(&self) -> Option<f64> {
+        self.duration
+    }
+
+    /// Returns the length of the travelled part of the segment in the units
+    /// of the coordinates, as used by route searches in planar mode.
+    pub fn get_planar_length(&self) -> f64 {
+        (self.stop - self.start).abs() * self.segment.get_length()
+    }
+
+    /// Returns the level of the segment relative to the ground, e.g. to draw
+    /// bridges above other segments, see `Segment.get_level`.
+    pub fn get_level(&self) -> i32 {
+        self.segment.get_level()
+    }
+
+    /// Returns the number of the exit the route takes if the part enters a
+    /// roundabout, e.g. 2 for the second exit, `undefined` otherwise.
+    pub fn get_roundabout_exit(&self) -> Option<u32> {
+        self.roundabout_exit
+    }
+
     /// Cuts the geometry of the segment at the start and stop positions.
     fn get_cutted_geometry(&self) -> geo::LineString<f64> {
         let linestring = Into::<geo::LineString<f64>>::into(self.segment.get_geometry().clone());
@@ -52,6 +101,10 @@ impl RouteSegment {
 
         let starting_point = linestring.line_interpolate_point(start).unwrap();
         let stopping_point = linestring.line_interpolate_point(stop).unwrap();
+        if start == stop {
+            // Without length, the line consists of the point twice.
+            return geo::LineString::new(vec![starting_point.0, stopping_point.0]);
+        }
 
         debug_log!("cut geometry {:?} at {:?}, {:?}", linestring, start, stop);
         let coords: Vec<_> = linestring.clone().into_inner();
@@ -76,39 +129,248 @@ impl RouteSegment {
         if self.stop != 1.0 {
             filtered.push(stopping_point.0);
         }
-        let coords_cloned: Vec<geo::Coord<f64>> =
-            filtered.into_iter().map(|coord| coord.clone()).collect();
-
-        let new = geo::LineString::new(coords_cloned);
+        let new = geo::LineString::new(filtered);
         debug_log!("new geometry {:?}", new);
         new
     }
 
-    /// Returns a GeoJSON feature representation of the route segment.
+    /// Returns the cut geometry in the direction of travel.
+    fn get_travelled_geometry(&self) -> geo::LineString<f64> {
+        let mut geometry = self.get_cutted_geometry();
+        if self.start > self.stop {
+            geometry.0.reverse();
+        }
+        geometry
+    }
+
+    /// Returns the bounding box of the travelled part of the segment.
+    fn bbox(&self) -> Option<Rect> {
+        self.get_cutted_geometry().bounding_rect().map(Rect::from)
+    }
+
+    /// Returns a GeoJSON feature representation of the route segment with a
+    /// `bbox` member.
     pub fn to_geojson(&self) -> String {
         let mut coordinates_str = String::new();
         for coordinate in self.get_cutted_geometry() {
             if !coordinates_str.is_empty() {
                 coordinates_str.push_str(", ");
             }
-            coordinates_str.push_str(&format!("[{}, {}]", coordinate.x, coordinate.y));
+            let [x, y] = round_position(coordinate.x, coordinate.y);
+            coordinates_str.push_str(&format!("[{}, {}]", x, y));
         }
         format!(
             r#"{{
             "type": "Feature",
             "id": "{}",
-            "geometry": {{
+            {}"geometry": {{
                 "type": "LineString",
                 "coordinates": [{}]
             }},
             "properties": {{}}
         }}"#,
             self.segment.get_id(),
+            bbox_member(self.bbox()),
             coordinates_str
         )
     }
 }
 
+#[derive(Debug, Clone)]
+#[wasm_bindgen]
+/// A junction where the route changes from one segment to the next.
+///
+/// Bearings are in degrees clockwise from north (0..360).
+pub struct Maneuver {
+    point: Point,
+    bearing_in: f64,
+    bearing_out: f64,
+    roundabout_exit: Option<u32>,
+}
+
+#[wasm_bindgen]
+impl Maneuver {
+    /// Returns the point of the junction.
+    pub fn get_point(&self) -> Point {
+        self.point.clone()
+    }
+
+    /// Returns the bearing of the route when arriving at the junction.
+    pub fn get_bearing_in(&self) -> f64 {
+        self.bearing_in
+    }
+
+    /// Returns the bearing of the route when leaving the junction.
+    pub fn get_bearing_out(&self) -> f64 {
+        self.bearing_out
+    }
+
+    /// Returns the number of the exit to take if the maneuver enters a
+    /// roundabout, e.g. 2 for "take the second exit", `undefined` otherwise.
+    pub fn get_roundabout_exit(&self) -> Option<u32> {
+        self.roundabout_exit
+    }
+
+    /// Returns the angle of the turn in degrees, from 0 for going straight
+    /// to 180 for turning around.
+    pub fn get_turn_angle(&self) -> f64 {
+        let angle = (self.bearing_out - self.bearing_in).rem_euclid(360.0);
+        angle.min(360.0 - angle)
+    }
+}
+
+/// Distance in meters up to which consecutive coordinates of a route are
+/// welded into one.
+const JOIN_TOLERANCE: f64 = 0.01;
+
+/// Smallest angle in degrees of a maneuver counted as turn.
+const TURN_ANGLE: f64 = 30.0;
+
+/// Values of the `highway` tag of major roads.
+const MAJOR_ROADS: [&str; 8] = [
+    "motorway",
+    "motorway_link",
+    "trunk",
+    "trunk_link",
+    "primary",
+    "primary_link",
+    "secondary",
+    "secondary_link",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+#[wasm_bindgen]
+/// Metrics of a route to rank alternatives by more than their length, see
+/// [`Route::get_metrics`].
+pub struct RouteMetrics {
+    distance: f64,
+    turns: usize,
+    sharpest_turn: f64,
+    distinct_segments: usize,
+    percent_major_roads: f64,
+}
+
+#[wasm_bindgen]
+impl RouteMetrics {
+    /// Returns the length of the route in meters.
+    pub fn get_distance(&self) -> f64 {
+        self.distance
+    }
+
+    /// Returns the number of maneuvers turning by more than 30 degrees.
+    pub fn get_turns(&self) -> usize {
+        self.turns
+    }
+
+    /// Returns the angle of the sharpest turn in degrees, 0 without
+    /// maneuvers.
+    pub fn get_sharpest_turn(&self) -> f64 {
+        self.sharpest_turn
+    }
+
+    /// Returns the number of distinct segment IDs of the route.
+    pub fn get_distinct_segments(&self) -> usize {
+        self.distinct_segments
+    }
+
+    /// Returns the percentage (0..100) of the length on major roads, i.e.
+    /// segments tagged `highway` with motorway, trunk, primary, secondary or
+    /// their links.
+    pub fn get_percent_major_roads(&self) -> f64 {
+        self.percent_major_roads
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[wasm_bindgen]
+/// The distance of a route on segments with one value of a tag, see
+/// [`Route::get_breakdown`].
+pub struct BreakdownEntry {
+    value: Option<String>,
+    distance: f64,
+}
+
+#[wasm_bindgen]
+impl BreakdownEntry {
+    /// Returns the value of the tag, `undefined` for segments without the
+    /// tag.
+    pub fn get_value(&self) -> Option<String> {
+        self.value.clone()
+    }
+
+    /// Returns the distance in meters.
+    pub fn get_distance(&self) -> f64 {
+        self.distance
+    }
+}
+
+#[derive(Debug, Clone)]
+#[wasm_bindgen]
+/// How closely a recorded trace followed a route, see
+/// [`Route::compare_trace`].
+pub struct TraceDeviation {
+    deviations: Vec<f64>,
+    percent_on_route: f64,
+    used_segments: Vec<String>,
+}
+
+#[wasm_bindgen]
+impl TraceDeviation {
+    /// Returns the distance of each trace point to the route in meters.
+    pub fn get_deviations(&self) -> Vec<f64> {
+        self.deviations.clone()
+    }
+
+    /// Returns the largest distance of a trace point to the route in meters,
+    /// 0 for an empty trace.
+    pub fn get_max_deviation(&self) -> f64 {
+        self.deviations.iter().cloned().fold(0.0, f64::max)
+    }
+
+    /// Returns the percentage (0..100) of trace points within the tolerance
+    /// of the route.
+    pub fn get_percent_on_route(&self) -> f64 {
+        self.percent_on_route
+    }
+
+    /// Returns the IDs of the route segments which trace points within the
+    /// tolerance are nearest to, in the order of the route.
+    pub fn get_used_segments(&self) -> Vec<String> {
+        self.used_segments.clone()
+    }
+}
+
+/// A route as plain object, see [`Route::to_js`].
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RouteObject {
+    stops: Vec<[f64; 2]>,
+    segments: Vec<RouteSegmentObject>,
+    geometry: Vec<[f64; 2]>,
+    summary: RouteSummary,
+}
+
+/// A route segment as plain object, see [`Route::to_js`].
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RouteSegmentObject {
+    id: String,
+    start: f64,
+    stop: f64,
+    level: i32,
+    distance: f64,
+    tags: BTreeMap<String, String>,
+}
+
+/// The summary of a route as plain object, see [`Route::to_js`].
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RouteSummary {
+    distance: f64,
+    duration: Option<f64>,
+    gap: Option<f64>,
+}
+
 #[derive(Debug, Clone)]
 #[wasm_bindgen]
 /// A calculated route.
@@ -117,43 +379,586 @@ pub struct Route {
     stops: Vec<Point>,
     /// Calculated segments.
     segments: Vec<RouteSegment>,
+    /// Statistics of the search.
+    stats: SearchStats,
+    /// Distance in meters from the end of a partial route to the stop.
+    gap: Option<f64>,
+    /// Estimated duration in seconds.
+    duration: Option<f64>,
+    /// Legs between consecutive stops of a route through several stops.
+    legs: Vec<Route>,
 }
 
 #[wasm_bindgen]
 impl Route {
     #[wasm_bindgen(constructor)]
+    /// Creates a route from its stops and segments.
     pub fn new(stops: Vec<Point>, segments: Vec<RouteSegment>) -> Route {
         Route {
             stops: stops.clone(),
             segments: segments.clone(),
+            stats: SearchStats::default(),
+            gap: None,
+            duration: None,
+            legs: Vec::new(),
         }
     }
 
+    /// Returns statistics of the search which found the route.
+    pub fn get_stats(&self) -> SearchStats {
+        self.stats.clone()
+    }
+
+    /// Returns the straight-line distance in meters from the end of the route
+    /// to the stop if the stop could not be reached (see
+    /// `RouteOptions.set_allow_partial`), `undefined` otherwise.
+    pub fn get_gap(&self) -> Option<f64> {
+        self.gap
+    }
+
+    /// Returns the length of the route in meters.
+    pub fn get_length(&self) -> f64 {
+        self.segments.iter().map(RouteSegment::get_length).sum()
+    }
+
+    /// Returns the length of the route in meters, like `get_length`.
+    pub fn get_distance(&self) -> f64 {
+        self.get_length()
+    }
+
+    /// Returns the number of segments of the route.
+    pub fn get_segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Returns the estimated duration of the route in seconds by the speeds
+    /// of the profile used, `undefined` if no profile was used.
+    pub fn get_duration(&self) -> Option<f64> {
+        self.duration
+    }
+
+    /// Returns the estimated duration of the route in seconds including
+    /// delays at connectors, like `get_duration`.
+    pub fn get_duration_seconds(&self) -> Option<f64> {
+        self.get_duration()
+    }
+
+    /// Returns the stops; first is the start, last is the finish.
     pub fn get_stops(&self) -> Vec<Point> {
         self.stops.clone()
     }
 
+    /// Returns the segments of the route in order.
     pub fn get_segments(&self) -> Vec<RouteSegment> {
         self.segments.clone()
     }
 
-    /// Returns the route as a GeoJSON collection of its segments.
+    #[wasm_bindgen(js_name = toJS)]
+    /// Returns the route as one plain object with its stops, segments,
+    /// geometry and a summary, e.g. to render it or post it to another
+    /// worker.
+    ///
+    /// All data is converted in a single call instead of a call per getter.
+    /// Points are `[x, y]` arrays, the segments have their `id`, `start`,
+    /// `stop`, `level`, `distance` and `tags`, the summary the `distance`,
+    /// `duration` and `gap` (`null` if unknown).
+    pub fn to_js(&self) -> Result<JsValue, JsValue> {
+        self.to_object()
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .map_err(JsValue::from)
+    }
+
+    /// Returns the legs between consecutive stops, e.g. of a route through
+    /// via points, each with its own stops, segments, distance and duration.
+    /// A route between two stops is its only leg.
+    pub fn get_legs(&self) -> Vec<Route> {
+        if self.legs.is_empty() {
+            vec![self.clone()]
+        } else {
+            self.legs.clone()
+        }
+    }
+
+    /// Returns the bounding box of the route geometry and its stops,
+    /// `undefined` for an empty route.
+    pub fn bbox(&self) -> Option<Rect> {
+        let segments = self.segments.iter().filter_map(|segment| {
+            segment
+                .get_cutted_geometry()
+                .bounding_rect()
+                .map(Rect::from)
+        });
+        let stops = self
+            .stops
+            .iter()
+            .map(|stop| Rect::new(stop.x(), stop.y(), stop.x(), stop.y()));
+        segments.chain(stops).reduce(|a, b| a.union(&b))
+    }
+
+    #[wasm_bindgen(js_name = findPoisAlong)]
+    /// Returns the point features of a GeoJSON FeatureCollection within
+    /// `max_distance` meters of the route as FeatureCollection, ordered by
+    /// their distance along the route.
+    ///
+    /// Each returned feature gets the properties `distance_from_route` and
+    /// `distance_along_route` in meters. Features of other geometry types
+    /// are ignored.
+    pub fn find_pois_along(
+        &self,
+        geojson: &str,
+        max_distance: f64,
+    ) -> Result<String, GeoJsonError> {
+        let value: Value = serde_json::from_str(geojson).or(Err(GeoJsonError::InvalidJson))?;
+        let features = value
+            .get("features")
+            .and_then(Value::as_array)
+            .ok_or(GeoJsonError::InvalidGeometry("missing features"))?;
+        let geometry = self.get_geometry();
+        let mut found = Vec::new();
+        for feature in features {
+            let point = match Geometry::from_value(feature) {
+                Ok(Geometry::Point(point)) => point,
+                Ok(_) | Err(GeoJsonError::UnsupportedType(_)) => continue,
+                Err(err) => return Err(err),
+            };
+            let (distance, along) = match locate_on_line(&geometry, &point.into()) {
+                Some(location) if location.0 <= max_distance => location,
+                _ => continue,
+            };
+            let mut feature = feature.clone();
+            if !feature.get("properties").is_some_and(Value::is_object) {
+                feature["properties"] = serde_json::json!({});
+            }
+            feature["properties"]["distance_from_route"] = distance.into();
+            feature["properties"]["distance_along_route"] = along.into();
+            found.push((along, feature));
+        }
+        found.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(serde_json::json!({
+            "type": "FeatureCollection",
+            "features": found.into_iter().map(|(_, feature)| feature).collect::<Vec<_>>(),
+        })
+        .to_string())
+    }
+
+    /// Returns the junctions where the route changes segments in order.
+    ///
+    /// Segments without length, e.g. when the route starts on a connector,
+    /// are skipped.
+    pub fn get_maneuvers(&self) -> Vec<Maneuver> {
+        let mut geometries: Vec<(geo::LineString<f64>, Option<u32>)> = Vec::new();
+        // The exit of a part without length is kept for the next one.
+        let mut exit = None;
+        for segment in &self.segments {
+            let geometry = segment.get_travelled_geometry();
+            exit = exit.or(segment.roundabout_exit);
+            if geometry.0.windows(2).any(|pair| pair[0] != pair[1]) {
+                geometries.push((geometry, exit.take()));
+            }
+        }
+        geometries
+            .windows(2)
+            .map(|pair| {
+                let point = *pair[0].0 .0.last().unwrap();
+                // Both geometries have distinct coordinates.
+                let before = pair[0].0 .0.iter().rev().find(|coord| **coord != point);
+                let after = pair[1].0 .0.iter().find(|coord| **coord != point);
+                Maneuver {
+                    point: geo::Point::from(point).into(),
+                    bearing_in: bearing(*before.unwrap(), point),
+                    bearing_out: bearing(point, *after.unwrap()),
+                    roundabout_exit: pair[1].1,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns metrics of the route, e.g. to rank alternative routes.
+    pub fn get_metrics(&self) -> RouteMetrics {
+        let angles: Vec<_> = self
+            .get_maneuvers()
+            .iter()
+            .map(Maneuver::get_turn_angle)
+            .collect();
+        let lengths: Vec<_> = self
+            .segments
+            .iter()
+            .map(|segment| segment.get_length())
+            .collect();
+        let distance: f64 = lengths.iter().sum();
+        let major: f64 = self
+            .segments
+            .iter()
+            .zip(&lengths)
+            .filter(|(segment, _)| {
+                segment
+                    .segment
+                    .attributes()
+                    .class()
+                    .is_some_and(|highway| MAJOR_ROADS.contains(&highway))
+            })
+            .map(|(_, length)| length)
+            .sum();
+        RouteMetrics {
+            distance,
+            turns: angles.iter().filter(|angle| **angle > TURN_ANGLE).count(),
+            sharpest_turn: angles.iter().cloned().fold(0.0, f64::max),
+            distinct_segments: self
+                .segments
+                .iter()
+                .map(|segment| segment.segment.get_id())
+                .collect::<HashSet<_>>()
+                .len(),
+            percent_major_roads: if distance > 0.0 {
+                major / distance * 100.0
+            } else {
+                0.0
+            },
+        }
+    }
+
+    /// Returns the distance by value of the tag, e.g. `highway` for road
+    /// classes or `surface`, longest first. Segments without the tag are
+    /// summed up in one entry without value.
+    pub fn get_breakdown(&self, key: &str) -> Vec<BreakdownEntry> {
+        let mut distances: Vec<(Option<String>, f64)> = Vec::new();
+        for segment in &self.segments {
+            let value = segment.segment.tag(key).map(String::from);
+            let length = segment.get_length();
+            match distances.iter_mut().find(|(other, _)| *other == value) {
+                Some((_, distance)) => *distance += length,
+                None => distances.push((value, length)),
+            }
+        }
+        // Stable, so equal distances keep the order along the route.
+        distances.sort_by(|a, b| b.1.total_cmp(&a.1));
+        distances
+            .into_iter()
+            .map(|(value, distance)| BreakdownEntry { value, distance })
+            .collect()
+    }
+
+    #[wasm_bindgen(js_name = compareTrace)]
+    /// Compares a recorded trace, e.g. of GPS positions, with the route.
+    ///
+    /// Trace points within `tolerance` meters of the route count as on the
+    /// route. This is no map matching: each point is compared with the
+    /// nearest part of the route regardless of the order of the points.
+    pub fn compare_trace(&self, trace: &LineString, tolerance: f64) -> TraceDeviation {
+        let geometries: Vec<_> = self
+            .segments
+            .iter()
+            .map(RouteSegment::get_travelled_geometry)
+            .collect();
+        let mut used = vec![false; self.segments.len()];
+        let mut deviations = Vec::new();
+        for coord in Into::<geo::LineString<f64>>::into(trace.clone()) {
+            let nearest = geometries
+                .iter()
+                .enumerate()
+                .filter_map(|(index, geometry)| {
+                    locate_on_line(geometry, &coord.into()).map(|(distance, _)| (index, distance))
+                })
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            match nearest {
+                Some((index, distance)) => {
+                    used[index] |= distance <= tolerance;
+                    deviations.push(distance);
+                }
+                None => deviations.push(f64::INFINITY),
+            }
+        }
+        let on_route = deviations
+            .iter()
+            .filter(|deviation| **deviation <= tolerance)
+            .count();
+        TraceDeviation {
+            percent_on_route: if deviations.is_empty() {
+                0.0
+            } else {
+                on_route as f64 * 100.0 / deviations.len() as f64
+            },
+            deviations,
+            used_segments: self
+                .segments
+                .iter()
+                .zip(used)
+                .filter(|(_, used)| *used)
+                .map(|(segment, _)| segment.segment.get_id())
+                .collect(),
+        }
+    }
+
+    /// Returns the geometry of the route smoothed with Chaikin's algorithm to
+    /// display routes over coarse tile geometries without sharp corners.
+    ///
+    /// Each iteration doubles the number of coordinates, so it is limited to
+    /// 8. Start and end are kept. The smoothed geometry is for display only;
+    /// lengths and other results of the route are not affected.
+    pub fn get_smoothed_geometry(&self, iterations: u32) -> LineString {
+        self.get_geometry()
+            .chaikin_smoothing(iterations.min(8) as usize)
+            .into()
+    }
+
+    /// Returns the data of [`Route::to_js`].
+    pub(crate) fn to_object(&self) -> RouteObject {
+        let point = |point: &Point| [point.x(), point.y()];
+        let segments: Vec<_> = self
+            .segments
+            .iter()
+            .map(|segment| RouteSegmentObject {
+                id: segment.segment.get_id(),
+                start: segment.start,
+                stop: segment.stop,
+                level: segment.get_level(),
+                distance: segment.get_length(),
+                tags: segment
+                    .segment
+                    .tags()
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect(),
+            })
+            .collect();
+        RouteObject {
+            stops: self.stops.iter().map(point).collect(),
+            summary: RouteSummary {
+                distance: segments.iter().map(|segment| segment.distance).sum(),
+                duration: self.duration,
+                gap: self.gap,
+            },
+            segments,
+            geometry: self
+                .get_geometry()
+                .coords()
+                .map(|coord| [coord.x, coord.y])
+                .collect(),
+        }
+    }
+
+    /// Returns the geometry of all segments merged in the direction of
+    /// travel.
+    ///
+    /// The segments are welded at their joins: the shared coordinate is kept
+    /// once, also if the cut geometries miss each other by up to
+    /// [`JOIN_TOLERANCE`]. The same applies to the points interpolated at the
+    /// cuts and the coordinates next to them.
+    pub(crate) fn get_geometry(&self) -> geo::LineString<f64> {
+        let mut coords: Vec<geo::Coord<f64>> = Vec::new();
+        for segment in &self.segments {
+            for coord in segment.get_travelled_geometry() {
+                let welded = coords.last().is_some_and(|last| {
+                    geo::Point::from(*last).haversine_distance(&geo::Point::from(coord))
+                        <= JOIN_TOLERANCE
+                });
+                if !welded {
+                    coords.push(coord);
+                }
+            }
+        }
+        // Routes without length, e.g. from a stop at the start, keep the
+        // point twice to remain a line.
+        if let [coord] = coords[..] {
+            coords.push(coord);
+        }
+        geo::LineString::new(coords)
+    }
+
+    /// Returns the statistics for updates while the route is found.
+    pub(crate) fn stats_mut(&mut self) -> &mut SearchStats {
+        &mut self.stats
+    }
+
+    /// Joins the routes of consecutive legs, e.g. between via points, into a
+    /// single route with the stops of all legs.
+    ///
+    /// Search statistics are summed up except for the snap distances of the
+    /// first start and the last stop.
+    pub(crate) fn join(legs: &[Route]) -> Route {
+        let mut stops: Vec<_> = legs
+            .iter()
+            .take(1)
+            .flat_map(|leg| leg.stops.first())
+            .collect();
+        stops.extend(legs.iter().flat_map(|leg| leg.stops.last()));
+        let mut route = Route::new(
+            stops.into_iter().cloned().collect(),
+            legs.iter().flat_map(|leg| leg.segments.clone()).collect(),
+        );
+        for leg in legs {
+            route.stats.nodes_expanded += leg.stats.nodes_expanded;
+            route.stats.heap_pushes += leg.stats.heap_pushes;
+            route.stats.tiles_used += leg.stats.tiles_used;
+        }
+        if let (Some(first), Some(last)) = (legs.first(), legs.last()) {
+            route.stats.start_snap_distance = first.stats.start_snap_distance;
+            route.stats.stop_snap_distance = last.stats.stop_snap_distance;
+        }
+        route.duration = legs.iter().map(|leg| leg.duration).sum();
+        route.legs = legs.iter().flat_map(Route::get_legs).collect();
+        route
+    }
+
+    /// Estimates the duration of the route by the speeds in km/h of its
+    /// segments plus the delay in seconds, e.g. at traffic signals.
+    pub(crate) fn estimate_duration(&mut self, speed: impl Fn(&Segment) -> f64, delay: f64) {
+        let mut travel = 0.0;
+        for segment in &mut self.segments {
+            let duration = segment.get_length() / (speed(&segment.segment) / 3.6);
+            segment.duration = Some(duration);
+            travel += duration;
+        }
+        self.duration = Some(travel + delay);
+    }
+
+    /// Sets the number of the exit taken from the roundabout entered by the
+    /// segment with the index.
+    pub(crate) fn set_roundabout_exit(&mut self, segment: usize, exit: u32) {
+        self.segments[segment].roundabout_exit = Some(exit);
+    }
+
+    /// Marks the route as partial, ending the given distance from the stop.
+    pub(crate) fn set_gap(&mut self, gap: Option<f64>) {
+        self.gap = gap;
+    }
+
+    #[wasm_bindgen(js_name = toFeatureCollection)]
+    /// Returns everything to draw the route as one GeoJSON FeatureCollection,
+    /// so a single map source renders it.
+    ///
+    /// Each feature has a `kind` property: `route` for the line of the route
+    /// with its `distance` and `duration`, `stop` for the given stops and
+    /// `snapped` for the positions they were snapped to on the network, both
+    /// with the `index` of the stop. The features of the optional `debug`
+    /// FeatureCollection, e.g. of `Router.isochrone` or a coverage grid, are
+    /// added with kind `debug`. The `bbox` members of the collection and of
+    /// the route line span the route and its stops, not the debug features.
+    pub fn to_feature_collection(&self, debug: Option<String>) -> Result<String, GeoJsonError> {
+        let point = |coord: geo::Coord<f64>, kind: &str, index: usize| {
+            serde_json::json!({
+                "type": "Feature",
+                "geometry": {"type": "Point", "coordinates": round_position(coord.x, coord.y)},
+                "properties": {"kind": kind, "index": index},
+            })
+        };
+        let line = self.get_geometry();
+        let geometry: Vec<_> = line
+            .coords()
+            .map(|coord| round_position(coord.x, coord.y))
+            .collect();
+        let mut features = vec![serde_json::json!({
+            "type": "Feature",
+            "geometry": {"type": "LineString", "coordinates": geometry},
+            "properties": {
+                "kind": "route",
+                "distance": self.get_distance(),
+                "duration": self.duration,
+            },
+        })];
+        for (index, stop) in self.stops.iter().enumerate() {
+            features.push(point(geo::Point::from(stop.clone()).0, "stop", index));
+        }
+        // The legs start at the snapped stops, the last leg ends at the
+        // snapped finish. Legs without segments were not snapped.
+        let legs = self.get_legs();
+        for (index, leg) in legs.iter().enumerate() {
+            let geometry = leg.get_geometry();
+            if let Some(coord) = geometry.0.first() {
+                features.push(point(*coord, "snapped", index));
+            }
+            if index + 1 == legs.len() {
+                if let Some(coord) = geometry.0.last() {
+                    features.push(point(*coord, "snapped", index + 1));
+                }
+            }
+        }
+        if let Some(debug) = debug {
+            let value: Value = serde_json::from_str(&debug).or(Err(GeoJsonError::InvalidJson))?;
+            let debug_features = value
+                .get("features")
+                .and_then(Value::as_array)
+                .ok_or(GeoJsonError::InvalidGeometry("missing features"))?;
+            for feature in debug_features {
+                let mut feature = feature.clone();
+                if !feature.get("properties").is_some_and(Value::is_object) {
+                    feature["properties"] = serde_json::json!({});
+                }
+                feature["properties"]["kind"] = "debug".into();
+                features.push(feature);
+            }
+        }
+        if let Some(bbox) = line.bounding_rect() {
+            features[0]["bbox"] = round_bbox(&bbox.into()).into();
+        }
+        let mut collection = serde_json::json!({"type": "FeatureCollection", "features": features});
+        if let Some(bbox) = self.bbox() {
+            collection["bbox"] = round_bbox(&bbox).into();
+        }
+        Ok(collection.to_string())
+    }
+
+    /// Returns the route as a GeoJSON collection of its segments. The
+    /// collection and each feature have a `bbox` member.
     pub fn get_segments_as_geojson(&self) -> String {
         let mut features = Vec::new();
         for segment in &self.segments {
             features.push(segment.to_geojson());
         }
+        let bbox = self
+            .segments
+            .iter()
+            .filter_map(RouteSegment::bbox)
+            .reduce(|a, b| a.union(&b));
         format!(
             r#"
                 {{
                     "type": "FeatureCollection",
-                    "features": [{}]
+                    {}"features": [{}]
                 }}"#,
+            bbox_member(bbox),
             features.join(",")
         )
     }
 }
 
+/// Returns the bearing from one coordinate to another in degrees clockwise from
+/// north (0..360).
+fn bearing(from: geo::Coord<f64>, to: geo::Coord<f64>) -> f64 {
+    geo::Point::from(from)
+        .haversine_bearing(to.into())
+        .rem_euclid(360.0)
+}
+
+/// Returns the distance of the point to the closest point of the line and the
+/// length of the line up to that point, both in meters. `None` for an empty
+/// line.
+pub(crate) fn locate_on_line(
+    line: &geo::LineString<f64>,
+    point: &geo::Point<f64>,
+) -> Option<(f64, f64)> {
+    if line.0.len() == 1 {
+        return Some((point.haversine_distance(&line.0[0].into()), 0.0));
+    }
+    let mut best: Option<(f64, f64)> = None;
+    let mut length = 0.0;
+    for part in line.lines() {
+        let closest = match part.closest_point(point) {
+            geo::Closest::Intersection(closest) | geo::Closest::SinglePoint(closest) => closest,
+            geo::Closest::Indeterminate => part.start_point(),
+        };
+        let distance = point.haversine_distance(&closest);
+        if best.is_none_or(|(best_distance, _)| distance < best_distance) {
+            best = Some((
+                distance,
+                length + part.start_point().haversine_distance(&closest),
+            ));
+        }
+        length += part.start_point().haversine_distance(&part.end_point());
+    }
+    best
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -194,6 +999,14 @@ mod tests {
             assert_eq!(cutted.0[3], coord!(x: 7.5, y: 0.0).into());
             assert_eq!(cutted.0.len(), 4);
         }
+        // Without length, on a coordinate or between coordinates.
+        for position in [0.6, 0.65] {
+            segment.start = position;
+            segment.stop = position;
+            let cutted = segment.get_cutted_geometry();
+            assert_eq!(cutted.0.len(), 2);
+            assert_eq!(cutted.0[0], cutted.0[1]);
+        }
     }
 
     #[test]
@@ -214,4 +1027,468 @@ mod tests {
         let cutted = segment.get_cutted_geometry();
         assert_eq!(cutted.0.len(), 2);
     }
+
+    #[test]
+    pub fn bbox() {
+        let segment = RouteSegment::new(
+            &Segment::new(
+                "foo".into(),
+                LineString::new(vec![coord!(x: 0.0, y: 0.0), coord!(x: 10.0, y: 2.0)]),
+                Vec::new(),
+            ),
+            0.5,
+            1.0,
+        );
+        let route = Route::new(
+            vec![Point::new(5.0, -1.0), Point::new(10.0, 2.0)],
+            vec![segment],
+        );
+        assert_eq!(route.bbox().unwrap().to_array(), vec![5.0, -1.0, 10.0, 2.0]);
+        assert_eq!(Route::new(vec![], vec![]).bbox(), None);
+    }
+
+    #[test]
+    pub fn find_pois_along() {
+        let route = Route::new(
+            vec![Point::new(0.0, 0.0), Point::new(0.02, 0.0)],
+            vec![
+                RouteSegment::new(
+                    &Segment::new(
+                        "a".into(),
+                        LineString::new(vec![coord!(x: 0.0, y: 0.0), coord!(x: 0.01, y: 0.0)]),
+                        Vec::new(),
+                    ),
+                    0.0,
+                    1.0,
+                ),
+                // Travelled against its direction.
+                RouteSegment::new(
+                    &Segment::new(
+                        "b".into(),
+                        LineString::new(vec![coord!(x: 0.02, y: 0.0), coord!(x: 0.01, y: 0.0)]),
+                        Vec::new(),
+                    ),
+                    1.0,
+                    0.0,
+                ),
+            ],
+        );
+        let pois = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "id": "late", "properties": {"name": "x"},
+             "geometry": {"type": "Point", "coordinates": [0.015, 0.0001]}},
+            {"type": "Feature", "id": "far", "properties": {},
+             "geometry": {"type": "Point", "coordinates": [0.005, 0.01]}},
+            {"type": "Feature", "id": "line", "properties": {},
+             "geometry": {"type": "LineString", "coordinates": [[0.0, 0.0], [0.01, 0.0]]}},
+            {"type": "Feature", "id": "early",
+             "geometry": {"type": "Point", "coordinates": [0.005, -0.0001]}}
+        ]}"#;
+        let found: Value =
+            serde_json::from_str(&route.find_pois_along(pois, 50.0).unwrap()).unwrap();
+        let features = found["features"].as_array().unwrap();
+        assert_eq!(features.len(), 2);
+        assert_eq!(features[0]["id"], "early");
+        assert_eq!(features[1]["id"], "late");
+        assert_eq!(features[1]["properties"]["name"], "x");
+        let distance = features[0]["properties"]["distance_from_route"]
+            .as_f64()
+            .unwrap();
+        assert!((distance - 11.1).abs() < 0.1);
+        let along = features[1]["properties"]["distance_along_route"]
+            .as_f64()
+            .unwrap();
+        assert!((along - 1668.0).abs() < 1.0);
+        assert_eq!(
+            route.find_pois_along("{}", 50.0),
+            Err(GeoJsonError::InvalidGeometry("missing features"))
+        );
+    }
+
+    #[test]
+    pub fn get_maneuvers() {
+        let segment = |id: &str, coords: Vec<Coord>, start, stop| {
+            RouteSegment::new(
+                &Segment::new(id.into(), LineString::new(coords), Vec::new()),
+                start,
+                stop,
+            )
+        };
+        let route = Route::new(
+            vec![Point::new(0.0, 0.0), Point::new(0.01, 0.01)],
+            vec![
+                // Starts on the connector.
+                segment(
+                    "x",
+                    vec![coord!(x: 0.0, y: 0.0), coord!(x: -0.01, y: 0.0)],
+                    0.0,
+                    0.0,
+                ),
+                segment(
+                    "a",
+                    vec![coord!(x: 0.0, y: 0.0), coord!(x: 0.01, y: 0.0)],
+                    0.0,
+                    1.0,
+                ),
+                segment(
+                    "b",
+                    vec![coord!(x: 0.01, y: 0.01), coord!(x: 0.01, y: 0.0)],
+                    1.0,
+                    0.0,
+                ),
+            ],
+        );
+        let maneuvers = route.get_maneuvers();
+        assert_eq!(maneuvers.len(), 1);
+        assert_eq!(
+            (maneuvers[0].get_point().x(), maneuvers[0].get_point().y()),
+            (0.01, 0.0)
+        );
+        assert!((maneuvers[0].get_bearing_in() - 90.0).abs() < 1e-3);
+        assert!(maneuvers[0].get_bearing_out().abs() < 1e-3);
+        assert!(Route::new(vec![], vec![]).get_maneuvers().is_empty());
+    }
+
+    #[test]
+    pub fn get_legs() {
+        let leg = |x: f64, duration: f64| {
+            let segment = Segment::new(
+                x.to_string(),
+                LineString::new(vec![coord!(x: x, y: 0.0), coord!(x: x + 0.01, y: 0.0)]),
+                Vec::new(),
+            );
+            let mut route = Route::new(
+                vec![Point::new(x, 0.0), Point::new(x + 0.01, 0.0)],
+                vec![RouteSegment::new(&segment, 0.0, 1.0)],
+            );
+            route.duration = Some(duration);
+            route
+        };
+        let single = leg(0.0, 60.0);
+        assert_eq!(single.get_legs().len(), 1);
+        assert_eq!(single.get_legs()[0].get_stops().len(), 2);
+
+        let route = Route::join(&[single, leg(0.01, 30.0), leg(0.02, 10.0)]);
+        let legs = route.get_legs();
+        assert_eq!(legs.len(), 3);
+        assert_eq!(legs[1].get_stops()[0].x(), 0.01);
+        assert_eq!(legs[1].get_segments()[0].get_segment().get_id(), "0.01");
+        assert_eq!(legs[1].get_duration(), Some(30.0));
+        let distance: f64 = legs.iter().map(Route::get_distance).sum();
+        assert!((distance - route.get_distance()).abs() < 1e-6);
+        // Joining joined routes keeps the legs between all stops.
+        assert_eq!(Route::join(&[route, leg(0.03, 5.0)]).get_legs().len(), 4);
+    }
+
+    #[test]
+    pub fn to_object() {
+        let mut segment = Segment::new(
+            "a".into(),
+            LineString::new(vec![coord!(x: 0.0, y: 0.0), coord!(x: 0.02, y: 0.0)]),
+            Vec::new(),
+        );
+        segment.set_tag("highway", "cycleway");
+        let mut route = Route::new(
+            vec![Point::new(0.0, 0.0), Point::new(0.01, 0.0)],
+            vec![RouteSegment::new(&segment, 0.0, 0.5)],
+        );
+        route.duration = Some(60.0);
+        let object = serde_json::to_value(route.to_object()).unwrap();
+        assert_eq!(
+            object["stops"],
+            serde_json::json!([[0.0, 0.0], [0.01, 0.0]])
+        );
+        assert_eq!(
+            object["geometry"],
+            serde_json::json!([[0.0, 0.0], [0.01, 0.0]])
+        );
+        let segment = &object["segments"][0];
+        assert_eq!(segment["id"], "a");
+        assert_eq!(segment["stop"], 0.5);
+        assert_eq!(segment["level"], 0);
+        assert_eq!(segment["tags"], serde_json::json!({"highway": "cycleway"}));
+        let distance = object["summary"]["distance"].as_f64().unwrap();
+        assert!((distance - route.get_distance()).abs() < 1e-6);
+        assert_eq!(object["summary"]["duration"], 60.0);
+        assert_eq!(object["summary"]["gap"], Value::Null);
+    }
+
+    #[test]
+    pub fn to_feature_collection() {
+        let segment = Segment::new(
+            "a".into(),
+            LineString::new(vec![coord!(x: 0.0, y: 0.0), coord!(x: 0.02, y: 0.0)]),
+            Vec::new(),
+        );
+        let route = Route::new(
+            vec![Point::new(0.001, 0.001), Point::new(0.01, 0.001)],
+            vec![RouteSegment::new(&segment, 0.05, 0.5)],
+        );
+        let debug = r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "geometry": {"type": "Point", "coordinates": [1, 2]}}
+        ]}"#;
+        let collection: Value =
+            serde_json::from_str(&route.to_feature_collection(Some(debug.into())).unwrap())
+                .unwrap();
+        let features = collection["features"].as_array().unwrap();
+        let kinds: Vec<_> = features
+            .iter()
+            .map(|feature| feature["properties"]["kind"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            kinds,
+            vec!["route", "stop", "stop", "snapped", "snapped", "debug"]
+        );
+        assert_eq!(
+            features[0]["geometry"]["coordinates"],
+            serde_json::json!([[0.001, 0.0], [0.01, 0.0]])
+        );
+        assert_eq!(features[0]["properties"]["duration"], Value::Null);
+        assert_eq!(
+            features[2]["geometry"]["coordinates"],
+            serde_json::json!([0.01, 0.001])
+        );
+        assert_eq!(features[4]["properties"]["index"], 1);
+        assert_eq!(
+            features[4]["geometry"]["coordinates"],
+            serde_json::json!([0.01, 0.0])
+        );
+        assert_eq!(
+            features[0]["bbox"],
+            serde_json::json!([0.001, 0.0, 0.01, 0.0])
+        );
+        assert_eq!(
+            collection["bbox"],
+            serde_json::json!([0.001, 0.0, 0.01, 0.001])
+        );
+        let segments: Value = serde_json::from_str(&route.get_segments_as_geojson()).unwrap();
+        assert_eq!(segments["bbox"], features[0]["bbox"]);
+        assert_eq!(segments["features"][0]["bbox"], features[0]["bbox"]);
+        assert_eq!(
+            route.to_feature_collection(Some("{}".into())).err(),
+            Some(GeoJsonError::InvalidGeometry("missing features"))
+        );
+    }
+
+    #[test]
+    pub fn get_metrics() {
+        let segment = |id: &str, coords: Vec<Coord>, highway: Option<&str>| {
+            let mut segment = Segment::new(id.into(), LineString::new(coords), Vec::new());
+            if let Some(highway) = highway {
+                segment.set_tag("highway", highway);
+            }
+            RouteSegment::new(&segment, 0.0, 1.0)
+        };
+        let route = Route::new(
+            vec![Point::new(0.0, 0.0), Point::new(0.0, 0.01)],
+            vec![
+                segment(
+                    "a",
+                    vec![coord!(x: 0.0, y: 0.0), coord!(x: 0.03, y: 0.0)],
+                    Some("primary"),
+                ),
+                // Slight bend, no turn.
+                segment(
+                    "b",
+                    vec![coord!(x: 0.03, y: 0.0), coord!(x: 0.04, y: 0.001)],
+                    Some("residential"),
+                ),
+                // Back onto "a", e.g. clipped at a tile border.
+                segment(
+                    "a",
+                    vec![coord!(x: 0.04, y: 0.001), coord!(x: 0.04, y: 0.01)],
+                    None,
+                ),
+            ],
+        );
+        let metrics = route.get_metrics();
+        assert!((metrics.get_distance() - route.get_distance()).abs() < 1e-6);
+        assert_eq!(route.get_segment_count(), 3);
+        let segments = route.get_segments();
+        assert!((segments[0].get_length() - 3335.8).abs() < 0.1);
+        assert!((segments[0].get_planar_length() - 0.03).abs() < 1e-12);
+        let length: f64 = segments.iter().map(RouteSegment::get_length).sum();
+        assert_eq!(route.get_length(), length);
+        assert_eq!(metrics.get_turns(), 1);
+        assert!((metrics.get_sharpest_turn() - 84.3).abs() < 0.1);
+        assert_eq!(metrics.get_distinct_segments(), 2);
+        let major = 0.03 / (0.03 + 0.01005 + 0.009);
+        assert!((metrics.get_percent_major_roads() - major * 100.0).abs() < 0.1);
+        assert_eq!(
+            Route::new(vec![], vec![])
+                .get_metrics()
+                .get_percent_major_roads(),
+            0.0
+        );
+    }
+
+    #[test]
+    pub fn get_breakdown() {
+        let segment = |id: &str, coords: Vec<Coord>, highway: Option<&str>| {
+            let mut segment = Segment::new(id.into(), LineString::new(coords), Vec::new());
+            if let Some(highway) = highway {
+                segment.set_tag("highway", highway);
+            }
+            RouteSegment::new(&segment, 0.0, 1.0)
+        };
+        let route = Route::new(
+            vec![Point::new(0.0, 0.0), Point::new(0.06, 0.0)],
+            vec![
+                segment(
+                    "a",
+                    vec![coord!(x: 0.0, y: 0.0), coord!(x: 0.01, y: 0.0)],
+                    Some("cycleway"),
+                ),
+                segment(
+                    "b",
+                    vec![coord!(x: 0.01, y: 0.0), coord!(x: 0.03, y: 0.0)],
+                    Some("primary"),
+                ),
+                segment(
+                    "c",
+                    vec![coord!(x: 0.03, y: 0.0), coord!(x: 0.05, y: 0.0)],
+                    Some("cycleway"),
+                ),
+                segment(
+                    "d",
+                    vec![coord!(x: 0.05, y: 0.0), coord!(x: 0.06, y: 0.0)],
+                    None,
+                ),
+            ],
+        );
+        let breakdown = route.get_breakdown("highway");
+        let values: Vec<_> = breakdown.iter().map(BreakdownEntry::get_value).collect();
+        assert_eq!(
+            values,
+            vec![Some("cycleway".into()), Some("primary".into()), None]
+        );
+        let meters_per_degree = 0.01f64.to_radians() * 6_371_008.8 / 0.01;
+        assert!((breakdown[0].get_distance() - 0.03 * meters_per_degree).abs() < 1.0);
+        assert!((breakdown[1].get_distance() - 0.02 * meters_per_degree).abs() < 1.0);
+        let total: f64 = breakdown.iter().map(BreakdownEntry::get_distance).sum();
+        assert!((total - route.get_distance()).abs() < 1e-6);
+        assert!(Route::new(vec![], vec![])
+            .get_breakdown("surface")
+            .is_empty());
+    }
+
+    #[test]
+    pub fn compare_trace() {
+        let segment = |id: &str, coords: Vec<Coord>| {
+            RouteSegment::new(
+                &Segment::new(id.into(), LineString::new(coords), Vec::new()),
+                0.0,
+                1.0,
+            )
+        };
+        let route = Route::new(
+            vec![Point::new(0.0, 0.0), Point::new(0.02, 0.0)],
+            vec![
+                segment("a", vec![coord!(x: 0.0, y: 0.0), coord!(x: 0.01, y: 0.0)]),
+                segment("b", vec![coord!(x: 0.01, y: 0.0), coord!(x: 0.02, y: 0.0)]),
+            ],
+        );
+        let trace = LineString::new(vec![
+            coord!(x: 0.001, y: 0.0001),
+            coord!(x: 0.005, y: 0.0),
+            coord!(x: 0.015, y: 0.001),
+            coord!(x: 0.009, y: -0.0002),
+        ]);
+        let deviation = route.compare_trace(&trace, 30.0);
+        let deviations = deviation.get_deviations();
+        assert_eq!(deviations.len(), 4);
+        assert!((deviations[0] - 11.1).abs() < 0.1);
+        assert_eq!(deviations[1], 0.0);
+        assert!((deviation.get_max_deviation() - 111.2).abs() < 0.1);
+        assert_eq!(deviation.get_percent_on_route(), 75.0);
+        assert_eq!(deviation.get_used_segments(), vec!["a".to_string()]);
+        let empty = route.compare_trace(&LineString::new(vec![]), 30.0);
+        assert_eq!(empty.get_percent_on_route(), 0.0);
+        assert_eq!(empty.get_max_deviation(), 0.0);
+    }
+
+    #[test]
+    pub fn get_smoothed_geometry() {
+        let route = Route::new(
+            vec![Point::new(0.0, 0.0), Point::new(1.0, 1.0)],
+            vec![RouteSegment::new(
+                &Segment::new(
+                    "a".into(),
+                    LineString::new(vec![
+                        coord!(x: 0.0, y: 0.0),
+                        coord!(x: 1.0, y: 0.0),
+                        coord!(x: 1.0, y: 1.0),
+                    ]),
+                    Vec::new(),
+                ),
+                0.0,
+                1.0,
+            )],
+        );
+        assert_eq!(route.get_smoothed_geometry(0).coordinate_count(), 3);
+        let smoothed = route.get_smoothed_geometry(1);
+        assert_eq!(
+            smoothed.to_array(),
+            vec![0.0, 0.0, 0.25, 0.0, 0.75, 0.0, 1.0, 0.25, 1.0, 0.75, 1.0, 1.0]
+        );
+        assert_eq!(route.get_smoothed_geometry(100).coordinate_count(), 768);
+    }
+
+    #[test]
+    pub fn get_geometry() {
+        let segment = |id: &str, coords: Vec<Coord>, start: f64, stop: f64| {
+            RouteSegment::new(
+                &Segment::new(id.into(), LineString::new(coords), Vec::new()),
+                start,
+                stop,
+            )
+        };
+        let route = Route::new(
+            vec![Point::new(0.0, 0.0), Point::new(0.02, 0.01)],
+            vec![
+                segment(
+                    "a",
+                    vec![coord!(x: 0.0, y: 0.0), coord!(x: 0.01, y: 0.0)],
+                    0.0,
+                    1.0,
+                ),
+                // Travelled backwards, ending a few millimeters off.
+                segment(
+                    "b",
+                    vec![coord!(x: 0.02, y: 0.0), coord!(x: 0.010_000_01, y: 0.0)],
+                    1.0,
+                    0.0,
+                ),
+                segment(
+                    "c",
+                    vec![coord!(x: 0.02, y: 0.0), coord!(x: 0.02, y: 0.01)],
+                    0.0,
+                    1.0,
+                ),
+            ],
+        );
+        assert_eq!(
+            route.get_geometry(),
+            geo::LineString::from(vec![(0.0, 0.0), (0.01, 0.0), (0.02, 0.0), (0.02, 0.01)])
+        );
+
+        // Gaps above the tolerance are kept.
+        let route = Route::new(
+            vec![Point::new(0.0, 0.0), Point::new(0.02, 0.0)],
+            vec![
+                segment(
+                    "a",
+                    vec![coord!(x: 0.0, y: 0.0), coord!(x: 0.01, y: 0.0)],
+                    0.0,
+                    1.0,
+                ),
+                segment(
+                    "b",
+                    vec![coord!(x: 0.0101, y: 0.0), coord!(x: 0.02, y: 0.0)],
+                    0.0,
+                    1.0,
+                ),
+            ],
+        );
+        assert_eq!(route.get_geometry().0.len(), 4);
+    }
 }
@@ -1,15 +1,30 @@
-use crate::debug::debug_log;
-use crate::geo_types::{LineString, Point};
-use crate::routing::{Route, RouteSegment};
+use crate::debug::{debug_log, span};
+use crate::geo_types::geojson::{bbox_member, round_position};
+use crate::geo_types::{LineString, Point, Rect};
+use crate::routing::conditional::Conditional;
+use crate::routing::graph::{Edge, Graph, PreparedGraph};
+use crate::routing::options::Via;
+use crate::routing::profile::{Profile, ProfileError};
+use crate::routing::{
+    Departure, Direction, DistanceMode, NetworkDelta, Route, RouteOptions, RouteSegment,
+    SearchStats, SegmentAttributes, TurnRestriction,
+};
+use crate::tile;
+use crate::tile::backend::FetchingError;
+use crate::tile::ParsedTile;
 use ::geo::Closest;
 use ::geo::ClosestPoint;
 use ::geo::EuclideanDistance;
 use ::geo::EuclideanLength;
+use ::geo::HaversineClosestPoint;
+use ::geo::HaversineDistance;
+use ::geo::HaversineLength;
 use ::geo::LineInterpolatePoint;
 use ::geo::LineLocatePoint;
 use geo::geometry as geo;
+use std::cell::OnceCell;
 use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
 use thiserror::Error;
 use wasm_bindgen::prelude::*;
 
@@ -19,25 +34,88 @@ use wasm_bindgen::prelude::*;
 pub struct Connector {
     id: String,
     point: Point,
+    /// Tags (properties) of the connector, e.g. `highway=traffic_signals`,
+    /// used by profiles.
+    tags: HashMap<String, String>,
 }
 
 #[wasm_bindgen]
 impl Connector {
     #[wasm_bindgen(constructor)]
+    /// Creates a connector at the point without tags.
     pub fn new(id: &str, point: &Point) -> Connector {
         Connector {
             id: id.into(),
             point: point.clone(),
+            tags: HashMap::new(),
         }
     }
 
+    /// Returns the ID of the connector.
     pub fn get_id(&self) -> String {
         self.id.clone()
     }
 
+    /// Returns the position of the connector.
     pub fn get_point(&self) -> Point {
         self.point.clone()
     }
+
+    /// Returns the value of the tag, `undefined` if the connector lacks it.
+    pub fn get_tag(&self, key: &str) -> Option<String> {
+        self.tags.get(key).cloned()
+    }
+
+    /// Sets the value of the tag.
+    pub fn set_tag(&mut self, key: &str, value: &str) {
+        self.tags.insert(key.into(), value.into());
+    }
+}
+
+impl Connector {
+    /// Returns the ID without copying it.
+    pub(crate) fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the value of the tag without copying it.
+    pub(crate) fn tag(&self, key: &str) -> Option<&str> {
+        self.tags.get(key).map(String::as_str)
+    }
+
+    /// Sets the GeoJSON properties (a JSON object) as tags, except the `id`.
+    pub(crate) fn set_properties_as_tags(&mut self, properties: Option<&serde_json::Value>) {
+        for (key, value) in property_tags(properties) {
+            self.set_tag(key, &value);
+        }
+    }
+
+    /// Returns all tags of the connector.
+    pub(crate) fn tags(&self) -> &HashMap<String, String> {
+        &self.tags
+    }
+}
+
+/// Returns the GeoJSON properties (a JSON object) usable as tags, i.e. all
+/// but `id` and `connector_ids` with string, number or boolean values.
+fn property_tags(
+    properties: Option<&serde_json::Value>,
+) -> impl Iterator<Item = (&String, String)> {
+    use serde_json::Value;
+    properties
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .filter_map(|(key, value)| {
+            let value = match value {
+                _ if key == "id" || key == "connector_ids" => return None,
+                Value::String(value) => value.clone(),
+                Value::Number(value) => value.to_string(),
+                Value::Bool(value) => value.to_string(),
+                _ => return None,
+            };
+            Some((key, value))
+        })
 }
 
 #[derive(Debug, Clone)]
@@ -48,37 +126,187 @@ pub struct Segment {
     geometry: LineString,
     /// List of connectors which are part of the segment.
     connectors: Vec<String>,
+    /// Length of the geometry.
+    length: f64,
+    /// Tags (properties) of the segment, e.g. `highway`, used by profiles.
+    tags: HashMap<String, String>,
+    /// Parsed conditional tags by the key they change, e.g. `access` for
+    /// `access:conditional`.
+    conditionals: HashMap<String, Conditional>,
+    /// Typed attributes parsed from the tags.
+    attributes: SegmentAttributes,
 }
 
 #[wasm_bindgen]
 impl Segment {
     #[wasm_bindgen(constructor)]
+    /// Creates a segment with the geometry passing the connectors with the
+    /// given IDs.
     pub fn new(id: String, geometry: LineString, connectors: Vec<String>) -> Segment {
+        #[cfg(feature = "console_error_panic_hook")]
         console_error_panic_hook::set_once();
+        let length = Into::<geo::LineString<f64>>::into(geometry.clone()).euclidean_length();
+        Segment::with_length(id, geometry, connectors, length)
+    }
+
+    /// Returns the length of the segment's geometry.
+    pub fn get_length(&self) -> f64 {
+        self.length
+    }
+
+    /// Returns the ID of the segment.
+    pub fn get_id(&self) -> String {
+        self.id.clone()
+    }
+
+    /// Returns the geometry of the segment.
+    pub fn get_geometry(&self) -> LineString {
+        self.geometry.clone()
+    }
+
+    /// Returns the value of the tag, `undefined` if the segment lacks it.
+    pub fn get_tag(&self, key: &str) -> Option<String> {
+        self.tags.get(key).cloned()
+    }
+
+    /// Sets the value of the tag.
+    ///
+    /// Conditional tags like `access:conditional=no @ (Mo-Fr 22:00-06:00)`
+    /// change the tag they refer to for routes with a departure in their time
+    /// windows, see `RouteOptions.set_departure`. The `oneway` tag sets the
+    /// direction of the segment.
+    pub fn set_tag(&mut self, key: &str, value: &str) {
+        if let Some(base) = key.strip_suffix(":conditional") {
+            self.conditionals
+                .insert(base.into(), Conditional::parse(value));
+        }
+        self.attributes.set(key, value);
+        self.tags.insert(key.into(), value.into());
+    }
+
+    /// Returns the typed attributes parsed from the tags.
+    pub fn get_attributes(&self) -> SegmentAttributes {
+        self.attributes.clone()
+    }
+
+    /// Returns the direction in which the segment can be travelled.
+    pub fn get_direction(&self) -> Direction {
+        self.attributes.get_direction()
+    }
+
+    /// Sets the direction in which the segment can be travelled, relative to
+    /// its geometry. Routes only use the segment in that direction.
+    pub fn set_direction(&mut self, direction: Direction) {
+        self.attributes.set_direction(direction);
+    }
+
+    /// Returns the level of the segment relative to the ground, e.g. 1 for
+    /// bridges and -1 for tunnels.
+    ///
+    /// The level is read from the `level` or `layer` tag, otherwise it is 1
+    /// for bridges, -1 for tunnels and 0 for all other segments.
+    pub fn get_level(&self) -> i32 {
+        let flag = |key| {
+            self.tag(key)
+                .is_some_and(|value| value != "no" && value != "false")
+        };
+        ["level", "layer"]
+            .iter()
+            .find_map(|key| self.tag(key).and_then(|value| value.trim().parse().ok()))
+            .unwrap_or(if flag("bridge") {
+                1
+            } else if flag("tunnel") {
+                -1
+            } else {
+                0
+            })
+    }
+
+    /// Returns whether the segment is part of a roundabout, i.e. it has the
+    /// tag `junction=roundabout` or `junction=circular`, or its geometry is a
+    /// closed loop.
+    pub fn is_roundabout(&self) -> bool {
+        if matches!(self.tag("junction"), Some("roundabout" | "circular")) {
+            return true;
+        }
+        let geometry = Into::<geo::LineString<f64>>::into(self.geometry.clone());
+        geometry.0.len() > 3 && geometry.is_closed()
+    }
+
+    /// Creates a segment with an already known length of its geometry.
+    pub(crate) fn with_length(
+        id: String,
+        geometry: LineString,
+        connectors: Vec<String>,
+        length: f64,
+    ) -> Segment {
         Segment {
             id,
             geometry,
             connectors,
+            length,
+            tags: HashMap::new(),
+            conditionals: HashMap::new(),
+            attributes: SegmentAttributes::default(),
         }
     }
 
-    pub fn get_id(&self) -> String {
-        return self.id.clone();
+    /// Returns the ID without copying it.
+    pub(crate) fn id(&self) -> &str {
+        &self.id
     }
 
-    pub fn get_geometry(&self) -> LineString {
-        return self.geometry.clone();
+    /// Returns the value of the tag without copying it.
+    pub(crate) fn tag(&self, key: &str) -> Option<&str> {
+        self.tags.get(key).map(String::as_str)
+    }
+
+    /// Returns the length of the geometry measured in the distance mode.
+    pub(crate) fn length(&self, mode: DistanceMode) -> f64 {
+        match mode {
+            DistanceMode::Planar => self.length,
+            DistanceMode::Geodesic => {
+                Into::<geo::LineString<f64>>::into(self.geometry.clone()).haversine_length()
+            }
+        }
+    }
+
+    /// Returns the value of the tag at the departure, changed by its
+    /// conditional tag if it applies.
+    pub(crate) fn tag_at(&self, key: &str, departure: Option<Departure>) -> Option<&str> {
+        departure
+            .and_then(|departure| self.conditionals.get(key)?.value_at(departure))
+            .or_else(|| self.tag(key))
     }
 
-    fn get_connectors(&self) -> &Vec<String> {
-        return &self.connectors;
+    /// Sets the GeoJSON properties (a JSON object) as tags, except the `id`
+    /// and `connector_ids`. Values other than strings, numbers and booleans
+    /// are skipped.
+    pub(crate) fn set_properties_as_tags(&mut self, properties: Option<&serde_json::Value>) {
+        for (key, value) in property_tags(properties) {
+            self.set_tag(key, &value);
+        }
+    }
+
+    /// Returns all tags of the segment.
+    pub(crate) fn tags(&self) -> &HashMap<String, String> {
+        &self.tags
+    }
+
+    /// Returns the typed attributes without copying them.
+    pub(crate) fn attributes(&self) -> &SegmentAttributes {
+        &self.attributes
+    }
+
+    pub(crate) fn get_connectors(&self) -> &Vec<String> {
+        &self.connectors
     }
 
     /// Returns the linear position of the given point on this segment.
-    fn get_point_position(&self, point: &Point) -> Option<f64> {
+    pub(crate) fn get_point_position(&self, point: &Point) -> Option<f64> {
         let geo_line_string = Into::<geo::LineString<f64>>::into(self.geometry.clone());
         let geo_point = &Into::<geo::Point<f64>>::into(point.clone());
-        let position = geo_line_string.line_locate_point(&geo_point);
+        let position = geo_line_string.line_locate_point(geo_point);
         debug_log!(
             "point position {:?} for linestring: {:?}, point: {:?}",
             position,
@@ -91,18 +319,34 @@ impl Segment {
 
 pub type Position = f64;
 
-#[derive(Debug)]
-/// A segment with a linear position on it.
-pub struct SegmentWithPosition<'a> {
-    segment: &'a Segment,
+/// Connectors which are crossings of segments at different levels, by their
+/// ID, with a connector for each level.
+pub(crate) type LevelCrossings<'a> = HashMap<&'a str, HashMap<i32, Connector>>;
+
+#[derive(Debug, Clone)]
+#[wasm_bindgen]
+/// A segment with a linear position on it, e.g. a point snapped to the
+/// network.
+pub struct SegmentWithPosition {
+    segment: Segment,
     position: Position,
+    /// Distance in meters from the snapped point.
+    distance: f64,
 }
 
-impl<'a> SegmentWithPosition<'a> {
-    pub fn get_segment(&self) -> &Segment {
-        self.segment
+#[wasm_bindgen]
+impl SegmentWithPosition {
+    /// Returns the segment.
+    pub fn get_segment(&self) -> Segment {
+        self.segment.clone()
+    }
+
+    /// Returns the ID of the segment.
+    pub fn get_segment_id(&self) -> String {
+        self.segment.get_id()
     }
 
+    /// Returns the linear position (0..1) on the segment.
     pub fn get_position(&self) -> Position {
         self.position
     }
@@ -114,33 +358,112 @@ impl<'a> SegmentWithPosition<'a> {
             .unwrap()
             .into()
     }
+
+    /// Returns the distance in meters between the snapped point and the
+    /// position.
+    pub fn get_distance(&self) -> f64 {
+        self.distance
+    }
 }
 
-#[derive(Debug)]
-enum Error {}
+impl SegmentWithPosition {
+    /// Returns the segment without copying it.
+    pub(crate) fn segment(&self) -> &Segment {
+        &self.segment
+    }
+}
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 #[wasm_bindgen]
+/// A router finding routes in a transport network.
 pub struct Router {
     segments: Vec<Segment>,
     connectors: Vec<Connector>,
+    /// Profiles by name, see [`Router::register_profile`].
+    profiles: HashMap<String, Profile>,
+    /// Graphs of the network by distance mode, prepared by the first search,
+    /// extended when features are added and dropped when the network
+    /// changes otherwise.
+    prepared: [OnceCell<PreparedGraph>; 2],
+    /// Turn restrictions by the ID of their connector.
+    restrictions: HashMap<String, Vec<TurnRestriction>>,
 }
 
 #[wasm_bindgen]
 impl Router {
     #[wasm_bindgen(constructor)]
+    /// Creates a router without any segments and connectors.
     pub fn new() -> Router {
-        Router {
-            segments: Vec::new(),
-            connectors: Vec::new(),
+        Router::default()
+    }
+
+    #[wasm_bindgen(js_name = registerProfile)]
+    /// Registers a profile defined as JSON (see [`crate::routing::profile`])
+    /// under the name, replacing a profile of the same name. Routes use it
+    /// if the name is set with `RouteOptions.set_profile`.
+    pub fn register_profile(&mut self, name: &str, definition: &str) -> Result<(), ProfileError> {
+        self.profiles
+            .insert(name.into(), Profile::from_json(definition)?);
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = pushTurnRestriction)]
+    /// Adds a turn restriction, which routes obey when turning at its
+    /// connector. Restrictions already known are skipped.
+    pub fn push_turn_restriction(&mut self, restriction: TurnRestriction) {
+        let restrictions = self
+            .restrictions
+            .entry(restriction.via().into())
+            .or_default();
+        if !restrictions.contains(&restriction) {
+            restrictions.push(restriction);
+        }
+    }
+
+    /// Adds the segments, connectors, turn restrictions and profiles of the
+    /// other router, e.g. local additions to a network loaded from tiles.
+    ///
+    /// Features of this router take priority: features of the other router
+    /// are skipped if this router has any feature with the same ID, like
+    /// tiles of a source with lower priority. Pieces of a segment clipped at
+    /// tile borders share the ID and are all kept from the same router.
+    pub fn extend(&mut self, other: &Router) {
+        let (segments, connectors) = (self.segments.len(), self.connectors.len());
+        let segment_ids: HashSet<_> = self.segments.iter().map(|s| s.id.clone()).collect();
+        let connector_ids: HashSet<_> = self.connectors.iter().map(|c| c.id.clone()).collect();
+        self.segments.extend(
+            other
+                .segments
+                .iter()
+                .filter(|segment| !segment_ids.contains(&segment.id))
+                .cloned(),
+        );
+        self.connectors.extend(
+            other
+                .connectors
+                .iter()
+                .filter(|connector| !connector_ids.contains(&connector.id))
+                .cloned(),
+        );
+        self.extend_prepared_graphs(segments, connectors);
+        for restriction in other.restrictions.values().flatten() {
+            self.push_turn_restriction(restriction.clone());
+        }
+        for (name, profile) in &other.profiles {
+            self.profiles
+                .entry(name.clone())
+                .or_insert_with(|| profile.clone());
         }
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone)]
 struct ToVisitState<'a> {
-    cost: u32,
-    connector_id: &'a String,
+    cost: f64,
+    connector_id: &'a str,
+    /// Index of the connector in the graph, or of the state in route
+    /// searches.
+    node: u32,
 }
 impl<'a> Ord for ToVisitState<'a> {
     fn cmp(&self, other: &Self) -> Ordering {
@@ -149,10 +472,17 @@ impl<'a> Ord for ToVisitState<'a> {
         // to make implementations of `PartialEq` and `Ord` consistent.
         other
             .cost
-            .cmp(&self.cost)
-            .then_with(|| self.connector_id.cmp(&other.connector_id))
+            .total_cmp(&self.cost)
+            .then_with(|| self.connector_id.cmp(other.connector_id))
+            .then_with(|| self.node.cmp(&other.node))
     }
 } // `PartialOrd` needs to be implemented as well.
+impl<'a> PartialEq for ToVisitState<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl<'a> Eq for ToVisitState<'a> {}
 impl<'a> PartialOrd for ToVisitState<'a> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
@@ -173,36 +503,67 @@ impl Router {
         self.connectors.len()
     }
 
+    #[wasm_bindgen(js_name = restrictionsLength)]
+    /// Returns number of stored turn restrictions.
+    pub fn restrictions_len(&self) -> usize {
+        self.restrictions.values().map(Vec::len).sum()
+    }
+
+    /// Returns the bounding box of all segments and connectors, `undefined`
+    /// if the router is empty.
+    pub fn bbox(&self) -> Option<Rect> {
+        let segments = self
+            .segments
+            .iter()
+            .filter_map(|segment| segment.get_geometry().bbox());
+        let connectors = self.connectors.iter().map(|connector| {
+            let point = connector.get_point();
+            Rect::new(point.x(), point.y(), point.x(), point.y())
+        });
+        segments.chain(connectors).reduce(|a, b| a.union(&b))
+    }
+
     #[wasm_bindgen(js_name = toGeoJSON)]
     /// Returns the transport network (segments and connectors) as GeoJSON
     /// feature collection.
+    ///
+    /// The collection and each feature have a `bbox` member.
     pub fn to_geojson(&self) -> String {
         let mut feature_strs = Vec::new();
+        let mut bbox: Option<Rect> = None;
         for segment in &self.segments {
+            let segment_bbox = segment.get_geometry().bbox();
+            bbox = match (bbox, segment_bbox.clone()) {
+                (Some(bbox), Some(segment_bbox)) => Some(bbox.union(&segment_bbox)),
+                (bbox, segment_bbox) => bbox.or(segment_bbox),
+            };
             let linestring = Into::<geo::LineString<f64>>::into(segment.get_geometry().clone());
             let mut coordinates_str = String::new();
             for coordinate in linestring {
                 if !coordinates_str.is_empty() {
                     coordinates_str.push_str(", ");
                 }
-                coordinates_str.push_str(&format!("[{}, {}]", coordinate.x, coordinate.y));
+                let [x, y] = round_position(coordinate.x, coordinate.y);
+                coordinates_str.push_str(&format!("[{}, {}]", x, y));
             }
             feature_strs.push(format!(
                 r#"{{
             "type": "Feature",
             "id": "{}",
-            "geometry": {{
+            {}"geometry": {{
                 "type": "LineString",
                 "coordinates": [{}]
             }},
             "properties": {{}}
         }}"#,
                 segment.get_id(),
+                bbox_member(segment_bbox),
                 coordinates_str
             ));
         }
         format!(
-            r#"{{ "type": "FeatureCollection", "features": [{}] }}"#,
+            r#"{{ "type": "FeatureCollection", {}"features": [{}] }}"#,
+            bbox_member(bbox),
             feature_strs.join(",")
         )
     }
@@ -210,446 +571,3136 @@ impl Router {
     #[wasm_bindgen(js_name = findRoute)]
     /// Find a route from start to stop.
     pub fn find_route(&self, start: &Point, stop: &Point) -> Result<Route, RoutingError> {
+        self.find_route_with_options(start, stop, &RouteOptions::default())
+    }
+
+    #[wasm_bindgen(js_name = findRouteWithOptions)]
+    /// Find a route from start to stop with the given options.
+    pub fn find_route_with_options(
+        &self,
+        start: &Point,
+        stop: &Point,
+        options: &RouteOptions,
+    ) -> Result<Route, RoutingError> {
+        let _span = span!("search route");
         debug_log!("find route for start {:?}, stop {:?}", start, stop);
         if self.segments_len() == 0 {
             return Err(RoutingError::MissingSegments);
         }
-        let start_segment = self.find_nearest(start).unwrap();
-        let stop_segment = self.find_nearest(stop).unwrap();
-
-        let start_connector = Connector {
-            id: "#start".into(),
-            point: start_segment.get_position_as_point(),
-        };
-        let stop_connector = Connector {
-            id: "#stop".into(),
-            point: stop_segment.get_position_as_point(),
+        let profile = self.profile(options)?;
+        // Snap to usable segments only.
+        let usable = |segment: &Segment| is_usable(segment, options, profile);
+        let mode = options.distance_mode;
+        let start_segments =
+            self.find_nearest_candidates(start, options.snap_candidates, mode, usable);
+        let stop_segments =
+            self.find_nearest_candidates(stop, options.snap_candidates, mode, usable);
+        let via = match &options.via {
+            Some(via) => self.via_position(via, options, profile)?,
+            None => {
+                return self.find_best_route(
+                    (start, &start_segments),
+                    (stop, &stop_segments),
+                    options,
+                    profile,
+                )
+            }
         };
-        let (mut connector_map, _) = self.build_maps(
-            &start_segment,
-            &stop_segment,
-            &start_connector,
-            &stop_connector,
-        );
+        let via_point = via.get_position_as_point();
+        let via_segments = [via];
+        let to_via = self.find_best_route(
+            (start, &start_segments),
+            (&via_point, &via_segments),
+            options,
+            profile,
+        )?;
+        let from_via = self.find_best_route(
+            (&via_point, &via_segments),
+            (stop, &stop_segments),
+            options,
+            profile,
+        )?;
+        Ok(Route::join(&[to_via, from_via]))
+    }
 
-        let mut to_visit = BinaryHeap::new();
+    #[wasm_bindgen(js_name = findNearest)]
+    /// Returns the position of the segment that is nearest to the given point.
+    ///
+    /// Returns None if there are no segments at all.
+    pub fn find_nearest(&self, point: &Point) -> Option<SegmentWithPosition> {
+        self.find_nearest_candidates(point, 1, DistanceMode::Planar, |_| true)
+            .pop()
+    }
+
+    #[wasm_bindgen(js_name = findNearestWithOptions)]
+    /// Returns the position of the segment that is nearest to the given point
+    /// measured in the distance mode of the options.
+    ///
+    /// Returns None if there are no segments at all.
+    pub fn find_nearest_with_options(
+        &self,
+        point: &Point,
+        options: &RouteOptions,
+    ) -> Option<SegmentWithPosition> {
+        self.find_nearest_candidates(point, 1, options.distance_mode, |_| true)
+            .pop()
+    }
+
+    #[wasm_bindgen(js_name = findRouteSnapped)]
+    /// Find a route between positions on segments, e.g. as returned by
+    /// `findNearest`, with the given options.
+    ///
+    /// The positions are used as they are, even if their segments are not
+    /// usable with the options.
+    pub fn find_route_snapped(
+        &self,
+        start: &SegmentWithPosition,
+        stop: &SegmentWithPosition,
+        options: &RouteOptions,
+    ) -> Result<Route, RoutingError> {
+        let _span = span!("search route");
+        if self.segments_len() == 0 {
+            return Err(RoutingError::MissingSegments);
+        }
+        let profile = self.profile(options)?;
+        self.find_route_between(
+            &start.get_position_as_point(),
+            &stop.get_position_as_point(),
+            start,
+            stop,
+            options,
+            profile,
+        )
+    }
+
+    #[wasm_bindgen(js_name = findRoutesFrom)]
+    /// Finds routes from start to each of the stops with a single search,
+    /// which is much cheaper than a search per stop for nearby stops.
+    ///
+    /// Start and stops are snapped to their nearest segment only. Stops which
+    /// cannot be reached get a route without segments, unless partial routes
+    /// are allowed by the options.
+    pub fn find_routes_from(
+        &self,
+        start: &Point,
+        stops: Vec<Point>,
+        options: &RouteOptions,
+    ) -> Result<Vec<Route>, RoutingError> {
+        let _span = span!("search routes");
+        if self.segments_len() == 0 {
+            return Err(RoutingError::MissingSegments);
+        }
+        let profile = self.profile(options)?;
+        let usable = |segment: &Segment| is_usable(segment, options, profile);
+        let start_segment = self
+            .find_nearest_candidates(start, 1, options.distance_mode, usable)
+            .pop()
+            .ok_or(RoutingError::CouldNotFindRoute)?;
+        let stop_segments: Vec<_> = stops
+            .iter()
+            .map(|stop| {
+                self.find_nearest_candidates(stop, 1, options.distance_mode, usable)
+                    .pop()
+            })
+            .collect();
+        let snapped: Vec<_> = stops
+            .iter()
+            .zip(&stop_segments)
+            .filter_map(|(stop, segment)| segment.as_ref().map(|segment| (stop, segment)))
+            .collect();
+        let mut routes = match snapped.len() {
+            0 => Vec::new(),
+            _ => self.find_routes_between(start, &start_segment, &snapped, options, profile),
+        }
+        .into_iter();
+        Ok(stops
+            .iter()
+            .zip(&stop_segments)
+            .map(|(stop, segment)| {
+                segment
+                    .as_ref()
+                    .and_then(|_| routes.next().unwrap().ok())
+                    .unwrap_or_else(|| Route::new(vec![start.clone(), stop.clone()], Vec::new()))
+            })
+            .collect())
+    }
+
+    /// Returns the lengths in meters of the routes from each source to each
+    /// target, e.g. to find the nearest of several stores.
+    ///
+    /// The matrix is stored by rows, the length from source `i` to target `j`
+    /// is at index `i * targets.length + j`. Targets which cannot be reached
+    /// from a source have an infinite length.
+    pub fn matrix(
+        &self,
+        sources: Vec<Point>,
+        targets: Vec<Point>,
+    ) -> Result<Vec<f64>, RoutingError> {
+        self.matrix_with_options(sources, targets, &RouteOptions::default())
+    }
+
+    #[wasm_bindgen(js_name = matrixWithOptions)]
+    /// Returns the lengths in meters of the routes with the given options
+    /// from each source to each target, see `matrix`.
+    pub fn matrix_with_options(
+        &self,
+        sources: Vec<Point>,
+        targets: Vec<Point>,
+        options: &RouteOptions,
+    ) -> Result<Vec<f64>, RoutingError> {
+        self.matrix_of(&sources, &targets, options, |route| {
+            Some(route.get_distance())
+        })
+    }
+
+    #[wasm_bindgen(js_name = durationMatrix)]
+    /// Returns the estimated durations in seconds of the routes from each
+    /// source to each target by the speeds of the profile of the options,
+    /// laid out like `matrix`.
+    ///
+    /// Without a profile, the durations are `NaN`.
+    pub fn duration_matrix(
+        &self,
+        sources: Vec<Point>,
+        targets: Vec<Point>,
+        options: &RouteOptions,
+    ) -> Result<Vec<f64>, RoutingError> {
+        self.matrix_of(&sources, &targets, options, Route::get_duration)
+    }
 
+    /// Returns the connectors reachable from start within the given network
+    /// length as GeoJSON feature collection of points.
+    ///
+    /// The length is measured in the units of the segment lengths. The
+    /// `distance` property of each point holds the length of the shortest
+    /// way to the connector.
+    pub fn isochrone(&self, start: &Point, max_length: f64) -> Result<String, RoutingError> {
+        let _span = span!("search isochrone");
+        if self.segments_len() == 0 {
+            return Err(RoutingError::MissingSegments);
+        }
+        let start_segment = self.find_nearest(start).unwrap();
+        let start_connector = Connector::new("#start", &start_segment.get_position_as_point());
+        let graph = Graph::new(
+            self.prepared_graph(DistanceMode::Planar),
+            &self.segments,
+            &self.connectors,
+            &[(start_segment.segment.id(), &start_connector)],
+        );
+        let start_node = graph.node(&start_connector.id).unwrap();
+        let mut distances = vec![None; graph.len()];
+        distances[start_node as usize] = Some(0.0);
+        let mut to_visit = BinaryHeap::new();
         to_visit.push(ToVisitState {
-            cost: 0,
+            cost: 0.0,
             connector_id: &start_connector.id,
+            node: start_node,
         });
-        connector_map
-            .get_mut(&start_connector.get_id())
-            .expect(&format!(
-                "Starting connector {} is missing in map",
-                start_connector.get_id()
-            ))
-            .distance = Some(0.0);
-        while to_visit.len() > 0 {
-            let visiting = connector_map
-                .get(to_visit.pop().unwrap().connector_id)
-                .unwrap()
-                .connector;
-            // debug_log!("Visiting {}", visiting.get_id());
-            if visiting.id == stop_connector.get_id() {
-                debug_log!("Found way to stop connector!");
-                break;
-            }
-            let visiting_data = (*connector_map.get(&visiting.id).unwrap()).clone();
-            // debug_log!("Data {:?}", visiting_data);
-            for neighbour in &visiting_data.neighbours {
-                // debug_log!("Checking neigbour {}", neighbour.connector.get_id());
-                let old_neighbour_data = connector_map.get(&neighbour.connector.id).unwrap();
-                let new_distance = visiting_data.distance.unwrap()
-                    + Into::<geo::LineString<f64>>::into(neighbour.segment.get_geometry())
-                        .euclidean_length();
-                let priority = new_distance
-                    + Into::<geo::Point<f64>>::into(neighbour.connector.get_point())
-                        .euclidean_distance(&Into::<geo::Point<f64>>::into(
-                            stop_connector.get_point(),
-                        ));
-                if old_neighbour_data
-                    .distance
-                    .is_some_and(|x| x <= new_distance)
-                {
+        while let Some(visiting) = to_visit.pop() {
+            let distance: f64 = distances[visiting.node as usize].unwrap();
+            for edge in graph.edges(visiting.node) {
+                let new_distance = distance + edge.length;
+                if new_distance > max_length {
+                    continue;
+                }
+                let old_distance = &mut distances[edge.target as usize];
+                if old_distance.is_some_and(|x| x <= new_distance) {
                     continue;
                 }
-                // debug_log!(
-                // "Found shorter way for {} coming from {}",
-                // neighbour.connector.get_id(), visiting.get_id()
-                // );
-                let data = connector_map.get_mut(&neighbour.connector.id).unwrap();
-                data.distance = Some(new_distance);
-                data.previous_segment = Some(neighbour.segment);
-                data.previous_connector = Some(visiting);
+                *old_distance = Some(new_distance);
                 to_visit.push(ToVisitState {
-                    cost: (priority * 1000.0).round() as u32,
-                    connector_id: &neighbour.connector.id,
+                    cost: new_distance,
+                    connector_id: graph.connector(edge.target).id(),
+                    node: edge.target,
                 });
             }
         }
-        let mut route_segments = Vec::new();
-        let mut current_connector = connector_map.get(&stop_connector.get_id()).unwrap();
-        if current_connector.previous_connector.is_none() {
-            return Err(RoutingError::CouldNotFindRoute);
-        };
-        loop {
-            debug_log!(
-                "Way back: {:?} through connector {:?}",
-                current_connector.previous_segment,
-                current_connector.previous_connector,
-            );
-            let start_position = match &current_connector.previous_connector {
-                Some(&ref connector) => current_connector
-                    .previous_segment
-                    .unwrap()
-                    .get_point_position(&connector.point)
-                    .unwrap(),
-                None => start_segment.position,
-            };
+        let mut reachable: Vec<_> = distances
+            .iter()
+            .enumerate()
+            .filter(|(node, _)| *node as u32 != start_node)
+            .filter_map(|(node, distance)| {
+                distance.map(|distance| (graph.connector(node as u32), distance))
+            })
+            .collect();
+        reachable.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.id.cmp(&b.0.id)));
+        let features: Vec<_> = reachable
+            .iter()
+            .map(|(connector, distance)| {
+                serde_json::json!({
+                    "type": "Feature",
+                    "id": connector.id,
+                    "geometry": connector.point,
+                    "properties": {"distance": distance},
+                })
+            })
+            .collect();
+        Ok(serde_json::json!({"type": "FeatureCollection", "features": features}).to_string())
+    }
 
-            let stop_position = current_connector
-                .previous_segment
-                .unwrap()
-                .get_point_position(&current_connector.connector.point);
+    #[wasm_bindgen(js_name = getCoverage)]
+    /// Returns the density of the network as GeoJSON feature collection of
+    /// grid cells, to show where routing data exists.
+    ///
+    /// The cells are squares of `cell_size` degrees aligned to multiples of
+    /// it. Only cells containing segments are returned, each with the
+    /// `length` of the segments within in meters and the number of
+    /// `segments` passing it.
+    pub fn get_coverage(&self, cell_size: f64) -> String {
+        let mut cells: BTreeMap<(i64, i64), (f64, HashSet<&str>)> = BTreeMap::new();
+        if cell_size > 0.0 {
+            for segment in &self.segments {
+                let geometry = Into::<geo::LineString<f64>>::into(segment.get_geometry());
+                for line in geometry.lines() {
+                    // Split the line so that its pieces are attributed to the
+                    // cells they are in.
+                    let pieces =
+                        ((line.dx().hypot(line.dy()) / cell_size * 2.0).ceil() as usize).max(1);
+                    let step = geo::Coord {
+                        x: line.dx() / pieces as f64,
+                        y: line.dy() / pieces as f64,
+                    };
+                    for index in 0..pieces {
+                        let start = line.start + step * index as f64;
+                        let end = start + step;
+                        let middle = start + step * 0.5;
+                        let cell = cells
+                            .entry((
+                                (middle.x / cell_size).floor() as i64,
+                                (middle.y / cell_size).floor() as i64,
+                            ))
+                            .or_default();
+                        cell.0 += geo::Point::from(start).haversine_distance(&end.into());
+                        cell.1.insert(&segment.id);
+                    }
+                }
+            }
+        }
+        let features: Vec<_> = cells
+            .iter()
+            .map(|((x, y), (length, segments))| {
+                let (west, south) = (*x as f64 * cell_size, *y as f64 * cell_size);
+                let [east, north] = round_position(west + cell_size, south + cell_size);
+                let [west, south] = round_position(west, south);
+                serde_json::json!({
+                    "type": "Feature",
+                    "geometry": {
+                        "type": "Polygon",
+                        "coordinates": [[
+                            [west, south], [east, south], [east, north], [west, north], [west, south]
+                        ]],
+                    },
+                    "properties": {"length": length, "segments": segments.len()},
+                })
+            })
+            .collect();
+        serde_json::json!({"type": "FeatureCollection", "features": features}).to_string()
+    }
+}
 
-            route_segments.push(RouteSegment::new(
-                current_connector.previous_segment.unwrap(),
-                start_position,
-                stop_position.unwrap(),
-            ));
+/// Distance in meters up to which snapped positions count as the same.
+const SAME_POSITION_TOLERANCE: f64 = 0.01;
 
-            current_connector = connector_map
-                .get(&current_connector.previous_connector.unwrap().id)
-                .unwrap();
+/// Returns whether the snapped positions are the same, i.e. on the same
+/// segment position or within [`SAME_POSITION_TOLERANCE`] of each other.
+fn is_same_position(a: &SegmentWithPosition, b: &SegmentWithPosition) -> bool {
+    (a.segment.id == b.segment.id && a.position == b.position)
+        || a.get_position_as_point()
+            .distance_meters(&b.get_position_as_point())
+            <= SAME_POSITION_TOLERANCE
+}
 
-            if current_connector.previous_connector.is_none() {
-                debug_log!("found way back to start");
-                break;
-            }
-        }
-        let last_segment = route_segments.pop().unwrap();
-        route_segments.push(RouteSegment::new(
-            &last_segment.get_segment(),
-            start_segment.get_position(),
-            last_segment.get_stop(),
-        ));
-        route_segments.reverse();
-        debug_log!("segments {:?}", route_segments);
-        Ok(Route::new(
-            vec![start.clone(), stop.clone()],
-            route_segments,
-        ))
+/// Returns the cost to compare routes between different snapped points: the
+/// length of the route geometry plus the distances to the snapped points,
+/// measured in the distance mode.
+fn route_cost(start: &Point, stop: &Point, route: &Route, mode: DistanceMode) -> f64 {
+    let geometry = route.get_geometry();
+    let snap_distance = |point: &Point, coord: Option<&geo::Coord<f64>>| {
+        coord.map_or(0.0, |coord| {
+            measure(&point.clone().into(), &geo::Point::from(*coord), mode)
+        })
+    };
+    let length = match mode {
+        DistanceMode::Planar => geometry.euclidean_length(),
+        DistanceMode::Geodesic => geometry.haversine_length(),
+    };
+    length + snap_distance(start, geometry.0.first()) + snap_distance(stop, geometry.0.last())
+}
+
+/// Returns the distance between the points measured in the distance mode.
+fn measure(a: &geo::Point<f64>, b: &geo::Point<f64>, mode: DistanceMode) -> f64 {
+    match mode {
+        DistanceMode::Planar => a.euclidean_distance(b),
+        DistanceMode::Geodesic => a.haversine_distance(b),
     }
 }
 
-#[derive(Clone, Debug)]
-struct ConnectorNeighbour<'a> {
-    connector: &'a Connector,
-    segment: &'a Segment,
+/// The way a search reached a state: the previous state and the edge from
+/// it.
+#[derive(Clone, Copy, Debug)]
+struct Step {
+    from: u32,
+    edge: Edge,
 }
 
-#[derive(Clone, Debug)]
-struct ConnectorData<'a> {
-    connector: &'a Connector,
-    distance: Option<f64>,
-    neighbours: Vec<ConnectorNeighbour<'a>>,
-    previous_segment: Option<&'a Segment>,
-    previous_connector: Option<&'a Connector>,
+/// The states of a route search.
+///
+/// Each connector of the graph is a state, numbered like the connector.
+/// Connectors with turn restrictions additionally get a state for each
+/// segment they are reached on, numbered after the connectors, as the way a
+/// connector was reached decides about the turns allowed there.
+struct SearchStates {
+    connectors: u32,
+    /// Connector of each additional state.
+    nodes: Vec<u32>,
+    /// Additional states by connector and segment.
+    index: HashMap<(u32, u32), u32>,
+    /// The way each state was reached, if it was.
+    steps: Vec<Option<Step>>,
 }
 
-impl Router {
-    pub fn push_segment(&mut self, segment: Segment) {
-        self.segments.push(segment);
+impl SearchStates {
+    fn new(graph: &Graph) -> SearchStates {
+        SearchStates {
+            connectors: graph.len() as u32,
+            nodes: Vec::new(),
+            index: HashMap::new(),
+            steps: vec![None; graph.len()],
+        }
     }
 
-    pub fn push_connector(&mut self, connector: Connector) {
-        self.connectors.push(connector);
+    /// Returns the number of states.
+    fn len(&self) -> usize {
+        self.connectors as usize + self.nodes.len()
     }
 
-    /// Returns the position of the segment that is nearest to the given point.
-    ///
-    /// Returns None if there are no segments at all.
-    pub fn find_nearest<'a>(&'a self, point: &Point) -> Option<SegmentWithPosition<'a>> {
-        debug_log!("find nearest for point {:?}", point);
-        let mut shortest_distance: f64 = std::f64::MAX;
-        let mut nearest_segment = None;
-        let mut position: f64 = 0.0;
-        for segment in &self.segments {
-            let geo_line_string = Into::<geo::LineString<f64>>::into(segment.geometry.clone());
-            let geo_point = &Into::<geo::Point<f64>>::into(point.clone());
-            let distance = geo_line_string.euclidean_distance(geo_point);
-            if distance < shortest_distance {
-                shortest_distance = distance;
-                nearest_segment = Some(segment);
-                let closest_point = geo_line_string.closest_point(geo_point);
-                match closest_point {
-                    Closest::Intersection(closest) | Closest::SinglePoint(closest) => {
-                        position = geo_line_string.line_locate_point(&closest).unwrap();
-                    }
-                    Closest::Indeterminate => {
-                        panic!("unimplemented")
-                    }
-                }
-            }
+    /// Returns the connector of the state.
+    fn node(&self, state: u32) -> u32 {
+        match state.checked_sub(self.connectors) {
+            Some(index) => self.nodes[index as usize],
+            None => state,
+        }
+    }
+
+    /// Returns the state of reaching the connector on the edge, the
+    /// connector itself unless it has turn restrictions.
+    fn reach(&mut self, edge: &Edge, restricted: bool) -> u32 {
+        if !restricted {
+            return edge.target;
+        }
+        let next = self.len() as u32;
+        let state = *self
+            .index
+            .entry((edge.target, edge.segment))
+            .or_insert(next);
+        if state == next {
+            self.nodes.push(edge.target);
+            self.steps.push(None);
+        }
+        state
+    }
+}
+
+/// Returns whether the way found to the state uses a segment with the ID of
+/// the given segment, other than by continuing along the segment itself.
+fn is_on_path(graph: &Graph, states: &SearchStates, state: u32, segment: u32) -> bool {
+    let id = &graph.segments()[segment as usize].id;
+    let mut state = state;
+    let mut continuing = true;
+    while let Some(step) = states.steps[state as usize] {
+        continuing = continuing && step.edge.segment == segment;
+        if !continuing && graph.segment(&step.edge).id == *id {
+            return true;
         }
-        match nearest_segment {
-            Some(segment) => {
-                let it = Some(SegmentWithPosition { segment, position });
-                debug_log!("found nearest {:?}", it);
-                return it;
+        state = step.from;
+    }
+    false
+}
+
+/// Returns the route to the stop connector found by the search, or to the
+/// reached connector closest to the stop if partial routes are allowed, with
+/// the connectors passed on the way.
+fn trace_route<'a>(
+    graph: &Graph<'a>,
+    states: &SearchStates,
+    start: &Point,
+    stop: &Point,
+    start_segment: &SegmentWithPosition,
+    stop_node: u32,
+    options: &RouteOptions,
+) -> Result<(Route, Vec<&'a Connector>), RoutingError> {
+    // Parts of the segments travelled, from stop to start.
+    let mut parts: Vec<(u32, Position, Position)> = Vec::new();
+    // Segment, start node and index of the part of each step, from stop to
+    // start.
+    let mut steps = Vec::new();
+    let mut passed = Vec::new();
+    let mut current = stop_node;
+    let mut gap = None;
+    if states.steps[current as usize].is_none() {
+        if !options.allow_partial {
+            return Err(RoutingError::CouldNotFindRoute);
+        }
+        // End the route at the reached connector closest to the stop.
+        let connector = |state: u32| graph.connector(states.node(state));
+        current = (0..states.len() as u32)
+            .filter(|state| states.steps[*state as usize].is_some())
+            .map(|state| (stop.distance_meters(&connector(state).point), state))
+            .min_by(|a, b| {
+                a.0.total_cmp(&b.0)
+                    .then_with(|| connector(a.1).id.cmp(&connector(b.1).id))
+                    .then_with(|| a.1.cmp(&b.1))
+            })
+            .map(|(distance, state)| {
+                gap = Some(distance);
+                state
+            })
+            .ok_or(RoutingError::CouldNotFindRoute)?;
+    };
+    while let Some(step) = states.steps[current as usize] {
+        debug_log!(
+            "Way back: {:?} through connector {:?}",
+            graph.segment(&step.edge).id,
+            graph.connector(states.node(step.from)).id,
+        );
+        let (start_position, stop_position) = step.edge.positions;
+
+        // Join the parts of a segment split at its connectors.
+        match parts.last_mut() {
+            Some(part) if part.0 == step.edge.segment && part.1 == stop_position => {
+                part.1 = start_position;
             }
-            None => None,
-        }
-    }
-
-    fn build_maps<'a>(
-        &'a self,
-        start_segment: &'a SegmentWithPosition,
-        stop_segment: &'a SegmentWithPosition,
-        start_connector: &'a Connector,
-        stop_connector: &'a Connector,
-    ) -> (HashMap<String, ConnectorData>, HashMap<&String, &Segment>) {
-        let mut connector_map = HashMap::with_capacity(self.connectors.len());
-        for connector in &self.connectors {
-            connector_map.insert(
-                connector.id.clone(),
-                ConnectorData {
-                    connector,
-                    distance: None,
-                    neighbours: Vec::new(),
-                    previous_segment: Some(start_segment.get_segment()),
-                    previous_connector: None,
-                },
-            );
+            _ => parts.push((step.edge.segment, start_position, stop_position)),
         }
-        connector_map.insert(
-            start_connector.get_id(),
-            ConnectorData {
-                connector: &start_connector,
-                distance: None,
-                neighbours: Vec::new(),
-                previous_segment: Some(start_segment.get_segment()),
-                previous_connector: None,
-            },
-        );
-        connector_map.insert(
-            stop_connector.get_id(),
-            ConnectorData {
-                connector: &stop_connector,
-                distance: None,
-                neighbours: Vec::new(),
-                previous_segment: Some(start_segment.get_segment()),
-                previous_connector: None,
-            },
-        );
-
-        let mut segment_map = HashMap::with_capacity(self.segments.len());
-        for segment in &self.segments {
-            segment_map.insert(&segment.id, segment);
-            let mut connectors = segment.get_connectors().clone();
-            if segment.get_id() == start_segment.get_segment().get_id() {
-                connectors.push(start_connector.get_id());
+        steps.push((step.edge.segment, states.node(step.from), parts.len() - 1));
+
+        current = step.from;
+        if states.steps[current as usize].is_none() {
+            debug_log!("found way back to start");
+            break;
+        }
+        passed.push(graph.connector(states.node(current)));
+    }
+    parts.last_mut().unwrap().1 = start_segment.get_position();
+    let last_part = parts.len() - 1;
+    let route_segments: Vec<_> = parts
+        .into_iter()
+        .rev()
+        .map(|(segment, start, stop)| {
+            RouteSegment::new(&graph.segments()[segment as usize], start, stop)
+        })
+        .collect();
+    debug_log!("segments {:?}", route_segments);
+    let mut route = Route::new(vec![start.clone(), stop.clone()], route_segments);
+    steps.reverse();
+    for (step, exit) in roundabout_exits(graph, &steps) {
+        route.set_roundabout_exit(last_part - steps[step].2, exit);
+    }
+    route.set_gap(gap);
+    Ok((route, passed))
+}
+
+/// Returns the index of each step entering a roundabout with the number of
+/// the exit taken, counting the connectors passed on the roundabout where a
+/// segment leaves it.
+///
+/// Steps are given from start to stop as segment, start node and part.
+fn roundabout_exits(graph: &Graph, steps: &[(u32, u32, usize)]) -> Vec<(usize, u32)> {
+    let roundabout = |segment: u32| graph.segments()[segment as usize].is_roundabout();
+    let has_exit = |node: u32| {
+        graph
+            .edges(node)
+            .any(|edge| !graph.segment(edge).is_roundabout())
+    };
+    let mut exits = Vec::new();
+    let mut entry = None;
+    let mut passed = 0;
+    for (index, (segment, node, _)) in steps.iter().enumerate() {
+        let on_roundabout = roundabout(*segment);
+        match entry {
+            Some(entered) if !on_roundabout => {
+                exits.push((entered, passed + 1));
+                entry = None;
             }
-            if segment.get_id() == stop_segment.get_segment().get_id() {
-                connectors.push(stop_connector.get_id());
+            Some(_) if has_exit(*node) => passed += 1,
+            None if on_roundabout && index > 0 && !roundabout(steps[index - 1].0) => {
+                entry = Some(index);
+                passed = 0;
             }
-            for connector_id in &connectors {
-                if !connector_map.contains_key(connector_id) {
-                    // Ignore unknown connectors.
-                    continue;
-                }
-                let new_neighbours: Vec<ConnectorNeighbour> = connectors
-                    .clone()
-                    .iter()
-                    .filter_map(|x| {
-                        if x == connector_id {
-                            return None;
-                        }
-                        match connector_map.get(x) {
-                            Some(neighbour) => Some(ConnectorNeighbour {
-                                connector: neighbour.connector,
-                                segment,
-                            }),
-                            None => None, // Ignore unknown connectors.
-                        }
-                    })
-                    .collect();
+            _ => {}
+        }
+    }
+    exits
+}
 
-                connector_map
-                    .get_mut(connector_id)
-                    .unwrap()
-                    .neighbours
-                    .extend(new_neighbours.into_iter());
-            }
+/// Returns the largest component, `None` if several components are the
+/// largest.
+fn main_component(components: &[u32]) -> Option<u32> {
+    let mut sizes: HashMap<u32, usize> = HashMap::new();
+    for component in components {
+        *sizes.entry(*component).or_default() += 1;
+    }
+    let largest = sizes.values().max()?;
+    match sizes
+        .iter()
+        .filter(|(_, size)| *size == largest)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [(component, _)] => Some(**component),
+        _ => None,
+    }
+}
+
+/// Returns the error explaining why the stop can't be reached from the start
+/// given the components of their connectors: the endpoint outside of the
+/// main component is unreachable.
+fn unreachable_error(
+    (start, start_segment): (u32, &SegmentWithPosition),
+    (stop, stop_segment): (u32, &SegmentWithPosition),
+    main: Option<u32>,
+) -> RoutingError {
+    if start == stop {
+        // The search skipped the way, e.g. because of the revisit penalty.
+        return RoutingError::CouldNotFindRoute;
+    }
+    match main {
+        Some(main) if main == start => RoutingError::UnreachableStop {
+            segment: stop_segment.segment.id.clone(),
+        },
+        Some(main) if main == stop => RoutingError::UnreachableStart {
+            segment: start_segment.segment.id.clone(),
+        },
+        _ => RoutingError::Disconnected,
+    }
+}
+
+/// Returns the angle in degrees (0 to 180) of the turn at the connector from
+/// the previous segment onto the neighbour's segment, 0 if unknown.
+fn turn_angle(
+    previous_segment: &Segment,
+    previous_positions: (Position, Position),
+    segment: &Segment,
+    positions: (Position, Position),
+) -> f64 {
+    let (from, to) = previous_positions;
+    let incoming = direction(previous_segment, to, from).map(|back| back + 180.0);
+    let outgoing = direction(segment, positions.0, positions.1);
+    match (incoming, outgoing) {
+        (Some(incoming), Some(outgoing)) => {
+            let angle = (outgoing - incoming).rem_euclid(360.0);
+            angle.min(360.0 - angle)
         }
-        (connector_map, segment_map)
+        _ => 0.0,
     }
 }
 
-#[derive(Error, Debug, PartialEq, Eq)]
-#[wasm_bindgen]
-pub enum RoutingError {
-    #[error("No segments added to router.")]
-    MissingSegments,
-    #[error("Could not fetch tile")]
-    TileFetchingError,
-    #[error("Could not parse tile")]
-    TileParsingError,
-    #[error("Could not find route")]
-    CouldNotFindRoute,
+/// Returns the direction in degrees in which the segment leaves the position
+/// towards the other position, `None` if they are at the same point.
+fn direction(segment: &Segment, position: Position, towards: Position) -> Option<f64> {
+    let line = Into::<geo::LineString<f64>>::into(segment.geometry.clone());
+    let start = line.line_interpolate_point(position)?;
+    let total = line.euclidean_length();
+    // Positions of the coordinates of the geometry.
+    let mut along = 0.0;
+    let mut positions = vec![0.0];
+    for part in line.lines() {
+        along += part.euclidean_length();
+        positions.push(if total > 0.0 { along / total } else { 0.0 });
+    }
+    // The next coordinate after the position gives the direction.
+    let next = if towards > position {
+        line.0
+            .iter()
+            .zip(&positions)
+            .find(|(_, at)| **at > position && **at <= towards)
+    } else {
+        line.0
+            .iter()
+            .zip(&positions)
+            .rev()
+            .find(|(_, at)| **at < position && **at >= towards)
+    };
+    let next = match next {
+        Some((coord, _)) => geo::Point::from(*coord),
+        None => line.line_interpolate_point(towards)?,
+    };
+    let (dx, dy) = (next.x() - start.x(), next.y() - start.y());
+    if dx == 0.0 && dy == 0.0 {
+        return None;
+    }
+    Some(dy.atan2(dx).to_degrees())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::geo_types::coord::{coord, Coord};
+/// Returns whether routes with the options may use the segment.
+fn is_usable(segment: &Segment, options: &RouteOptions, profile: Option<&Profile>) -> bool {
+    !options.blocked_segments.contains(&segment.id)
+        && options.is_inside(&segment.geometry.clone().into())
+        && profile.is_none_or(|profile| !profile.is_excluded(segment, options.departure))
+}
 
-    #[test]
-    /// General tests.
-    fn genereal() {
-        let router = Router::new();
-        assert_eq!(router.segments.len(), 0);
-        assert_eq!(router.connectors.len(), 0);
+/// Returns the part of the cost of the whole segment for the way along the
+/// edge.
+fn part_of(cost: f64, segment: &Segment, edge: &Edge) -> f64 {
+    match segment.get_length() {
+        length if length > 0.0 => cost * edge.length / length,
+        _ => cost,
     }
+}
 
-    #[test]
-    /// Test find_nearest method.
+impl Router {
+    /// Returns the registered profile selected by the options, if any.
+    fn profile(&self, options: &RouteOptions) -> Result<Option<&Profile>, RoutingError> {
+        match &options.profile {
+            Some(name) => self
+                .profiles
+                .get(name)
+                .map(Some)
+                .ok_or_else(|| RoutingError::UnknownProfile(name.clone())),
+            None => Ok(None),
+        }
+    }
+
+    /// Replaces the registered profiles.
+    pub(crate) fn set_profiles(&mut self, profiles: HashMap<String, Profile>) {
+        self.profiles = profiles;
+    }
+
+    /// Returns the cheapest route between any of the snapped positions of
+    /// start and stop, preferring routes which reach the stop.
+    fn find_best_route(
+        &self,
+        (start, start_segments): (&Point, &[SegmentWithPosition]),
+        (stop, stop_segments): (&Point, &[SegmentWithPosition]),
+        options: &RouteOptions,
+        profile: Option<&Profile>,
+    ) -> Result<Route, RoutingError> {
+        let mut best: Option<((bool, f64), Route)> = None;
+        let mut error = RoutingError::CouldNotFindRoute;
+        for start_segment in start_segments {
+            for stop_segment in stop_segments {
+                match self.find_route_between(
+                    start,
+                    stop,
+                    start_segment,
+                    stop_segment,
+                    options,
+                    profile,
+                ) {
+                    Ok(route) => {
+                        // Routes reaching the stop are preferred over partial ones.
+                        let cost = (
+                            route.get_gap().is_some(),
+                            route_cost(start, stop, &route, options.distance_mode),
+                        );
+                        if best.as_ref().is_none_or(|(best_cost, _)| cost < *best_cost) {
+                            best = Some((cost, route));
+                        }
+                    }
+                    Err(err) => error = err,
+                }
+            }
+        }
+        best.map(|(_, route)| route).ok_or(error)
+    }
+
+    /// Returns the position a route has to pass: the middle of the longest
+    /// piece of the via segment or the via connector snapped to the nearest
+    /// usable segment at it.
+    fn via_position(
+        &self,
+        via: &Via,
+        options: &RouteOptions,
+        profile: Option<&Profile>,
+    ) -> Result<SegmentWithPosition, RoutingError> {
+        match via {
+            Via::Segment(id) => self
+                .segments
+                .iter()
+                .filter(|segment| segment.id == *id)
+                .max_by(|a, b| a.get_length().total_cmp(&b.get_length()))
+                .map(|segment| SegmentWithPosition {
+                    segment: segment.clone(),
+                    position: 0.5,
+                    distance: 0.0,
+                })
+                .ok_or_else(|| RoutingError::UnknownVia(id.clone())),
+            Via::Connector(id) => {
+                let connector = self
+                    .connectors
+                    .iter()
+                    .find(|connector| connector.id == *id)
+                    .ok_or_else(|| RoutingError::UnknownVia(id.clone()))?;
+                let at_connector = |segment: &Segment| {
+                    segment.connectors.contains(id) && is_usable(segment, options, profile)
+                };
+                self.find_nearest_candidates(
+                    &connector.point,
+                    1,
+                    options.distance_mode,
+                    at_connector,
+                )
+                .pop()
+                .ok_or(RoutingError::CouldNotFindRoute)
+            }
+        }
+    }
+
+    /// Find a route from start to stop snapped to the given segments.
+    fn find_route_between(
+        &self,
+        start: &Point,
+        stop: &Point,
+        start_segment: &SegmentWithPosition,
+        stop_segment: &SegmentWithPosition,
+        options: &RouteOptions,
+        profile: Option<&Profile>,
+    ) -> Result<Route, RoutingError> {
+        self.find_routes_between(
+            start,
+            start_segment,
+            &[(stop, stop_segment)],
+            options,
+            profile,
+        )
+        .pop()
+        .unwrap()
+    }
+
+    /// Find routes from start to each of the stops snapped to the given
+    /// segments with a single search.
+    ///
+    /// The search is directed towards the stop if there is only one.
+    fn find_routes_between(
+        &self,
+        start: &Point,
+        start_segment: &SegmentWithPosition,
+        stops: &[(&Point, &SegmentWithPosition)],
+        options: &RouteOptions,
+        profile: Option<&Profile>,
+    ) -> Vec<Result<Route, RoutingError>> {
+        let start_connector = Connector::new("#start", &start_segment.get_position_as_point());
+        let stop_connectors: Vec<_> = stops
+            .iter()
+            .enumerate()
+            .map(|(index, (_, stop_segment))| {
+                let id = match stops.len() {
+                    1 => "#stop".into(),
+                    _ => format!("#stop{}", index),
+                };
+                Connector::new(&id, &stop_segment.get_position_as_point())
+            })
+            .collect();
+        let mut endpoints = vec![(start_segment, &start_connector)];
+        endpoints.extend(
+            stops
+                .iter()
+                .map(|(_, stop_segment)| *stop_segment)
+                .zip(&stop_connectors),
+        );
+        let endpoints: Vec<_> = endpoints
+            .into_iter()
+            .map(|(segment, connector)| (segment.segment.id(), connector))
+            .collect();
+        let graph = Graph::new(
+            self.prepared_graph(options.distance_mode),
+            &self.segments,
+            &self.connectors,
+            &endpoints,
+        );
+        // Segments outside the region are never expanded.
+        let outside: HashSet<&str> = match options.region {
+            Some(_) => self
+                .segments
+                .iter()
+                .filter(|segment| !options.is_inside(&segment.geometry.clone().into()))
+                .map(|segment| segment.id.as_str())
+                .collect(),
+            None => HashSet::new(),
+        };
+        let target = match stop_connectors.as_slice() {
+            [stop_connector] => Some(Into::<geo::Point<f64>>::into(stop_connector.get_point())),
+            _ => None,
+        };
+        // Stops at the start get a route without length instead of a search.
+        let at_start: Vec<_> = stops
+            .iter()
+            .map(|(_, stop_segment)| is_same_position(start_segment, stop_segment))
+            .collect();
+        let stop_nodes: Vec<u32> = stop_connectors
+            .iter()
+            .map(|connector| graph.node(&connector.id).unwrap())
+            .collect();
+        let mut remaining: HashSet<u32> = stop_nodes
+            .iter()
+            .zip(&at_start)
+            .filter(|(_, at_start)| !**at_start)
+            .map(|(node, _)| *node)
+            .collect();
+
+        let mut stats = SearchStats {
+            start_snap_distance: start.distance_meters(&start_connector.point),
+            ..SearchStats::default()
+        };
+        let mut to_visit = BinaryHeap::new();
+        let start_node = graph.node(&start_connector.id).unwrap_or_else(|| {
+            panic!(
+                "Starting connector {} is missing in graph",
+                start_connector.get_id()
+            )
+        });
+
+        if !remaining.is_empty() {
+            stats.heap_pushes += 1;
+            to_visit.push(ToVisitState {
+                cost: 0.0,
+                connector_id: &start_connector.id,
+                node: start_node,
+            });
+        }
+        let mut states = SearchStates::new(&graph);
+        let mut distances: Vec<Option<f64>> = vec![None; states.len()];
+        distances[start_node as usize] = Some(0.0);
+        while let Some(state) = to_visit.pop() {
+            let visiting = state.node;
+            if remaining.remove(&visiting) && remaining.is_empty() {
+                debug_log!("Found way to stop connectors!");
+                break;
+            }
+            stats.nodes_expanded += 1;
+            let distance = distances[visiting as usize].unwrap();
+            let previous = states.steps[visiting as usize];
+            let node = states.node(visiting);
+            for edge in graph.edges(node) {
+                let segment = graph.segment(edge);
+                let neighbour = graph.connector(edge.target);
+                if outside.contains(segment.id.as_str())
+                    || options.blocked_segments.contains(&segment.id)
+                {
+                    continue;
+                }
+                // The way the search reached the connector decides about the
+                // turns allowed there.
+                if previous.is_some_and(|previous| {
+                    self.is_restricted(
+                        graph.segment(&previous.edge),
+                        graph.connector(node),
+                        segment,
+                    )
+                }) {
+                    continue;
+                }
+                let mut length = match profile {
+                    Some(profile) => {
+                        match profile.cost(segment, options.objective, options.departure) {
+                            Some(cost) => part_of(cost, segment, edge),
+                            None => continue,
+                        }
+                    }
+                    None => edge.length,
+                };
+                if options.revisit_penalty > 1.0
+                    && is_on_path(&graph, &states, visiting, edge.segment)
+                {
+                    if options.revisit_penalty.is_infinite() {
+                        continue;
+                    }
+                    length *= options.revisit_penalty;
+                }
+                if let Some(profile) = profile {
+                    length += profile.delay_cost(
+                        neighbour,
+                        segment,
+                        options.objective,
+                        options.distance_mode,
+                    );
+                }
+                if options.turn_penalty > 0.0 {
+                    if let Some(previous) = previous {
+                        length += options.turn_penalty
+                            * turn_angle(
+                                graph.segment(&previous.edge),
+                                previous.edge.positions,
+                                segment,
+                                edge.positions,
+                            )
+                            / 180.0;
+                    }
+                }
+                let new_distance = distance + length;
+                let priority = new_distance
+                    + target.map_or(0.0, |target| {
+                        let [x, y] = graph.point(edge.target);
+                        measure(&geo::Point::new(x, y), &target, options.distance_mode)
+                    });
+                let next = states.reach(edge, self.restrictions.contains_key(neighbour.id()));
+                distances.resize(states.len(), None);
+                let old_distance = &mut distances[next as usize];
+                if old_distance.is_some_and(|x| x <= new_distance) {
+                    continue;
+                }
+                *old_distance = Some(new_distance);
+                states.steps[next as usize] = Some(Step {
+                    from: visiting,
+                    edge: *edge,
+                });
+                stats.heap_pushes += 1;
+                to_visit.push(ToVisitState {
+                    cost: priority,
+                    connector_id: neighbour.id(),
+                    node: next,
+                });
+            }
+        }
+        // Parts of the network are only computed to explain missing routes.
+        let unreached = stop_nodes
+            .iter()
+            .zip(&at_start)
+            .any(|(node, at_start)| !at_start && states.steps[*node as usize].is_none());
+        let components = unreached.then(|| {
+            let components = graph.components(|edge| {
+                let segment = graph.segment(edge);
+                !outside.contains(segment.id.as_str())
+                    && !options.blocked_segments.contains(&segment.id)
+                    && profile.is_none_or(|profile| {
+                        profile
+                            .cost(segment, options.objective, options.departure)
+                            .is_some()
+                    })
+            });
+            let main = main_component(&components);
+            (components, main)
+        });
+        stops
+            .iter()
+            .zip(&stop_connectors)
+            .zip(stop_nodes.iter().zip(&at_start))
+            .map(
+                |(((stop, stop_segment), stop_connector), (stop_node, at_start))| {
+                    let mut stats = stats.clone();
+                    stats.stop_snap_distance = stop.distance_meters(&stop_connector.point);
+                    let (mut route, passed) = if *at_start {
+                        let route = Route::new(
+                            vec![start.clone(), (*stop).clone()],
+                            vec![RouteSegment::new(
+                                start_segment.segment(),
+                                start_segment.position,
+                                start_segment.position,
+                            )],
+                        );
+                        (route, Vec::new())
+                    } else {
+                        trace_route(
+                            &graph,
+                            &states,
+                            start,
+                            stop,
+                            start_segment,
+                            *stop_node,
+                            options,
+                        )
+                        .map_err(|err| match (&err, &components) {
+                            (RoutingError::CouldNotFindRoute, Some((components, main))) => {
+                                unreachable_error(
+                                    (components[start_node as usize], start_segment),
+                                    (components[*stop_node as usize], stop_segment),
+                                    *main,
+                                )
+                            }
+                            _ => err,
+                        })?
+                    };
+                    *route.stats_mut() = stats;
+                    if let Some(profile) = profile {
+                        let delay = passed
+                            .iter()
+                            .map(|connector| profile.delay(connector))
+                            .sum();
+                        route.estimate_duration(
+                            |segment| profile.speed(segment, options.departure),
+                            delay,
+                        );
+                    }
+                    Ok(route)
+                },
+            )
+            .collect()
+    }
+
+    /// Adds a segment to the network.
+    pub fn push_segment(&mut self, segment: Segment) {
+        self.segments.push(segment);
+        self.extend_prepared_graphs(self.segments.len() - 1, self.connectors.len());
+    }
+
+    /// Adds a connector to the network.
+    pub fn push_connector(&mut self, connector: Connector) {
+        self.connectors.push(connector);
+        self.extend_prepared_graphs(self.segments.len(), self.connectors.len() - 1);
+    }
+
+    /// Adds the segments, connectors and turn restrictions of a parsed tile.
+    pub fn merge_tile(&mut self, tile: &ParsedTile) {
+        let (segments, connectors) = (self.segments.len(), self.connectors.len());
+        self.segments.extend_from_slice(tile.get_segments());
+        self.connectors.extend_from_slice(tile.get_connectors());
+        for restriction in tile.get_restrictions() {
+            self.push_turn_restriction(restriction.clone());
+        }
+        self.extend_prepared_graphs(segments, connectors);
+    }
+
+    /// Returns whether a turn restriction at the connector prohibits turning
+    /// from the segment onto the other one.
+    fn is_restricted(&self, from: &Segment, via: &Connector, to: &Segment) -> bool {
+        self.restrictions.get(&via.id).is_some_and(|restrictions| {
+            restrictions
+                .iter()
+                .any(|restriction| restriction.prohibits(&from.id, &to.id))
+        })
+    }
+
+    /// Applies the changes of the delta to the network.
+    ///
+    /// Segments and connectors removed or modified by the delta are dropped,
+    /// including all features sharing their IDs, before the added and
+    /// modified ones are pushed.
+    pub fn apply_delta(&mut self, delta: &NetworkDelta) {
+        self.segments
+            .retain(|segment| !delta.replaces_segment(&segment.id));
+        self.connectors
+            .retain(|connector| !delta.replaces_connector(&connector.id));
+        self.segments.extend(delta.segments().cloned());
+        self.connectors.extend(delta.connectors().cloned());
+        self.drop_prepared_graphs();
+    }
+
+    /// Returns the graph of the network with edge lengths measured in the
+    /// distance mode, prepared by the first search using the mode.
+    fn prepared_graph(&self, mode: DistanceMode) -> &PreparedGraph {
+        self.prepared[mode as usize].get_or_init(|| {
+            let _span = span!("prepare graph");
+            PreparedGraph::new(
+                &self.segments,
+                &self.connectors,
+                &self.level_crossings(),
+                mode,
+            )
+        })
+    }
+
+    /// Appends the segments and connectors added after the given numbers of
+    /// segments and connectors to the prepared graphs, or drops the graphs
+    /// if they can't be extended, e.g. because a connector was replaced.
+    fn extend_prepared_graphs(&mut self, segments: usize, connectors: usize) {
+        let (all_segments, new_connectors) = (&self.segments, &self.connectors[connectors..]);
+        for prepared in &mut self.prepared {
+            let extended = prepared.get_mut().is_none_or(|graph| {
+                new_connectors
+                    .iter()
+                    .all(|connector| graph.push_connector(connector))
+                    && (segments..all_segments.len())
+                        .all(|index| graph.push_segment(&all_segments[index], index as u32))
+            });
+            if !extended {
+                *prepared = OnceCell::new();
+            }
+        }
+    }
+
+    /// Drops the prepared graphs after changes of the network.
+    fn drop_prepared_graphs(&mut self) {
+        self.prepared = Default::default();
+    }
+
+    /// Returns the value of the route from each source to each target by
+    /// rows, with a search per source. Unreachable targets get an infinite
+    /// value, routes without a value `NaN`.
+    fn matrix_of(
+        &self,
+        sources: &[Point],
+        targets: &[Point],
+        options: &RouteOptions,
+        value: impl Fn(&Route) -> Option<f64>,
+    ) -> Result<Vec<f64>, RoutingError> {
+        let _span = span!("search matrix");
+        let mut matrix = Vec::with_capacity(sources.len() * targets.len());
+        for source in sources {
+            let routes = match self.find_routes_from(source, targets.to_vec(), options) {
+                Ok(routes) => routes,
+                Err(RoutingError::CouldNotFindRoute) => {
+                    matrix.extend(targets.iter().map(|_| f64::INFINITY));
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            matrix.extend(routes.iter().map(|route| {
+                if route.get_segments().is_empty() || route.get_gap().is_some() {
+                    f64::INFINITY
+                } else {
+                    value(route).unwrap_or(f64::NAN)
+                }
+            }));
+        }
+        Ok(matrix)
+    }
+
+    /// Returns the positions on the given number of segments nearest to the
+    /// given point, nearest first, measured in the distance mode. Only
+    /// segments passing the filter are considered.
+    pub(crate) fn find_nearest_candidates(
+        &self,
+        point: &Point,
+        count: usize,
+        mode: DistanceMode,
+        filter: impl Fn(&Segment) -> bool,
+    ) -> Vec<SegmentWithPosition> {
+        debug_log!("find {} nearest for point {:?}", count, point);
+        let geo_point = &Into::<geo::Point<f64>>::into(point.clone());
+        let closest_point = |geo_line_string: &geo::LineString<f64>| {
+            let closest = match mode {
+                DistanceMode::Planar => geo_line_string.closest_point(geo_point),
+                DistanceMode::Geodesic => geo_line_string.haversine_closest_point(geo_point),
+            };
+            match closest {
+                Closest::Intersection(closest) | Closest::SinglePoint(closest) => closest,
+                Closest::Indeterminate => {
+                    panic!("unimplemented")
+                }
+            }
+        };
+        let mut distances: Vec<_> = self
+            .segments
+            .iter()
+            .filter(|segment| filter(segment))
+            .map(|segment| {
+                let geo_line_string = Into::<geo::LineString<f64>>::into(segment.geometry.clone());
+                let distance = match mode {
+                    DistanceMode::Planar => geo_line_string.euclidean_distance(geo_point),
+                    DistanceMode::Geodesic => {
+                        closest_point(&geo_line_string).haversine_distance(geo_point)
+                    }
+                };
+                (distance, segment)
+            })
+            .collect();
+        distances.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let it: Vec<_> = distances
+            .into_iter()
+            .take(count)
+            .map(|(_, segment)| {
+                let geo_line_string = Into::<geo::LineString<f64>>::into(segment.geometry.clone());
+                let closest = closest_point(&geo_line_string);
+                SegmentWithPosition {
+                    segment: segment.clone(),
+                    position: geo_line_string.line_locate_point(&closest).unwrap(),
+                    distance: point.distance_meters(&closest.into()),
+                }
+            })
+            .collect();
+        debug_log!("found nearest {:?}", it);
+        it
+    }
+
+    /// Returns the connectors which are only inside of segments at different
+    /// levels, e.g. where a bridge crosses a road, with a connector for each
+    /// level.
+    ///
+    /// Segments are connected through them only with segments at the same
+    /// level. Connectors at the end of a segment, e.g. of a bridge joining
+    /// the road, connect all segments.
+    fn level_crossings(&self) -> LevelCrossings<'_> {
+        if self.segments.iter().all(|segment| segment.get_level() == 0) {
+            return HashMap::new();
+        }
+        let connectors: HashMap<&str, &Connector> = self
+            .connectors
+            .iter()
+            .map(|connector| (connector.id.as_str(), connector))
+            .collect();
+        let mut levels: HashMap<&str, HashSet<i32>> = HashMap::new();
+        let mut ends: HashSet<&str> = HashSet::new();
+        for segment in &self.segments {
+            let level = segment.get_level();
+            for id in segment.get_connectors() {
+                let position = connectors
+                    .get(id.as_str())
+                    .and_then(|connector| segment.get_point_position(&connector.point));
+                match position {
+                    Some(position) if position > 0.0 && position < 1.0 => {
+                        levels.entry(id).or_default().insert(level);
+                    }
+                    Some(_) => {
+                        ends.insert(id);
+                    }
+                    None => {}
+                }
+            }
+        }
+        levels
+            .into_iter()
+            .filter(|(id, levels)| levels.len() > 1 && !ends.contains(id))
+            .map(|(id, levels)| {
+                let connectors = levels
+                    .into_iter()
+                    .map(|level| {
+                        let connector = Connector {
+                            id: format!("{}@{}", id, level),
+                            ..connectors[id].clone()
+                        };
+                        (level, connector)
+                    })
+                    .collect();
+                (id, connectors)
+            })
+            .collect()
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+/// Errors while routing.
+///
+/// In JS, errors are thrown as `Error` objects whose `kind` property holds the
+/// [`RoutingErrorKind`]. Tile fetching errors additionally have a `tile`
+/// property ([z, x, y]) and a `status` property with the HTTP status (404 if
+/// the tile does not exist), if known.
+pub enum RoutingError {
+    /// The router has no segments, e.g. because no tiles could be loaded.
+    #[error("No segments added to router.")]
+    MissingSegments,
+    /// A tile could not be fetched.
+    #[error("Could not fetch tile {}/{}/{}: {message}", .coord.z, .coord.x, .coord.y)]
+    TileFetchingError {
+        /// Coordinate of the tile.
+        coord: tile::Coord,
+        /// HTTP status of the response, if any.
+        status: Option<u16>,
+        /// Description of the failure.
+        message: String,
+    },
+    /// A tile could not be parsed.
+    #[error("Could not parse tile")]
+    TileParsingError,
+    /// Start and stop are not connected.
+    #[error("Could not find route")]
+    CouldNotFindRoute,
+    /// The metadata of the tile archive could not be read.
+    #[error("Could not read archive metadata")]
+    MetadataFetchingError,
+    /// The tile archive has no tiles for the routing zoom.
+    #[error("Routing zoom is not covered by the tile archive")]
+    UnsupportedZoom,
+    /// The given region is not a valid polygon or bounding box.
+    #[error("Invalid region")]
+    InvalidRegion,
+    /// The region covers more tiles than can be downloaded at once, see
+    /// [`MAX_REGION_TILES`](crate::tile::region::MAX_REGION_TILES).
+    #[error(
+        "Region covers {0} tiles, more than the maximum of {}",
+        crate::tile::region::MAX_REGION_TILES
+    )]
+    RegionTooLarge(u64),
+    /// The offline store is not set or could not be accessed.
+    #[error("Could not access offline tile store")]
+    OfflineStoreError,
+    /// No profile is registered under the name.
+    #[error("Unknown profile {0}")]
+    UnknownProfile(String),
+    /// A route needs at least two stops.
+    #[error("At least two stops are needed")]
+    InvalidStops,
+    /// The segment or connector the route has to pass is not in the network.
+    #[error("Unknown via segment or connector {0}")]
+    UnknownVia(String),
+    /// The start snapped to a part of the network which is not connected to
+    /// the largest part, e.g. an isolated path.
+    #[error("Start is not connected to the network at segment {segment}")]
+    UnreachableStart {
+        /// ID of the segment the start snapped to.
+        segment: String,
+    },
+    /// The stop snapped to a part of the network which is not connected to
+    /// the largest part, e.g. an isolated path.
+    #[error("Stop is not connected to the network at segment {segment}")]
+    UnreachableStop {
+        /// ID of the segment the stop snapped to.
+        segment: String,
+    },
+    /// Start and stop are in different parts of the network, neither of
+    /// them the largest, e.g. because the tiles in between are missing.
+    #[error("Start and stop are in disconnected parts of the network")]
+    Disconnected,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[wasm_bindgen]
+/// Kind of a routing error, see [`RoutingError`].
+pub enum RoutingErrorKind {
+    MissingSegments,
+    TileFetchingError,
+    TileParsingError,
+    CouldNotFindRoute,
+    MetadataFetchingError,
+    UnsupportedZoom,
+    InvalidRegion,
+    RegionTooLarge,
+    OfflineStoreError,
+    UnknownProfile,
+    InvalidStops,
+    UnknownVia,
+    UnreachableStart,
+    UnreachableStop,
+    Disconnected,
+}
+
+impl RoutingError {
+    /// Returns the kind of the error.
+    pub fn kind(&self) -> RoutingErrorKind {
+        match self {
+            RoutingError::MissingSegments => RoutingErrorKind::MissingSegments,
+            RoutingError::TileFetchingError { .. } => RoutingErrorKind::TileFetchingError,
+            RoutingError::TileParsingError => RoutingErrorKind::TileParsingError,
+            RoutingError::CouldNotFindRoute => RoutingErrorKind::CouldNotFindRoute,
+            RoutingError::MetadataFetchingError => RoutingErrorKind::MetadataFetchingError,
+            RoutingError::UnsupportedZoom => RoutingErrorKind::UnsupportedZoom,
+            RoutingError::InvalidRegion => RoutingErrorKind::InvalidRegion,
+            RoutingError::RegionTooLarge(_) => RoutingErrorKind::RegionTooLarge,
+            RoutingError::OfflineStoreError => RoutingErrorKind::OfflineStoreError,
+            RoutingError::UnknownProfile(_) => RoutingErrorKind::UnknownProfile,
+            RoutingError::InvalidStops => RoutingErrorKind::InvalidStops,
+            RoutingError::UnknownVia(_) => RoutingErrorKind::UnknownVia,
+            RoutingError::UnreachableStart { .. } => RoutingErrorKind::UnreachableStart,
+            RoutingError::UnreachableStop { .. } => RoutingErrorKind::UnreachableStop,
+            RoutingError::Disconnected => RoutingErrorKind::Disconnected,
+        }
+    }
+
+    /// Returns whether no route was found in the loaded network, which may
+    /// be caused by missing tiles.
+    pub(crate) fn is_no_route(&self) -> bool {
+        matches!(
+            self,
+            RoutingError::MissingSegments
+                | RoutingError::CouldNotFindRoute
+                | RoutingError::UnreachableStart { .. }
+                | RoutingError::UnreachableStop { .. }
+                | RoutingError::Disconnected
+        )
+    }
+
+    /// Creates an error for a failed tile request.
+    pub(crate) fn tile_fetching(
+        coord: &tile::Coord,
+        err: &(dyn std::error::Error + 'static),
+    ) -> Self {
+        RoutingError::TileFetchingError {
+            coord: coord.clone(),
+            status: err
+                .downcast_ref::<FetchingError>()
+                .and_then(|err| err.status()),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<RoutingError> for JsValue {
+    fn from(err: RoutingError) -> JsValue {
+        let error = js_sys::Error::new(&err.to_string());
+        error.set_name("RoutingError");
+        let mut properties = vec![("kind", JsValue::from(err.kind()))];
+        if let RoutingError::TileFetchingError { coord, status, .. } = &err {
+            let tile = js_sys::Array::of3(
+                &JsValue::from(coord.z),
+                &JsValue::from(coord.x),
+                &JsValue::from(coord.y),
+            );
+            properties.push(("tile", tile.into()));
+            properties.push(("status", status.map_or(JsValue::UNDEFINED, JsValue::from)));
+        }
+        if let RoutingError::UnreachableStart { segment }
+        | RoutingError::UnreachableStop { segment } = &err
+        {
+            properties.push(("segment", JsValue::from_str(segment)));
+        }
+        for (key, value) in properties {
+            let _ = js_sys::Reflect::set(&error, &JsValue::from_str(key), &value);
+        }
+        error.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo_types::coord::{coord, Coord};
+    use crate::routing::Objective;
+
+    #[test]
+    /// General tests.
+    fn genereal() {
+        let router = Router::new();
+        assert_eq!(router.segments.len(), 0);
+        assert_eq!(router.connectors.len(), 0);
+    }
+
+    #[test]
+    fn bbox_empty() {
+        assert_eq!(Router::new().bbox(), None);
+    }
+
+    #[test]
+    fn bbox() {
+        let mut router = Router::new();
+        router.push_segment(Segment::new(
+            "a".into(),
+            LineString::new(vec![coord!( x: 0.0, y: 1.0 ), coord!( x: 2.0, y: 3.0 )]),
+            vec![],
+        ));
+        router.push_connector(Connector::new("b", &Point::new(-1.0, 2.0)));
+        assert_eq!(router.bbox().unwrap().to_array(), vec![-1.0, 1.0, 2.0, 3.0]);
+        // The GeoJSON only has the segments.
+        let geojson: serde_json::Value = serde_json::from_str(&router.to_geojson()).unwrap();
+        assert_eq!(geojson["bbox"], serde_json::json!([0.0, 1.0, 2.0, 3.0]));
+        assert_eq!(geojson["features"][0]["bbox"], geojson["bbox"]);
+    }
+
+    #[test]
+    fn is_roundabout() {
+        let ring = LineString::new(vec![
+            coord!( x: 0.0, y: 0.0 ),
+            coord!( x: 1.0, y: 0.0 ),
+            coord!( x: 1.0, y: 1.0 ),
+            coord!( x: 0.0, y: 0.0 ),
+        ]);
+        assert!(Segment::new("a".into(), ring, vec![]).is_roundabout());
+        let line = LineString::new(vec![coord!( x: 0.0, y: 0.0 ), coord!( x: 1.0, y: 0.0 )]);
+        let mut segment = Segment::new("b".into(), line, vec![]);
+        assert!(!segment.is_roundabout());
+        segment.set_tag("junction", "roundabout");
+        assert!(segment.is_roundabout());
+    }
+
+    #[test]
+    fn find_route_long_distances() {
+        // Costs beyond the range of integers still find the shortest route.
+        // Equal costs are ordered by connector ID, so the short way is only
+        // found late if its connector sorts before the stop.
+        let mut router = Router::new();
+        let connectors = [
+            ("a", 0.0, 0.0),
+            ("!c", 5e6, 0.0),
+            ("y", 5e6, 5e6),
+            ("z", 1e7, 0.0),
+            ("w", 1.2e7, 0.0),
+        ];
+        for (id, x, y) in connectors {
+            router.push_connector(Connector::new(id, &Point::new(x, y)));
+        }
+        let coord = |id: &str| {
+            let (_, x, y) = connectors
+                .iter()
+                .find(|(other, _, _)| *other == id)
+                .unwrap();
+            coord!(x: *x, y: *y)
+        };
+        for (from, to) in [("a", "!c"), ("!c", "z"), ("a", "y"), ("y", "z"), ("z", "w")] {
+            router.push_segment(Segment::new(
+                format!("{}{}", from, to),
+                LineString::new(vec![coord(from), coord(to)]),
+                vec![from.into(), to.into()],
+            ));
+        }
+        let route = router
+            .find_route(&Point::new(0.0, 0.0), &Point::new(1.1e7, 0.0))
+            .unwrap();
+        let ids: Vec<_> = route
+            .get_segments()
+            .iter()
+            .map(|segment| segment.get_segment().get_id())
+            .collect();
+        assert_eq!(ids, ["a!c", "!cz", "zw"]);
+    }
+
+    #[test]
+    fn roundabout_exits() {
+        let mut router = Router::new();
+        let connectors = [
+            ("w", -0.001, 0.0),
+            ("s", 0.0, -0.001),
+            ("e", 0.001, 0.0),
+            ("n", 0.0, 0.001),
+        ];
+        for (id, x, y) in connectors {
+            router.push_connector(Connector::new(id, &Point::new(x, y)));
+            router.push_connector(Connector::new(
+                &format!("{}-end", id),
+                &Point::new(x * 5.0, y * 5.0),
+            ));
+            router.push_segment(Segment::new(
+                format!("{}-arm", id),
+                LineString::new(vec![coord!(x: x * 5.0, y: y * 5.0), coord!(x: x, y: y)]),
+                vec![format!("{}-end", id), id.to_string()],
+            ));
+        }
+        // Counter-clockwise ring from west via south, east and north.
+        for (index, (from, x0, y0)) in connectors.iter().enumerate() {
+            let (to, x1, y1) = connectors[(index + 1) % connectors.len()];
+            let mut segment = Segment::new(
+                format!("{}-{}", from, to),
+                LineString::new(vec![coord!(x: *x0, y: *y0), coord!(x: x1, y: y1)]),
+                vec![from.to_string(), to.to_string()],
+            );
+            segment.set_tag("junction", "roundabout");
+            segment.set_tag("oneway", "yes");
+            router.push_segment(segment);
+        }
+        let exit = |router: &Router, x: f64, y: f64| {
+            let route = router
+                .find_route(&Point::new(-0.005, 0.0), &Point::new(x, y))
+                .unwrap();
+            let maneuvers = route.get_maneuvers();
+            assert_eq!(
+                maneuvers[0].get_roundabout_exit(),
+                route.get_segments()[1].get_roundabout_exit()
+            );
+            assert!(maneuvers[1..]
+                .iter()
+                .all(|maneuver| maneuver.get_roundabout_exit().is_none()));
+            maneuvers[0].get_roundabout_exit()
+        };
+        assert_eq!(exit(&router, 0.0, -0.005), Some(1));
+        assert_eq!(exit(&router, 0.005, 0.0), Some(2));
+        assert_eq!(exit(&router, 0.0, 0.005), Some(3));
+        // Without the southern arm, there is one exit less to pass.
+        router.segments.retain(|segment| segment.id != "s-arm");
+        router.drop_prepared_graphs();
+        assert_eq!(exit(&router, 0.005, 0.0), Some(1));
+    }
+
+    #[test]
+    fn get_coverage() {
+        let mut router = Router::new();
+        router.push_segment(Segment::new(
+            "a".into(),
+            LineString::new(vec![coord!( x: 0.05, y: 0.05 ), coord!( x: 0.25, y: 0.05 )]),
+            vec![],
+        ));
+        router.push_segment(Segment::new(
+            "b".into(),
+            LineString::new(vec![coord!( x: 0.05, y: 0.02 ), coord!( x: 0.05, y: 0.08 )]),
+            vec![],
+        ));
+        let coverage: serde_json::Value = serde_json::from_str(&router.get_coverage(0.1)).unwrap();
+        let cells = coverage["features"].as_array().unwrap();
+        assert_eq!(cells.len(), 3);
+        assert_eq!(cells[0]["properties"]["segments"], 2);
+        assert_eq!(
+            cells[0]["geometry"]["coordinates"][0][2],
+            serde_json::json!([0.1, 0.1])
+        );
+        let length = |cell: &serde_json::Value| cell["properties"]["length"].as_f64().unwrap();
+        assert!((length(&cells[0]) - 12232.0).abs() < 10.0);
+        assert!((length(&cells[1]) - 11119.0).abs() < 10.0);
+        assert_eq!(cells[2]["properties"]["segments"], 1);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&router.get_coverage(0.0)).unwrap()
+                ["features"],
+            serde_json::json!([])
+        );
+    }
+
+    #[test]
+    /// Test find_nearest method.
     fn find_nearest() {
         let mut router = Router::new();
-        assert_eq!(router.find_nearest(&Point::new(0.0, 0.0)).is_none(), true);
+        assert!(router.find_nearest(&Point::new(0.0, 0.0)).is_none());
+        router.push_segment(Segment::new(
+            "a".into(),
+            LineString::new(vec![
+                coord!( x: 0.0, y: 0.0 ),
+                coord!( x: 1.0, y: 1.0 ),
+                coord!( x: 1.0, y: 2.0 ),
+            ]),
+            vec![],
+        ));
+        router.push_segment(Segment::new(
+            "b".into(),
+            LineString::new(vec![
+                coord!( x: 2.0, y: 3.0 ),
+                coord!( x: 2.0, y: 2.0 ),
+                coord!( x: 3.0, y: 1.0 ),
+                coord!( x: 3.0, y: 0.0 ),
+            ]),
+            vec![],
+        ));
+        router.push_segment(Segment::new(
+            "c".into(),
+            LineString::new(vec![
+                coord!( x: 4.0, y: 1.0 ),
+                coord!( x: 4.0, y: 0.0 ),
+                coord!( x: 5.0, y: 0.0 ),
+            ]),
+            vec![],
+        ));
+        {
+            let nearest = router.find_nearest(&Point::new(0.0, 2.0)).unwrap();
+            assert_eq!(nearest.position, 1.0);
+            assert_eq!(nearest.segment.id, "a");
+        }
+        {
+            let nearest = router.find_nearest(&Point::new(2.0, 1.0)).unwrap();
+            assert_eq!(nearest.position, 0.5);
+            assert_eq!(nearest.segment.id, "b");
+        }
+        {
+            let nearest = router.find_nearest(&Point::new(5.0, 1.0)).unwrap();
+            assert_eq!(nearest.position, 1.0);
+            assert_eq!(nearest.get_segment_id(), "c");
+            assert!((nearest.get_distance() - 111_195.0).abs() < 1.0);
+        }
+    }
+
+    #[test]
+    fn find_route_snapped() {
+        let mut router = Router::new();
+        for (id, x) in [("a", 0.0), ("b", 1.0), ("c", 2.0)] {
+            router.push_connector(Connector::new(id, &Point::new(x, 0.0)));
+        }
+        for (id, from, to) in [("1", "a", "b"), ("2", "b", "c")] {
+            let x = |id| {
+                router
+                    .connectors
+                    .iter()
+                    .find(|c| c.id == id)
+                    .unwrap()
+                    .point
+                    .x()
+            };
+            router.push_segment(Segment::new(
+                id.into(),
+                LineString::new(vec![
+                    coord!( x: x(from), y: 0.0 ),
+                    coord!( x: x(to), y: 0.0 ),
+                ]),
+                vec![from.into(), to.into()],
+            ));
+        }
+        let start = router.find_nearest(&Point::new(0.5, 0.1)).unwrap();
+        let stop = router.find_nearest(&Point::new(1.5, -0.1)).unwrap();
+        let route = router
+            .find_route_snapped(&start, &stop, &RouteOptions::new())
+            .unwrap();
+        let segments = route.get_segments();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].get_start(), 0.5);
+        assert_eq!(segments[1].get_stop(), 0.5);
+        assert_eq!(route.get_stops()[0].y(), 0.0);
+    }
+
+    #[test]
+    fn find_route_level_crossing() {
+        let mut router = Router::new();
+        for (id, x, y) in [
+            ("a", 0.0, 0.0),
+            ("b", 2.0, 0.0),
+            ("c", 1.0, -1.0),
+            ("d", 1.0, 1.0),
+            ("x", 1.0, 0.0),
+        ] {
+            router.push_connector(Connector::new(id, &Point::new(x, y)));
+        }
+        let road = Segment::new(
+            "road".into(),
+            LineString::new(vec![coord!( x: 0.0, y: 0.0 ), coord!( x: 2.0, y: 0.0 )]),
+            vec!["a".into(), "x".into(), "b".into()],
+        );
+        let mut bridge = Segment::new(
+            "bridge".into(),
+            LineString::new(vec![coord!( x: 1.0, y: -1.0 ), coord!( x: 1.0, y: 1.0 )]),
+            vec!["c".into(), "x".into(), "d".into()],
+        );
+        bridge.set_tag("bridge", "yes");
+        assert_eq!(road.get_level(), 0);
+        assert_eq!(bridge.get_level(), 1);
+        router.push_segment(road);
+        router.push_segment(bridge.clone());
+        let (start, stop) = (Point::new(0.0, 0.0), Point::new(1.0, 1.0));
+        assert_eq!(
+            router.find_route(&start, &stop).err(),
+            Some(RoutingError::Disconnected)
+        );
+        let route = router.find_route(&Point::new(1.0, -1.0), &stop).unwrap();
+        assert_eq!(route.get_segments()[0].get_level(), 1);
+
+        // Crossings at the same level are junctions.
+        bridge.set_tag("layer", "0");
+        router.segments[1] = bridge.clone();
+        router.drop_prepared_graphs();
+        assert_eq!(
+            router
+                .find_route(&start, &stop)
+                .unwrap()
+                .get_segments()
+                .len(),
+            2
+        );
+
+        // So are connectors at the end of a segment, e.g. a ramp.
+        bridge.set_tag("layer", "1");
+        router.segments[1] = bridge;
+        router.drop_prepared_graphs();
+        router.push_connector(Connector::new("e", &Point::new(1.0, 2.0)));
+        router.push_segment(Segment::new(
+            "ramp".into(),
+            LineString::new(vec![coord!( x: 1.0, y: 0.0 ), coord!( x: 1.0, y: 2.0 )]),
+            vec!["x".into(), "e".into()],
+        ));
+        assert!(router.find_route(&start, &stop).is_ok());
+    }
+
+    #[test]
+    fn find_route_zero_length() {
+        let mut router = Router::new();
+        for (id, x) in [("a", 0.0), ("b", 1.0), ("c", 2.0)] {
+            router.push_connector(Connector::new(id, &Point::new(x, 0.0)));
+        }
+        for (id, from, to) in [("1", 0.0, 1.0), ("2", 1.0, 2.0)] {
+            router.push_segment(Segment::new(
+                id.into(),
+                LineString::new(vec![coord!( x: from, y: 0.0 ), coord!( x: to, y: 0.0 )]),
+                match id {
+                    "1" => vec!["a".into(), "b".into()],
+                    _ => vec!["b".into(), "c".into()],
+                },
+            ));
+        }
+        let point = Point::new(0.5, 0.1);
+        let route = router.find_route(&point, &point).unwrap();
+        let segments = route.get_segments();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].get_start(), 0.5);
+        assert_eq!(segments[0].get_stop(), 0.5);
+        assert_eq!(route.get_distance(), 0.0);
+        assert_eq!(route.get_geometry().0.len(), 2);
+        assert!(route.get_maneuvers().is_empty());
+        assert_eq!(route.get_stats().get_nodes_expanded(), 0);
+
+        // Snapped to the ends of different segments at the same connector.
+        let end_of_1 = SegmentWithPosition {
+            segment: router.segments[0].clone(),
+            position: 1.0,
+            distance: 0.0,
+        };
+        let start_of_2 = SegmentWithPosition {
+            segment: router.segments[1].clone(),
+            position: 0.0,
+            distance: 0.0,
+        };
+        let route = router
+            .find_route_snapped(&end_of_1, &start_of_2, &RouteOptions::new())
+            .unwrap();
+        assert_eq!(route.get_segments().len(), 1);
+        assert_eq!(route.get_distance(), 0.0);
+
+        let routes = router
+            .find_routes_from(
+                &point,
+                vec![Point::new(0.5, -0.1), Point::new(1.5, 0.0)],
+                &RouteOptions::new(),
+            )
+            .unwrap();
+        assert_eq!(routes[0].get_distance(), 0.0);
+        assert_eq!(routes[1].get_segments().len(), 2);
+    }
+
+    #[test]
+    /// Test find_route method.
+    fn find_route_away_from_points() {
+        let mut router = Router::new();
+        router.push_segment(Segment::new(
+            "1".into(),
+            LineString::new(vec![coord!( x: 1.0, y: 0.0 ), coord!( x: 9.0, y: 0.0 )]),
+            vec![],
+        ));
+        let route = router
+            .find_route(&Point::new(0.0, 0.0), &Point::new(10.0, 0.0))
+            .unwrap();
+        assert_eq!(route.get_segments().len(), 1);
+        let segment = &route.get_segments()[0];
+        assert_eq!(segment.get_segment().get_id(), "1");
+        assert_eq!(segment.get_start(), 0.0);
+        assert_eq!(segment.get_stop(), 1.0);
+        let stats = route.get_stats();
+        assert!((stats.get_start_snap_distance() - 111_195.0).abs() < 1.0);
+        assert!((stats.get_stop_snap_distance() - 111_195.0).abs() < 1.0);
+        assert_eq!(stats.get_nodes_expanded(), 1);
+        assert_eq!(stats.get_heap_pushes(), 2);
+        assert_eq!(stats.get_tiles_used(), 0);
+    }
+
+    #[test]
+    fn isochrone() {
+        let mut router = Router::new();
+        assert_eq!(
+            router.isochrone(&Point::new(0.0, 0.0), 1.0).err(),
+            Some(RoutingError::MissingSegments)
+        );
+        router.push_connector(Connector::new("a", &Point::new(0.0, 0.0)));
+        router.push_connector(Connector::new("b", &Point::new(1.0, 0.0)));
+        router.push_connector(Connector::new("c", &Point::new(3.0, 0.0)));
+        router.push_segment(Segment::new(
+            "1".into(),
+            LineString::new(vec![coord!( x: 0.0, y: 0.0 ), coord!( x: 1.0, y: 0.0 )]),
+            vec!["a".into(), "b".into()],
+        ));
+        router.push_segment(Segment::new(
+            "2".into(),
+            LineString::new(vec![coord!( x: 1.0, y: 0.0 ), coord!( x: 3.0, y: 0.0 )]),
+            vec!["b".into(), "c".into()],
+        ));
+        let reachable = |max_length| {
+            let geojson: serde_json::Value =
+                serde_json::from_str(&router.isochrone(&Point::new(0.0, 0.1), max_length).unwrap())
+                    .unwrap();
+            geojson["features"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|feature| {
+                    (
+                        feature["id"].as_str().unwrap().to_string(),
+                        feature["properties"]["distance"].as_f64().unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        // The start is snapped onto connector "a".
+        assert_eq!(reachable(0.5), vec![("a".into(), 0.0)]);
+        assert_eq!(reachable(1.0), vec![("a".into(), 0.0), ("b".into(), 1.0)]);
+        assert_eq!(
+            reachable(5.0),
+            vec![("a".into(), 0.0), ("b".into(), 1.0), ("c".into(), 3.0)]
+        );
+    }
+
+    #[test]
+    fn find_route_no_route() {
+        let mut router = Router::new();
+        router.push_segment(Segment::new(
+            "1".into(),
+            LineString::new(vec![coord!( x: 1.0, y: 0.0 ), coord!( x: 4.0, y: 0.0 )]),
+            vec![],
+        ));
+        router.push_segment(Segment::new(
+            "2".into(),
+            LineString::new(vec![coord!( x: 5.0, y: 0.0 ), coord!( x: 8.0, y: 0.0 )]),
+            vec![],
+        ));
+        let route = router.find_route(&Point::new(0.0, 0.0), &Point::new(10.0, 0.0));
+        assert_eq!(route.err().unwrap(), RoutingError::Disconnected);
+    }
+
+    #[test]
+    fn find_route_unreachable() {
+        let mut router = Router::new();
+        for (id, x) in [
+            ("a", 0.0),
+            ("b", 1.0),
+            ("c", 2.0),
+            ("d", 3.0),
+            ("e", 5.0),
+            ("f", 6.0),
+        ] {
+            router.push_connector(Connector::new(id, &Point::new(x, 0.0)));
+        }
+        for (id, from, to, connectors) in [
+            ("1", 0.0, 1.0, ["a", "b"]),
+            ("2", 1.0, 2.0, ["b", "c"]),
+            ("3", 2.0, 3.0, ["c", "d"]),
+            ("island", 5.0, 6.0, ["e", "f"]),
+        ] {
+            router.push_segment(Segment::new(
+                id.into(),
+                LineString::new(vec![coord!( x: from, y: 0.0 ), coord!( x: to, y: 0.0 )]),
+                connectors.iter().map(|id| id.to_string()).collect(),
+            ));
+        }
+        let (main, island) = (Point::new(0.5, 0.1), Point::new(5.5, 0.1));
+        assert_eq!(
+            router.find_route(&island, &main).err(),
+            Some(RoutingError::UnreachableStart {
+                segment: "island".into()
+            })
+        );
+        assert_eq!(
+            router.find_route(&main, &island).err(),
+            Some(RoutingError::UnreachableStop {
+                segment: "island".into()
+            })
+        );
+        let mut options = RouteOptions::new();
+        options.set_blocked_segments(vec!["2".into()]);
+        assert_eq!(
+            router
+                .find_route_with_options(&Point::new(2.5, 0.1), &island, &options)
+                .err(),
+            Some(RoutingError::Disconnected)
+        );
+        assert_eq!(
+            RoutingError::UnreachableStop {
+                segment: "island".into()
+            }
+            .kind(),
+            RoutingErrorKind::UnreachableStop
+        );
+    }
+
+    #[test]
+    fn find_route_partial() {
+        let mut router = Router::new();
+        for (id, x) in [("a", 1.0), ("b", 4.0), ("c", 5.0), ("d", 8.0)] {
+            router.push_connector(Connector::new(id, &Point::new(x, 0.0)));
+        }
+        router.push_segment(Segment::new(
+            "1".into(),
+            LineString::new(vec![coord!( x: 1.0, y: 0.0 ), coord!( x: 4.0, y: 0.0 )]),
+            vec!["a".into(), "b".into()],
+        ));
+        router.push_segment(Segment::new(
+            "2".into(),
+            LineString::new(vec![coord!( x: 5.0, y: 0.0 ), coord!( x: 8.0, y: 0.0 )]),
+            vec!["c".into(), "d".into()],
+        ));
+        let (start, stop) = (Point::new(2.0, 0.1), Point::new(10.0, 0.0));
+        let mut options = RouteOptions::new();
+        assert_eq!(
+            router
+                .find_route_with_options(&start, &stop, &options)
+                .err(),
+            Some(RoutingError::Disconnected)
+        );
+        options.set_allow_partial(true);
+        let route = router
+            .find_route_with_options(&start, &stop, &options)
+            .unwrap();
+        let segments = route.get_segments();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].get_segment().get_id(), "1");
+        assert_eq!(segments[0].get_stop(), 1.0);
+        assert!((route.get_gap().unwrap() - 6.0 * 111_195.0).abs() < 10.0);
+
+        // Complete routes have no gap.
+        let route = router
+            .find_route_with_options(&start, &Point::new(3.0, 0.0), &options)
+            .unwrap();
+        assert_eq!(route.get_gap(), None);
+    }
+
+    #[test]
+    fn matrix() {
+        let mut router = Router::new();
+        for (id, x, y) in [
+            ("a", 0.0, 0.0),
+            ("b", 0.01, 0.0),
+            ("c", 0.02, 0.0),
+            ("e", 1.0, 1.0),
+            ("f", 1.01, 1.0),
+        ] {
+            router.push_connector(Connector::new(id, &Point::new(x, y)));
+        }
+        for (id, from, to) in [("1", "a", "b"), ("2", "b", "c"), ("3", "e", "f")] {
+            let point = |id| {
+                router
+                    .connectors
+                    .iter()
+                    .find(|c| c.id == id)
+                    .unwrap()
+                    .point
+                    .clone()
+            };
+            let geometry = LineString::new(vec![point(from).into(), point(to).into()]);
+            router.push_segment(Segment::new(
+                id.into(),
+                geometry,
+                vec![from.into(), to.into()],
+            ));
+        }
+        let sources = vec![Point::new(0.0, 0.0), Point::new(1.0, 1.0)];
+        let targets = vec![Point::new(0.02, 0.0), Point::new(1.01, 1.0)];
+        let matrix = router.matrix(sources.clone(), targets.clone()).unwrap();
+        assert_eq!(matrix.len(), 4);
+        assert!((matrix[0] - 2224.0).abs() < 1.0);
+        assert_eq!(matrix[1], f64::INFINITY);
+        assert_eq!(matrix[2], f64::INFINITY);
+        assert!((matrix[3] - 1112.0).abs() < 1.0);
+
+        let durations = router
+            .duration_matrix(sources, targets, &RouteOptions::new())
+            .unwrap();
+        assert!(durations[0].is_nan());
+        assert_eq!(durations[1], f64::INFINITY);
+    }
+
+    #[test]
+    fn find_routes_from() {
+        let mut router = Router::new();
+        for (id, x, y) in [
+            ("a", 0.0, 0.0),
+            ("b", 1.0, 0.0),
+            ("c", 2.0, 0.0),
+            ("d", 3.0, 0.0),
+            ("e", 10.0, 10.0),
+            ("f", 11.0, 10.0),
+        ] {
+            router.push_connector(Connector::new(id, &Point::new(x, y)));
+        }
+        for (id, from, to) in [
+            ("1", "a", "b"),
+            ("2", "b", "c"),
+            ("3", "c", "d"),
+            ("4", "e", "f"),
+        ] {
+            let point = |id| {
+                router
+                    .connectors
+                    .iter()
+                    .find(|c| c.id == id)
+                    .unwrap()
+                    .point
+                    .clone()
+            };
+            let geometry = LineString::new(vec![point(from).into(), point(to).into()]);
+            router.push_segment(Segment::new(
+                id.into(),
+                geometry,
+                vec![from.into(), to.into()],
+            ));
+        }
+        let start = Point::new(1.5, 0.1);
+        let stops = vec![
+            Point::new(0.5, -0.1),
+            Point::new(2.9, 0.1),
+            Point::new(10.5, 10.1),
+        ];
+        let ids = |route: &Route| -> Vec<String> {
+            route
+                .get_segments()
+                .iter()
+                .map(|segment| segment.get_segment().get_id())
+                .collect()
+        };
+        let mut options = RouteOptions::new();
+        let routes = router
+            .find_routes_from(&start, stops.clone(), &options)
+            .unwrap();
+        assert_eq!(routes.len(), 3);
+        for (route, stop) in routes.iter().zip(&stops).take(2) {
+            let single = router.find_route(&start, stop).unwrap();
+            assert_eq!(ids(route), ids(&single));
+            assert_eq!(route.get_stops()[1].x(), stop.x());
+        }
+        assert_eq!(ids(&routes[0]), vec!["2", "1"]);
+        assert_eq!(ids(&routes[1]), vec!["2", "3"]);
+        // The island is not reachable.
+        assert!(ids(&routes[2]).is_empty());
+        assert_eq!(routes[2].get_gap(), None);
+
+        options.set_allow_partial(true);
+        let routes = router.find_routes_from(&start, stops, &options).unwrap();
+        assert_eq!(ids(&routes[2]), vec!["2", "3"]);
+        assert!(routes[2].get_gap().is_some());
+    }
+
+    #[test]
+    fn find_route_away_from_start() {
+        let mut router = Router::new();
+        router.push_connector(Connector::new("a", &Point::new(3.0, 0.0)));
+        router.push_connector(Connector::new("b", &Point::new(6.0, 0.0)));
+        router.push_segment(Segment::new(
+            "1".into(),
+            LineString::new(vec![coord!( x: 1.0, y: 0.0 ), coord!( x: 4.0, y: 0.0 )]),
+            vec!["a".into(), "b".into()],
+        ));
+        router.push_segment(Segment::new(
+            "2".into(),
+            LineString::new(vec![coord!( x: 5.0, y: 0.0 ), coord!( x: 8.0, y: 0.0 )]),
+            vec!["a".into(), "b".into()],
+        ));
+        let route = router
+            .find_route(&Point::new(0.0, 0.0), &Point::new(10.0, 0.0))
+            .unwrap();
+        assert_eq!(route.get_segments().len(), 2);
+        {
+            let segment = &route.get_segments()[0];
+            assert_eq!(segment.get_segment().get_id(), "1");
+            assert_eq!(segment.get_start(), 0.0);
+            assert_eq!(segment.get_stop(), 1.0);
+        }
+        {
+            let segment = &route.get_segments()[1];
+            assert_eq!(segment.get_segment().get_id(), "2");
+            assert_eq!(segment.get_start(), 1.0 / 3.0);
+            assert_eq!(segment.get_stop(), 1.0);
+        }
+    }
+
+    #[test]
+    fn find_route_interior_connectors() {
+        let mut router = Router::new();
+        for (id, x, y) in [
+            ("e", -1.0, 0.0),
+            ("a", 0.0, 0.0),
+            ("m", 5.0, 0.0),
+            ("b", 10.0, 0.0),
+            ("c", 5.0, 3.0),
+        ] {
+            router.push_connector(Connector::new(id, &Point::new(x, y)));
+        }
+        for (id, coords, connectors) in [
+            ("s", vec![(-1.0, 0.0), (0.0, 0.0)], vec!["e", "a"]),
+            // "m" lies in the middle of the segment.
+            ("long", vec![(0.0, 0.0), (10.0, 0.0)], vec!["a", "m", "b"]),
+            ("side", vec![(5.0, 0.0), (5.0, 3.0)], vec!["m", "c"]),
+            (
+                "detour",
+                vec![(0.0, 0.0), (0.0, 3.5), (5.0, 3.5), (5.0, 3.0)],
+                vec!["a", "c"],
+            ),
+        ] {
+            router.push_segment(Segment::new(
+                id.into(),
+                LineString::new(
+                    coords
+                        .into_iter()
+                        .map(|(x, y)| coord!( x: x, y: y ))
+                        .collect(),
+                ),
+                connectors.into_iter().map(String::from).collect(),
+            ));
+        }
+        let route = router
+            .find_route(&Point::new(-1.0, -0.1), &Point::new(5.1, 2.5))
+            .unwrap();
+        let segments: Vec<_> = route
+            .get_segments()
+            .iter()
+            .map(|segment| {
+                (
+                    segment.get_segment().get_id(),
+                    segment.get_start(),
+                    segment.get_stop(),
+                )
+            })
+            .collect();
+        // Only half of "long" is travelled, which is shorter than the detour.
+        assert_eq!(
+            segments,
+            vec![
+                ("s".into(), 0.0, 1.0),
+                ("long".into(), 0.0, 0.5),
+                ("side".into(), 0.0, 2.5 / 3.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn find_route_snap_candidates() {
+        let mut router = Router::new();
+        for (id, x, y) in [
+            ("a", 0.0, 0.5),
+            ("b", 10.0, 0.5),
+            ("c", 0.0, -0.6),
+            ("d", 10.0, -0.6),
+        ] {
+            router.push_connector(Connector::new(id, &Point::new(x, y)));
+        }
+        router.push_segment(Segment::new(
+            "parallel".into(),
+            LineString::new(vec![coord!( x: 0.0, y: 0.5 ), coord!( x: 10.0, y: 0.5 )]),
+            vec!["a".into(), "b".into()],
+        ));
+        router.push_segment(Segment::new(
+            "direct".into(),
+            LineString::new(vec![coord!( x: 0.0, y: -0.6 ), coord!( x: 10.0, y: -0.6 )]),
+            vec!["c".into(), "d".into()],
+        ));
+        router.push_segment(Segment::new(
+            "detour".into(),
+            LineString::new(vec![
+                coord!( x: 10.0, y: 0.5 ),
+                coord!( x: 10.0, y: 20.0 ),
+                coord!( x: 20.0, y: 20.0 ),
+                coord!( x: 20.0, y: -0.6 ),
+                coord!( x: 10.0, y: -0.6 ),
+            ]),
+            vec!["b".into(), "d".into()],
+        ));
+        let ids = |route: Route| -> Vec<String> {
+            route
+                .get_segments()
+                .iter()
+                .map(|segment| segment.get_segment().get_id())
+                .collect()
+        };
+        let (start, stop) = (Point::new(5.0, 0.0), Point::new(9.0, -0.6));
+        assert_eq!(
+            ids(router.find_route(&start, &stop).unwrap()),
+            vec!["parallel", "detour", "direct"]
+        );
+        let mut options = RouteOptions::new();
+        options.set_snap_candidates(2);
+        assert_eq!(
+            ids(router
+                .find_route_with_options(&start, &stop, &options)
+                .unwrap()),
+            vec!["direct"]
+        );
+    }
+
+    #[test]
+    fn find_route_turn_restriction() {
+        let mut router = Router::new();
+        for (id, x) in [("a", 0.0), ("x", 1.0), ("b", 2.0)] {
+            router.push_connector(Connector::new(id, &Point::new(x, 0.0)));
+        }
+        for (id, coords, connectors) in [
+            ("in", vec![(0.0, 0.0), (1.0, 0.0)], ["a", "x"]),
+            ("direct", vec![(1.0, 0.0), (2.0, 0.0)], ["x", "b"]),
+            (
+                "detour",
+                vec![(1.0, 0.0), (1.0, 1.0), (2.0, 1.0), (2.0, 0.0)],
+                ["x", "b"],
+            ),
+        ] {
+            router.push_segment(Segment::new(
+                id.into(),
+                LineString::new(
+                    coords
+                        .into_iter()
+                        .map(|(x, y)| coord!( x: x, y: y ))
+                        .collect(),
+                ),
+                connectors.iter().map(|id| id.to_string()).collect(),
+            ));
+        }
+        let uses_detour = |router: &Router| {
+            router
+                .find_route(&Point::new(0.0, 0.0), &Point::new(2.0, 0.0))
+                .unwrap()
+                .get_segments()
+                .iter()
+                .any(|segment| segment.get_segment().get_id() == "detour")
+        };
+        assert!(!uses_detour(&router));
+        let mut restricted = Router::new();
+        restricted.extend(&router);
+        restricted.push_turn_restriction(TurnRestriction::new(
+            "in".into(),
+            "x".into(),
+            "direct".into(),
+        ));
+        assert!(uses_detour(&restricted));
+        let mut restricted = Router::new();
+        restricted.extend(&router);
+        restricted.push_turn_restriction(TurnRestriction::only(
+            "in".into(),
+            "x".into(),
+            "detour".into(),
+        ));
+        assert!(uses_detour(&restricted));
+    }
+
+    #[test]
+    fn find_route_turn_restriction_other_way_in() {
+        let mut router = Router::new();
+        for (id, x) in [("S", 0.0), ("A", 1.0), ("X", 2.0), ("B", 3.0)] {
+            router.push_connector(Connector::new(id, &Point::new(x, 0.0)));
+        }
+        for (id, coords, connectors) in [
+            ("lead", vec![(0.0, 0.0), (1.0, 0.0)], ["S", "A"]),
+            ("in", vec![(1.0, 0.0), (2.0, 0.0)], ["A", "X"]),
+            (
+                "alt_in",
+                vec![(1.0, 0.0), (1.0, 1.0), (2.0, 1.0), (2.0, 0.0)],
+                ["A", "X"],
+            ),
+            ("out", vec![(2.0, 0.0), (3.0, 0.0)], ["X", "B"]),
+        ] {
+            router.push_segment(Segment::new(
+                id.into(),
+                LineString::new(
+                    coords
+                        .into_iter()
+                        .map(|(x, y)| coord!( x: x, y: y ))
+                        .collect(),
+                ),
+                connectors.iter().map(|id| id.to_string()).collect(),
+            ));
+        }
+        router.push_turn_restriction(TurnRestriction::new("in".into(), "X".into(), "out".into()));
+        // X is reached first on `in`, but may still be reached on `alt_in` to
+        // turn onto `out`.
+        let route = router
+            .find_route(&Point::new(0.0, 0.0), &Point::new(3.0, 0.0))
+            .unwrap();
+        let ids: Vec<_> = route
+            .get_segments()
+            .iter()
+            .map(|segment| segment.get_segment().get_id())
+            .collect();
+        assert_eq!(ids, vec!["lead", "alt_in", "out"]);
+    }
+
+    #[test]
+    fn find_route_oneway() {
+        let mut router = Router::new();
+        router.push_connector(Connector::new("a", &Point::new(0.0, 0.0)));
+        router.push_connector(Connector::new("b", &Point::new(1.0, 0.0)));
+        let mut short = Segment::new(
+            "short".into(),
+            LineString::new(vec![coord!( x: 0.0, y: 0.0 ), coord!( x: 1.0, y: 0.0 )]),
+            vec!["a".into(), "b".into()],
+        );
+        short.set_direction(Direction::Forward);
+        router.push_segment(short);
         router.push_segment(Segment::new(
-            "a".into(),
+            "long".into(),
             LineString::new(vec![
                 coord!( x: 0.0, y: 0.0 ),
+                coord!( x: 0.0, y: 1.0 ),
                 coord!( x: 1.0, y: 1.0 ),
-                coord!( x: 1.0, y: 2.0 ),
+                coord!( x: 1.0, y: 0.0 ),
             ]),
-            vec![],
+            vec!["a".into(), "b".into()],
         ));
+        let uses_long = |route: Route| {
+            route
+                .get_segments()
+                .iter()
+                .any(|segment| segment.get_segment().get_id() == "long")
+        };
+        let (a, b) = (Point::new(0.0, 0.0), Point::new(1.0, 0.0));
+        assert!(!uses_long(router.find_route(&a, &b).unwrap()));
+        assert!(uses_long(router.find_route(&b, &a).unwrap()));
+    }
+
+    #[test]
+    fn find_route_geodesic() {
+        // At 60° north, a degree of longitude is half as long as a degree of
+        // latitude.
+        let mut router = Router::new();
+        router.push_connector(Connector::new("a", &Point::new(0.0, 60.0)));
+        router.push_connector(Connector::new("b", &Point::new(0.03, 60.0)));
         router.push_segment(Segment::new(
-            "b".into(),
+            "north".into(),
             LineString::new(vec![
-                coord!( x: 2.0, y: 3.0 ),
-                coord!( x: 2.0, y: 2.0 ),
-                coord!( x: 3.0, y: 1.0 ),
-                coord!( x: 3.0, y: 0.0 ),
+                coord!( x: 0.0, y: 60.0 ),
+                coord!( x: 0.0, y: 60.02 ),
+                coord!( x: 0.03, y: 60.02 ),
+                coord!( x: 0.03, y: 60.0 ),
             ]),
-            vec![],
+            vec!["a".into(), "b".into()],
         ));
         router.push_segment(Segment::new(
-            "c".into(),
+            "south".into(),
             LineString::new(vec![
-                coord!( x: 4.0, y: 1.0 ),
-                coord!( x: 4.0, y: 0.0 ),
-                coord!( x: 5.0, y: 0.0 ),
+                coord!( x: 0.0, y: 60.0 ),
+                coord!( x: -0.015, y: 59.999 ),
+                coord!( x: 0.045, y: 59.999 ),
+                coord!( x: 0.03, y: 60.0 ),
             ]),
-            vec![],
+            vec!["a".into(), "b".into()],
         ));
-        {
-            let nearest = router.find_nearest(&Point::new(0.0, 2.0)).unwrap();
-            assert_eq!(nearest.position, 1.0);
-            assert_eq!(nearest.segment.id, "a");
-        }
-        {
-            let nearest = router.find_nearest(&Point::new(2.0, 1.0)).unwrap();
-            assert_eq!(nearest.position, 0.5);
-            assert_eq!(nearest.segment.id, "b");
-        }
-        {
-            let nearest = router.find_nearest(&Point::new(5.0, 1.0)).unwrap();
-            assert_eq!(nearest.position, 1.0);
-            assert_eq!(nearest.segment.id, "c");
+        // Start and stop are snapped onto the north segment, so the route
+        // passes the south segment only if it takes it.
+        let uses_south = |route: Route| {
+            route
+                .get_segments()
+                .iter()
+                .any(|segment| segment.get_segment().get_id() == "south")
+        };
+        let (start, stop) = (Point::new(0.0, 60.0), Point::new(0.03, 60.0));
+        assert!(!uses_south(router.find_route(&start, &stop).unwrap()));
+        let mut options = RouteOptions::new();
+        options.set_distance_mode(DistanceMode::Geodesic);
+        assert!(uses_south(
+            router
+                .find_route_with_options(&start, &stop, &options)
+                .unwrap()
+        ));
+
+        let point = Point::new(-0.012, 60.006);
+        assert_eq!(
+            router.find_nearest(&point).unwrap().get_segment_id(),
+            "south"
+        );
+        let nearest = router.find_nearest_with_options(&point, &options).unwrap();
+        assert_eq!(nearest.get_segment_id(), "north");
+        assert!((nearest.get_distance() - 667.0).abs() < 5.0);
+    }
+
+    #[test]
+    fn prepared_graph() {
+        let mut router = Router::new();
+        for (id, x, y) in [("a", 0.0, 0.0), ("b", 2.0, 0.0)] {
+            router.push_connector(Connector::new(id, &Point::new(x, y)));
         }
+        router.push_segment(Segment::new(
+            "detour".into(),
+            LineString::new(vec![
+                coord!( x: 0.0, y: 0.0 ),
+                coord!( x: 1.0, y: 5.0 ),
+                coord!( x: 2.0, y: 0.0 ),
+            ]),
+            vec!["a".into(), "b".into()],
+        ));
+        let (start, stop) = (Point::new(0.0, 0.0), Point::new(2.0, 0.0));
+        let length = |router: &Router| router.find_route(&start, &stop).unwrap().get_distance();
+        let detour = length(&router);
+        assert!(router.prepared[DistanceMode::Planar as usize]
+            .get()
+            .is_some());
+        assert!(router.prepared[DistanceMode::Geodesic as usize]
+            .get()
+            .is_none());
+        assert_eq!(length(&router), detour);
+
+        // Added features are appended to the prepared graph.
+        router.push_connector(Connector::new("c", &Point::new(1.0, 0.0)));
+        router.push_segment(Segment::new(
+            "direct".into(),
+            LineString::new(vec![coord!( x: 0.0, y: 0.0 ), coord!( x: 2.0, y: 0.0 )]),
+            vec!["a".into(), "c".into(), "b".into()],
+        ));
+        assert!(router.prepared[DistanceMode::Planar as usize]
+            .get()
+            .is_some());
+        let direct = length(&router);
+        assert!(direct < detour);
+
+        // Replaced connectors and new level crossings drop it.
+        router.push_connector(Connector::new("c", &Point::new(1.0, 0.0)));
+        assert!(router.prepared[DistanceMode::Planar as usize]
+            .get()
+            .is_none());
+        assert_eq!(length(&router), direct);
+        router.push_connector(Connector::new("d", &Point::new(1.0, -1.0)));
+        router.push_connector(Connector::new("e", &Point::new(1.0, 1.0)));
+        assert!(router.prepared[DistanceMode::Planar as usize]
+            .get()
+            .is_some());
+        let mut bridge = Segment::new(
+            "bridge".into(),
+            LineString::new(vec![coord!( x: 1.0, y: -1.0 ), coord!( x: 1.0, y: 1.0 )]),
+            vec!["d".into(), "c".into(), "e".into()],
+        );
+        bridge.set_tag("layer", "1");
+        router.push_segment(bridge);
+        assert!(router.prepared[DistanceMode::Planar as usize]
+            .get()
+            .is_none());
+        assert_eq!(length(&router), direct);
+        // The connector of the level crossing is numbered before appended
+        // connectors.
+        router.push_connector(Connector::new("f", &Point::new(2.0, 1.0)));
+        router.push_segment(Segment::new(
+            "spur".into(),
+            LineString::new(vec![coord!( x: 2.0, y: 0.0 ), coord!( x: 2.0, y: 1.0 )]),
+            vec!["b".into(), "f".into()],
+        ));
+        assert!(router.prepared[DistanceMode::Planar as usize]
+            .get()
+            .is_some());
+        let route = router.find_route(&start, &Point::new(2.0, 1.0)).unwrap();
+        assert_eq!(
+            route.get_segments().last().unwrap().get_segment().get_id(),
+            "spur"
+        );
+        assert!(route.get_distance() > direct);
     }
 
     #[test]
-    /// Test find_route method.
-    fn find_route_away_from_points() {
+    fn find_route_revisit_penalty() {
         let mut router = Router::new();
+        for (id, x, y) in [
+            ("a", 0.0, 0.0),
+            ("b", 1.0, 0.0),
+            ("c", 1.0, 1.0),
+            ("d", 2.0, 1.0),
+            ("e", 0.0, -1.0),
+        ] {
+            router.push_connector(Connector::new(id, &Point::new(x, y)));
+        }
         router.push_segment(Segment::new(
-            "1".into(),
-            LineString::new(vec![coord!( x: 1.0, y: 0.0 ), coord!( x: 9.0, y: 0.0 )]),
-            vec![],
+            "s".into(),
+            LineString::new(vec![coord!( x: 0.0, y: -1.0 ), coord!( x: 0.0, y: 0.0 )]),
+            vec!["e".into(), "a".into()],
+        ));
+        // Two parts of the same segment "x", e.g. clipped at a tile border.
+        router.push_segment(Segment::new(
+            "x".into(),
+            LineString::new(vec![coord!( x: 0.0, y: 0.0 ), coord!( x: 1.0, y: 0.0 )]),
+            vec!["a".into(), "b".into()],
+        ));
+        router.push_segment(Segment::new(
+            "x".into(),
+            LineString::new(vec![coord!( x: 1.0, y: 0.0 ), coord!( x: 1.0, y: 1.0 )]),
+            vec!["b".into(), "c".into()],
+        ));
+        router.push_segment(Segment::new(
+            "y".into(),
+            LineString::new(vec![
+                coord!( x: 0.0, y: 0.0 ),
+                coord!( x: -1.0, y: 0.0 ),
+                coord!( x: -1.0, y: 1.0 ),
+                coord!( x: 1.0, y: 1.0 ),
+            ]),
+            vec!["a".into(), "c".into()],
         ));
+        router.push_segment(Segment::new(
+            "z".into(),
+            LineString::new(vec![coord!( x: 1.0, y: 1.0 ), coord!( x: 2.0, y: 1.0 )]),
+            vec!["c".into(), "d".into()],
+        ));
+        let ids = |options: &RouteOptions| -> Vec<String> {
+            router
+                .find_route_with_options(&Point::new(-0.1, -0.5), &Point::new(1.5, 1.1), options)
+                .unwrap()
+                .get_segments()
+                .iter()
+                .map(|segment| segment.get_segment().get_id())
+                .collect()
+        };
+        let mut options = RouteOptions::new();
+        assert_eq!(ids(&options), vec!["s", "x", "x", "z"]);
+        options.set_revisit_penalty(10.0);
+        assert_eq!(ids(&options), vec!["s", "y", "z"]);
+        options.set_revisit_penalty(f64::INFINITY);
+        assert_eq!(ids(&options), vec!["s", "y", "z"]);
+    }
+
+    #[test]
+    fn find_route_turn_penalty() {
+        // A 3x3 grid, where several shortest routes exist.
+        let mut router = Router::new();
+        for x in 0..3 {
+            for y in 0..3 {
+                let id = format!("{}{}", x, y);
+                router.push_connector(Connector::new(&id, &Point::new(x as f64, y as f64)));
+                for (kind, dx, dy) in [("h", 1, 0), ("v", 0, 1)] {
+                    if x + dx > 2 || y + dy > 2 {
+                        continue;
+                    }
+                    let to = ((x + dx) as f64, (y + dy) as f64);
+                    router.push_segment(Segment::new(
+                        format!("{}{}", kind, id),
+                        LineString::new(vec![
+                            coord!( x: x as f64, y: y as f64 ),
+                            coord!( x: to.0, y: to.1 ),
+                        ]),
+                        vec![id.clone(), format!("{}{}", x + dx, y + dy)],
+                    ));
+                }
+            }
+        }
+        let turns = |options: &RouteOptions| -> usize {
+            let route = router
+                .find_route_with_options(&Point::new(0.5, -0.1), &Point::new(2.1, 1.5), options)
+                .unwrap();
+            let kinds: Vec<_> = route
+                .get_segments()
+                .iter()
+                .map(|segment| segment.get_segment().get_id().remove(0))
+                .collect();
+            kinds.windows(2).filter(|pair| pair[0] != pair[1]).count()
+        };
+        let mut options = RouteOptions::new();
+        assert!(turns(&options) >= 1);
+        options.set_turn_penalty(0.5);
+        assert_eq!(turns(&options), 1);
+    }
+
+    #[test]
+    fn find_route_region() {
+        let mut router = Router::new();
+        for (id, x, y) in [
+            ("w", -1.0, 0.0),
+            ("a", 0.0, 0.0),
+            ("b", 10.0, 0.0),
+            ("e", 11.0, 0.0),
+        ] {
+            router.push_connector(Connector::new(id, &Point::new(x, y)));
+        }
+        for (id, coords, connectors) in [
+            ("start", vec![(-1.0, 0.0), (0.0, 0.0)], ["w", "a"]),
+            (
+                "road",
+                vec![(0.0, 0.0), (5.0, 3.0), (10.0, 0.0)],
+                ["a", "b"],
+            ),
+            (
+                "path",
+                vec![(0.0, 0.0), (0.0, -2.0), (10.0, -2.0), (10.0, 0.0)],
+                ["a", "b"],
+            ),
+            ("stop", vec![(10.0, 0.0), (11.0, 0.0)], ["b", "e"]),
+        ] {
+            router.push_segment(Segment::new(
+                id.into(),
+                LineString::new(
+                    coords
+                        .into_iter()
+                        .map(|(x, y)| coord!( x: x, y: y ))
+                        .collect(),
+                ),
+                connectors.iter().map(|id| id.to_string()).collect(),
+            ));
+        }
+        let (start, stop) = (Point::new(-0.5, 0.1), Point::new(10.5, 0.1));
+        let ids = |options: &RouteOptions| -> Vec<String> {
+            router
+                .find_route_with_options(&start, &stop, options)
+                .unwrap()
+                .get_segments()
+                .iter()
+                .map(|segment| segment.get_segment().get_id())
+                .collect()
+        };
+        let mut options = RouteOptions::new();
+        assert_eq!(ids(&options), vec!["start", "road", "stop"]);
+        options.set_bounds(vec![-2.0, -3.0, 12.0, 1.0]).unwrap();
+        assert_eq!(ids(&options), vec!["start", "path", "stop"]);
+        options
+            .set_polygon(vec![-2.0, -3.0, 12.0, -3.0, 12.0, 1.0, -2.0, 1.0])
+            .unwrap();
+        assert_eq!(ids(&options), vec!["start", "path", "stop"]);
+        options.set_bounds(vec![20.0, 20.0, 30.0, 30.0]).unwrap();
+        assert_eq!(
+            router
+                .find_route_with_options(&start, &stop, &options)
+                .err(),
+            Some(RoutingError::CouldNotFindRoute)
+        );
+        assert_eq!(
+            options.set_bounds(vec![1.0, 0.0, 0.0, 1.0]),
+            Err(RoutingError::InvalidRegion)
+        );
+        options.clear_region();
+        assert_eq!(ids(&options), vec!["start", "road", "stop"]);
+    }
+
+    #[test]
+    fn find_route_blocked_segments() {
+        let mut router = Router::new();
+        for (id, x, y) in [("a", 0.0, 0.0), ("b", 10.0, 0.0)] {
+            router.push_connector(Connector::new(id, &Point::new(x, y)));
+        }
+        for (id, coords) in [
+            ("road", vec![(0.0, 0.0), (10.0, 0.0)]),
+            ("detour", vec![(0.0, 0.0), (5.0, 5.0), (10.0, 0.0)]),
+        ] {
+            router.push_segment(Segment::new(
+                id.into(),
+                LineString::new(
+                    coords
+                        .into_iter()
+                        .map(|(x, y)| coord!( x: x, y: y ))
+                        .collect(),
+                ),
+                vec!["a".into(), "b".into()],
+            ));
+        }
+        let (start, stop) = (Point::new(0.0, -0.1), Point::new(10.0, -0.1));
+        let mut options = RouteOptions::new();
+        options.set_blocked_segments(vec!["road".into()]);
         let route = router
-            .find_route(&Point::new(0.0, 0.0), &Point::new(10.0, 0.0))
+            .find_route_with_options(&start, &stop, &options)
             .unwrap();
-        assert_eq!(route.get_segments().len(), 1);
-        let segment = &route.get_segments()[0];
-        assert_eq!(segment.get_segment().get_id(), "1");
-        assert_eq!(segment.get_start(), 0.0);
-        assert_eq!(segment.get_stop(), 1.0);
+        assert_eq!(route.get_segments()[0].get_segment().get_id(), "detour");
+        // The router itself is unchanged.
+        let route = router.find_route(&start, &stop).unwrap();
+        assert_eq!(route.get_segments()[0].get_segment().get_id(), "road");
+        options.set_blocked_segments(vec!["road".into(), "detour".into()]);
+        assert_eq!(
+            router
+                .find_route_with_options(&start, &stop, &options)
+                .err(),
+            Some(RoutingError::CouldNotFindRoute)
+        );
     }
 
     #[test]
-    fn find_route_no_route() {
+    fn find_route_delays() {
         let mut router = Router::new();
+        for (id, x) in [("a", 0.0), ("b", 1.0), ("c", 2.0)] {
+            router.push_connector(Connector::new(id, &Point::new(x, 0.0)));
+        }
+        let mut signals = Connector::new("b", &Point::new(1.0, 0.0));
+        signals.set_tag("highway", "traffic_signals");
+        router.connectors[1] = signals;
+        router.drop_prepared_graphs();
         router.push_segment(Segment::new(
-            "1".into(),
-            LineString::new(vec![coord!( x: 1.0, y: 0.0 ), coord!( x: 4.0, y: 0.0 )]),
-            vec![],
+            "main".into(),
+            LineString::new(vec![coord!( x: 0.0, y: 0.0 ), coord!( x: 2.0, y: 0.0 )]),
+            vec!["a".into(), "b".into(), "c".into()],
         ));
         router.push_segment(Segment::new(
-            "2".into(),
-            LineString::new(vec![coord!( x: 5.0, y: 0.0 ), coord!( x: 8.0, y: 0.0 )]),
-            vec![],
+            "detour".into(),
+            LineString::new(vec![
+                coord!( x: 0.0, y: 0.0 ),
+                coord!( x: 1.0, y: 0.1 ),
+                coord!( x: 2.0, y: 0.0 ),
+            ]),
+            vec!["a".into(), "c".into()],
         ));
-        let route = router.find_route(&Point::new(0.0, 0.0), &Point::new(10.0, 0.0));
-        assert_eq!(route.err().unwrap(), RoutingError::CouldNotFindRoute);
+        router
+            .register_profile(
+                "car",
+                r#"{"default_speed": 36, "delays": {"highway=traffic_signals": 300}}"#,
+            )
+            .unwrap();
+        let (start, stop) = (Point::new(-0.1, 0.0), Point::new(2.1, 0.0));
+        let mut options = RouteOptions::new();
+        options.set_profile(Some("car".into()));
+        options.set_objective(Objective::Time);
+        let route = router
+            .find_route_with_options(&start, &stop, &options)
+            .unwrap();
+        let uses_detour = |route: &Route| {
+            route
+                .get_segments()
+                .iter()
+                .any(|segment| segment.get_segment().get_id() == "detour")
+        };
+        assert!(uses_detour(&route));
+        options.set_objective(Objective::Distance);
+        let route = router
+            .find_route_with_options(&start, &stop, &options)
+            .unwrap();
+        assert!(!uses_detour(&route));
+        let delay = route.get_duration().unwrap() - route.get_distance() / 10.0;
+        assert!((delay - 300.0).abs() < 1e-6);
     }
 
     #[test]
-    fn find_route_away_from_start() {
+    fn find_route_departure() {
         let mut router = Router::new();
-        router.push_connector(Connector {
-            id: "a".to_string(),
-            point: Point::new(3.0, 0.0),
-        });
-        router.push_connector(Connector {
-            id: "b".to_string(),
-            point: Point::new(6.0, 0.0),
-        });
-        router.push_segment(Segment::new(
-            "1".into(),
-            LineString::new(vec![coord!( x: 1.0, y: 0.0 ), coord!( x: 4.0, y: 0.0 )]),
-            vec!["a".into(), "b".into()],
+        for (id, x, y) in [("a", 0.0, 0.0), ("b", 10.0, 0.0)] {
+            router.push_connector(Connector::new(id, &Point::new(x, y)));
+        }
+        for (id, coords) in [
+            ("road", vec![(0.0, 0.0), (10.0, 0.0)]),
+            ("detour", vec![(0.0, 0.0), (5.0, 5.0), (10.0, 0.0)]),
+        ] {
+            let mut segment = Segment::new(
+                id.into(),
+                LineString::new(
+                    coords
+                        .into_iter()
+                        .map(|(x, y)| coord!( x: x, y: y ))
+                        .collect(),
+                ),
+                vec!["a".into(), "b".into()],
+            );
+            if id == "road" {
+                segment.set_tag("motor_vehicle:conditional", "no @ (Mo-Fr 22:00-06:00)");
+            }
+            router.push_segment(segment);
+        }
+        router
+            .register_profile("car", r#"{"exclude": ["motor_vehicle=no"]}"#)
+            .unwrap();
+        let (start, stop) = (Point::new(0.0, -0.1), Point::new(10.0, -0.1));
+        let mut options = RouteOptions::new();
+        options.set_profile(Some("car".into()));
+        let id = |options: &RouteOptions| {
+            router
+                .find_route_with_options(&start, &stop, options)
+                .unwrap()
+                .get_segments()[0]
+                .get_segment()
+                .get_id()
+        };
+        assert_eq!(id(&options), "road");
+        // Monday at 23:00.
+        options.set_departure(0, 23 * 3600);
+        assert_eq!(options.get_departure_weekday(), Some(0));
+        assert_eq!(id(&options), "detour");
+        // Saturday at 23:00.
+        options.set_departure(5, 23 * 3600);
+        assert_eq!(id(&options), "road");
+        options.clear_departure();
+        assert_eq!(options.get_departure_time(), None);
+    }
+
+    #[test]
+    fn apply_delta() {
+        let mut router = Router::new();
+        for (id, x) in [("a", 0.0), ("b", 10.0)] {
+            router.push_connector(Connector::new(id, &Point::new(x, 0.0)));
+        }
+        // A segment clipped at a tile border.
+        for coords in [[(0.0, 0.0), (5.0, 0.0)], [(5.0, 0.0), (10.0, 0.0)]] {
+            router.push_segment(Segment::new(
+                "road".into(),
+                LineString::new(coords.iter().map(|&(x, y)| coord!( x: x, y: y )).collect()),
+                vec!["a".into(), "b".into()],
+            ));
+        }
+        let mut delta = NetworkDelta::new();
+        delta.put_segment(Segment::new(
+            "road".into(),
+            LineString::new(vec![coord!( x: 0.0, y: 0.0 ), coord!( x: 10.0, y: 1.0 )]),
+            vec!["a".into(), "c".into()],
         ));
-        router.push_segment(Segment::new(
-            "2".into(),
-            LineString::new(vec![coord!( x: 5.0, y: 0.0 ), coord!( x: 8.0, y: 0.0 )]),
+        delta.put_connector(Connector::new("c", &Point::new(10.0, 1.0)));
+        delta.remove_connector("b");
+        router.apply_delta(&delta);
+        assert_eq!(router.segments_len(), 1);
+        assert_eq!(router.connectors_len(), 2);
+        let route = router
+            .find_route(&Point::new(1.0, 0.0), &Point::new(9.0, 1.0))
+            .unwrap();
+        assert_eq!(route.get_segments().len(), 1);
+
+        let mut delta = NetworkDelta::new();
+        delta.remove_segment("road");
+        router.apply_delta(&delta);
+        assert_eq!(router.segments_len(), 0);
+    }
+
+    #[test]
+    fn extend() {
+        let mut tiles = Router::new();
+        for (id, x) in [("a", 0.0), ("b", 10.0)] {
+            tiles.push_connector(Connector::new(id, &Point::new(x, 0.0)));
+        }
+        tiles.push_segment(Segment::new(
+            "road".into(),
+            LineString::new(vec![coord!( x: 0.0, y: 0.0 ), coord!( x: 10.0, y: 0.0 )]),
             vec!["a".into(), "b".into()],
         ));
+        let mut local = Router::new();
+        for (id, x, y) in [("b", 99.0, 99.0), ("c", 10.0, 5.0)] {
+            local.push_connector(Connector::new(id, &Point::new(x, y)));
+        }
+        for (id, coords, connectors) in [
+            ("road", [(0.0, 0.0), (0.0, 5.0)], ["a", "c"]),
+            ("path", [(10.0, 0.0), (10.0, 5.0)], ["b", "c"]),
+        ] {
+            local.push_segment(Segment::new(
+                id.into(),
+                LineString::new(coords.iter().map(|&(x, y)| coord!( x: x, y: y )).collect()),
+                connectors.iter().map(|&id| id.into()).collect(),
+            ));
+        }
+        tiles.extend(&local);
+        assert_eq!(tiles.segments_len(), 2);
+        assert_eq!(tiles.connectors_len(), 3);
+        let route = tiles
+            .find_route(&Point::new(1.0, 0.0), &Point::new(10.0, 4.0))
+            .unwrap();
+        let ids: Vec<_> = route
+            .get_segments()
+            .iter()
+            .map(|segment| segment.get_segment().get_id())
+            .collect();
+        assert_eq!(ids, vec!["road", "path"]);
+    }
+
+    #[test]
+    fn find_route_via() {
+        let mut router = Router::new();
+        for (id, x, y) in [
+            ("w", -1.0, 0.0),
+            ("a", 0.0, 0.0),
+            ("b", 10.0, 0.0),
+            ("c", 11.0, 0.0),
+            ("m", 5.0, -2.0),
+        ] {
+            router.push_connector(Connector::new(id, &Point::new(x, y)));
+        }
+        for (id, coords, connectors) in [
+            ("start", vec![(-1.0, 0.0), (0.0, 0.0)], ["w", "a"]),
+            ("short", vec![(0.0, 0.0), (10.0, 0.0)], ["a", "b"]),
+            (
+                "scenic",
+                vec![(0.0, 0.0), (5.0, 3.0), (10.0, 0.0)],
+                ["a", "b"],
+            ),
+            ("south", vec![(0.0, 0.0), (5.0, -2.0)], ["a", "m"]),
+            ("north", vec![(5.0, -2.0), (10.0, 0.0)], ["m", "b"]),
+            ("stop", vec![(10.0, 0.0), (11.0, 0.0)], ["b", "c"]),
+        ] {
+            router.push_segment(Segment::new(
+                id.into(),
+                LineString::new(coords.iter().map(|&(x, y)| coord!( x: x, y: y )).collect()),
+                connectors.iter().map(|id| id.to_string()).collect(),
+            ));
+        }
+        let (start, stop) = (Point::new(-0.5, 0.1), Point::new(10.5, 0.1));
+        let ids = |options: &RouteOptions| -> Vec<String> {
+            let mut ids: Vec<String> = router
+                .find_route_with_options(&start, &stop, options)
+                .unwrap()
+                .get_segments()
+                .iter()
+                .map(|segment| segment.get_segment().get_id())
+                .collect();
+            ids.dedup();
+            ids
+        };
+        let mut options = RouteOptions::new();
+        assert_eq!(ids(&options), vec!["start", "short", "stop"]);
+
+        options.set_via_segment(Some("scenic".into()));
+        assert_eq!(ids(&options), vec!["start", "scenic", "stop"]);
         let route = router
-            .find_route(&Point::new(0.0, 0.0), &Point::new(10.0, 0.0))
+            .find_route_with_options(&start, &stop, &options)
             .unwrap();
-        assert_eq!(route.get_segments().len(), 2);
-        {
-            let segment = &route.get_segments()[0];
-            assert_eq!(segment.get_segment().get_id(), "1");
-            assert_eq!(segment.get_start(), 0.0);
-            assert_eq!(segment.get_stop(), 1.0);
+        assert_eq!(route.get_legs().len(), 2);
+        assert!((route.get_stops()[1].y() - 3.0).abs() < 1e-9);
+
+        options.set_via_connector(Some("m".into()));
+        assert_eq!(options.get_via_segment(), None);
+        assert_eq!(ids(&options), vec!["start", "south", "north", "stop"]);
+
+        options.set_via_segment(None);
+        assert_eq!(options.get_via_connector(), Some("m".into()));
+        options.set_via_connector(Some("x".into()));
+        assert_eq!(
+            router
+                .find_route_with_options(&start, &stop, &options)
+                .err(),
+            Some(RoutingError::UnknownVia("x".into()))
+        );
+        options.set_via_connector(None);
+        assert_eq!(ids(&options), vec!["start", "short", "stop"]);
+    }
+
+    #[test]
+    fn find_route_profile() {
+        let mut router = Router::new();
+        for (id, x, y) in [("a", 0.0, 0.0), ("b", 10.0, 0.0), ("c", 11.0, 0.0)] {
+            router.push_connector(Connector::new(id, &Point::new(x, y)));
         }
-        {
-            let segment = &route.get_segments()[1];
-            assert_eq!(segment.get_segment().get_id(), "2");
-            assert_eq!(segment.get_start(), 1.0 / 3.0);
-            assert_eq!(segment.get_stop(), 1.0);
+        for (id, coords, connectors, highway) in [
+            (
+                "start",
+                vec![(-1.0, 0.0), (0.0, 0.0)],
+                ["w", "a"],
+                "residential",
+            ),
+            ("track", vec![(0.0, 0.0), (10.0, 0.0)], ["a", "b"], "track"),
+            (
+                "primary",
+                vec![(0.0, 0.0), (5.0, 3.0), (10.0, 0.0)],
+                ["a", "b"],
+                "primary",
+            ),
+            (
+                "stop",
+                vec![(10.0, 0.0), (11.0, 0.0)],
+                ["b", "c"],
+                "residential",
+            ),
+        ] {
+            let mut segment = Segment::new(
+                id.into(),
+                LineString::new(
+                    coords
+                        .into_iter()
+                        .map(|(x, y)| coord!( x: x, y: y ))
+                        .collect(),
+                ),
+                connectors.iter().map(|id| id.to_string()).collect(),
+            );
+            segment.set_tag("highway", highway);
+            router.push_segment(segment);
         }
+        router
+            .register_profile(
+                "car",
+                r#"{"speeds": {"highway=primary": 100, "highway": 30}}"#,
+            )
+            .unwrap();
+        router
+            .register_profile("hiking", r#"{"exclude": ["highway=primary"]}"#)
+            .unwrap();
+        let (start, stop) = (Point::new(-0.5, 0.1), Point::new(10.5, 0.1));
+        let ids = |profile: Option<&str>| -> Vec<String> {
+            let mut options = RouteOptions::new();
+            options.set_profile(profile.map(String::from));
+            router
+                .find_route_with_options(&start, &stop, &options)
+                .unwrap()
+                .get_segments()
+                .iter()
+                .map(|segment| segment.get_segment().get_id())
+                .collect()
+        };
+        assert_eq!(ids(None), vec!["start", "track", "stop"]);
+        assert_eq!(ids(Some("car")), vec!["start", "primary", "stop"]);
+        assert_eq!(ids(Some("hiking")), vec!["start", "track", "stop"]);
+        let mut options = RouteOptions::new();
+        options.set_profile(Some("bike".into()));
+        assert_eq!(
+            router
+                .find_route_with_options(&start, &stop, &options)
+                .err(),
+            Some(RoutingError::UnknownProfile("bike".into()))
+        );
+        assert!(router.register_profile("bike", "[]").is_err());
+
+        router
+            .register_profile(
+                "toll",
+                r#"{"speeds": {"highway=primary": 100, "highway": 30},
+                    "penalties": {"highway=primary": 5}}"#,
+            )
+            .unwrap();
+        options.set_profile(Some("toll".into()));
+        let mut route = |objective| {
+            options.set_objective(objective);
+            router
+                .find_route_with_options(&start, &stop, &options)
+                .unwrap()
+        };
+        let shortest = route(Objective::Distance);
+        assert_eq!(shortest.get_segments()[1].get_segment().get_id(), "track");
+        assert_eq!(
+            route(Objective::Cost).get_segments()[1]
+                .get_segment()
+                .get_id(),
+            "track"
+        );
+        let fastest = route(Objective::Time);
+        assert_eq!(fastest.get_segments()[1].get_segment().get_id(), "primary");
+        // 0.5 + 11.66 + 0.5 degrees at the equator.
+        let meters = (1.0 + 2.0 * 34.0_f64.sqrt()) * 111_195.0;
+        assert!((fastest.get_distance() / meters - 1.0).abs() < 0.001);
+        let hours = 1.0 / 30.0 + 2.0 * 34.0_f64.sqrt() / 100.0;
+        assert!((fastest.get_duration().unwrap() / (hours * 111.195 * 3600.0) - 1.0).abs() < 0.001);
+        assert!(fastest.get_duration() < shortest.get_duration());
+        // The durations of the segments add up to that of the route.
+        let durations: f64 = fastest
+            .get_segments()
+            .iter()
+            .map(|segment| segment.get_duration_seconds().unwrap())
+            .sum();
+        assert!((durations - fastest.get_duration_seconds().unwrap()).abs() < 1e-6);
+        assert_eq!(
+            router.find_route(&start, &stop).unwrap().get_duration(),
+            None
+        );
     }
 
     #[test]
     /// Test find_route method.
     fn find_route_single_segment() {
         let mut router = Router::new();
-        router.push_connector(Connector {
-            id: "a".to_string(),
-            point: Point::new(0.0, 0.0),
-        });
-        router.push_connector(Connector {
-            id: "b".to_string(),
-            point: Point::new(10.0, 0.0),
-        });
+        router.push_connector(Connector::new("a", &Point::new(0.0, 0.0)));
+        router.push_connector(Connector::new("b", &Point::new(10.0, 0.0)));
         router.push_segment(Segment::new(
             "1".into(),
             LineString::new(vec![coord!( x: 0.0, y: 0.0 ), coord!( x: 10.0, y: 0.0 )]),
@@ -669,22 +3720,10 @@ mod tests {
     /// Test find_route method.
     fn find_route() {
         let mut router = Router::new();
-        router.push_connector(Connector {
-            id: "a".to_string(),
-            point: Point::new(2.0, 0.0),
-        });
-        router.push_connector(Connector {
-            id: "b".to_string(),
-            point: Point::new(3.0, 3.0),
-        });
-        router.push_connector(Connector {
-            id: "c".to_string(),
-            point: Point::new(2.0, 4.0),
-        });
-        router.push_connector(Connector {
-            id: "d".to_string(),
-            point: Point::new(3.0, 5.0),
-        });
+        router.push_connector(Connector::new("a", &Point::new(2.0, 0.0)));
+        router.push_connector(Connector::new("b", &Point::new(3.0, 3.0)));
+        router.push_connector(Connector::new("c", &Point::new(2.0, 4.0)));
+        router.push_connector(Connector::new("d", &Point::new(3.0, 5.0)));
         router.push_segment(Segment::new(
             "1".into(),
             LineString::new(vec![coord!( x: 0.0, y: 0.0 ), coord!( x: 4.0, y: 0.0 )]),
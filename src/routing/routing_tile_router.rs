@@ -0,0 +1,26 @@
+use wasm_bindgen::prelude::*;
+
+use crate::routing::tile_router::tile_router_methods;
+use crate::tile::backend::routing_tile_backend::{RoutingTileBackend, Tile};
+use crate::tile::backend::CachedTileNetwork;
+
+#[wasm_bindgen]
+/// A router using pre-built routing tiles served from an XYZ URL template.
+pub struct RoutingTileRouter {
+    network: CachedTileNetwork<RoutingTileBackend, Tile>,
+}
+
+#[wasm_bindgen]
+impl RoutingTileRouter {
+    #[wasm_bindgen(constructor)]
+    /// Create the router using the given URL template.
+    ///
+    /// The template must contain `{z}`, `{x}` and `{y}` placeholders.
+    pub fn new(url_template: &str) -> RoutingTileRouter {
+        RoutingTileRouter {
+            network: CachedTileNetwork::new(RoutingTileBackend::new(url_template)),
+        }
+    }
+}
+
+tile_router_methods!(RoutingTileRouter);
@@ -0,0 +1,43 @@
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Default, PartialEq)]
+/// Statistics of the search which found a route, e.g. to tune cache sizes.
+pub struct SearchStats {
+    pub(crate) nodes_expanded: usize,
+    pub(crate) heap_pushes: usize,
+    pub(crate) tiles_used: usize,
+    pub(crate) start_snap_distance: f64,
+    pub(crate) stop_snap_distance: f64,
+}
+
+#[wasm_bindgen]
+impl SearchStats {
+    /// Returns the number of connectors whose neighbours were visited.
+    pub fn get_nodes_expanded(&self) -> usize {
+        self.nodes_expanded
+    }
+
+    /// Returns the number of connectors pushed to the priority queue.
+    pub fn get_heap_pushes(&self) -> usize {
+        self.heap_pushes
+    }
+
+    /// Returns the number of tiles (of all sources) the graph was built from,
+    /// 0 if the graph was not built from tiles.
+    pub fn get_tiles_used(&self) -> usize {
+        self.tiles_used
+    }
+
+    /// Returns the distance in meters from the start to the point it was
+    /// snapped to on the network.
+    pub fn get_start_snap_distance(&self) -> f64 {
+        self.start_snap_distance
+    }
+
+    /// Returns the distance in meters from the stop to the point it was
+    /// snapped to on the network.
+    pub fn get_stop_snap_distance(&self) -> f64 {
+        self.stop_snap_distance
+    }
+}
@@ -0,0 +1,319 @@
+//! Methods shared by the routers on tiles.
+
+// Implements the JS methods shared by the routers which keep their
+// `CachedTileNetwork` in the `network` field. Each router only implements
+// its own constructors and backend-specific methods.
+//
+// With `before_search: method`, the async method is awaited before each
+// search, e.g. to read the metadata of an archive.
+macro_rules! tile_router_methods {
+    ($router:ident $(, before_search: $before_search:ident)?) => {
+        #[::wasm_bindgen::prelude::wasm_bindgen]
+        impl $router {
+            #[wasm_bindgen(js_name = findRoute)]
+            /// Find a route for the given start and stop points.
+            pub async fn find_route(
+                &mut self,
+                start: &$crate::geo_types::Point,
+                stop: &$crate::geo_types::Point,
+            ) -> Result<$crate::routing::Route, $crate::routing::RoutingError> {
+                $crate::debug::debug_log!(
+                    "{}::find_route {:?}, {:?}",
+                    stringify!($router),
+                    start,
+                    stop
+                );
+                $(self.$before_search().await;)?
+                self.network.find_route(start, stop).await
+            }
+
+            #[wasm_bindgen(js_name = findRouteWithOptions)]
+            /// Find a route for the given start and stop points with the given
+            /// options.
+            pub async fn find_route_with_options(
+                &mut self,
+                start: &$crate::geo_types::Point,
+                stop: &$crate::geo_types::Point,
+                options: &$crate::routing::RouteOptions,
+            ) -> Result<$crate::routing::Route, $crate::routing::RoutingError> {
+                $crate::debug::debug_log!(
+                    "{}::find_route_with_options {:?}, {:?}",
+                    stringify!($router),
+                    start,
+                    stop
+                );
+                $(self.$before_search().await;)?
+                self.network
+                    .find_route_with_options(start, stop, options)
+                    .await
+            }
+
+            #[wasm_bindgen(js_name = findViaRoute)]
+            /// Find a route through all stops in order. Legs between unchanged
+            /// stops are reused from the previous via route.
+            pub async fn find_via_route(
+                &mut self,
+                stops: Vec<$crate::geo_types::Point>,
+                options: &$crate::routing::RouteOptions,
+            ) -> Result<$crate::routing::Route, $crate::routing::RoutingError> {
+                $crate::debug::debug_log!("{}::find_via_route {:?}", stringify!($router), stops);
+                $(self.$before_search().await;)?
+                self.network.find_via_route(stops, options).await
+            }
+
+            #[wasm_bindgen(js_name = findRoutes)]
+            /// Find a route from each start to the stop at the same index,
+            /// loading the tiles shared by the routes only once.
+            pub async fn find_routes(
+                &mut self,
+                starts: Vec<$crate::geo_types::Point>,
+                stops: Vec<$crate::geo_types::Point>,
+                options: &$crate::routing::RouteOptions,
+            ) -> Result<Vec<$crate::routing::Route>, $crate::routing::RoutingError> {
+                $crate::debug::debug_log!(
+                    "{}::find_routes {:?}, {:?}",
+                    stringify!($router),
+                    starts,
+                    stops
+                );
+                $(self.$before_search().await;)?
+                self.network.find_routes(starts, stops, options).await
+            }
+
+            #[wasm_bindgen(js_name = findRoutesFrom)]
+            /// Find routes from start to each of the stops with a single search.
+            pub async fn find_routes_from(
+                &mut self,
+                start: &$crate::geo_types::Point,
+                stops: Vec<$crate::geo_types::Point>,
+                options: &$crate::routing::RouteOptions,
+            ) -> Result<Vec<$crate::routing::Route>, $crate::routing::RoutingError> {
+                $crate::debug::debug_log!(
+                    "{}::find_routes_from {:?}, {:?}",
+                    stringify!($router),
+                    start,
+                    stops
+                );
+                $(self.$before_search().await;)?
+                self.network.find_routes_from(start, stops, options).await
+            }
+
+            #[wasm_bindgen(js_name = getZoom)]
+            /// Returns the zoom level of the tiles used for routing.
+            pub fn get_zoom(&self) -> u8 {
+                self.network.get_zoom()
+            }
+
+            #[wasm_bindgen(js_name = getParseReport)]
+            /// Returns the features of the loaded tiles which were skipped
+            /// because they could not be parsed, e.g. because of missing
+            /// properties.
+            pub fn get_parse_report(&self) -> Vec<$crate::tile::ParseIssue> {
+                self.network.get_parse_issues()
+            }
+
+            #[wasm_bindgen(js_name = getLastTimings)]
+            /// Returns where the time of the last `findRoute` call was spent,
+            /// `undefined` before the first call.
+            pub fn get_last_timings(&self) -> Option<$crate::routing::QueryTimings> {
+                self.network.get_last_timings()
+            }
+
+            #[wasm_bindgen(js_name = setCaptureFailures)]
+            /// Enables or disables capturing reports of failed `findRoute`
+            /// calls.
+            pub fn set_capture_failures(&mut self, enabled: bool) {
+                self.network.set_capture_failures(enabled);
+            }
+
+            #[wasm_bindgen(js_name = getFailureReport)]
+            /// Returns a JSON report of the last failed `findRoute` call to
+            /// attach to bug reports, `undefined` if none was captured.
+            ///
+            /// The report contains the start and stop points, the error and
+            /// the coordinates of the loaded tiles.
+            pub fn get_failure_report(&self) -> Option<String> {
+                self.network.get_failure_report()
+            }
+
+            #[wasm_bindgen(js_name = getCoverage)]
+            /// Returns the density of the loaded network as GeoJSON grid of
+            /// cells of `cell_size` degrees with the `length` of segments (in
+            /// meters) and the number of `segments` within, to show where
+            /// routing data exists.
+            pub fn get_coverage(&self, cell_size: f64) -> String {
+                self.network.get_coverage(cell_size)
+            }
+
+            #[wasm_bindgen(js_name = getCachedTiles)]
+            /// Returns the coordinates of the cached tiles, most recently used
+            /// first.
+            pub fn get_cached_tiles(&self) -> Vec<$crate::tile::Coord> {
+                self.network.get_cached_tiles()
+            }
+
+            #[wasm_bindgen(js_name = registerProfile)]
+            /// Registers a profile defined as JSON under the name, which routes
+            /// use if it is set with `RouteOptions.set_profile`.
+            pub fn register_profile(
+                &mut self,
+                name: &str,
+                definition: &str,
+            ) -> Result<(), $crate::routing::ProfileError> {
+                self.network.register_profile(name, definition)
+            }
+
+            #[wasm_bindgen(js_name = applyDelta)]
+            /// Applies the changes of the delta, e.g. from a live editing
+            /// session, on top of the loaded tiles without reloading them.
+            pub fn apply_delta(&mut self, delta: &$crate::routing::NetworkDelta) {
+                self.network.apply_delta(delta);
+            }
+
+            #[wasm_bindgen(js_name = clearDeltas)]
+            /// Discards all changes applied with `applyDelta`.
+            pub fn clear_deltas(&mut self) {
+                self.network.clear_deltas();
+            }
+
+            #[wasm_bindgen(js_name = setZoom)]
+            /// Sets the zoom level of the tiles used for routing.
+            pub fn set_zoom(&mut self, zoom: u8) {
+                self.network.set_zoom(zoom);
+            }
+
+            #[wasm_bindgen(js_name = setMaxSourceZoom)]
+            /// Sets the highest zoom level the tile source provides.
+            ///
+            /// If the routing zoom is higher, the parent tiles at this zoom
+            /// level are used instead.
+            pub fn set_max_source_zoom(&mut self, zoom: Option<u8>) {
+                self.network.set_max_source_zoom(zoom);
+            }
+
+            #[wasm_bindgen(js_name = setMaxTileAge)]
+            /// Sets the maximum age in seconds after which cached tiles are
+            /// refetched.
+            ///
+            /// Expired tiles are revalidated using their ETag or Last-Modified
+            /// header where the source provides them. Without a maximum age,
+            /// tiles are kept until evicted from the cache.
+            pub fn set_max_tile_age(&mut self, seconds: Option<f64>) {
+                self.network.set_max_age(seconds);
+            }
+
+            #[wasm_bindgen(js_name = setStaleTileAge)]
+            /// Sets the age in seconds after which cached tiles are stale.
+            ///
+            /// Stale tiles are used for searches right away and refetched by
+            /// `refreshStaleTiles`.
+            pub fn set_stale_tile_age(&mut self, seconds: Option<f64>) {
+                self.network.set_stale_after(seconds);
+            }
+
+            #[wasm_bindgen(js_name = refreshStaleTiles)]
+            /// Refetches the stale tiles used by searches, so that later
+            /// searches use the updated tiles. Returns the number of tiles
+            /// which changed.
+            pub async fn refresh_stale_tiles(
+                &mut self,
+            ) -> Result<usize, $crate::routing::RoutingError> {
+                self.network.refresh_stale_tiles().await
+            }
+
+            #[wasm_bindgen(js_name = setEvictionRadius)]
+            /// Sets the distance in tiles from the tiles of a search beyond
+            /// which cached tiles are dropped, to bound the memory of long
+            /// sessions.
+            ///
+            /// Without a radius, tiles are kept until the cache is full.
+            pub fn set_eviction_radius(&mut self, radius: Option<u32>) {
+                self.network.set_eviction_radius(radius);
+            }
+
+            #[wasm_bindgen(js_name = setOfflineStore)]
+            /// Uses the browser cache with the given name to store offline
+            /// regions.
+            ///
+            /// Tiles of downloaded regions are read from the cache instead of
+            /// the network. Passing `undefined` disables the offline store.
+            pub fn set_offline_store(&mut self, name: Option<String>) {
+                self.network
+                    .set_store(name.map(|name| $crate::tile::TileStore::browser_cache(&name)));
+            }
+
+            #[wasm_bindgen(js_name = setPersistTiles)]
+            /// Sets whether tiles loaded for route searches are saved parsed to
+            /// the offline store too, so later sessions neither download nor
+            /// parse them.
+            pub fn set_persist_tiles(&mut self, enabled: bool) {
+                self.network.set_persist_tiles(enabled);
+            }
+
+            #[wasm_bindgen(js_name = estimateRegion)]
+            /// Estimates the download of the region given as [west, south,
+            /// east, north].
+            pub fn estimate_region(
+                &self,
+                bounds: Vec<f64>,
+            ) -> Result<$crate::tile::RegionEstimate, $crate::routing::RoutingError> {
+                self.network
+                    .estimate_region(&$crate::tile::region::bounds_to_region(&bounds)?)
+            }
+
+            #[wasm_bindgen(js_name = downloadRegion)]
+            /// Stores all tiles of the region given as [west, south, east,
+            /// north].
+            ///
+            /// The optional `progress` function is called with the number of
+            /// processed tiles, the total number of tiles and the stored bytes.
+            pub async fn download_region(
+                &self,
+                bounds: Vec<f64>,
+                progress: Option<js_sys::Function>,
+            ) -> Result<$crate::tile::RegionDownload, $crate::routing::RoutingError> {
+                let region = $crate::tile::region::bounds_to_region(&bounds)?;
+                self.network
+                    .download_region(&region, $crate::tile::region::js_progress(progress))
+                    .await
+            }
+
+            #[wasm_bindgen(js_name = downloadPolygon)]
+            /// Stores all tiles of the polygon given as [x0, y0, x1, y1, ...].
+            pub async fn download_polygon(
+                &self,
+                ring: Vec<f64>,
+                progress: Option<js_sys::Function>,
+            ) -> Result<$crate::tile::RegionDownload, $crate::routing::RoutingError> {
+                let region = $crate::tile::region::ring_to_region(&ring)?;
+                self.network
+                    .download_region(&region, $crate::tile::region::js_progress(progress))
+                    .await
+            }
+
+            #[wasm_bindgen(js_name = setSessionManifest)]
+            /// Uses the browser cache with the given name to save the
+            /// coordinates of the tiles used in this session.
+            ///
+            /// The manifest is updated after each route search. Passing
+            /// `undefined` disables the manifest.
+            pub fn set_session_manifest(&mut self, name: Option<String>) {
+                self.network.set_manifest_store(
+                    name.map(|name| $crate::tile::TileStore::browser_cache(&name)),
+                );
+            }
+
+            #[wasm_bindgen(js_name = warmFromManifest)]
+            /// Prefetches the tiles used in the last session.
+            ///
+            /// Call this on startup to make the first route search fast.
+            /// Returns the number of loaded tiles.
+            pub async fn warm_from_manifest(
+                &mut self,
+            ) -> Result<usize, $crate::routing::RoutingError> {
+                self.network.warm_from_manifest().await
+            }
+        }
+    };
+}
+pub(crate) use tile_router_methods;
@@ -0,0 +1,42 @@
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Default, PartialEq)]
+/// Time in milliseconds spent in the phases of a route search.
+pub struct QueryTimings {
+    pub(crate) fetch: f64,
+    pub(crate) parse: f64,
+    pub(crate) build: f64,
+    pub(crate) search: f64,
+    pub(crate) total: f64,
+}
+
+#[wasm_bindgen]
+impl QueryTimings {
+    /// Returns the time spent loading tiles from the offline store and the
+    /// network. Tiles are fetched concurrently, so this is the wall time.
+    pub fn get_fetch(&self) -> f64 {
+        self.fetch
+    }
+
+    /// Returns the time spent parsing fetched tiles.
+    pub fn get_parse(&self) -> f64 {
+        self.parse
+    }
+
+    /// Returns the time spent merging the tiles into the routing graph.
+    pub fn get_build(&self) -> f64 {
+        self.build
+    }
+
+    /// Returns the time spent searching the route in the graph.
+    pub fn get_search(&self) -> f64 {
+        self.search
+    }
+
+    /// Returns the time of the whole query, including e.g. saving the
+    /// session manifest.
+    pub fn get_total(&self) -> f64 {
+        self.total
+    }
+}
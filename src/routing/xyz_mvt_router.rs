@@ -0,0 +1,26 @@
+use wasm_bindgen::prelude::*;
+
+use crate::routing::tile_router::tile_router_methods;
+use crate::tile::backend::xyz_mvt_backend::{Tile, XYZMVTBackend};
+use crate::tile::backend::CachedTileNetwork;
+
+#[wasm_bindgen]
+/// A router using Mapbox Vector Tiles served from an XYZ URL template.
+pub struct XYZMVTRouter {
+    network: CachedTileNetwork<XYZMVTBackend, Tile>,
+}
+
+#[wasm_bindgen]
+impl XYZMVTRouter {
+    #[wasm_bindgen(constructor)]
+    /// Create the router using the given URL template.
+    ///
+    /// The template must contain `{z}`, `{x}` and `{y}` placeholders.
+    pub fn new(url_template: &str) -> XYZMVTRouter {
+        XYZMVTRouter {
+            network: CachedTileNetwork::new(XYZMVTBackend::new(url_template)),
+        }
+    }
+}
+
+tile_router_methods!(XYZMVTRouter);
@@ -1,72 +1,1690 @@
-use crate::debug::debug_log;
+use crate::debug::{async_span, info_log, span, warn_log};
 use crate::geo_types::Point;
-use crate::routing::{Route, Router, RoutingError};
+use crate::routing::profile::{Profile, ProfileError};
+use crate::routing::{NetworkDelta, QueryTimings, Route, RouteOptions, Router, RoutingError};
 use crate::tile;
-use crate::tile::backend::{Backend, Tile};
-use crate::tile::point_to_tile_coord;
+use crate::tile::backend::{Backend, Revalidation, Tile, TileFuture, Validators};
+use crate::tile::region::{tiles_covering, RegionDownload, RegionEstimate};
+use crate::tile::selection;
+use crate::tile::{point_to_tile_coord, routing_tile, ParseIssue, ParsedTile, TileStore};
+use crate::time;
 use futures::future::join_all;
+use futures::StreamExt;
 use lru::LruCache;
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
 use std::num::NonZeroUsize;
 
-/// A transport network which caches tiles.
+/// Number of cached tiles per backend, unless more tiles are needed for a
+/// single search.
+const TILES_PER_BACKEND: usize = 27;
+
+/// Number of tiles downloaded in parallel for offline regions.
+const CONCURRENT_DOWNLOADS: usize = 6;
+
+/// Assumed size in bytes of an encoded tile if no tile was loaded yet.
+const DEFAULT_TILE_SIZE: f64 = 20_000.0;
+
+/// Key of the session manifest in the manifest store.
+const MANIFEST_KEY: &str = "manifest";
+
+/// Key of the data version in the offline store.
+const VERSION_KEY: &str = "version";
+
+/// A parsed tile in the cache.
+struct CachedTile {
+    tile: ParsedTile,
+    /// Time of fetching in milliseconds since the UNIX epoch.
+    fetched_at: f64,
+    /// Validators to check if the tile is still up to date.
+    validators: Option<Validators>,
+}
+
+impl CachedTile {
+    fn new(tile: ParsedTile, validators: Option<Validators>) -> Self {
+        CachedTile {
+            tile,
+            fetched_at: time::now(),
+            validators,
+        }
+    }
+
+    /// Returns whether the tile is older than the given max age (in seconds).
+    fn is_expired(&self, max_age: Option<f64>, now: f64) -> bool {
+        max_age.is_some_and(|max_age| now - self.fetched_at > max_age * 1000.0)
+    }
+}
+
+/// A leg of a via route.
+struct Leg {
+    start: Point,
+    stop: Point,
+    route: Route,
+}
+
+impl Leg {
+    /// Returns whether the leg is between the points.
+    fn is_between(&self, start: &Point, stop: &Point) -> bool {
+        let same = |a: &Point, b: &Point| a.x() == b.x() && a.y() == b.y();
+        same(&self.start, start) && same(&self.stop, stop)
+    }
+}
+
+/// A transport network which caches parsed tiles.
+///
+/// The network may be fed by several backends. Features of all backends are
+/// merged, backends added first take priority for duplicate IDs.
 pub struct CachedTileNetwork<B: Backend<T>, T: Tile> {
-    backend: B,
+    backends: Vec<B>,
     router: Router,
-    tiles: LruCache<tile::Coord, T>,
+    /// Parsed tiles by backend index and tile coordinate.
+    tiles: LruCache<(usize, tile::Coord), CachedTile>,
+    tile_type: PhantomData<T>,
+    /// Zoom level of the tiles used for routing.
+    zoom: u8,
+    /// Highest zoom level provided by the backends.
+    max_source_zoom: Option<u8>,
+    /// Maximum age of cached tiles in seconds.
+    max_age: Option<f64>,
+    /// Age of cached tiles in seconds after which they are used, but queued
+    /// for refetching.
+    stale_after: Option<f64>,
+    /// Cached tiles used while stale, refetched by `refresh_stale_tiles`.
+    stale: Vec<(usize, tile::Coord)>,
+    /// Distance in tiles from a search beyond which cached tiles are dropped.
+    eviction_radius: Option<u32>,
+    /// Store of tiles downloaded for offline use.
+    store: Option<TileStore>,
+    /// Whether tiles fetched for searches are saved to the offline store.
+    persist_tiles: bool,
+    /// Store of the coordinates of the tiles used in the last session.
+    manifest_store: Option<TileStore>,
+    /// Version of the source data the cached tiles belong to.
+    data_version: Option<String>,
+    /// Timings of the last route search.
+    last_timings: Option<QueryTimings>,
+    /// Whether failed route searches are captured.
+    capture_failures: bool,
+    /// Report of the last failed route search, if captured.
+    failure_report: Option<String>,
+    /// Profiles by name, set to the router of each search.
+    profiles: HashMap<String, Profile>,
+    /// Changes applied on top of the tiles of each search.
+    delta: NetworkDelta,
+    /// Legs of the last via route with the options used, cleared when the
+    /// network changes.
+    legs: Option<(RouteOptions, Vec<Leg>)>,
+    /// Coordinates of the tiles the router was built from, cleared when the
+    /// network or the cached tiles change. The router and its prepared graph
+    /// are reused by searches with the same tiles.
+    router_tiles: Option<Vec<tile::Coord>>,
 }
 
 impl<B: Backend<T>, T: Tile> CachedTileNetwork<B, T> {
+    /// Creates the network using a single backend.
     pub fn new(backend: B) -> Self {
         CachedTileNetwork {
             router: Router::new(),
-            tiles: LruCache::new(NonZeroUsize::new(27).unwrap()),
-            backend,
+            tiles: LruCache::new(NonZeroUsize::new(TILES_PER_BACKEND).unwrap()),
+            backends: vec![backend],
+            tile_type: PhantomData,
+            zoom: 14,
+            max_source_zoom: None,
+            max_age: None,
+            stale_after: None,
+            stale: Vec::new(),
+            eviction_radius: None,
+            store: None,
+            persist_tiles: false,
+            manifest_store: None,
+            data_version: None,
+            last_timings: None,
+            capture_failures: false,
+            failure_report: None,
+            profiles: HashMap::new(),
+            delta: NetworkDelta::new(),
+            legs: None,
+            router_tiles: None,
         }
     }
 
-    pub async fn find_route(&mut self, start: &Point, stop: &Point) -> Result<Route, RoutingError> {
-        let backend = &self.backend;
-        debug_log!("find route");
-        let tile_coord = point_to_tile_coord(&start, 14);
-        self.router = Router::new();
+    /// Drops the results depending on the network after it changed.
+    fn network_changed(&mut self) {
+        self.legs = None;
+        self.router_tiles = None;
+    }
+
+    /// Adds a backend with lower priority than the existing ones.
+    pub fn add_backend(&mut self, backend: B) {
+        self.backends.push(backend);
+        self.network_changed();
+        self.tiles
+            .resize(NonZeroUsize::new(TILES_PER_BACKEND * self.backends.len()).unwrap());
+    }
+
+    /// Returns the backend with the highest priority.
+    pub fn get_backend(&self) -> &B {
+        &self.backends[0]
+    }
+
+    /// Returns the backends in order of priority.
+    pub fn get_backends_mut(&mut self) -> &mut [B] {
+        &mut self.backends
+    }
+
+    /// Returns the zoom level of the tiles used for routing.
+    pub fn get_zoom(&self) -> u8 {
+        self.zoom
+    }
+
+    /// Sets the zoom level of the tiles used for routing.
+    pub fn set_zoom(&mut self, zoom: u8) {
+        self.zoom = zoom;
+        self.network_changed();
+    }
+
+    /// Returns the highest zoom level the backends provide tiles for.
+    pub fn get_max_source_zoom(&self) -> Option<u8> {
+        self.max_source_zoom
+    }
+
+    /// Sets the highest zoom level the backends provide tiles for.
+    ///
+    /// If the routing zoom is higher, the parent tiles at this zoom level are
+    /// used instead, so the network does not need tiles at the routing zoom.
+    pub fn set_max_source_zoom(&mut self, zoom: Option<u8>) {
+        self.max_source_zoom = zoom;
+        self.network_changed();
+    }
+
+    /// Returns the zoom level of the tiles fetched from the backends.
+    fn tile_zoom(&self) -> u8 {
+        match self.max_source_zoom {
+            Some(max_zoom) => self.zoom.min(max_zoom),
+            None => self.zoom,
+        }
+    }
+
+    /// Returns the tiles needed for routing from the point.
+    ///
+    /// These are the tile at the routing zoom containing the point and its
+    /// neighbours, or the parent tiles covering them if the backends don't
+    /// provide the routing zoom.
+    fn tiles_around(&self, point: &Point) -> Vec<tile::Coord> {
+        self.source_tiles(point_to_tile_coord(point, self.zoom).with_neighbours())
+    }
+
+    /// Returns the tiles to fetch for the tiles at the routing zoom, i.e.
+    /// their parent tiles if the backends don't provide the routing zoom.
+    fn source_tiles(&self, tiles: Vec<tile::Coord>) -> Vec<tile::Coord> {
+        let tile_zoom = self.tile_zoom();
+        let mut coords = Vec::new();
+        for coord in tiles {
+            let coord = coord.ancestor(tile_zoom);
+            if !coords.contains(&coord) {
+                coords.push(coord);
+            }
+        }
+        coords
+    }
+
+    /// Sets the maximum age in seconds after which cached tiles are refetched.
+    ///
+    /// If refetching fails, the expired tile is still used. `None` keeps tiles
+    /// until they are evicted from the cache.
+    pub fn set_max_age(&mut self, max_age: Option<f64>) {
+        self.max_age = max_age;
+    }
+
+    /// Sets the age in seconds after which cached tiles are stale.
+    ///
+    /// Stale tiles are still used for searches without waiting for the
+    /// backends, but queued for refetching by `refresh_stale_tiles`, so that
+    /// later searches use the updated tiles. Tiles older than the max age
+    /// are refetched before searching as before.
+    pub fn set_stale_after(&mut self, seconds: Option<f64>) {
+        self.stale_after = seconds;
+        if seconds.is_none() {
+            self.stale.clear();
+        }
+    }
+
+    /// Refetches the tiles which were used while stale and replaces them in
+    /// the cache. Returns the number of tiles which changed.
+    ///
+    /// Call this after a search, e.g. when idle, to refresh the network
+    /// without delaying the search. Tiles which can't be fetched are kept.
+    pub async fn refresh_stale_tiles(&mut self) -> Result<usize, RoutingError> {
+        let now = time::now();
+        let stale_after = self.stale_after;
+        let keys: Vec<_> = std::mem::take(&mut self.stale)
+            .into_iter()
+            .filter_map(|key| {
+                let cached = self.tiles.peek(&key)?;
+                cached
+                    .is_expired(stale_after, now)
+                    .then(|| (key, cached.validators.clone()))
+            })
+            .collect();
+        let (_, changed) = self.fetch_tiles(keys, &mut QueryTimings::default()).await?;
+        if changed > 0 {
+            self.network_changed();
+        }
+        Ok(changed)
+    }
+
+    /// Sets the distance in tiles from the tiles of a search beyond which
+    /// cached tiles are dropped after the search, e.g. to bound the memory
+    /// of long sessions moving across a large area. Tiles of other zoom
+    /// levels are dropped as well. `None`, the default, keeps tiles until
+    /// the cache is full.
+    pub fn set_eviction_radius(&mut self, radius: Option<u32>) {
+        self.eviction_radius = radius;
+    }
+
+    /// Sets the store of offline tiles.
+    ///
+    /// Tiles found in the store are used without accessing the backends.
+    pub fn set_store(&mut self, store: Option<TileStore>) {
+        self.store = store;
+    }
+
+    /// Sets whether tiles fetched for route searches are saved to the
+    /// offline store as well, not only tiles of downloaded regions.
+    ///
+    /// The tiles are saved parsed as routing tiles, so in later sessions
+    /// they are neither downloaded nor parsed again. Saved tiles are
+    /// refetched after the max age and cleared if the data version changes.
+    pub fn set_persist_tiles(&mut self, enabled: bool) {
+        self.persist_tiles = enabled;
+    }
+
+    /// Sets the version of the source data, e.g. read from archive metadata.
+    ///
+    /// If the version differs from the one of the cached tiles, the cache
+    /// and the offline store are cleared, so old and new tiles are never
+    /// mixed. Returns whether the caches were invalidated.
+    pub async fn set_data_version(
+        &mut self,
+        version: Option<String>,
+    ) -> Result<bool, RoutingError> {
+        let mut invalidated = false;
+        if self.data_version.is_some() && self.data_version != version {
+            info_log!("data version changed to {:?}", version);
+            self.tiles.clear();
+            self.network_changed();
+            invalidated = true;
+        }
+        self.data_version = version;
+        let (store, version) = match (&self.store, &self.data_version) {
+            (Some(store), Some(version)) => (store, version),
+            _ => return Ok(invalidated),
+        };
+        let stored = store
+            .get(VERSION_KEY)
+            .await
+            .or(Err(RoutingError::OfflineStoreError))?;
+        if stored.as_deref() != Some(version.as_bytes()) {
+            if stored.is_some() {
+                info_log!("clearing offline store of outdated data");
+                store
+                    .clear()
+                    .await
+                    .or(Err(RoutingError::OfflineStoreError))?;
+                invalidated = true;
+            }
+            store
+                .put(VERSION_KEY, version.as_bytes().to_vec())
+                .await
+                .or(Err(RoutingError::OfflineStoreError))?;
+        }
+        Ok(invalidated)
+    }
+
+    /// Registers a profile defined as JSON under the name, see
+    /// [`Router::register_profile`].
+    pub fn register_profile(&mut self, name: &str, definition: &str) -> Result<(), ProfileError> {
+        self.profiles
+            .insert(name.into(), Profile::from_json(definition)?);
+        self.network_changed();
+        Ok(())
+    }
+
+    /// Returns the features which were skipped while parsing the cached tiles.
+    pub fn get_parse_issues(&self) -> Vec<ParseIssue> {
+        self.tiles
+            .iter()
+            .flat_map(|(_, cached)| cached.tile.get_issues().iter().cloned())
+            .collect()
+    }
+
+    /// Applies the changes of the delta to the network.
+    ///
+    /// The changes are kept in addition to earlier deltas and applied on top
+    /// of the tiles for every search, so they persist when tiles are fetched
+    /// again or evicted.
+    pub fn apply_delta(&mut self, delta: &NetworkDelta) {
+        self.delta.merge(delta);
+        self.network_changed();
+    }
+
+    /// Discards all changes applied with [`Self::apply_delta`].
+    pub fn clear_deltas(&mut self) {
+        self.delta = NetworkDelta::new();
+        self.network_changed();
+    }
+
+    /// Returns the density of all cached tiles as GeoJSON grid, see
+    /// [`Router::get_coverage`].
+    pub fn get_coverage(&self, cell_size: f64) -> String {
+        let sources: Vec<Vec<&ParsedTile>> = (0..self.backends.len())
+            .map(|index| {
+                self.tiles
+                    .iter()
+                    .filter(|((backend, _), _)| *backend == index)
+                    .map(|(_, cached)| &cached.tile)
+                    .collect()
+            })
+            .collect();
+        let mut router = Router::new();
+        merge_with_priority(&mut router, &sources);
+        router.apply_delta(&self.delta);
+        router.get_coverage(cell_size)
+    }
+
+    /// Estimates number and size of the tiles covering the region.
+    ///
+    /// The size is estimated from the tiles loaded so far.
+    pub fn estimate_region(
+        &self,
+        region: &geo::Polygon<f64>,
+    ) -> Result<RegionEstimate, RoutingError> {
+        let tiles = tiles_covering(region, self.tile_zoom())?.len() * self.backends.len();
+        let sizes: Vec<usize> = self
+            .tiles
+            .iter()
+            .map(|(_, cached)| routing_tile::encode(&cached.tile).len())
+            .collect();
+        let tile_size = if sizes.is_empty() {
+            DEFAULT_TILE_SIZE
+        } else {
+            sizes.iter().sum::<usize>() as f64 / sizes.len() as f64
+        };
+        Ok(RegionEstimate::new(tiles, tiles as f64 * tile_size))
+    }
+
+    /// Downloads all tiles covering the region into the offline store.
+    ///
+    /// `progress` is called after each tile with the number of processed
+    /// tiles, the total number of tiles and the bytes stored so far. Tiles
+    /// which can't be fetched, e.g. because the network doesn't cover them,
+    /// are skipped.
+    pub async fn download_region<F: FnMut(usize, usize, f64)>(
+        &self,
+        region: &geo::Polygon<f64>,
+        mut progress: F,
+    ) -> Result<RegionDownload, RoutingError> {
+        let store = self.store.as_ref().ok_or(RoutingError::OfflineStoreError)?;
+        let coords = tiles_covering(region, self.tile_zoom())?;
+        if let Some(version) = &self.data_version {
+            store
+                .put(VERSION_KEY, version.as_bytes().to_vec())
+                .await
+                .or(Err(RoutingError::OfflineStoreError))?;
+        }
+        let backends = &self.backends;
+        let total = coords.len() * backends.len();
+        let mut downloads = futures::stream::iter(
+            (0..backends.len()).flat_map(|index| coords.iter().map(move |coord| (index, coord))),
+        )
+        .map(|(index, coord)| async move {
+            let tile = match backends[index].get_tile(coord).await {
+                Ok(tile) => tile,
+                Err(err) => {
+                    warn_log!("Skipping offline tile {:?}: {:?}", coord, err);
+                    return Ok(None);
+                }
+            };
+            let data = routing_tile::encode(&tile.parse().or(Err(RoutingError::TileParsingError))?);
+            let size = data.len();
+            store
+                .put(&store_key(index, coord), data)
+                .await
+                .or(Err(RoutingError::OfflineStoreError))?;
+            Ok(Some(size))
+        })
+        .buffer_unordered(CONCURRENT_DOWNLOADS);
+        let mut download = RegionDownload::default();
+        let mut done = 0;
+        while let Some(result) = downloads.next().await {
+            match result? {
+                Some(size) => {
+                    download.tiles += 1;
+                    download.bytes += size as f64;
+                }
+                None => download.failed += 1,
+            }
+            done += 1;
+            progress(done, total, download.bytes);
+        }
+        Ok(download)
+    }
+
+    /// Loads tiles which are not cached from the offline store.
+    async fn load_stored_tiles(&mut self, coords: &[tile::Coord]) {
+        let store = match &self.store {
+            Some(store) => store,
+            None => return,
+        };
         let mut futures = Vec::new();
-        for x in (tile_coord.x - 1)..=(tile_coord.x + 1) {
-            for y in (tile_coord.y - 1)..=(tile_coord.y + 1) {
-                let rel_coord = tile::Coord {
-                    x,
-                    y,
-                    z: tile_coord.z,
-                };
-                if self.tiles.get(&rel_coord).is_none() {
-                    let coord_clone = rel_coord.clone();
-                    futures
-                        .push(async move { (backend.get_tile(&coord_clone).await, coord_clone) });
-                }
-            }
-        }
-        let tiles = join_all(futures).await;
-        for (tile, coord) in tiles {
-            if tile.is_ok() {
-                self.tiles.push(coord.clone(), tile.unwrap());
-            }
-        }
-        for x in (tile_coord.x - 1)..=(tile_coord.x + 1) {
-            for y in (tile_coord.y - 1)..=(tile_coord.y + 1) {
-                let rel_coord = tile::Coord {
-                    x,
-                    y,
-                    z: tile_coord.z,
-                };
-                let tile = self.tiles.get(&rel_coord);
-                if let Some(tile) = tile {
-                    let result = tile.parse(&mut self.router);
-                    if result.is_err() {
-                        debug_log!("Tile parsing error: {:?}", result);
-                        return Err(RoutingError::TileParsingError);
+        for index in 0..self.backends.len() {
+            for coord in coords {
+                let key = (index, coord.clone());
+                if !self.tiles.contains(&key) {
+                    futures.push(async move { (store.get(&store_key(index, &key.1)).await, key) });
+                }
+            }
+        }
+        for (result, key) in join_all(futures).await {
+            match result.map(|data| data.map(|data| routing_tile::decode(&data))) {
+                Ok(Some(Ok(tile))) => {
+                    self.tiles.push(key, CachedTile::new(tile, None));
+                    self.router_tiles = None;
+                }
+                Ok(None) => {}
+                _ => {
+                    warn_log!("Could not load stored tile {:?}", key);
+                }
+            }
+        }
+    }
+
+    /// Loads the tiles of all backends into the cache.
+    ///
+    /// Tiles are taken from the offline store if possible. Cached tiles are
+    /// only refetched if they are expired, stale tiles are queued for
+    /// `refresh_stale_tiles`. Returns the errors of tiles which could neither
+    /// be fetched nor taken from the cache. The time spent is added to the
+    /// fetch and parse timings.
+    async fn load_tiles(
+        &mut self,
+        coords: &[tile::Coord],
+        timings: &mut QueryTimings,
+    ) -> Result<Vec<RoutingError>, RoutingError> {
+        // All tiles of a search have to fit into the cache at once.
+        let needed = coords.len() * self.backends.len();
+        if needed > self.tiles.cap().get() {
+            self.tiles.resize(NonZeroUsize::new(needed).unwrap());
+        }
+        self.load_stored_tiles(coords).await;
+        let now = time::now();
+        let mut keys = Vec::new();
+        for index in 0..self.backends.len() {
+            for coord in coords {
+                let key = (index, coord.clone());
+                match self.tiles.get(&key) {
+                    Some(cached) if !cached.is_expired(self.max_age, now) => {
+                        if cached.is_expired(self.stale_after, now) && !self.stale.contains(&key) {
+                            self.stale.push(key);
+                        }
+                    }
+                    Some(cached) => {
+                        let validators = cached.validators.clone();
+                        keys.push((key, validators));
+                    }
+                    None => keys.push((key, None)),
+                }
+            }
+        }
+        let (fetching_errors, _) = self.fetch_tiles(keys, timings).await?;
+        Ok(fetching_errors)
+    }
+
+    /// Fetches the tiles, revalidating them if validators are given, and
+    /// puts them into the cache.
+    ///
+    /// Returns the errors of tiles which could neither be fetched nor taken
+    /// from the cache and the number of tiles which changed.
+    async fn fetch_tiles(
+        &mut self,
+        keys: Vec<((usize, tile::Coord), Option<Validators>)>,
+        timings: &mut QueryTimings,
+    ) -> Result<(Vec<RoutingError>, usize), RoutingError> {
+        let fetch_span = async_span!("load tiles");
+        let now = time::now();
+        let backends = &self.backends;
+        let futures = keys.into_iter().map(|(key, validators)| async move {
+            let span = async_span!("fetch tile {:?} of source {}", key.1, key.0);
+            let backend = &backends[key.0];
+            let result = span
+                .instrument(async {
+                    match validators {
+                        Some(validators) => backend.revalidate(&key.1, &validators).await,
+                        None => backend.get_tile(&key.1).await.map(Revalidation::Modified),
+                    }
+                })
+                .await;
+            (result, key)
+        });
+        let tiles = fetch_span.instrument(join_all(futures)).await;
+        timings.fetch += fetch_span.finish();
+        let mut fetching_errors = Vec::new();
+        let mut changed = 0;
+        let mut persisted = Vec::new();
+        for (result, key) in tiles {
+            match result {
+                Ok(Revalidation::NotModified) => {
+                    if let Some(cached) = self.tiles.get_mut(&key) {
+                        cached.fetched_at = now;
                     }
                 }
+                Ok(Revalidation::Modified(tile)) => {
+                    let span = span!("parse tile {:?} of source {}", key.1, key.0);
+                    let parsed = tile.parse();
+                    timings.parse += span.finish();
+                    match parsed {
+                        Ok(parsed) => {
+                            if self.persist_tiles && self.store.is_some() {
+                                persisted.push((
+                                    store_key(key.0, &key.1),
+                                    routing_tile::encode(&parsed),
+                                ));
+                            }
+                            self.tiles
+                                .push(key, CachedTile::new(parsed, tile.get_validators()));
+                            self.router_tiles = None;
+                            changed += 1;
+                        }
+                        Err(err) => {
+                            warn_log!("Tile parsing error: {:?}", err);
+                            return Err(RoutingError::TileParsingError);
+                        }
+                    }
+                }
+                Err(err) => {
+                    warn_log!("Could not fetch tile {:?}: {}", key, err);
+                    // Expired tiles are still used if refetching fails.
+                    if !self.tiles.contains(&key) {
+                        fetching_errors.push(RoutingError::tile_fetching(&key.1, err.as_ref()));
+                    }
+                }
+            }
+        }
+        if let Some(store) = &self.store {
+            let saves = persisted
+                .into_iter()
+                .map(|(key, data)| async move { (store.put(&key, data).await, key) });
+            for (result, key) in join_all(saves).await {
+                if let Err(err) = result {
+                    warn_log!("Could not save tile {}: {:?}", key, err);
+                }
             }
         }
-        self.router.find_route(start, stop)
+        Ok((fetching_errors, changed))
+    }
+
+    /// Sets the store of the session manifest.
+    ///
+    /// If set, the coordinates of the cached tiles are saved to the store after
+    /// each route search, so they can be prefetched with `warm_from_manifest`
+    /// in the next session.
+    pub fn set_manifest_store(&mut self, store: Option<TileStore>) {
+        self.manifest_store = store;
+    }
+
+    /// Returns the coordinates of the cached tiles of any backend, most
+    /// recently used first.
+    pub fn get_cached_tiles(&self) -> Vec<tile::Coord> {
+        self.manifest()
+    }
+
+    /// Returns the coordinates of the cached tiles, most recently used first.
+    fn manifest(&self) -> Vec<tile::Coord> {
+        let mut coords = Vec::new();
+        for ((_, coord), _) in self.tiles.iter() {
+            if !coords.contains(coord) {
+                coords.push(coord.clone());
+            }
+        }
+        coords
+    }
+
+    /// Saves the coordinates of the cached tiles to the manifest store.
+    pub async fn save_manifest(&self) -> Result<(), RoutingError> {
+        let store = self
+            .manifest_store
+            .as_ref()
+            .ok_or(RoutingError::OfflineStoreError)?;
+        let coords: Vec<[u32; 3]> = self
+            .manifest()
+            .iter()
+            .map(|coord| [u32::from(coord.z), coord.x, coord.y])
+            .collect();
+        let data = serde_json::to_vec(&coords).or(Err(RoutingError::OfflineStoreError))?;
+        store
+            .put(MANIFEST_KEY, data)
+            .await
+            .or(Err(RoutingError::OfflineStoreError))
+    }
+
+    /// Prefetches the tiles listed in the manifest of the last session.
+    ///
+    /// Tiles of other zoom levels than the current one are ignored. Returns
+    /// the number of tiles which were loaded, tiles which can't be fetched
+    /// are skipped.
+    pub async fn warm_from_manifest(&mut self) -> Result<usize, RoutingError> {
+        let store = self
+            .manifest_store
+            .as_ref()
+            .ok_or(RoutingError::OfflineStoreError)?;
+        let data = match store
+            .get(MANIFEST_KEY)
+            .await
+            .or(Err(RoutingError::OfflineStoreError))?
+        {
+            Some(data) => data,
+            None => return Ok(0),
+        };
+        let zoom = self.tile_zoom();
+        let coords: Vec<tile::Coord> = serde_json::from_slice::<Vec<[u32; 3]>>(&data)
+            .or(Err(RoutingError::OfflineStoreError))?
+            .into_iter()
+            .filter(|[z, _, _]| *z == u32::from(zoom))
+            .map(|[_, x, y]| tile::Coord { x, y, z: zoom })
+            .take(TILES_PER_BACKEND)
+            .collect();
+        self.load_tiles(&coords, &mut QueryTimings::default())
+            .await?;
+        Ok(coords
+            .iter()
+            .filter(|coord| {
+                (0..self.backends.len())
+                    .any(|index| self.tiles.contains(&(index, (*coord).clone())))
+            })
+            .count())
+    }
+
+    /// Finds a route between the points using the tiles around the start.
+    pub async fn find_route(&mut self, start: &Point, stop: &Point) -> Result<Route, RoutingError> {
+        self.find_route_with_options(start, stop, &RouteOptions::default())
+            .await
+    }
+
+    /// Finds a route between the points with the given options using the
+    /// tiles around the start.
+    pub async fn find_route_with_options(
+        &mut self,
+        start: &Point,
+        stop: &Point,
+        options: &RouteOptions,
+    ) -> Result<Route, RoutingError> {
+        let span = async_span!("find route");
+        let mut timings = QueryTimings::default();
+        let selector = selection::selector(options.tile_selection, options.tile_radius);
+        let mut selected = selector.select(start, stop, self.zoom, 0);
+        let mut attempt = 0;
+        let (coords, result) = loop {
+            let coords = self.source_tiles(selected.unwrap_or_default());
+            let (fetching_errors, tiles_used) = match span
+                .instrument(self.build_router(&coords, &mut timings))
+                .await
+            {
+                Ok(built) => built,
+                Err(err) => {
+                    timings.total = span.finish();
+                    self.last_timings = Some(timings);
+                    return Err(err);
+                }
+            };
+            let search_start = time::monotonic();
+            let mut result =
+                span.in_scope(|| self.router.find_route_with_options(start, stop, options));
+            if let Ok(route) = &mut result {
+                route.stats_mut().tiles_used = tiles_used;
+            }
+            timings.search += time::monotonic() - search_start;
+            // Strategies with further attempts get them for partial routes too.
+            let found = result.as_ref().is_ok_and(|route| route.get_gap().is_none());
+            attempt += 1;
+            selected = selector.select(start, stop, self.zoom, attempt);
+            if !found && selected.is_some() {
+                continue;
+            }
+            break (
+                coords,
+                match result {
+                    Err(err) if err.is_no_route() && !fetching_errors.is_empty() => {
+                        Err(most_relevant_error(fetching_errors))
+                    }
+                    result => result,
+                },
+            );
+        };
+        span.instrument(self.save_manifest_if_set()).await;
+        timings.total = span.finish();
+        self.last_timings = Some(timings);
+        if let Err(err) = &result {
+            self.capture_failure(start, stop, &coords, err);
+        }
+        result
+    }
+
+    /// Finds a route through all stops in order, e.g. with via points between
+    /// start and finish.
+    ///
+    /// The legs between consecutive stops are kept until the next via route,
+    /// so that moving a single via point only searches the two legs next to
+    /// it. They are discarded if the options differ or the network changes.
+    pub async fn find_via_route(
+        &mut self,
+        stops: Vec<Point>,
+        options: &RouteOptions,
+    ) -> Result<Route, RoutingError> {
+        if stops.len() < 2 {
+            return Err(RoutingError::InvalidStops);
+        }
+        let previous = match self.legs.take() {
+            Some((previous_options, legs)) if previous_options == *options => legs,
+            _ => Vec::new(),
+        };
+        let mut legs: Vec<Leg> = Vec::with_capacity(stops.len() - 1);
+        for pair in stops.windows(2) {
+            let (start, stop) = (&pair[0], &pair[1]);
+            let route = match previous.iter().find(|leg| leg.is_between(start, stop)) {
+                Some(leg) => leg.route.clone(),
+                None => match self.find_route_with_options(start, stop, options).await {
+                    Ok(route) => route,
+                    Err(err) => {
+                        self.legs = Some((options.clone(), legs));
+                        return Err(err);
+                    }
+                },
+            };
+            legs.push(Leg {
+                start: start.clone(),
+                stop: stop.clone(),
+                route,
+            });
+        }
+        let routes: Vec<_> = legs.iter().map(|leg| leg.route.clone()).collect();
+        self.legs = Some((options.clone(), legs));
+        Ok(Route::join(&routes))
+    }
+
+    /// Finds routes from start to each of the stops with a single search
+    /// using the tiles around the start, see [`Router::find_routes_from`].
+    pub async fn find_routes_from(
+        &mut self,
+        start: &Point,
+        stops: Vec<Point>,
+        options: &RouteOptions,
+    ) -> Result<Vec<Route>, RoutingError> {
+        let span = async_span!("find routes");
+        let mut timings = QueryTimings::default();
+        let coords = self.tiles_around(start);
+        let (fetching_errors, tiles_used) = match span
+            .instrument(self.build_router(&coords, &mut timings))
+            .await
+        {
+            Ok(built) => built,
+            Err(err) => {
+                timings.total = span.finish();
+                self.last_timings = Some(timings);
+                return Err(err);
+            }
+        };
+        let search_start = time::monotonic();
+        let result = match span.in_scope(|| self.router.find_routes_from(start, stops, options)) {
+            Ok(mut routes) => {
+                for route in &mut routes {
+                    route.stats_mut().tiles_used = tiles_used;
+                }
+                Ok(routes)
+            }
+            Err(err) if err.is_no_route() && !fetching_errors.is_empty() => {
+                Err(most_relevant_error(fetching_errors))
+            }
+            Err(err) => Err(err),
+        };
+        timings.search = time::monotonic() - search_start;
+        span.instrument(self.save_manifest_if_set()).await;
+        timings.total = span.finish();
+        self.last_timings = Some(timings);
+        result
+    }
+
+    /// Finds a route from each start to the stop at the same index.
+    ///
+    /// The tiles of all pairs are planned before loading: pairs are ordered
+    /// by the tile of their start and grouped into batches whose tiles fit
+    /// into the cache. The tiles of a batch are loaded once and all of its
+    /// pairs are searched with the same router, instead of loading the tiles
+    /// around each start in turn. Pairs without a route get a route without
+    /// segments, like [`Router::find_routes_from`].
+    pub async fn find_routes(
+        &mut self,
+        starts: Vec<Point>,
+        stops: Vec<Point>,
+        options: &RouteOptions,
+    ) -> Result<Vec<Route>, RoutingError> {
+        if starts.len() != stops.len() {
+            return Err(RoutingError::InvalidStops);
+        }
+        let span = async_span!("find routes");
+        let mut timings = QueryTimings::default();
+        let mut order: Vec<usize> = (0..starts.len()).collect();
+        order.sort_by_key(|&index| {
+            let coord = point_to_tile_coord(&starts[index], self.zoom);
+            (coord.y, coord.x)
+        });
+        let mut batches: Vec<(Vec<tile::Coord>, Vec<usize>)> = Vec::new();
+        for index in order {
+            let around = self.tiles_around(&starts[index]);
+            if let Some((coords, indices)) = batches.last_mut() {
+                let added: Vec<_> = around
+                    .iter()
+                    .filter(|coord| !coords.contains(coord))
+                    .collect();
+                if coords.len() + added.len() <= TILES_PER_BACKEND {
+                    coords.extend(added.into_iter().cloned());
+                    indices.push(index);
+                    continue;
+                }
+            }
+            batches.push((around, vec![index]));
+        }
+        let mut routes: Vec<Option<Route>> = vec![None; starts.len()];
+        let mut result = Ok(());
+        for (coords, indices) in &batches {
+            let (fetching_errors, tiles_used) = match span
+                .instrument(self.build_router(coords, &mut timings))
+                .await
+            {
+                Ok(built) => built,
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            };
+            let search_start = time::monotonic();
+            for &index in indices {
+                let (start, stop) = (&starts[index], &stops[index]);
+                match span.in_scope(|| self.router.find_route_with_options(start, stop, options)) {
+                    Ok(mut route) => {
+                        route.stats_mut().tiles_used = tiles_used;
+                        routes[index] = Some(route);
+                    }
+                    Err(err) if err.is_no_route() && !fetching_errors.is_empty() => {
+                        result = Err(most_relevant_error(fetching_errors.clone()));
+                    }
+                    Err(err) if err.is_no_route() => {}
+                    Err(err) => result = Err(err),
+                }
+                if result.is_err() {
+                    break;
+                }
+            }
+            timings.search += time::monotonic() - search_start;
+            if result.is_err() {
+                break;
+            }
+        }
+        span.instrument(self.save_manifest_if_set()).await;
+        timings.total = span.finish();
+        self.last_timings = Some(timings);
+        result?;
+        Ok(routes
+            .into_iter()
+            .zip(starts.into_iter().zip(stops))
+            .map(|(route, (start, stop))| {
+                route.unwrap_or_else(|| Route::new(vec![start, stop], Vec::new()))
+            })
+            .collect())
+    }
+
+    /// Loads the tiles and rebuilds the router from them, unless it was built
+    /// from the same tiles and neither they nor the network changed since.
+    ///
+    /// Returns the errors of tiles which could not be fetched and the number
+    /// of tiles used.
+    async fn build_router(
+        &mut self,
+        coords: &[tile::Coord],
+        timings: &mut QueryTimings,
+    ) -> Result<(Vec<RoutingError>, usize), RoutingError> {
+        let fetching_errors = self.load_tiles(coords, timings).await?;
+        let mut sources = Vec::new();
+        let cached = &mut self.tiles;
+        for index in 0..self.backends.len() {
+            for coord in coords {
+                cached.promote(&(index, coord.clone()));
+            }
+        }
+        for index in 0..self.backends.len() {
+            sources.push(
+                coords
+                    .iter()
+                    .filter_map(|coord| cached.peek(&(index, coord.clone())))
+                    .map(|cached| &cached.tile)
+                    .collect(),
+            );
+        }
+        if self.router_tiles.as_deref() != Some(coords) {
+            self.router = Router::new();
+            self.router.set_profiles(self.profiles.clone());
+            let build_span = span!("merge tiles");
+            merge_with_priority(&mut self.router, &sources);
+            self.router.apply_delta(&self.delta);
+            timings.build += build_span.finish();
+            self.router_tiles = Some(coords.to_vec());
+        }
+        let tiles_used = sources.iter().map(Vec::len).sum();
+        self.evict_far_tiles(coords);
+        Ok((fetching_errors, tiles_used))
+    }
+
+    /// Drops the cached tiles farther than the eviction radius from all of the
+    /// given tiles.
+    fn evict_far_tiles(&mut self, coords: &[tile::Coord]) {
+        let radius = match self.eviction_radius {
+            Some(radius) => radius,
+            None => return,
+        };
+        let far: Vec<_> = self
+            .tiles
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|(_, coord)| {
+                coords.iter().all(|near| {
+                    coord
+                        .distance(near)
+                        .is_none_or(|distance| distance > radius)
+                })
+            })
+            .cloned()
+            .collect();
+        for key in far {
+            self.tiles.pop(&key);
+        }
+    }
+
+    /// Saves the manifest of the used tiles, if its store is set.
+    async fn save_manifest_if_set(&self) {
+        if self.manifest_store.is_some() {
+            if let Err(err) = self.save_manifest().await {
+                warn_log!("Could not save manifest: {:?}", err);
+            }
+        }
+    }
+
+    /// Sets whether failed route searches are captured, see
+    /// `get_failure_report`.
+    pub fn set_capture_failures(&mut self, enabled: bool) {
+        self.capture_failures = enabled;
+        if !enabled {
+            self.failure_report = None;
+        }
+    }
+
+    /// Returns a JSON report of the last failed route search, if failures
+    /// are captured.
+    ///
+    /// The report contains the start and stop, the error, the zoom levels and
+    /// the coordinates of the tiles which were loaded for each source, so the
+    /// failure can be reproduced with the same data.
+    pub fn get_failure_report(&self) -> Option<String> {
+        self.failure_report.clone()
+    }
+
+    /// Saves the report of a failed route search if enabled.
+    fn capture_failure(
+        &mut self,
+        start: &Point,
+        stop: &Point,
+        coords: &[tile::Coord],
+        err: &RoutingError,
+    ) {
+        if !self.capture_failures {
+            return;
+        }
+        let tiles: Vec<Vec<[u32; 3]>> = (0..self.backends.len())
+            .map(|index| {
+                coords
+                    .iter()
+                    .filter(|coord| self.tiles.contains(&(index, (*coord).clone())))
+                    .map(|coord| [u32::from(coord.z), coord.x, coord.y])
+                    .collect()
+            })
+            .collect();
+        let missing: Vec<[u32; 3]> = coords
+            .iter()
+            .filter(|coord| {
+                !(0..self.backends.len())
+                    .any(|index| self.tiles.contains(&(index, (*coord).clone())))
+            })
+            .map(|coord| [u32::from(coord.z), coord.x, coord.y])
+            .collect();
+        let report = serde_json::json!({
+            "start": [start.x(), start.y()],
+            "stop": [stop.x(), stop.y()],
+            "error": err.to_string(),
+            "zoom": self.zoom,
+            "tileZoom": self.tile_zoom(),
+            "dataVersion": self.data_version,
+            "tiles": tiles,
+            "missingTiles": missing,
+            "capturedAt": time::now(),
+        });
+        self.failure_report = Some(report.to_string());
+    }
+
+    /// Returns the timings of the last route search, `None` before the first
+    /// search.
+    pub fn get_last_timings(&self) -> Option<QueryTimings> {
+        self.last_timings.clone()
+    }
+}
+
+/// Returns the fetching error which most likely caused a routing failure.
+///
+/// Tiles which don't exist (404) are usually just outside the covered area,
+/// so other errors like server or network failures are preferred.
+fn most_relevant_error(mut errors: Vec<RoutingError>) -> RoutingError {
+    let index = errors
+        .iter()
+        .position(|err| {
+            !matches!(
+                err,
+                RoutingError::TileFetchingError {
+                    status: Some(404),
+                    ..
+                }
+            )
+        })
+        .unwrap_or(0);
+    errors.swap_remove(index)
+}
+
+/// Returns the key of a tile in the offline store.
+fn store_key(index: usize, coord: &tile::Coord) -> String {
+    format!("{}/{}/{}/{}", index, coord.z, coord.x, coord.y)
+}
+
+/// Merges the tiles of several sources into the router.
+///
+/// Sources are given in order of priority. Segments and connectors whose IDs
+/// were already provided by a source of higher priority are skipped, as are
+/// turn restrictions at such connectors. Features with the same ID from the
+/// same source (e.g. a segment clipped at tile borders) are all kept.
+fn merge_with_priority(router: &mut Router, sources: &[Vec<&ParsedTile>]) {
+    let mut known_segments = HashSet::new();
+    let mut known_connectors = HashSet::new();
+    for tiles in sources {
+        let mut source_segments = HashSet::new();
+        let mut source_connectors = HashSet::new();
+        for tile in tiles {
+            for segment in tile.get_segments() {
+                if !known_segments.contains(&segment.get_id()) {
+                    source_segments.insert(segment.get_id());
+                    router.push_segment(segment.clone());
+                }
+            }
+            for connector in tile.get_connectors() {
+                if !known_connectors.contains(&connector.get_id()) {
+                    source_connectors.insert(connector.get_id());
+                    router.push_connector(connector.clone());
+                }
+            }
+            for restriction in tile.get_restrictions() {
+                if !known_connectors.contains(restriction.via()) {
+                    router.push_turn_restriction(restriction.clone());
+                }
+            }
+        }
+        known_segments.extend(source_segments);
+        known_connectors.extend(source_connectors);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo_types::coord::{coord, Coord};
+    use crate::geo_types::LineString;
+    use crate::routing::{Connector, Segment, TileSelection, TurnRestriction};
+    use crate::tile::backend::FetchingError;
+    use crate::tile::region::bounds_to_region;
+    use futures::executor::block_on;
+    use thiserror::Error;
+
+    fn tile(segment_ids: &[&str], connector_ids: &[&str]) -> ParsedTile {
+        let mut tile = ParsedTile::new();
+        for id in segment_ids {
+            tile.push_segment(Segment::new(
+                id.to_string(),
+                LineString::new(vec![coord!(x: 0.0, y: 0.0), coord!(x: 1.0, y: 0.0)]),
+                vec![],
+            ));
+        }
+        for id in connector_ids {
+            tile.push_connector(Connector::new(id, &Point::new(0.0, 0.0)));
+        }
+        tile
+    }
+
+    #[test]
+    fn merge_with_priority() {
+        let base_a = tile(&["1", "2"], &["a"]);
+        let base_b = tile(&["2", "3"], &["b"]);
+        let mut overlay = tile(&["2", "4"], &["a", "c"]);
+        // Dropped, connector a is provided by the base.
+        overlay.push_restriction(TurnRestriction::new("1".into(), "a".into(), "2".into()));
+        overlay.push_restriction(TurnRestriction::new("4".into(), "c".into(), "2".into()));
+        let mut router = Router::new();
+        super::merge_with_priority(&mut router, &[vec![&base_a, &base_b], vec![&overlay]]);
+        // Segment 2 from both base tiles, but not from the overlay.
+        assert_eq!(router.segments_len(), 5);
+        assert_eq!(router.connectors_len(), 3);
+        assert_eq!(router.restrictions_len(), 1);
+    }
+
+    /// Backend returning the same tile for all coordinates.
+    struct NetworkBackend(ParsedTile);
+
+    struct NetworkTile(ParsedTile);
+
+    impl Tile for NetworkTile {
+        fn parse(&self) -> Result<ParsedTile, Box<dyn std::error::Error>> {
+            Ok(self.0.clone())
+        }
+    }
+
+    impl Backend<NetworkTile> for NetworkBackend {
+        fn get_tile<'a>(&'a self, _coord: &'a tile::Coord) -> TileFuture<'a, NetworkTile> {
+            Box::pin(async move { Ok(NetworkTile(self.0.clone())) })
+        }
+    }
+
+    #[test]
+    fn turn_restrictions() {
+        let mut network_tile = ParsedTile::new();
+        for (id, x) in [("a", 0.1), ("x", 0.5), ("b", 0.9)] {
+            network_tile.push_connector(Connector::new(id, &Point::new(x, 0.1)));
+        }
+        for (id, coords, connectors) in [
+            ("in", vec![(0.1, 0.1), (0.5, 0.1)], ["a", "x"]),
+            ("direct", vec![(0.5, 0.1), (0.9, 0.1)], ["x", "b"]),
+            (
+                "detour",
+                vec![(0.5, 0.1), (0.5, 0.5), (0.9, 0.5), (0.9, 0.1)],
+                ["x", "b"],
+            ),
+        ] {
+            network_tile.push_segment(Segment::new(
+                id.into(),
+                LineString::new(
+                    coords
+                        .into_iter()
+                        .map(|(x, y)| coord!(x: x, y: y))
+                        .collect(),
+                ),
+                connectors.iter().map(|id| id.to_string()).collect(),
+            ));
+        }
+        let uses_detour = |network_tile: &ParsedTile| {
+            let mut network = CachedTileNetwork::new(NetworkBackend(network_tile.clone()));
+            network.set_zoom(2);
+            block_on(network.find_route(&Point::new(0.1, 0.1), &Point::new(0.9, 0.1)))
+                .unwrap()
+                .get_segments()
+                .iter()
+                .any(|segment| segment.get_segment().get_id() == "detour")
+        };
+        assert!(!uses_detour(&network_tile));
+        network_tile.push_restriction(TurnRestriction::new(
+            "in".into(),
+            "x".into(),
+            "direct".into(),
+        ));
+        assert!(uses_detour(&network_tile));
+    }
+
+    #[test]
+    fn cached_tile_expiry() {
+        let cached = CachedTile {
+            tile: ParsedTile::new(),
+            fetched_at: 1000.0,
+            validators: None,
+        };
+        assert!(!cached.is_expired(None, 1_000_000.0));
+        assert!(!cached.is_expired(Some(60.0), 61_000.0));
+        assert!(cached.is_expired(Some(60.0), 61_001.0));
+    }
+
+    #[derive(Error, Debug)]
+    #[error("Tile missing")]
+    struct MissingTile;
+
+    struct MockTile;
+
+    impl Tile for MockTile {
+        fn parse(&self) -> Result<ParsedTile, Box<dyn std::error::Error>> {
+            Ok(tile(&["1"], &["a"]))
+        }
+    }
+
+    /// Backend which only has tiles with x coordinates up to max_x.
+    struct MockBackend {
+        max_x: u32,
+    }
+
+    impl Backend<MockTile> for MockBackend {
+        fn get_tile<'a>(&'a self, coord: &'a tile::Coord) -> TileFuture<'a, MockTile> {
+            Box::pin(async move {
+                if coord.x <= self.max_x {
+                    Ok(MockTile)
+                } else {
+                    Err(MissingTile.into())
+                }
+            })
+        }
+    }
+
+    #[test]
+    fn download_region() {
+        let mut network = CachedTileNetwork::new(MockBackend { max_x: 1 });
+        network.set_zoom(2);
+        let region = bounds_to_region(&[-10.0, -10.0, 10.0, 10.0]).unwrap();
+        assert_eq!(network.estimate_region(&region).unwrap().get_tiles(), 4);
+        assert!(block_on(network.download_region(&region, |_, _, _| {})).is_err());
+
+        network.set_store(Some(TileStore::memory()));
+        let mut calls = Vec::new();
+        let download =
+            block_on(network.download_region(&region, |done, total, _| calls.push((done, total))))
+                .unwrap();
+        assert_eq!(download.get_tiles(), 2);
+        assert_eq!(download.get_failed(), 2);
+        assert_eq!(calls.last(), Some(&(4, 4)));
+
+        let coords = tiles_covering(&region, 2).unwrap();
+        block_on(network.load_stored_tiles(&coords));
+        assert_eq!(network.tiles.len(), 2);
+        assert_eq!(
+            network
+                .tiles
+                .peek(&(0, coords[0].clone()))
+                .unwrap()
+                .tile
+                .segments_len(),
+            1
+        );
+    }
+
+    #[test]
+    fn session_manifest() {
+        let mut network = CachedTileNetwork::new(MockBackend { max_x: 1 });
+        network.set_zoom(2);
+        assert!(block_on(network.warm_from_manifest()).is_err());
+        network.set_manifest_store(Some(TileStore::memory()));
+        assert_eq!(block_on(network.warm_from_manifest()).unwrap(), 0);
+
+        let coords = tile::Coord { x: 1, y: 1, z: 2 }.with_neighbours();
+        let errors = block_on(network.load_tiles(&coords, &mut QueryTimings::default())).unwrap();
+        assert_eq!(errors.len(), 3);
+        block_on(network.save_manifest()).unwrap();
+
+        let mut next = CachedTileNetwork::new(MockBackend { max_x: 1 });
+        next.set_zoom(2);
+        next.set_manifest_store(network.manifest_store.take());
+        assert_eq!(block_on(next.warm_from_manifest()).unwrap(), 6);
+        assert_eq!(next.tiles.len(), 6);
+        assert_eq!(next.get_cached_tiles().len(), 6);
+
+        next.set_zoom(3);
+        assert_eq!(block_on(next.warm_from_manifest()).unwrap(), 0);
+    }
+
+    #[test]
+    fn persist_tiles() {
+        let mut network = CachedTileNetwork::new(MockBackend { max_x: 1 });
+        network.set_zoom(2);
+        network.set_store(Some(TileStore::memory()));
+        let coords = tile::Coord { x: 1, y: 1, z: 2 }.with_neighbours();
+        block_on(network.load_tiles(&coords, &mut QueryTimings::default())).unwrap();
+        assert!(block_on(
+            network
+                .store
+                .as_ref()
+                .unwrap()
+                .get(&store_key(0, &coords[0]))
+        )
+        .unwrap()
+        .is_none());
+
+        network.tiles.clear();
+        network.set_persist_tiles(true);
+        block_on(network.load_tiles(&coords, &mut QueryTimings::default())).unwrap();
+
+        // The next session reads the parsed tiles from the store, even if the
+        // backend has none.
+        let mut next = CachedTileNetwork::new(MockBackend { max_x: 0 });
+        next.set_zoom(2);
+        next.set_store(network.store.take());
+        let errors = block_on(next.load_tiles(&coords, &mut QueryTimings::default())).unwrap();
+        assert_eq!(errors.len(), 3);
+        assert_eq!(next.tiles.len(), 6);
+    }
+
+    #[test]
+    fn refresh_stale_tiles() {
+        let mut network = CachedTileNetwork::new(MockBackend { max_x: 1 });
+        network.set_zoom(2);
+        network.set_stale_after(Some(60.0));
+        let coords = tile::Coord { x: 1, y: 1, z: 2 }.with_neighbours();
+        block_on(network.load_tiles(&coords, &mut QueryTimings::default())).unwrap();
+        assert!(network.stale.is_empty());
+
+        for (_, cached) in network.tiles.iter_mut() {
+            cached.fetched_at -= 120_000.0;
+        }
+        // Stale tiles are used without fetching, even if the backend fails.
+        network.get_backends_mut()[0].max_x = 0;
+        let errors = block_on(network.load_tiles(&coords, &mut QueryTimings::default())).unwrap();
+        assert_eq!(errors.len(), 3);
+        assert_eq!(network.stale.len(), 6);
+
+        // Tiles which can't be refetched stay stale in the cache.
+        assert_eq!(block_on(network.refresh_stale_tiles()).unwrap(), 3);
+        assert!(network.stale.is_empty());
+        assert_eq!(network.tiles.len(), 6);
+        block_on(network.load_tiles(&coords, &mut QueryTimings::default())).unwrap();
+        assert_eq!(network.stale.len(), 3);
+    }
+
+    #[test]
+    fn data_version() {
+        let mut network = CachedTileNetwork::new(MockBackend { max_x: 1 });
+        network.set_zoom(2);
+        network.set_store(Some(TileStore::memory()));
+        assert!(!block_on(network.set_data_version(Some("1".into()))).unwrap());
+        let region = bounds_to_region(&[-10.0, -10.0, 10.0, 10.0]).unwrap();
+        block_on(network.download_region(&region, |_, _, _| {})).unwrap();
+        let coords = tiles_covering(&region, 2).unwrap();
+        block_on(network.load_tiles(&coords, &mut QueryTimings::default())).unwrap();
+        assert_eq!(network.tiles.len(), 2);
+
+        assert!(!block_on(network.set_data_version(Some("1".into()))).unwrap());
+        assert_eq!(network.tiles.len(), 2);
+
+        assert!(block_on(network.set_data_version(Some("2".into()))).unwrap());
+        assert_eq!(network.tiles.len(), 0);
+        let store = network.store.as_ref().unwrap();
+        assert!(block_on(store.get(&store_key(0, &coords[0])))
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            block_on(store.get(VERSION_KEY)).unwrap(),
+            Some(b"2".to_vec())
+        );
+    }
+
+    #[test]
+    fn overzoom() {
+        let mut network = CachedTileNetwork::new(MockBackend { max_x: 1 });
+        network.set_zoom(4);
+        let region = bounds_to_region(&[-50.0, -50.0, 50.0, 50.0]).unwrap();
+        assert!(network.estimate_region(&region).unwrap().get_tiles() > 4);
+        network.set_max_source_zoom(Some(2));
+        assert_eq!(network.tile_zoom(), 2);
+        assert_eq!(network.estimate_region(&region).unwrap().get_tiles(), 4);
+        assert_eq!(network.get_zoom(), 4);
+        network.set_max_source_zoom(Some(16));
+        assert_eq!(network.tile_zoom(), 4);
+    }
+
+    #[test]
+    fn query_timings() {
+        let mut network = CachedTileNetwork::new(MockBackend { max_x: 3 });
+        network.set_zoom(2);
+        assert_eq!(network.get_last_timings(), None);
+        let route =
+            block_on(network.find_route(&Point::new(0.1, 0.0), &Point::new(0.9, 0.0))).unwrap();
+        assert_eq!(route.get_stats().get_tiles_used(), 9);
+        let timings = network.get_last_timings().unwrap();
+        for phase in [
+            timings.get_fetch(),
+            timings.get_parse(),
+            timings.get_build(),
+            timings.get_search(),
+        ] {
+            assert!(phase >= 0.0 && phase <= timings.get_total());
+        }
+    }
+
+    #[test]
+    fn apply_delta() {
+        let mut network = CachedTileNetwork::new(MockBackend { max_x: 3 });
+        network.set_zoom(2);
+        let (start, stop) = (Point::new(0.1, 0.0), Point::new(0.9, 0.0));
+        let mut delta = NetworkDelta::new();
+        delta.remove_segment("1");
+        network.apply_delta(&delta);
+        assert_eq!(
+            block_on(network.find_route(&start, &stop)).err(),
+            Some(RoutingError::MissingSegments)
+        );
+
+        let mut delta = NetworkDelta::new();
+        delta.put_segment(Segment::new(
+            "2".into(),
+            LineString::new(vec![coord!(x: 0.0, y: 0.0), coord!(x: 1.0, y: 0.0)]),
+            vec![],
+        ));
+        network.apply_delta(&delta);
+        let route = block_on(network.find_route(&start, &stop)).unwrap();
+        assert_eq!(route.get_segments()[0].get_segment().get_id(), "2");
+        // Segment 2 is also in the coverage, but segment 1 still removed.
+        let coverage: serde_json::Value = serde_json::from_str(&network.get_coverage(1.0)).unwrap();
+        assert_eq!(coverage["features"][0]["properties"]["segments"], 1);
+
+        network.clear_deltas();
+        let route = block_on(network.find_route(&start, &stop)).unwrap();
+        assert_eq!(route.get_segments()[0].get_segment().get_id(), "1");
+    }
+
+    #[test]
+    fn reuse_router() {
+        let mut network = CachedTileNetwork::new(MockBackend { max_x: 3 });
+        network.set_zoom(2);
+        let (start, stop) = (Point::new(0.1, 0.0), Point::new(0.9, 0.0));
+        block_on(network.find_route(&start, &stop)).unwrap();
+        let connectors = network.router.connectors_len();
+        // A marker shows whether the router is rebuilt.
+        network
+            .router
+            .push_connector(Connector::new("marker", &Point::new(5.0, 5.0)));
+        block_on(network.find_route(&start, &stop)).unwrap();
+        assert_eq!(network.router.connectors_len(), connectors + 1);
+
+        network.apply_delta(&NetworkDelta::new());
+        block_on(network.find_route(&start, &stop)).unwrap();
+        assert_eq!(network.router.connectors_len(), connectors);
+    }
+
+    #[test]
+    fn find_routes_from() {
+        let mut network = CachedTileNetwork::new(MockBackend { max_x: 3 });
+        network.set_zoom(2);
+        let routes = block_on(network.find_routes_from(
+            &Point::new(0.1, 0.0),
+            vec![Point::new(0.5, 0.0), Point::new(0.9, 0.0)],
+            &RouteOptions::default(),
+        ))
+        .unwrap();
+        assert_eq!(routes.len(), 2);
+        for route in routes {
+            assert_eq!(route.get_segments()[0].get_segment().get_id(), "1");
+            assert_eq!(route.get_stats().get_tiles_used(), 9);
+        }
+        assert!(network.get_last_timings().is_some());
+    }
+
+    #[test]
+    fn find_via_route() {
+        let mut network = CachedTileNetwork::new(MockBackend { max_x: 3 });
+        network.set_zoom(2);
+        let options = RouteOptions::default();
+        let stops = vec![
+            Point::new(0.1, 0.0),
+            Point::new(0.5, 0.0),
+            Point::new(0.9, 0.0),
+        ];
+        assert_eq!(
+            block_on(network.find_via_route(stops[..1].to_vec(), &options)).err(),
+            Some(RoutingError::InvalidStops)
+        );
+        let route = block_on(network.find_via_route(stops.clone(), &options)).unwrap();
+        assert_eq!(route.get_stops().len(), 3);
+        assert_eq!(route.get_stats().get_tiles_used(), 18);
+
+        // Unchanged legs are not searched again.
+        network.last_timings = None;
+        let again = block_on(network.find_via_route(stops.clone(), &options)).unwrap();
+        assert_eq!(again.get_segments().len(), route.get_segments().len());
+        assert_eq!(network.get_last_timings(), None);
+
+        let mut moved = stops.clone();
+        moved[1] = Point::new(0.6, 0.0);
+        block_on(network.find_via_route(moved, &options)).unwrap();
+        assert!(network.get_last_timings().is_some());
+
+        network.last_timings = None;
+        network.apply_delta(&NetworkDelta::new());
+        block_on(network.find_via_route(stops, &options)).unwrap();
+        assert!(network.get_last_timings().is_some());
+    }
+
+    #[test]
+    fn find_routes() {
+        let mut network = CachedTileNetwork::new(MockBackend { max_x: u32::MAX });
+        network.set_zoom(6);
+        let options = RouteOptions::default();
+        let starts = vec![
+            Point::new(100.0, 0.0),
+            Point::new(0.1, 0.0),
+            Point::new(-100.0, 0.0),
+            Point::new(50.0, 0.0),
+            Point::new(0.2, 0.0),
+        ];
+        let stops = vec![Point::new(0.9, 0.0); starts.len()];
+        assert_eq!(
+            block_on(network.find_routes(starts.clone(), stops[1..].to_vec(), &options)).err(),
+            Some(RoutingError::InvalidStops)
+        );
+        let routes = block_on(network.find_routes(starts.clone(), stops, &options)).unwrap();
+        assert_eq!(routes.len(), starts.len());
+        for (route, start) in routes.iter().zip(&starts) {
+            assert_eq!(route.get_stops()[0].x(), start.x());
+        }
+        // Three areas of 9 tiles fill the cache, the fourth is a new batch.
+        let tiles_used: Vec<_> = routes
+            .iter()
+            .map(|route| route.get_stats().get_tiles_used())
+            .collect();
+        assert_eq!(tiles_used, vec![9, 27, 27, 27, 27]);
+        assert!(block_on(network.find_routes(vec![], vec![], &options))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn tile_selection() {
+        let mut network = CachedTileNetwork::new(MockBackend { max_x: u32::MAX });
+        network.set_zoom(6);
+        let mut options = RouteOptions::default();
+        options.set_tile_selection(TileSelection::Corridor);
+        let (start, stop) = (Point::new(0.1, 0.0), Point::new(20.0, 0.0));
+        let route = block_on(network.find_route_with_options(&start, &stop, &options)).unwrap();
+        // Six columns of three tiles along the line, more than fit into the
+        // cache by default.
+        assert_eq!(route.get_stats().get_tiles_used(), 18);
+
+        // The tiles around the points are missing, the next column is not.
+        let mut network = CachedTileNetwork::new(MockBackend { max_x: 30 });
+        network.set_zoom(6);
+        let (start, stop) = (Point::new(0.1, 0.0), Point::new(0.9, 0.0));
+        assert!(block_on(network.find_route(&start, &stop)).is_err());
+        options.set_tile_selection(TileSelection::Expanding);
+        let route = block_on(network.find_route_with_options(&start, &stop, &options)).unwrap();
+        assert_eq!(route.get_stats().get_tiles_used(), 5);
+    }
+
+    #[test]
+    fn evict_far_tiles() {
+        let mut network = CachedTileNetwork::new(MockBackend { max_x: u32::MAX });
+        network.set_zoom(6);
+        let stop = Point::new(0.9, 0.0);
+        block_on(network.find_route(&Point::new(0.1, 0.0), &stop)).unwrap();
+        block_on(network.find_route(&Point::new(20.0, 0.0), &stop)).unwrap();
+        assert_eq!(network.get_cached_tiles().len(), 18);
+
+        // The three columns around the first start are one to three
+        // columns away from the ones around the second.
+        network.set_eviction_radius(Some(3));
+        block_on(network.find_route(&Point::new(20.0, 0.0), &stop)).unwrap();
+        assert_eq!(network.get_cached_tiles().len(), 18);
+        network.set_eviction_radius(Some(1));
+        block_on(network.find_route(&Point::new(20.0, 0.0), &stop)).unwrap();
+        assert_eq!(network.get_cached_tiles().len(), 12);
+    }
+
+    #[test]
+    fn failure_report() {
+        let mut network = CachedTileNetwork::new(MockBackend { max_x: 0 });
+        network.set_zoom(2);
+        let (start, stop) = (Point::new(0.1, 0.0), Point::new(0.9, 0.0));
+        assert!(block_on(network.find_route(&start, &stop)).is_err());
+        assert_eq!(network.get_failure_report(), None);
+
+        network.set_capture_failures(true);
+        let err = block_on(network.find_route(&start, &stop)).err().unwrap();
+        let report: serde_json::Value =
+            serde_json::from_str(&network.get_failure_report().unwrap()).unwrap();
+        assert_eq!(report["start"], serde_json::json!([0.1, 0.0]));
+        assert_eq!(report["error"], err.to_string());
+        assert_eq!(report["tiles"], serde_json::json!([[]]));
+        assert_eq!(report["missingTiles"].as_array().unwrap().len(), 9);
+        assert_eq!(report["missingTiles"][0], serde_json::json!([2, 1, 1]));
+
+        network.set_capture_failures(false);
+        assert_eq!(network.get_failure_report(), None);
+    }
+
+    #[test]
+    fn most_relevant_error() {
+        let coord = tile::Coord { x: 1, y: 2, z: 3 };
+        let not_found = RoutingError::tile_fetching(&coord, &FetchingError::TileNotFound);
+        let server_error = RoutingError::tile_fetching(&coord, &FetchingError::HttpStatus(500));
+        assert_eq!(
+            server_error,
+            RoutingError::TileFetchingError {
+                coord: coord.clone(),
+                status: Some(500),
+                message: "Request failed with HTTP status 500".into(),
+            }
+        );
+        assert_eq!(
+            super::most_relevant_error(vec![not_found.clone(), server_error.clone()]),
+            server_error
+        );
+        assert_eq!(
+            super::most_relevant_error(vec![not_found.clone()]),
+            not_found
+        );
+        assert_eq!(
+            not_found.to_string(),
+            "Could not fetch tile 3/1/2: Tile not found"
+        );
     }
 }
@@ -0,0 +1,140 @@
+use crate::debug::warn_log;
+use crate::tile::backend::{Backend, Revalidation, Tile, TileFuture, Validators};
+use crate::tile::Coord;
+
+/// A backend which tries several backends in order.
+///
+/// If fetching a tile from the primary backend fails (or it has no such tile),
+/// the fallback backends are tried one after another.
+pub struct FallbackBackend<B> {
+    backends: Vec<B>,
+}
+
+#[cfg_attr(not(feature = "pmtiles"), allow(dead_code))]
+impl<B> FallbackBackend<B> {
+    pub fn new(primary: B) -> Self {
+        FallbackBackend {
+            backends: vec![primary],
+        }
+    }
+
+    /// Adds a backend which is tried after the existing ones.
+    pub fn add_fallback(&mut self, backend: B) {
+        self.backends.push(backend);
+    }
+
+    pub fn get_primary(&self) -> &B {
+        &self.backends[0]
+    }
+
+    pub fn get_backends_mut(&mut self) -> &mut [B] {
+        &mut self.backends
+    }
+}
+
+impl<T: Tile, B: Backend<T>> Backend<T> for FallbackBackend<B> {
+    fn get_tile<'a>(&'a self, coord: &'a Coord) -> TileFuture<'a, T> {
+        Box::pin(async move {
+            let mut last_error = None;
+            for backend in &self.backends {
+                match backend.get_tile(coord).await {
+                    Ok(tile) => return Ok(tile),
+                    Err(err) => {
+                        warn_log!("Backend failed for tile {:?}: {}", coord, err);
+                        last_error = Some(err);
+                    }
+                }
+            }
+            Err(last_error.unwrap())
+        })
+    }
+
+    fn revalidate<'a>(
+        &'a self,
+        coord: &'a Coord,
+        validators: &'a Validators,
+    ) -> TileFuture<'a, Revalidation<T>> {
+        Box::pin(async move {
+            let mut last_error = None;
+            for backend in &self.backends {
+                match backend.revalidate(coord, validators).await {
+                    Ok(revalidation) => return Ok(revalidation),
+                    Err(err) => {
+                        warn_log!("Backend failed for tile {:?}: {}", coord, err);
+                        last_error = Some(err);
+                    }
+                }
+            }
+            Err(last_error.unwrap())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile::ParsedTile;
+    use futures::executor::block_on;
+    use thiserror::Error;
+
+    #[derive(Error, Debug)]
+    #[error("Tile missing")]
+    struct MissingTile;
+
+    struct MockTile(&'static str);
+
+    impl Tile for MockTile {
+        fn parse(&self) -> Result<ParsedTile, Box<dyn std::error::Error>> {
+            Ok(ParsedTile::new())
+        }
+    }
+
+    /// Backend which only has tiles with x coordinates in the given range.
+    struct MockBackend {
+        name: &'static str,
+        max_x: u32,
+    }
+
+    impl Backend<MockTile> for MockBackend {
+        fn get_tile<'a>(&'a self, coord: &'a Coord) -> TileFuture<'a, MockTile> {
+            Box::pin(async move {
+                if coord.x <= self.max_x {
+                    Ok(MockTile(self.name))
+                } else {
+                    Err(MissingTile.into())
+                }
+            })
+        }
+    }
+
+    #[test]
+    fn get_tile() {
+        let mut backend = FallbackBackend::new(MockBackend {
+            name: "primary",
+            max_x: 1,
+        });
+        backend.add_fallback(MockBackend {
+            name: "fallback",
+            max_x: 2,
+        });
+        let coord = |x| Coord { x, y: 0, z: 1 };
+        assert_eq!(block_on(backend.get_tile(&coord(1))).unwrap().0, "primary");
+        assert_eq!(block_on(backend.get_tile(&coord(2))).unwrap().0, "fallback");
+        assert!(block_on(backend.get_tile(&coord(3))).is_err());
+    }
+
+    #[test]
+    fn boxed_backends() {
+        let mut backend: FallbackBackend<Box<dyn Backend<MockTile>>> =
+            FallbackBackend::new(Box::new(MockBackend {
+                name: "primary",
+                max_x: 1,
+            }));
+        backend.add_fallback(Box::new(FallbackBackend::new(MockBackend {
+            name: "nested",
+            max_x: 2,
+        })));
+        let coord = Coord { x: 2, y: 0, z: 1 };
+        assert_eq!(block_on(backend.get_tile(&coord)).unwrap().0, "nested");
+    }
+}
@@ -0,0 +1,209 @@
+use wasm_bindgen::{prelude::*, JsCast, JsValue};
+
+use super::{Backend, Coord, TileFuture};
+use crate::debug::debug_log;
+use crate::geo_types::{LineString, Point};
+use crate::routing::{Connector, Segment};
+use crate::tile::ParsedTile;
+use serde_json::Value;
+use thiserror::Error;
+
+#[wasm_bindgen(module = "flatgeobuf/lib/mjs/geojson.js")]
+extern "C" {
+    /// Returns an async iterator over the GeoJSON features intersecting rect.
+    fn deserialize(url: &str, rect: &JsValue) -> JsValue;
+}
+
+/// Features of a tile read from FlatGeobuf files.
+pub struct Tile(ParsedTile);
+
+impl super::Tile for Tile {
+    fn parse(&self) -> Result<ParsedTile, Box<dyn std::error::Error>> {
+        Ok(self.0.clone())
+    }
+}
+
+/// A backend reading the network from FlatGeobuf files.
+///
+/// Segments (LineStrings) and connectors (Points) are stored in separate
+/// files. Only the features within a tile's bounds are fetched, using the
+/// spatial index of the files and HTTP range requests.
+pub struct FlatGeobufBackend {
+    segments_url: String,
+    connectors_url: String,
+}
+
+#[derive(Error, Debug)]
+enum FetchingError {
+    #[error("Could not read FlatGeobuf features")]
+    ReadFailed,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub(crate) enum ParsingError {
+    #[error("Feature is missing an ID")]
+    InvalidID,
+    #[error("Connector with id `{0}` has an invalid geometry")]
+    InvalidConnector(String),
+    #[error("Segment with id `{segment_id}` is invalid: {context}")]
+    InvalidSegment { segment_id: String, context: String },
+}
+
+impl FlatGeobufBackend {
+    pub fn new(segments_url: &str, connectors_url: &str) -> Self {
+        FlatGeobufBackend {
+            segments_url: segments_url.into(),
+            connectors_url: connectors_url.into(),
+        }
+    }
+
+    /// Reads all features of the file intersecting the tile.
+    async fn read_features(url: &str, coord: &Coord) -> Result<Vec<Value>, FetchingError> {
+        let (west, north) = coord.position_to_lon_lat(0.0, 0.0, 1.0);
+        let (east, south) = coord.position_to_lon_lat(1.0, 1.0, 1.0);
+        let rect = js_sys::Object::new();
+        for (key, value) in [
+            ("minX", west),
+            ("minY", south),
+            ("maxX", east),
+            ("maxY", north),
+        ] {
+            js_sys::Reflect::set(&rect, &JsValue::from_str(key), &JsValue::from(value))
+                .or(Err(FetchingError::ReadFailed))?;
+        }
+        let iterator: js_sys::AsyncIterator = deserialize(url, &rect).unchecked_into();
+        let mut features = Vec::new();
+        loop {
+            let next = iterator.next().or(Err(FetchingError::ReadFailed))?;
+            let result: js_sys::IteratorNext = wasm_bindgen_futures::JsFuture::from(next)
+                .await
+                .or(Err(FetchingError::ReadFailed))?
+                .unchecked_into();
+            if result.done() {
+                break;
+            }
+            let json = js_sys::JSON::stringify(&result.value())
+                .ok()
+                .and_then(|json| json.as_string())
+                .ok_or(FetchingError::ReadFailed)?;
+            features.push(serde_json::from_str(&json).or(Err(FetchingError::ReadFailed))?);
+        }
+        Ok(features)
+    }
+}
+
+impl Backend<Tile> for FlatGeobufBackend {
+    fn get_tile<'a>(&'a self, coord: &'a Coord) -> TileFuture<'a, Tile> {
+        Box::pin(async move {
+            debug_log!("get FlatGeobuf features for tile {:?}", coord);
+            let segments = Self::read_features(&self.segments_url, coord).await?;
+            let connectors = Self::read_features(&self.connectors_url, coord).await?;
+            Ok(Tile(parse_features(&segments, &connectors)?))
+        })
+    }
+}
+
+/// Returns the ID of a GeoJSON feature from its properties or its id member.
+fn feature_id(feature: &Value) -> Result<String, ParsingError> {
+    match feature
+        .pointer("/properties/id")
+        .or_else(|| feature.get("id"))
+    {
+        Some(Value::String(id)) => Ok(id.clone()),
+        Some(Value::Number(id)) => Ok(id.to_string()),
+        _ => Err(ParsingError::InvalidID),
+    }
+}
+
+/// Returns the coordinates of a GeoJSON position.
+fn position(value: &Value) -> Option<(f64, f64)> {
+    Some((value.get(0)?.as_f64()?, value.get(1)?.as_f64()?))
+}
+
+/// Converts GeoJSON segment and connector features into a parsed tile.
+pub(crate) fn parse_features(
+    segments: &[Value],
+    connectors: &[Value],
+) -> Result<ParsedTile, ParsingError> {
+    let mut tile = ParsedTile::new();
+    for feature in connectors {
+        let id = feature_id(feature)?;
+        let (x, y) = feature
+            .pointer("/geometry/coordinates")
+            .and_then(position)
+            .ok_or_else(|| ParsingError::InvalidConnector(id.clone()))?;
+        let mut connector = Connector::new(&id, &Point::new(x, y));
+        connector.set_properties_as_tags(feature.get("properties"));
+        tile.push_connector(connector);
+    }
+    for feature in segments {
+        let id = feature_id(feature)?;
+        let invalid = |context: &str| ParsingError::InvalidSegment {
+            segment_id: id.clone(),
+            context: context.into(),
+        };
+        let coords: Vec<geo::Coord<f64>> = feature
+            .pointer("/geometry/coordinates")
+            .and_then(|coordinates| coordinates.as_array())
+            .ok_or_else(|| invalid("Geometry missing"))?
+            .iter()
+            .map(|coordinate| position(coordinate).map(|(x, y)| geo::Coord { x, y }))
+            .collect::<Option<_>>()
+            .ok_or_else(|| invalid("Invalid coordinates"))?;
+        // Connector IDs are either an array or a JSON encoded array.
+        let connector_ids: Vec<String> = match feature.pointer("/properties/connector_ids") {
+            Some(Value::String(ids)) => serde_json::from_str(ids).ok(),
+            Some(ids) => serde_json::from_value(ids.clone()).ok(),
+            None => None,
+        }
+        .ok_or_else(|| invalid("Connector ids missing or invalid"))?;
+        let mut segment = Segment::new(
+            id,
+            LineString::from(geo::LineString::new(coords)),
+            connector_ids,
+        );
+        segment.set_properties_as_tags(feature.get("properties"));
+        tile.push_segment(segment);
+    }
+    Ok(tile)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_features() {
+        let segments = vec![
+            json!({
+                "type": "Feature",
+                "properties": {"id": "s1", "connector_ids": "[\"c1\", \"c2\"]"},
+                "geometry": {"type": "LineString", "coordinates": [[0.0, 0.0], [1.0, 1.0]]}
+            }),
+            json!({
+                "type": "Feature",
+                "id": 2,
+                "properties": {"connector_ids": ["c2"]},
+                "geometry": {"type": "LineString", "coordinates": [[1.0, 1.0], [2.0, 1.0]]}
+            }),
+        ];
+        let connectors = vec![json!({
+            "type": "Feature",
+            "properties": {"id": "c1"},
+            "geometry": {"type": "Point", "coordinates": [0.0, 0.0]}
+        })];
+        let tile = super::parse_features(&segments, &connectors).unwrap();
+        assert_eq!(tile.segments_len(), 2);
+        assert_eq!(tile.connectors_len(), 1);
+        assert_eq!(tile.get_segments()[1].get_id(), "2");
+        assert_eq!(tile.get_segments()[0].get_connectors(), &vec!["c1", "c2"]);
+
+        let invalid = vec![json!({
+            "type": "Feature",
+            "properties": {"id": "s3"},
+            "geometry": {"type": "LineString", "coordinates": [[0.0, 0.0], [1.0, 1.0]]}
+        })];
+        assert!(super::parse_features(&invalid, &[]).is_err());
+    }
+}
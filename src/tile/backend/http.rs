@@ -0,0 +1,156 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use super::{js_error_message, Coord, FetchingError, Validators};
+use crate::debug::debug_log;
+use web_sys::{Request, RequestInit, Response};
+
+/// Tiles served via HTTP from an XYZ URL template.
+///
+/// The template contains `{z}`, `{x}` and `{y}` placeholders, e.g.
+/// `https://example.com/network/{z}/{x}/{y}.mvt`.
+pub(crate) struct HttpTileSource {
+    url_template: String,
+}
+
+impl HttpTileSource {
+    pub(crate) fn new(url_template: &str) -> Self {
+        HttpTileSource {
+            url_template: url_template.into(),
+        }
+    }
+
+    /// Returns the URL of the tile with the given coordinate.
+    pub(crate) fn get_url(&self, coord: &Coord) -> String {
+        self.url_template
+            .replace("{z}", &coord.z.to_string())
+            .replace("{x}", &coord.x.to_string())
+            .replace("{y}", &coord.y.to_string())
+    }
+
+    /// Fetches the tile data, sending the validators if given.
+    ///
+    /// Returns `None` if the server reports that the tile was not modified.
+    pub(crate) async fn fetch(
+        &self,
+        coord: &Coord,
+        validators: Option<&Validators>,
+    ) -> Result<Option<(Vec<u8>, Validators)>, FetchingError> {
+        let url = self.get_url(coord);
+        debug_log!("fetch tile {}", url);
+        let init = RequestInit::new();
+        init.set_method("GET");
+        let request = Request::new_with_str_and_init(&url, &init).map_err(request_failed)?;
+        if let Some(validators) = validators {
+            if let Some(etag) = &validators.etag {
+                request
+                    .headers()
+                    .set("If-None-Match", etag)
+                    .map_err(request_failed)?;
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                request
+                    .headers()
+                    .set("If-Modified-Since", last_modified)
+                    .map_err(request_failed)?;
+            }
+        }
+        let response = fetch_request(&request).await?;
+        match response.status() {
+            304 => return Ok(None),
+            200 => {}
+            404 | 204 => return Err(FetchingError::TileNotFound),
+            status => return Err(FetchingError::HttpStatus(status)),
+        }
+        let headers = response.headers();
+        let validators = Validators {
+            etag: headers.get("ETag").ok().flatten(),
+            last_modified: headers.get("Last-Modified").ok().flatten(),
+        };
+        let buffer: JsValue = response
+            .array_buffer()
+            .map(wasm_bindgen_futures::JsFuture::from)
+            .map_err(request_failed)?
+            .await
+            .map_err(request_failed)?;
+        Ok(Some((
+            js_sys::Uint8Array::new(&buffer).to_vec(),
+            validators,
+        )))
+    }
+}
+
+#[wasm_bindgen]
+extern "C" {
+    /// The global `fetch`, available in browsers, workers and Node.js 18+.
+    #[wasm_bindgen(catch, js_name = fetch)]
+    fn global_fetch(request: &Request) -> Result<js_sys::Promise, JsValue>;
+}
+
+fn request_failed(err: JsValue) -> FetchingError {
+    FetchingError::RequestFailed(js_error_message(&err))
+}
+
+async fn fetch_request(request: &Request) -> Result<Response, FetchingError> {
+    wasm_bindgen_futures::JsFuture::from(global_fetch(request).map_err(request_failed)?)
+        .await
+        .and_then(|response| response.dyn_into())
+        .map_err(request_failed)
+}
+
+/// Sends a request without body to the URL.
+#[cfg_attr(not(feature = "pmtiles"), allow(dead_code))]
+async fn send(url: &str, method: &str) -> Result<Response, FetchingError> {
+    let init = RequestInit::new();
+    init.set_method(method);
+    let request = Request::new_with_str_and_init(url, &init).map_err(request_failed)?;
+    let response = fetch_request(&request).await?;
+    if response.ok() {
+        Ok(response)
+    } else {
+        Err(FetchingError::HttpStatus(response.status()))
+    }
+}
+
+/// Returns the size in bytes of the resource as reported by a HEAD request.
+#[cfg_attr(not(feature = "pmtiles"), allow(dead_code))]
+pub(crate) async fn content_length(url: &str) -> Result<Option<f64>, FetchingError> {
+    let response = send(url, "HEAD").await?;
+    Ok(response
+        .headers()
+        .get("Content-Length")
+        .ok()
+        .flatten()
+        .and_then(|length| length.parse().ok()))
+}
+
+/// Fetches the whole resource.
+#[cfg_attr(not(feature = "pmtiles"), allow(dead_code))]
+pub(crate) async fn fetch_buffer(url: &str) -> Result<js_sys::ArrayBuffer, FetchingError> {
+    let response = send(url, "GET").await?;
+    response
+        .array_buffer()
+        .map(wasm_bindgen_futures::JsFuture::from)
+        .map_err(request_failed)?
+        .await
+        .and_then(|buffer| buffer.dyn_into())
+        .map_err(request_failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_url() {
+        let source = HttpTileSource::new("https://example.com/{z}/{x}/{y}.mvt");
+        assert_eq!(
+            source.get_url(&Coord {
+                x: 8580,
+                y: 5548,
+                z: 14
+            }),
+            "https://example.com/14/8580/5548.mvt"
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use wasm_bindgen::prelude::*;
+
+use super::mvt_tile::parse_mvt_buffer;
+use super::{js_error_message, Backend, Coord, FetchingError, TileFuture};
+use crate::debug::debug_log;
+use crate::tile::{routing_tile, ParsedTile};
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Format of the tile data returned by a JS backend.
+pub enum TileFormat {
+    /// Mapbox Vector Tiles with the connectors and segments layers.
+    Mvt,
+    /// Routing tiles as created by `parseMVTTile`.
+    RoutingTile,
+}
+
+/// Tile data returned by a JS function.
+pub struct Tile {
+    data: Vec<u8>,
+    coord: Coord,
+    format: TileFormat,
+}
+
+impl super::Tile for Tile {
+    fn parse(&self) -> Result<ParsedTile, Box<dyn std::error::Error>> {
+        Ok(match self.format {
+            TileFormat::Mvt => parse_mvt_buffer(&self.data, &self.coord, false)?,
+            TileFormat::RoutingTile => routing_tile::decode(&self.data)?,
+        })
+    }
+}
+
+/// A backend getting tiles from a user-supplied JS function.
+///
+/// The function is called with the z, x and y coordinates of the tile and
+/// must return a promise resolving to the tile data (Uint8Array or
+/// ArrayBuffer), or to `null` or `undefined` if there is no such tile.
+pub struct JsBackend {
+    get_tile: js_sys::Function,
+    format: TileFormat,
+}
+
+impl JsBackend {
+    pub fn new(get_tile: js_sys::Function, format: TileFormat) -> Self {
+        JsBackend { get_tile, format }
+    }
+}
+
+impl Backend<Tile> for JsBackend {
+    fn get_tile<'a>(&'a self, coord: &'a Coord) -> TileFuture<'a, Tile> {
+        Box::pin(async move {
+            debug_log!("get tile {:?} from JS", coord);
+            let promise: js_sys::Promise = self
+                .get_tile
+                .call3(
+                    &JsValue::NULL,
+                    &JsValue::from(coord.z),
+                    &JsValue::from(coord.x),
+                    &JsValue::from(coord.y),
+                )
+                .map_err(|err| FetchingError::RequestFailed(js_error_message(&err)))?
+                .into();
+            let data = wasm_bindgen_futures::JsFuture::from(promise)
+                .await
+                .map_err(|err| FetchingError::RequestFailed(js_error_message(&err)))?;
+            if data.is_null() || data.is_undefined() {
+                return Err(FetchingError::TileNotFound.into());
+            }
+            Ok(Tile {
+                data: js_sys::Uint8Array::new(&data).to_vec(),
+                coord: coord.clone(),
+                format: self.format,
+            })
+        })
+    }
+}
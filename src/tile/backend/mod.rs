@@ -3,17 +3,145 @@
 pub mod cached;
 pub use cached::CachedTileNetwork;
 
+pub mod flatgeobuf_backend;
+pub use flatgeobuf_backend::FlatGeobufBackend;
+
+pub mod js_backend;
+pub use js_backend::JsBackend;
+
+pub mod fallback;
+pub use fallback::FallbackBackend;
+
+mod http;
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "native"))]
+pub mod native_http;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod pmtiles_file_backend;
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "native"))]
+pub mod pmtiles_http_backend;
+
+pub mod mvt_tile;
+
+#[cfg(feature = "pmtiles")]
 pub mod pmtiles_mvt_backend;
+#[cfg(feature = "pmtiles")]
 pub use pmtiles_mvt_backend::PMTilesMVTBackend;
 
-use crate::{routing::Router, tile::Coord};
+pub mod xyz_mvt_backend;
+pub use xyz_mvt_backend::XYZMVTBackend;
+
+pub mod routing_tile_backend;
+pub use routing_tile_backend::RoutingTileBackend;
+
+use crate::tile::{Coord, ParsedTile};
+use futures::future::LocalBoxFuture;
+use thiserror::Error;
+use wasm_bindgen::{JsCast, JsValue};
+
+/// Errors while fetching a tile.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum FetchingError {
+    /// The tile does not exist, e.g. because it's outside the covered area.
+    #[error("Tile not found")]
+    TileNotFound,
+    /// The server responded with an unexpected HTTP status.
+    #[error("Request failed with HTTP status {0}")]
+    HttpStatus(u16),
+    /// The request failed, e.g. because of network problems.
+    #[error("Request failed: {0}")]
+    RequestFailed(String),
+}
+
+impl FetchingError {
+    /// Returns the HTTP status of the error, 404 if the tile was not found.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            FetchingError::TileNotFound => Some(404),
+            FetchingError::HttpStatus(status) => Some(*status),
+            FetchingError::RequestFailed(_) => None,
+        }
+    }
+}
+
+/// Returns the message of a JS error or a description of the thrown value.
+pub(crate) fn js_error_message(value: &JsValue) -> String {
+    match value.dyn_ref::<js_sys::Error>() {
+        Some(error) => String::from(error.message()),
+        None => value.as_string().unwrap_or_else(|| format!("{:?}", value)),
+    }
+}
 
 /// Trait for tile implementations.
 pub trait Tile {
-    fn parse(&self, router: &mut Router) -> Result<(), Box<dyn std::error::Error>>;
+    fn parse(&self) -> Result<ParsedTile, Box<dyn std::error::Error>>;
+
+    /// Returns the validators to check whether the tile is still up to date.
+    fn get_validators(&self) -> Option<Validators> {
+        None
+    }
+}
+
+/// HTTP validators of a fetched tile.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
 }
 
+/// Result of revalidating a cached tile.
+pub enum Revalidation<T> {
+    /// The cached tile is still up to date.
+    NotModified,
+    /// The tile has changed.
+    Modified(T),
+}
+
+/// Future returned by backends.
+pub type TileFuture<'a, T> = LocalBoxFuture<'a, Result<T, Box<dyn std::error::Error>>>;
+
 /// Trait for tile backend implementations.
+///
+/// The trait is object safe, so backends can be boxed as
+/// `Box<dyn Backend<T>>`.
 pub trait Backend<T: Tile> {
-    async fn get_tile(&self, coord: &Coord) -> Result<T, Box<dyn std::error::Error>>;
+    /// Fetches the tile with the given coordinate.
+    fn get_tile<'a>(&'a self, coord: &'a Coord) -> TileFuture<'a, T>;
+
+    /// Checks whether a cached tile with the given validators is up to date.
+    ///
+    /// Backends which don't support validation simply fetch the tile again.
+    fn revalidate<'a>(
+        &'a self,
+        coord: &'a Coord,
+        _validators: &'a Validators,
+    ) -> TileFuture<'a, Revalidation<T>> {
+        Box::pin(async move { Ok(Revalidation::Modified(self.get_tile(coord).await?)) })
+    }
+}
+
+impl<T: Tile, B: Backend<T> + ?Sized> Backend<T> for Box<B> {
+    fn get_tile<'a>(&'a self, coord: &'a Coord) -> TileFuture<'a, T> {
+        (**self).get_tile(coord)
+    }
+
+    fn revalidate<'a>(
+        &'a self,
+        coord: &'a Coord,
+        validators: &'a Validators,
+    ) -> TileFuture<'a, Revalidation<T>> {
+        (**self).revalidate(coord, validators)
+    }
+}
+
+impl Tile for Box<dyn Tile> {
+    fn parse(&self) -> Result<ParsedTile, Box<dyn std::error::Error>> {
+        (**self).parse()
+    }
+
+    fn get_validators(&self) -> Option<Validators> {
+        (**self).get_validators()
+    }
 }
@@ -0,0 +1,520 @@
+//! Parsing of Mapbox Vector Tiles with `connectors` and `segments` layers.
+
+use wasm_bindgen::prelude::*;
+
+use super::Coord;
+use crate::debug::warn_log;
+use crate::geo_types::Point;
+use crate::routing::{Connector, Direction, Segment, TurnRestriction};
+use crate::tile::{ParseIssue, ParsedTile};
+use mvt_reader::Reader;
+use std::convert::TryFrom;
+use thiserror::Error;
+
+/// Extent of the MVT tile coordinates.
+const EXTENT: f64 = 4096.0;
+
+/// A Mapbox Vector Tile, e.g. read from a PMTiles archive.
+pub enum Tile {
+    /// Raw MVT data which still needs to be parsed with the options.
+    Raw {
+        data: Vec<u8>,
+        coord: Coord,
+        options: ParseOptions,
+    },
+    /// A tile which was already parsed off the main thread.
+    Parsed(ParsedTile),
+}
+
+impl super::Tile for Tile {
+    fn parse(&self) -> Result<ParsedTile, Box<dyn std::error::Error>> {
+        match self {
+            Tile::Raw {
+                data,
+                coord,
+                options,
+            } => Ok(parse_mvt(data, coord, options)?),
+            Tile::Parsed(parsed) => Ok(parsed.clone()),
+        }
+    }
+}
+
+#[wasm_bindgen(js_name = parseMVTTile)]
+/// Parses the given MVT tile and returns the result in the routing tile format.
+///
+/// Meant to be called inside a Web Worker, see `PMTilesMVTRouter.setParser`.
+/// The result can also be stored and served for the `RoutingTileRouter`.
+///
+/// The direction of segments is read from the given property, from `oneway`
+/// without it.
+pub fn parse_mvt_tile(
+    data: &[u8],
+    z: u8,
+    x: u32,
+    y: u32,
+    direction_property: Option<String>,
+) -> Result<Vec<u8>, JsError> {
+    let options = ParseOptions {
+        direction_property,
+        ..Default::default()
+    };
+    let tile = parse_mvt(data, &Coord { x, y, z }, &options)?;
+    Ok(tile.to_bytes())
+}
+
+/// Errors while parsing an MVT tile.
+#[derive(Error, Debug)]
+pub enum ParsingError {
+    /// The data is not a valid MVT tile.
+    #[error("Could not parse MVT tile")]
+    MVTError,
+    /// A feature of the connectors layer is invalid.
+    #[error("Connector with id `{connector_id:?}` is invalid: {context}")]
+    InvalidConnector {
+        /// ID of the feature.
+        connector_id: String,
+        /// Description of the problem.
+        context: String,
+    },
+    /// A feature of the segments layer is invalid.
+    #[error("Segment with id `{segment_id:?}` is invalid: {context}")]
+    InvalidSegment {
+        /// ID of the feature.
+        segment_id: String,
+        /// Description of the problem.
+        context: String,
+    },
+    /// A feature has no `id` property.
+    #[error("Missing ID")]
+    InvalidID,
+    /// A feature of the restrictions layer lacks its `from`, `via` or `to`
+    /// property.
+    #[error("Turn restriction without `from`, `via` and `to`")]
+    InvalidRestriction,
+}
+
+/// Options of [`parse_mvt`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Whether invalid features fail the parsing. Otherwise they are skipped
+    /// and reported as issues of the parsed tile.
+    pub strict: bool,
+    /// Property of the segments holding the direction in which they can be
+    /// travelled, with values like those of `oneway`. Without it, the
+    /// `oneway` property is used.
+    pub direction_property: Option<String>,
+}
+
+/// Parses the segments and connectors of an MVT tile with the coordinate,
+/// e.g. in custom backends or to test tiles.
+///
+/// The first layer holds the connectors, the second one the segments. Their
+/// positions are projected to longitude and latitude within the tile. Turn
+/// restrictions are read from a layer named `restrictions` if present.
+pub fn parse_mvt(
+    data: &[u8],
+    coord: &Coord,
+    options: &ParseOptions,
+) -> Result<ParsedTile, ParsingError> {
+    let reader = Reader::new(data.to_vec()).map_err(|_| ParsingError::MVTError)?;
+    let layers = reader
+        .get_layer_names()
+        .map_err(|_| ParsingError::MVTError)?;
+    let layer = |index: usize| layers.get(index).cloned().unwrap_or_default();
+    let mut tile = ParsedTile::new();
+    parse_connectors(&mut tile, &reader, &layer(0), coord, options.strict)?;
+    parse_segments(&mut tile, &reader, &layer(1), coord, options)?;
+    if let Some(index) = layers.iter().position(|name| name == "restrictions") {
+        parse_restrictions(&mut tile, &reader, index, coord, options.strict)?;
+    }
+    Ok(tile)
+}
+
+/// Handles an invalid feature.
+///
+/// In strict mode, the error is returned. Otherwise the feature is skipped and
+/// reported as issue of the parsed tile.
+fn handle_invalid_feature(
+    tile: &mut ParsedTile,
+    coord: &Coord,
+    layer: &str,
+    feature_id: &str,
+    err: ParsingError,
+    strict: bool,
+) -> Result<(), ParsingError> {
+    if strict {
+        return Err(err);
+    }
+    warn_log!("{}", err);
+    tile.push_issue(ParseIssue::new(coord, layer, feature_id, &err.to_string()));
+    Ok(())
+}
+
+fn feature_id(feature: &mvt_reader::feature::Feature) -> Option<String> {
+    feature
+        .properties
+        .as_ref()
+        .and_then(|p| p.get("id"))
+        .cloned()
+}
+
+fn parse_connectors(
+    tile: &mut ParsedTile,
+    reader: &Reader,
+    layer: &str,
+    coord: &Coord,
+    strict: bool,
+) -> Result<(), ParsingError> {
+    for feature in reader.get_features(0).map_err(|_| ParsingError::MVTError)? {
+        let id = match feature_id(&feature) {
+            Some(id) => id,
+            None => {
+                handle_invalid_feature(tile, coord, layer, "", ParsingError::InvalidID, strict)?;
+                continue;
+            }
+        };
+        let point = match geo::MultiPoint::<f32>::try_from(feature.geometry) {
+            Ok(p) => p.into_iter().next(),
+            Err(err) => {
+                let err = ParsingError::InvalidConnector {
+                    connector_id: id.clone(),
+                    context: format!("Could not parse geometry {:?} for connector {}", err, id),
+                };
+                handle_invalid_feature(tile, coord, layer, &id, err, strict)?;
+                continue;
+            }
+        };
+        match point {
+            Some(point) => {
+                let (x, y) =
+                    coord.position_to_lon_lat(f64::from(point.x()), f64::from(point.y()), EXTENT);
+                let mut connector = Connector::new(id.as_str(), &Point::new(x, y));
+                // Other properties are tags of the connector.
+                for (key, value) in feature.properties.iter().flatten() {
+                    if key != "id" {
+                        connector.set_tag(key, value);
+                    }
+                }
+                tile.push_connector(connector);
+            }
+            None => {
+                let err = ParsingError::InvalidConnector {
+                    connector_id: id.clone(),
+                    context: format!("Empty geometry for connector {}", id),
+                };
+                handle_invalid_feature(tile, coord, layer, &id, err, strict)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_segments(
+    tile: &mut ParsedTile,
+    reader: &Reader,
+    layer: &str,
+    coord: &Coord,
+    options: &ParseOptions,
+) -> Result<(), ParsingError> {
+    let strict = options.strict;
+    for feature in reader.get_features(1).map_err(|_| ParsingError::MVTError)? {
+        let id = match feature_id(&feature) {
+            Some(id) => id,
+            None => {
+                handle_invalid_feature(tile, coord, layer, "", ParsingError::InvalidID, strict)?;
+                continue;
+            }
+        };
+        let invalid = |context: &str| ParsingError::InvalidSegment {
+            segment_id: id.clone(),
+            context: context.into(),
+        };
+        let coords = match geo::LineString::<f32>::try_from(feature.geometry) {
+            Ok(line_string) => line_string.into_inner(),
+            Err(_) => {
+                let err = invalid("Geometry is not a LineString");
+                handle_invalid_feature(tile, coord, layer, &id, err, strict)?;
+                continue;
+            }
+        };
+        let geometry: geo::LineString<f64> = coords
+            .iter()
+            .map(|position| {
+                let (x, y) =
+                    coord.position_to_lon_lat(f64::from(position.x), f64::from(position.y), EXTENT);
+                geo::Coord { x, y }
+            })
+            .collect();
+        let connector_ids: Option<Vec<String>> = feature
+            .properties
+            .as_ref()
+            .and_then(|p| p.get("connector_ids"))
+            .and_then(|ids| serde_json::from_str(ids).ok());
+        match connector_ids {
+            Some(connector_ids) => {
+                let mut segment = Segment::new(id.clone(), geometry.into(), connector_ids);
+                // Other properties are tags of the segment.
+                for (key, value) in feature.properties.iter().flatten() {
+                    if key != "id" && key != "connector_ids" {
+                        segment.set_tag(key, value);
+                    }
+                }
+                if let Some(property) = &options.direction_property {
+                    let value = feature.properties.as_ref().and_then(|p| p.get(property));
+                    segment.set_direction(Direction::from_tag(value.map_or("", String::as_str)));
+                }
+                tile.push_segment(segment)
+            }
+            None => {
+                let err = invalid("Connector ids missing or invalid");
+                handle_invalid_feature(tile, coord, layer, &id, err, strict)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses the turn restrictions of the layer with the index.
+///
+/// Features have the IDs of the segments and of the connector of the turn in
+/// their `from`, `to` and `via` properties. A `restriction` property starting
+/// with `only_`, like `only_straight_on`, makes the turn the only one allowed.
+fn parse_restrictions(
+    tile: &mut ParsedTile,
+    reader: &Reader,
+    index: usize,
+    coord: &Coord,
+    strict: bool,
+) -> Result<(), ParsingError> {
+    for feature in reader
+        .get_features(index)
+        .map_err(|_| ParsingError::MVTError)?
+    {
+        let property = |key: &str| {
+            feature
+                .properties
+                .as_ref()
+                .and_then(|p| p.get(key))
+                .cloned()
+        };
+        let (from, via, to) = match (property("from"), property("via"), property("to")) {
+            (Some(from), Some(via), Some(to)) => (from, via, to),
+            _ => {
+                let id = feature_id(&feature).unwrap_or_default();
+                let err = ParsingError::InvalidRestriction;
+                handle_invalid_feature(tile, coord, "restrictions", &id, err, strict)?;
+                continue;
+            }
+        };
+        let only = property("restriction").is_some_and(|value| value.starts_with("only_"));
+        tile.push_restriction(match only {
+            true => TurnRestriction::only(from, via, to),
+            false => TurnRestriction::new(from, via, to),
+        });
+    }
+    Ok(())
+}
+
+// Parses the segments and connectors included in the given MVT tile.
+pub(crate) fn parse_mvt_buffer(
+    buffer: &[u8],
+    coord: &Coord,
+    strict: bool,
+) -> Result<ParsedTile, ParsingError> {
+    let options = ParseOptions {
+        strict,
+        ..Default::default()
+    };
+    parse_mvt(buffer, coord, &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Coord;
+
+    #[test]
+    /// Test find_route method.
+    fn parse_mvt_buffer() {
+        let mut tile = mvt::Tile::new(4096);
+        {
+            let layer = tile.create_layer("connectors");
+            let b = mvt::GeomEncoder::new(mvt::GeomType::Point)
+                .point(0.0, 0.0)
+                .unwrap()
+                .encode()
+                .unwrap();
+            let mut feature = layer.into_feature(b);
+            feature.set_id(1);
+            feature.add_tag_string("id", "foo");
+            let layer = feature.into_layer();
+            tile.add_layer(layer).unwrap();
+        }
+        {
+            let layer = tile.create_layer("segments");
+            let b = mvt::GeomEncoder::new(mvt::GeomType::Linestring)
+                .point(0.0, 0.0)
+                .unwrap()
+                .point(1024.0, 0.0)
+                .unwrap()
+                .point(1024.0, 2048.0)
+                .unwrap()
+                .point(4096.0, 4096.0)
+                .unwrap()
+                .encode()
+                .unwrap();
+            let mut feature = layer.into_feature(b);
+            feature.set_id(1);
+            feature.add_tag_string("id", "foo");
+            feature.add_tag_string("connector_ids", "[\"foo\"]");
+            let layer = feature.into_layer();
+            tile.add_layer(layer).unwrap();
+        }
+        let data = tile.to_bytes().unwrap();
+        let mut router = crate::routing::Router::new();
+        router.merge_tile(
+            &super::parse_mvt_buffer(&data, &Coord { x: 0, y: 0, z: 0 }, true).unwrap(),
+        );
+        assert_eq!(1, router.segments_len());
+        assert_eq!(1, router.connectors_len());
+    }
+
+    #[test]
+    fn parse_mvt() {
+        let mut tile = mvt::Tile::new(4096);
+        tile.add_layer(tile.create_layer("connectors")).unwrap();
+        let line = mvt::GeomEncoder::new(mvt::GeomType::Linestring)
+            .point(0.0, 0.0)
+            .unwrap()
+            .point(4096.0, 4096.0)
+            .unwrap()
+            .encode()
+            .unwrap();
+        let mut feature = tile.create_layer("segments").into_feature(line);
+        feature.add_tag_string("id", "no-connectors");
+        tile.add_layer(feature.into_layer()).unwrap();
+        let data = tile.to_bytes().unwrap();
+        let coord = Coord { x: 0, y: 0, z: 1 };
+
+        let strict = super::ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert!(matches!(
+            super::parse_mvt(&data, &coord, &strict),
+            Err(super::ParsingError::InvalidSegment { .. })
+        ));
+        let parsed = super::parse_mvt(&data, &coord, &Default::default()).unwrap();
+        assert_eq!(parsed.get_issues().len(), 1);
+        assert!(matches!(
+            super::parse_mvt(b"invalid", &coord, &Default::default()),
+            Err(super::ParsingError::MVTError)
+        ));
+    }
+
+    #[test]
+    fn direction_property() {
+        let mut tile = mvt::Tile::new(4096);
+        tile.add_layer(tile.create_layer("connectors")).unwrap();
+        let line = mvt::GeomEncoder::new(mvt::GeomType::Linestring)
+            .point(0.0, 0.0)
+            .unwrap()
+            .point(1024.0, 0.0)
+            .unwrap()
+            .encode()
+            .unwrap();
+        let mut feature = tile.create_layer("segments").into_feature(line);
+        feature.add_tag_string("id", "foo");
+        feature.add_tag_string("connector_ids", "[]");
+        feature.add_tag_string("oneway", "yes");
+        feature.add_tag_string("flow", "backward");
+        tile.add_layer(feature.into_layer()).unwrap();
+        let data = tile.to_bytes().unwrap();
+        let coord = Coord { x: 0, y: 0, z: 1 };
+        let direction = |direction_property: Option<&str>| {
+            let options = super::ParseOptions {
+                direction_property: direction_property.map(String::from),
+                ..Default::default()
+            };
+            let parsed = super::parse_mvt(&data, &coord, &options).unwrap();
+            parsed.get_segments()[0].get_direction()
+        };
+        assert_eq!(direction(None), super::Direction::Forward);
+        assert_eq!(direction(Some("flow")), super::Direction::Backward);
+        assert_eq!(direction(Some("missing")), super::Direction::Both);
+    }
+
+    #[test]
+    fn parse_restrictions() {
+        let mut tile = mvt::Tile::new(4096);
+        tile.add_layer(tile.create_layer("connectors")).unwrap();
+        tile.add_layer(tile.create_layer("segments")).unwrap();
+        let point = || {
+            mvt::GeomEncoder::new(mvt::GeomType::Point)
+                .point(0.0, 0.0)
+                .unwrap()
+                .encode()
+                .unwrap()
+        };
+        let mut feature = tile.create_layer("restrictions").into_feature(point());
+        feature.add_tag_string("from", "a");
+        feature.add_tag_string("via", "x");
+        feature.add_tag_string("to", "b");
+        feature.add_tag_string("restriction", "only_straight_on");
+        let mut feature = feature.into_layer().into_feature(point());
+        feature.add_tag_string("id", "incomplete");
+        feature.add_tag_string("from", "a");
+        tile.add_layer(feature.into_layer()).unwrap();
+        let data = tile.to_bytes().unwrap();
+        let coord = Coord { x: 0, y: 0, z: 1 };
+
+        let parsed = super::parse_mvt_buffer(&data, &coord, false).unwrap();
+        assert_eq!(
+            parsed.get_restrictions(),
+            &[super::TurnRestriction::only(
+                "a".into(),
+                "x".into(),
+                "b".into()
+            )]
+        );
+        assert_eq!(parsed.get_issues()[0].get_feature_id(), "incomplete");
+        assert!(super::parse_mvt_buffer(&data, &coord, true).is_err());
+    }
+
+    #[test]
+    fn parse_issues() {
+        let mut tile = mvt::Tile::new(4096);
+        tile.add_layer(tile.create_layer("connectors")).unwrap();
+        let line = || {
+            mvt::GeomEncoder::new(mvt::GeomType::Linestring)
+                .point(0.0, 0.0)
+                .unwrap()
+                .point(1024.0, 0.0)
+                .unwrap()
+                .encode()
+                .unwrap()
+        };
+        let mut feature = tile.create_layer("segments").into_feature(line());
+        feature.add_tag_string("id", "no-connectors");
+        let layer = feature.into_layer();
+        let mut feature = layer.into_feature(line());
+        feature.add_tag_string("connector_ids", "[]");
+        tile.add_layer(feature.into_layer()).unwrap();
+        let data = tile.to_bytes().unwrap();
+        let coord = Coord { x: 1, y: 2, z: 3 };
+
+        assert!(super::parse_mvt_buffer(&data, &coord, true).is_err());
+        let parsed = super::parse_mvt_buffer(&data, &coord, false).unwrap();
+        assert_eq!(parsed.segments_len(), 0);
+        let issues = parsed.get_issues();
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].get_tile(), vec![3, 1, 2]);
+        assert_eq!(issues[0].get_layer(), "segments");
+        assert_eq!(issues[0].get_feature_id(), "no-connectors");
+        assert_eq!(
+            issues[0].get_reason(),
+            "Segment with id `\"no-connectors\"` is invalid: Connector ids missing or invalid"
+        );
+        assert_eq!(issues[1].get_feature_id(), "");
+        assert_eq!(issues[1].get_reason(), "Missing ID");
+    }
+}
@@ -0,0 +1,271 @@
+//! Fetching of tiles via HTTP on native targets.
+//!
+//! The HTTP client is pluggable, so services can use the client of their
+//! async runtime. [`ReqwestClient`] is used by default.
+
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+
+use super::http::HttpTileSource;
+use super::xyz_mvt_backend::Tile;
+use super::{Backend, Coord, FetchingError, Revalidation, TileFuture, Validators};
+use crate::debug::debug_log;
+
+/// Response to an HTTP GET request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HttpResponse {
+    /// The HTTP status code.
+    pub status: u16,
+    /// Response headers as (name, value) pairs.
+    pub headers: Vec<(String, String)>,
+    /// The response body.
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Returns the value of the header, ignoring the case of the name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Future of an HTTP response.
+pub type HttpFuture<'a> = BoxFuture<'a, Result<HttpResponse, FetchingError>>;
+
+/// A client sending HTTP GET requests.
+pub trait HttpClient: Send + Sync {
+    /// Sends a GET request with the additional headers to the URL.
+    ///
+    /// Responses with error statuses are returned as well, only failing
+    /// requests (e.g. network errors) should result in an error.
+    fn get<'a>(&'a self, url: &'a str, headers: &'a [(&'a str, String)]) -> HttpFuture<'a>;
+}
+
+/// An async HTTP client using reqwest, supporting HTTP and HTTPS.
+///
+/// Requests need to be run within a tokio runtime.
+#[derive(Debug, Clone)]
+pub struct ReqwestClient {
+    client: reqwest::Client,
+}
+
+impl Default for ReqwestClient {
+    fn default() -> Self {
+        ReqwestClient::with_timeout(Some(Duration::from_secs(30)))
+    }
+}
+
+impl ReqwestClient {
+    /// Creates a client with a timeout of 30 seconds.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a client with the timeout for whole requests, `None` waits
+    /// indefinitely.
+    pub fn with_timeout(timeout: Option<Duration>) -> Self {
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(timeout);
+        }
+        ReqwestClient {
+            client: builder.build().expect("Could not create HTTP client"),
+        }
+    }
+
+    /// Uses the given reqwest client, e.g. to share its connection pool with
+    /// the rest of a service.
+    pub fn from_client(client: reqwest::Client) -> Self {
+        ReqwestClient { client }
+    }
+
+    async fn request(
+        &self,
+        url: &str,
+        headers: &[(&str, String)],
+    ) -> Result<HttpResponse, reqwest::Error> {
+        let mut request = self.client.get(url);
+        for (name, value) in headers {
+            request = request.header(*name, value);
+        }
+        let response = request.send().await?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let body = response.bytes().await?.to_vec();
+        Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+impl HttpClient for ReqwestClient {
+    fn get<'a>(&'a self, url: &'a str, headers: &'a [(&'a str, String)]) -> HttpFuture<'a> {
+        Box::pin(async move {
+            self.request(url, headers)
+                .await
+                .map_err(|err| FetchingError::RequestFailed(err.to_string()))
+        })
+    }
+}
+
+/// A backend fetching Mapbox Vector Tiles from an XYZ URL template on native
+/// targets.
+pub struct NativeXYZMVTBackend<C: HttpClient = ReqwestClient> {
+    source: HttpTileSource,
+    client: C,
+}
+
+impl NativeXYZMVTBackend {
+    /// Creates the backend using the given URL template and the
+    /// [`ReqwestClient`].
+    ///
+    /// The template must contain `{z}`, `{x}` and `{y}` placeholders.
+    pub fn new(url_template: &str) -> Self {
+        Self::with_client(url_template, ReqwestClient::new())
+    }
+}
+
+impl<C: HttpClient> NativeXYZMVTBackend<C> {
+    /// Creates the backend using the given URL template and HTTP client.
+    pub fn with_client(url_template: &str, client: C) -> Self {
+        NativeXYZMVTBackend {
+            source: HttpTileSource::new(url_template),
+            client,
+        }
+    }
+
+    /// Fetches the tile data, sending the validators if given.
+    ///
+    /// Returns `None` if the server reports that the tile was not modified.
+    async fn fetch(
+        &self,
+        coord: &Coord,
+        validators: Option<&Validators>,
+    ) -> Result<Option<Tile>, FetchingError> {
+        let url = self.source.get_url(coord);
+        debug_log!("fetch tile {}", url);
+        let mut headers = Vec::new();
+        if let Some(validators) = validators {
+            if let Some(etag) = &validators.etag {
+                headers.push(("If-None-Match", etag.clone()));
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                headers.push(("If-Modified-Since", last_modified.clone()));
+            }
+        }
+        let response = self.client.get(&url, &headers).await?;
+        match response.status {
+            304 => return Ok(None),
+            200 => {}
+            404 | 204 => return Err(FetchingError::TileNotFound),
+            status => return Err(FetchingError::HttpStatus(status)),
+        }
+        let validators = Validators {
+            etag: response.header("ETag").map(String::from),
+            last_modified: response.header("Last-Modified").map(String::from),
+        };
+        Ok(Some(Tile::new(response.body, coord.clone(), validators)))
+    }
+}
+
+impl<C: HttpClient> Backend<Tile> for NativeXYZMVTBackend<C> {
+    fn get_tile<'a>(&'a self, coord: &'a Coord) -> TileFuture<'a, Tile> {
+        Box::pin(async move {
+            Ok(self
+                .fetch(coord, None)
+                .await?
+                .ok_or_else(|| FetchingError::RequestFailed("Unexpected 304 response".into()))?)
+        })
+    }
+
+    fn revalidate<'a>(
+        &'a self,
+        coord: &'a Coord,
+        validators: &'a Validators,
+    ) -> TileFuture<'a, Revalidation<Tile>> {
+        Box::pin(async move {
+            Ok(match self.fetch(coord, Some(validators)).await? {
+                Some(tile) => Revalidation::Modified(tile),
+                None => Revalidation::NotModified,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// Serves the responses to consecutive connections and returns the URL
+    /// of the server and a handle resolving to the received requests.
+    pub(crate) fn serve(responses: Vec<Vec<u8>>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let read = stream.read(&mut buffer).unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                requests.push(String::from_utf8(request).unwrap());
+                stream.write_all(&response).unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    #[tokio::test]
+    async fn native_xyz_backend() {
+        let (url, server) = serve(
+            vec![
+                "HTTP/1.0 200 OK\r\nETag: \"abc\"\r\nContent-Length: 4\r\n\r\ndata",
+                "HTTP/1.0 304 Not Modified\r\n\r\n",
+                "HTTP/1.0 404 Not Found\r\nContent-Length: 0\r\n\r\n",
+                "HTTP/1.0 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n",
+            ]
+            .into_iter()
+            .map(|response| response.as_bytes().to_vec())
+            .collect(),
+        );
+        let backend = NativeXYZMVTBackend::new(&format!("{}/{{z}}/{{x}}/{{y}}.mvt", url));
+        let coord = Coord { x: 1, y: 2, z: 3 };
+        let tile = backend.get_tile(&coord).await.unwrap();
+        let validators = super::super::Tile::get_validators(&tile).unwrap();
+        assert_eq!(validators.etag.as_deref(), Some("\"abc\""));
+        assert!(matches!(
+            backend.revalidate(&coord, &validators).await.unwrap(),
+            Revalidation::NotModified
+        ));
+        let err = backend.get_tile(&coord).await.err().unwrap();
+        assert_eq!(
+            err.downcast_ref::<FetchingError>(),
+            Some(&FetchingError::TileNotFound)
+        );
+        let err = backend.get_tile(&coord).await.err().unwrap();
+        assert_eq!(
+            err.downcast_ref::<FetchingError>(),
+            Some(&FetchingError::HttpStatus(503))
+        );
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("GET /3/1/2.mvt HTTP/1.1\r\n"));
+        assert!(requests[1].contains("if-none-match: \"abc\"\r\n"));
+    }
+}
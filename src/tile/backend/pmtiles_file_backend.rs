@@ -0,0 +1,226 @@
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::num::NonZeroUsize;
+use std::path::Path;
+
+use lru::LruCache;
+
+use super::mvt_tile::Tile;
+use super::{Backend, Coord, FetchingError, TileFuture};
+use crate::debug::debug_log;
+use crate::tile::pmtiles::{
+    add_offset, find_tile, parse_directory, tile_coord, tile_id, Entry, Header, PMTilesError,
+    HEADER_SIZE, MAX_DIRECTORY_DEPTH,
+};
+use futures::FutureExt;
+use std::future::ready;
+
+/// Number of cached leaf directories.
+const CACHED_DIRECTORIES: usize = 64;
+
+/// A backend reading Mapbox Vector Tiles from a local PMTiles archive.
+///
+/// Only available on native targets. Tiles are read on demand, so the archive
+/// may be larger than the available memory.
+pub struct PMTilesFileBackend<R: Read + Seek = BufReader<File>> {
+    reader: RefCell<R>,
+    header: Header,
+    root: Vec<Entry>,
+    /// Leaf directories by offset.
+    leaves: RefCell<LruCache<u64, Vec<Entry>>>,
+}
+
+impl PMTilesFileBackend {
+    /// Opens the PMTiles archive at the path.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, PMTilesError> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read + Seek> PMTilesFileBackend<R> {
+    /// Reads the PMTiles archive from the reader.
+    pub fn from_reader(mut reader: R) -> Result<Self, PMTilesError> {
+        let header = Header::parse(&read_range(&mut reader, 0, HEADER_SIZE as u64)?)?;
+        let root = read_range(&mut reader, header.root_offset, header.root_length)?;
+        let root = parse_directory(&header.internal_compression.decompress(root)?)?;
+        Ok(PMTilesFileBackend {
+            reader: RefCell::new(reader),
+            header,
+            root,
+            leaves: RefCell::new(LruCache::new(
+                NonZeroUsize::new(CACHED_DIRECTORIES).unwrap(),
+            )),
+        })
+    }
+
+    /// Returns the lowest zoom level of the archive.
+    pub fn get_min_zoom(&self) -> u8 {
+        self.header.min_zoom
+    }
+
+    /// Returns the highest zoom level of the archive.
+    pub fn get_max_zoom(&self) -> u8 {
+        self.header.max_zoom
+    }
+
+    /// Returns the bounds of the archive as [west, south, east, north].
+    pub fn get_bounds(&self) -> [f64; 4] {
+        self.header.bounds
+    }
+
+    /// Returns the leaf directory at the offset within the leaf section.
+    fn leaf_directory(&self, offset: u64, length: u64) -> Result<Vec<Entry>, PMTilesError> {
+        if let Some(entries) = self.leaves.borrow_mut().get(&offset) {
+            return Ok(entries.clone());
+        }
+        let data = read_range(
+            &mut *self.reader.borrow_mut(),
+            add_offset(self.header.leaf_offset, offset)?,
+            length,
+        )?;
+        let entries = parse_directory(&self.header.internal_compression.decompress(data)?)?;
+        self.leaves.borrow_mut().put(offset, entries.clone());
+        Ok(entries)
+    }
+
+    /// Returns the coordinates of all tiles in the archive, ordered by tile
+    /// ID.
+    pub fn tile_coords(&self) -> Result<Vec<Coord>, PMTilesError> {
+        let mut coords = Vec::new();
+        self.collect_tile_coords(&self.root, 0, &mut coords)?;
+        Ok(coords)
+    }
+
+    /// Adds the coordinates of the tiles in the directory and its leaves.
+    fn collect_tile_coords(
+        &self,
+        directory: &[Entry],
+        depth: usize,
+        coords: &mut Vec<Coord>,
+    ) -> Result<(), PMTilesError> {
+        if depth >= MAX_DIRECTORY_DEPTH {
+            return Err(PMTilesError::InvalidArchive(
+                "directories nested too deeply",
+            ));
+        }
+        for entry in directory {
+            if entry.run_length == 0 {
+                let leaf = self.leaf_directory(entry.offset, entry.length)?;
+                self.collect_tile_coords(&leaf, depth + 1, coords)?;
+            } else {
+                add_offset(entry.tile_id, entry.run_length)?;
+                coords.extend((0..entry.run_length).map(|index| tile_coord(entry.tile_id + index)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the decompressed data of the tile, `None` if it doesn't exist.
+    pub(crate) fn read_tile(&self, coord: &Coord) -> Result<Option<Vec<u8>>, PMTilesError> {
+        // Leaf directories are read synchronously, so the walk completes
+        // without waiting.
+        let entry = find_tile(&self.root, tile_id(coord), |offset, length| {
+            ready(self.leaf_directory(offset, length))
+        })
+        .now_or_never()
+        .expect("reading leaf directories doesn't wait")?;
+        let entry = match entry {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let data = read_range(
+            &mut *self.reader.borrow_mut(),
+            add_offset(self.header.tile_data_offset, entry.offset)?,
+            entry.length,
+        )?;
+        Ok(Some(self.header.tile_compression.decompress(data)?))
+    }
+}
+
+/// Reads `length` bytes at the offset.
+fn read_range<R: Read + Seek>(
+    reader: &mut R,
+    offset: u64,
+    length: u64,
+) -> Result<Vec<u8>, PMTilesError> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut data = Vec::new();
+    reader.take(length).read_to_end(&mut data)?;
+    if data.len() as u64 != length {
+        return Err(PMTilesError::InvalidArchive("unexpected end of archive"));
+    }
+    Ok(data)
+}
+
+impl<R: Read + Seek> Backend<Tile> for PMTilesFileBackend<R> {
+    fn get_tile<'a>(&'a self, coord: &'a Coord) -> TileFuture<'a, Tile> {
+        Box::pin(async move {
+            debug_log!("read tile {:?}", coord);
+            match self.read_tile(coord)? {
+                Some(data) => Ok(Tile::Raw {
+                    data,
+                    coord: coord.clone(),
+                    options: Default::default(),
+                }),
+                None => Err(FetchingError::TileNotFound.into()),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile::pmtiles::tests::{directory, entry, header};
+    use futures::executor::block_on;
+    use std::io::Cursor;
+
+    /// Creates an archive whose root directory points to a leaf directory
+    /// with tiles 1 (data "a") and 2 to 4 (data "bc").
+    fn archive() -> Vec<u8> {
+        let leaf = directory(&[entry(1, 0, 1, 1), entry(2, 1, 2, 3)]);
+        let root = directory(&[entry(1, 0, leaf.len() as u64, 0)]);
+        let leaf_offset = (HEADER_SIZE + root.len()) as u64;
+        let mut data = header(
+            root.len() as u64,
+            leaf_offset,
+            leaf_offset + leaf.len() as u64,
+        );
+        data.extend(root);
+        data.extend(leaf);
+        data.extend(b"abc");
+        data
+    }
+
+    #[test]
+    fn read_tiles() {
+        let backend = PMTilesFileBackend::from_reader(Cursor::new(archive())).unwrap();
+        assert_eq!(backend.get_max_zoom(), 14);
+        let read = |z, x, y| backend.read_tile(&Coord { x, y, z }).unwrap();
+        assert_eq!(read(1, 0, 0), Some(b"a".to_vec()));
+        assert_eq!(read(1, 0, 1), Some(b"bc".to_vec()));
+        assert_eq!(read(1, 1, 0), Some(b"bc".to_vec()));
+        assert_eq!(read(0, 0, 0), None);
+        assert_eq!(read(2, 0, 0), None);
+        assert_eq!(backend.leaves.borrow().len(), 1);
+        assert_eq!(
+            backend.tile_coords().unwrap(),
+            vec![
+                Coord { x: 0, y: 0, z: 1 },
+                Coord { x: 0, y: 1, z: 1 },
+                Coord { x: 1, y: 1, z: 1 },
+                Coord { x: 1, y: 0, z: 1 },
+            ]
+        );
+
+        let err = block_on(backend.get_tile(&Coord { x: 0, y: 0, z: 0 }))
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.downcast_ref::<FetchingError>(),
+            Some(&FetchingError::TileNotFound)
+        );
+        assert!(PMTilesFileBackend::from_reader(Cursor::new(&archive()[..100])).is_err());
+    }
+}
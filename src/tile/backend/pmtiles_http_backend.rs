@@ -0,0 +1,209 @@
+use std::cell::RefCell;
+use std::num::NonZeroUsize;
+
+use lru::LruCache;
+
+use super::mvt_tile::Tile;
+use super::native_http::{HttpClient, ReqwestClient};
+use super::{Backend, Coord, FetchingError, TileFuture};
+use crate::debug::debug_log;
+use crate::tile::pmtiles::{
+    add_offset, find_tile, parse_directory, tile_id, Entry, Header, PMTilesError, HEADER_SIZE,
+};
+
+/// Number of cached leaf directories.
+const CACHED_DIRECTORIES: usize = 64;
+
+/// A backend reading Mapbox Vector Tiles from a PMTiles archive via HTTP range
+/// requests.
+///
+/// Only available on native targets with the `native` feature.
+pub struct PMTilesHttpBackend<C: HttpClient = ReqwestClient> {
+    url: String,
+    client: C,
+    header: Header,
+    root: Vec<Entry>,
+    /// Leaf directories by offset.
+    leaves: RefCell<LruCache<u64, Vec<Entry>>>,
+}
+
+impl PMTilesHttpBackend {
+    /// Opens the PMTiles archive at the URL using the [`ReqwestClient`].
+    pub async fn open(url: &str) -> Result<Self, PMTilesError> {
+        Self::open_with_client(url, ReqwestClient::new()).await
+    }
+}
+
+impl<C: HttpClient> PMTilesHttpBackend<C> {
+    /// Opens the PMTiles archive at the URL using the HTTP client.
+    pub async fn open_with_client(url: &str, client: C) -> Result<Self, PMTilesError> {
+        let header = Header::parse(&read_range(&client, url, 0, HEADER_SIZE as u64).await?)?;
+        let root = read_range(&client, url, header.root_offset, header.root_length).await?;
+        let root = parse_directory(&header.internal_compression.decompress(root)?)?;
+        Ok(PMTilesHttpBackend {
+            url: url.into(),
+            client,
+            header,
+            root,
+            leaves: RefCell::new(LruCache::new(
+                NonZeroUsize::new(CACHED_DIRECTORIES).unwrap(),
+            )),
+        })
+    }
+
+    /// Returns the lowest zoom level of the archive.
+    pub fn get_min_zoom(&self) -> u8 {
+        self.header.min_zoom
+    }
+
+    /// Returns the highest zoom level of the archive.
+    pub fn get_max_zoom(&self) -> u8 {
+        self.header.max_zoom
+    }
+
+    /// Returns the bounds of the archive as [west, south, east, north].
+    pub fn get_bounds(&self) -> [f64; 4] {
+        self.header.bounds
+    }
+
+    /// Returns the leaf directory at the offset within the leaf section.
+    async fn leaf_directory(&self, offset: u64, length: u64) -> Result<Vec<Entry>, PMTilesError> {
+        if let Some(entries) = self.leaves.borrow_mut().get(&offset) {
+            return Ok(entries.clone());
+        }
+        let data = read_range(
+            &self.client,
+            &self.url,
+            add_offset(self.header.leaf_offset, offset)?,
+            length,
+        )
+        .await?;
+        let entries = parse_directory(&self.header.internal_compression.decompress(data)?)?;
+        self.leaves.borrow_mut().put(offset, entries.clone());
+        Ok(entries)
+    }
+
+    /// Reads the decompressed data of the tile, `None` if it doesn't exist.
+    pub(crate) async fn read_tile(&self, coord: &Coord) -> Result<Option<Vec<u8>>, PMTilesError> {
+        let entry = find_tile(&self.root, tile_id(coord), |offset, length| {
+            self.leaf_directory(offset, length)
+        })
+        .await?;
+        let entry = match entry {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let data = read_range(
+            &self.client,
+            &self.url,
+            add_offset(self.header.tile_data_offset, entry.offset)?,
+            entry.length,
+        )
+        .await?;
+        Ok(Some(self.header.tile_compression.decompress(data)?))
+    }
+}
+
+/// Fetches `length` bytes at the offset with a range request.
+async fn read_range<C: HttpClient>(
+    client: &C,
+    url: &str,
+    offset: u64,
+    length: u64,
+) -> Result<Vec<u8>, PMTilesError> {
+    if length == 0 {
+        return Ok(Vec::new());
+    }
+    let range = format!("bytes={}-{}", offset, add_offset(offset, length - 1)?);
+    debug_log!("fetch {} {}", url, range);
+    let response = client.get(url, &[("Range", range)]).await?;
+    match response.status {
+        206 => {}
+        200 => {
+            return Err(FetchingError::RequestFailed(format!(
+                "{} does not support range requests",
+                url
+            ))
+            .into())
+        }
+        status => return Err(FetchingError::HttpStatus(status).into()),
+    }
+    if response.body.len() as u64 != length {
+        return Err(PMTilesError::InvalidArchive("unexpected end of archive"));
+    }
+    Ok(response.body)
+}
+
+impl<C: HttpClient> Backend<Tile> for PMTilesHttpBackend<C> {
+    fn get_tile<'a>(&'a self, coord: &'a Coord) -> TileFuture<'a, Tile> {
+        Box::pin(async move {
+            debug_log!("read tile {:?}", coord);
+            match self.read_tile(coord).await? {
+                Some(data) => Ok(Tile::Raw {
+                    data,
+                    coord: coord.clone(),
+                    options: Default::default(),
+                }),
+                None => Err(FetchingError::TileNotFound.into()),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile::backend::native_http::tests::serve;
+    use crate::tile::pmtiles::tests::{directory, entry, header};
+
+    /// Returns a response with the bytes of the range of the archive.
+    fn range_response(archive: &[u8], offset: usize, length: usize) -> Vec<u8> {
+        let mut response = format!(
+            "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            length
+        )
+        .into_bytes();
+        response.extend_from_slice(&archive[offset..offset + length]);
+        response
+    }
+
+    #[tokio::test]
+    async fn read_tiles() {
+        let leaf = directory(&[entry(1, 0, 1, 1), entry(2, 1, 2, 3)]);
+        let root = directory(&[entry(1, 0, leaf.len() as u64, 0)]);
+        let leaf_offset = HEADER_SIZE + root.len();
+        let data_offset = leaf_offset + leaf.len();
+        let mut archive = header(root.len() as u64, leaf_offset as u64, data_offset as u64);
+        archive.extend(&root);
+        archive.extend(&leaf);
+        archive.extend(b"abc");
+
+        let (url, server) = serve(vec![
+            range_response(&archive, 0, HEADER_SIZE),
+            range_response(&archive, HEADER_SIZE, root.len()),
+            range_response(&archive, leaf_offset, leaf.len()),
+            range_response(&archive, data_offset + 1, 2),
+            b"HTTP/1.1 200 OK\r\nContent-Length: 1\r\nConnection: close\r\n\r\na".to_vec(),
+        ]);
+        let backend = PMTilesHttpBackend::open(&url).await.unwrap();
+        assert_eq!(backend.get_max_zoom(), 14);
+        let coord = |z, x, y| Coord { x, y, z };
+        assert_eq!(
+            backend.read_tile(&coord(1, 0, 1)).await.unwrap(),
+            Some(b"bc".to_vec())
+        );
+        assert_eq!(backend.read_tile(&coord(0, 0, 0)).await.unwrap(), None);
+        assert!(matches!(
+            backend.read_tile(&coord(1, 0, 0)).await,
+            Err(PMTilesError::Fetching(FetchingError::RequestFailed(_)))
+        ));
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains(&format!("range: bytes=0-{}\r\n", HEADER_SIZE - 1)));
+        assert!(requests[3].contains(&format!(
+            "range: bytes={}-{}\r\n",
+            data_offset + 1,
+            data_offset + 2
+        )));
+    }
+}
@@ -1,12 +1,9 @@
 use wasm_bindgen::{prelude::*, JsValue};
 
-use super::{Backend, Coord};
-use crate::debug::debug_log;
-use crate::geo_types::Point;
-use crate::routing::{Connector, Router, Segment};
-use mercantile::LngLatBbox;
-use mvt_reader::Reader;
-use std::convert::TryFrom;
+use super::mvt_tile::{ParseOptions, Tile};
+use super::{http, js_error_message, Backend, Coord, FetchingError, TileFuture};
+use crate::debug::{debug_log, info_log};
+use crate::tile::ParsedTile;
 use thiserror::Error;
 
 #[wasm_bindgen(module = "pmtiles")]
@@ -14,227 +11,304 @@ extern "C" {
     type PMTiles;
 
     #[wasm_bindgen(constructor)]
-    fn new(url: String) -> PMTiles;
+    fn new_with_source(source: &JsValue) -> PMTiles;
+
+    #[wasm_bindgen(constructor)]
+    fn new_with_cache(url: String, cache: &SharedPromiseCache) -> PMTiles;
 
     #[wasm_bindgen(method, js_name = getZxy)]
     fn get_zxy(this: &PMTiles, z: u8, x: u32, y: u32) -> JsValue;
+
+    #[wasm_bindgen(method, js_name = getHeader)]
+    fn get_header(this: &PMTiles) -> JsValue;
+
+    #[wasm_bindgen(method, js_name = getMetadata)]
+    fn get_metadata(this: &PMTiles) -> JsValue;
+
+    type SharedPromiseCache;
+
+    #[wasm_bindgen(constructor)]
+    fn new() -> SharedPromiseCache;
+}
+
+thread_local! {
+    /// Headers and directories of the archives by URL, shared by all
+    /// backends, so further routers for an archive skip their range requests.
+    static DIRECTORY_CACHE: SharedPromiseCache = SharedPromiseCache::new();
+}
+
+/// Creates the archive for the URL using the shared directory cache.
+fn shared_archive(url: &str) -> PMTiles {
+    DIRECTORY_CACHE.with(|cache| PMTiles::new_with_cache(url.into(), cache))
+}
+
+#[wasm_bindgen(js_name = prewarmPMTiles)]
+/// Reads the header and root directory of the PMTiles archive at the URL into
+/// the directory cache shared by all `PMTilesMVTRouter`s.
+///
+/// Routers created afterwards, e.g. after navigating within a single page
+/// app, fetch only tiles and leaf directories.
+pub async fn prewarm_pmtiles(url: String) -> Result<(), JsError> {
+    let archive = shared_archive(&url);
+    wasm_bindgen_futures::JsFuture::from(js_sys::Promise::from(archive.get_header()))
+        .await
+        .or(Err(ArchiveError::MetadataNotFound))?;
+    Ok(())
 }
 
-pub struct Tile {
-    data: Vec<u8>,
-    coord: Coord,
+#[derive(Debug, Clone)]
+#[wasm_bindgen]
+/// Metadata of a PMTiles archive.
+pub struct ArchiveInfo {
+    min_zoom: u8,
+    max_zoom: u8,
+    bounds: [f64; 4],
+    layers: Vec<String>,
+    version: Option<String>,
 }
 
-impl super::Tile for Tile {
-    fn parse(&self, router: &mut Router) -> Result<(), Box<dyn std::error::Error>> {
-        Ok(parse_mvt_buffer(router, &self.data, &self.coord, false)?)
+#[wasm_bindgen]
+impl ArchiveInfo {
+    pub fn get_min_zoom(&self) -> u8 {
+        self.min_zoom
+    }
+
+    pub fn get_max_zoom(&self) -> u8 {
+        self.max_zoom
+    }
+
+    /// Returns the bounds of the archive as [west, south, east, north].
+    pub fn get_bounds(&self) -> Vec<f64> {
+        self.bounds.to_vec()
+    }
+
+    /// Returns the ids of the vector layers contained in the archive.
+    pub fn get_layers(&self) -> Vec<String> {
+        self.layers.clone()
+    }
+
+    /// Returns the version of the archive data.
+    ///
+    /// This is the `version` of the archive metadata or, if missing, the ETag
+    /// of the archive.
+    pub fn get_version(&self) -> Option<String> {
+        self.version.clone()
+    }
+
+    /// Returns whether the archive contains tiles at the given zoom level.
+    pub fn covers_zoom(&self, zoom: u8) -> bool {
+        self.min_zoom <= zoom && zoom <= self.max_zoom
     }
 }
 
 pub struct PMTilesMVTBackend {
+    url: String,
     pm_tiles: PMTiles,
+    /// Whether the whole archive is held in memory.
+    in_memory: bool,
+    /// Optional JS function which parses tiles, e.g. inside a Web Worker.
+    parser: Option<js_sys::Function>,
+    /// Property of the segments holding their direction.
+    direction_property: Option<String>,
 }
 
 impl PMTilesMVTBackend {
     pub fn new(url: &str) -> Self {
         PMTilesMVTBackend {
-            pm_tiles: PMTiles::new(url.into()),
+            url: url.into(),
+            pm_tiles: shared_archive(url),
+            in_memory: false,
+            parser: None,
+            direction_property: None,
         }
     }
-}
 
-#[derive(Error, Debug)]
-enum FetchingError {
-    #[error("Could not find tile")]
-    TileNotFound,
-}
+    /// Creates the backend serving the archive from the given bytes.
+    ///
+    /// The bytes are copied, so Node.js Buffers sharing a memory pool can be
+    /// passed as well.
+    pub fn from_buffer(key: &str, data: &js_sys::Uint8Array) -> Self {
+        let buffer = data.slice(0, data.length()).buffer();
+        PMTilesMVTBackend {
+            url: key.into(),
+            pm_tiles: PMTiles::new_with_source(&buffer_source(key, buffer)),
+            in_memory: true,
+            parser: None,
+            direction_property: None,
+        }
+    }
 
-impl Backend<Tile> for PMTilesMVTBackend {
-    async fn get_tile(&self, coord: &Coord) -> Result<Tile, Box<dyn std::error::Error>> {
-        debug_log!("get tile {:?}", coord);
-        let promise = js_sys::Promise::from(self.pm_tiles.get_zxy(coord.z, coord.x, coord.y));
-        wasm_bindgen_futures::JsFuture::from(promise)
-            .await
-            .and_then(|inside| js_sys::Reflect::get(&inside, &JsValue::from(String::from("data"))))
-            .and_then(|data| {
-                Ok(Tile {
-                    data: js_sys::Uint8Array::new(&data).to_vec(),
-                    coord: coord.clone(),
+    /// Sets a JS function which parses tiles instead of the calling thread.
+    ///
+    /// The function is called with the MVT data (Uint8Array), the z, x and
+    /// y coordinates of the tile and the direction property, if set. It must
+    /// return a promise resolving to the bytes of the parsed tile as returned
+    /// by `parseMVTTile`.
+    pub fn set_parser(&mut self, parser: js_sys::Function) {
+        self.parser = Some(parser);
+    }
+
+    /// Sets the property of the segments holding the direction in which they
+    /// can be travelled, `oneway` if `None`.
+    pub fn set_direction_property(&mut self, property: Option<String>) {
+        self.direction_property = property;
+    }
+
+    /// Fetches the whole archive if it is not larger than `max_size` bytes.
+    ///
+    /// Tiles of a preloaded archive are read from memory instead of using a
+    /// range request per tile. Returns whether the archive was preloaded.
+    pub async fn preload(&mut self, max_size: f64) -> Result<bool, Box<dyn std::error::Error>> {
+        if self.in_memory {
+            return Ok(true);
+        }
+        match http::content_length(&self.url).await? {
+            Some(size) if size <= max_size => {}
+            _ => return Ok(false),
+        }
+        info_log!("preload archive {}", self.url);
+        let buffer = http::fetch_buffer(&self.url).await?;
+        self.pm_tiles = PMTiles::new_with_source(&buffer_source(&self.url, buffer));
+        self.in_memory = true;
+        Ok(true)
+    }
+
+    /// Reads zoom range, bounds and vector layers of the archive.
+    pub async fn get_archive_info(&self) -> Result<ArchiveInfo, Box<dyn std::error::Error>> {
+        let header =
+            wasm_bindgen_futures::JsFuture::from(js_sys::Promise::from(self.pm_tiles.get_header()))
+                .await
+                .or(Err(ArchiveError::MetadataNotFound))?;
+        let metadata = wasm_bindgen_futures::JsFuture::from(js_sys::Promise::from(
+            self.pm_tiles.get_metadata(),
+        ))
+        .await
+        .or(Err(ArchiveError::MetadataNotFound))?;
+        let number = |object: &JsValue, key: &str| {
+            js_sys::Reflect::get(object, &JsValue::from_str(key))
+                .ok()
+                .and_then(|value| value.as_f64())
+                .ok_or(ArchiveError::MetadataNotFound)
+        };
+        let vector_layers = js_sys::Reflect::get(&metadata, &JsValue::from_str("vector_layers"))
+            .unwrap_or(JsValue::UNDEFINED);
+        let layers = if js_sys::Array::is_array(&vector_layers) {
+            js_sys::Array::from(&vector_layers)
+                .iter()
+                .filter_map(|layer| {
+                    js_sys::Reflect::get(&layer, &JsValue::from_str("id"))
+                        .ok()
+                        .and_then(|id| id.as_string())
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let string = |object: &JsValue, key: &str| {
+            js_sys::Reflect::get(object, &JsValue::from_str(key))
+                .ok()
+                .and_then(|value| {
+                    value
+                        .as_string()
+                        .or_else(|| value.as_f64().map(|number| number.to_string()))
                 })
-            })
-            .or(Err(FetchingError::TileNotFound.into()))
+                .filter(|value| !value.is_empty())
+        };
+        let version = string(&metadata, "version").or_else(|| string(&header, "etag"));
+        Ok(ArchiveInfo {
+            min_zoom: number(&header, "minZoom")? as u8,
+            max_zoom: number(&header, "maxZoom")? as u8,
+            bounds: [
+                number(&header, "minLon")?,
+                number(&header, "minLat")?,
+                number(&header, "maxLon")?,
+                number(&header, "maxLat")?,
+            ],
+            layers,
+            version,
+        })
+    }
+
+    /// Parses the tile data using the configured parser function.
+    async fn parse_with(
+        &self,
+        parser: &js_sys::Function,
+        data: &[u8],
+        coord: &Coord,
+    ) -> Result<ParsedTile, ArchiveError> {
+        let args = js_sys::Array::of5(
+            &js_sys::Uint8Array::from(data),
+            &JsValue::from(coord.z),
+            &JsValue::from(coord.x),
+            &JsValue::from(coord.y),
+            &JsValue::from(self.direction_property.clone()),
+        );
+        let promise = parser
+            .apply(&JsValue::NULL, &args)
+            .map(js_sys::Promise::from)
+            .map_err(|_| ArchiveError::ParserFailed)?;
+        let parsed = wasm_bindgen_futures::JsFuture::from(promise)
+            .await
+            .map_err(|_| ArchiveError::ParserFailed)?;
+        ParsedTile::from_bytes(&js_sys::Uint8Array::new(&parsed).to_vec())
+            .map_err(|_| ArchiveError::ParserFailed)
     }
 }
 
 #[derive(Error, Debug)]
-enum ParsingError {
-    #[error("Could not parse MVT tile")]
-    MVTError,
-    #[error("Connector with id `{connector_id:?}` is invalid: {context}")]
-    InvalidConnector {
-        connector_id: String,
-        context: String,
-    },
-    #[error("Segment with id `{segment_id:?}` is invalid: {context}")]
-    InvalidSegment { segment_id: String, context: String },
-    #[error("Missing ID")]
-    InvalidID,
+enum ArchiveError {
+    #[error("Parser function failed")]
+    ParserFailed,
+    #[error("Could not read archive metadata")]
+    MetadataNotFound,
 }
 
-fn parse_connectors(
-    segments: &mut Router,
-    reader: &Reader,
-    extent: f64,
-    bbox: &LngLatBbox,
-    strict: bool,
-) -> Result<(), ParsingError> {
-    for feature in reader.get_features(0).unwrap() {
-        let id = feature
-            .properties
-            .as_ref()
-            .and_then(|p| p.get("id"))
-            .ok_or(ParsingError::InvalidID)?
-            .to_string();
-        let point = match geo::MultiPoint::<f32>::try_from(feature.geometry) {
-            Ok(p) => p.into_iter().next(),
-            Err(err) => {
-                let err = ParsingError::InvalidConnector {
-                    connector_id: id.clone(),
-                    context: format!("Could not parse geometry {:?} for connector {}", err, id),
-                };
-                if strict {
-                    return Err(err);
-                } else {
-                    debug_log!("{}", err);
-                    continue;
-                }
-            }
-        };
-        match point {
-            Some(point) => {
-                let x = bbox.west + point.x() as f64 / extent * (bbox.east - bbox.west);
-                let y = bbox.north + point.y() as f64 / extent * (bbox.south - bbox.north);
-                segments.push_connector(Connector::new(id.as_str(), &Point::new(x, y)));
+impl Backend<Tile> for PMTilesMVTBackend {
+    fn get_tile<'a>(&'a self, coord: &'a Coord) -> TileFuture<'a, Tile> {
+        Box::pin(async move {
+            debug_log!("get tile {:?}", coord);
+            let promise = js_sys::Promise::from(self.pm_tiles.get_zxy(coord.z, coord.x, coord.y));
+            let response = wasm_bindgen_futures::JsFuture::from(promise)
+                .await
+                .map_err(|err| FetchingError::RequestFailed(js_error_message(&err)))?;
+            // Tiles missing in the archive resolve to undefined.
+            if response.is_undefined() {
+                return Err(FetchingError::TileNotFound.into());
             }
-            None => {
-                let err = ParsingError::InvalidConnector {
-                    connector_id: id.clone(),
-                    context: format!("Empty geometry for connector {}", id),
-                };
-                if strict {
-                    return Err(err);
-                } else {
-                    debug_log!("{}", err);
-                }
+            let data = js_sys::Reflect::get(&response, &JsValue::from(String::from("data")))
+                .map(|data| js_sys::Uint8Array::new(&data).to_vec())
+                .map_err(|err| FetchingError::RequestFailed(js_error_message(&err)))?;
+            match &self.parser {
+                Some(parser) => Ok(Tile::Parsed(self.parse_with(parser, &data, coord).await?)),
+                None => Ok(Tile::Raw {
+                    data,
+                    coord: coord.clone(),
+                    options: ParseOptions {
+                        direction_property: self.direction_property.clone(),
+                        ..Default::default()
+                    },
+                }),
             }
-        }
-    }
-    Ok(())
-}
-
-fn parse_segments(
-    segments: &mut Router,
-    reader: &Reader,
-    extent: f64,
-    bbox: &LngLatBbox,
-    _strict: bool,
-) -> Result<(), ParsingError> {
-    for feature in reader.get_features(1).unwrap() {
-        let id = feature.properties.as_ref().unwrap().get("id").unwrap();
-        if geo::MultiLineString::<f32>::try_from(feature.geometry.clone()).is_ok() {
-            continue;
-        }
-        let coords = geo::LineString::<f32>::try_from(feature.geometry)
-            .unwrap()
-            .into_inner();
-        let geometry: geo::LineString<f64> = coords
-            .iter()
-            .map(|coord| geo::Coord {
-                x: bbox.west + coord.x as f64 / extent * (bbox.east - bbox.west),
-                y: bbox.north + coord.y as f64 / extent * (bbox.south - bbox.north),
-            })
-            .collect();
-        let connector_ids: Vec<String> = feature
-            .properties
-            .as_ref()
-            .and_then(|p| p.get("connector_ids"))
-            .and_then(|ids| serde_json::from_str(ids).ok())
-            .ok_or(ParsingError::InvalidSegment {
-                segment_id: id.clone(),
-                context: "Connector ids missing or invalid".into(),
-            })?;
-        let segment = Segment::new(id.clone(), geometry.into(), connector_ids);
-        segments.push_segment(segment);
+        })
     }
-    Ok(())
-}
-
-// Parses the given MVT tile and adds the included segments and connectors to
-// the router.
-fn parse_mvt_buffer(
-    router: &mut Router,
-    buffer: &Vec<u8>,
-    coord: &Coord,
-    strict: bool,
-) -> Result<(), ParsingError> {
-    let tile = mercantile::Tile::new(
-        i32::try_from(coord.x).unwrap(),
-        i32::try_from(coord.y).unwrap(),
-        i32::try_from(coord.z).unwrap(),
-    );
-    let bbox = mercantile::bounds(tile);
-    let extent: f64 = 4096.0;
-    let reader = Reader::new(buffer.to_vec()).map_err(|_| ParsingError::MVTError)?;
-    parse_connectors(router, &reader, extent, &bbox, strict)?;
-    parse_segments(router, &reader, extent, &bbox, strict)?;
-    Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::Coord;
-
-    #[test]
-    /// Test find_route method.
-    fn parse_mvt_buffer() {
-        let mut tile = mvt::Tile::new(4096);
-        {
-            let layer = tile.create_layer("connectors");
-            let b = mvt::GeomEncoder::new(mvt::GeomType::Point)
-                .point(0.0, 0.0)
-                .unwrap()
-                .encode()
-                .unwrap();
-            let mut feature = layer.into_feature(b);
-            feature.set_id(1);
-            feature.add_tag_string("id", "foo");
-            let layer = feature.into_layer();
-            tile.add_layer(layer).unwrap();
-        }
-        {
-            let layer = tile.create_layer("segments");
-            let b = mvt::GeomEncoder::new(mvt::GeomType::Linestring)
-                .point(0.0, 0.0)
-                .unwrap()
-                .point(1024.0, 0.0)
-                .unwrap()
-                .point(1024.0, 2048.0)
-                .unwrap()
-                .point(4096.0, 4096.0)
-                .unwrap()
-                .encode()
-                .unwrap();
-            let mut feature = layer.into_feature(b);
-            feature.set_id(1);
-            feature.add_tag_string("id", "foo");
-            feature.add_tag_string("connector_ids", "[\"foo\"]");
-            let layer = feature.into_layer();
-            tile.add_layer(layer).unwrap();
-        }
-        let data = tile.to_bytes().unwrap();
-        let mut router = crate::routing::Router::new();
-        super::parse_mvt_buffer(&mut router, &data, &Coord { x: 0, y: 0, z: 0 }, true).unwrap();
-        assert_eq!(1, router.segments_len());
-        assert_eq!(1, router.connectors_len());
+/// Creates a PMTiles source which serves the archive from the buffer.
+fn buffer_source(key: &str, buffer: js_sys::ArrayBuffer) -> JsValue {
+    let get_bytes = Closure::<dyn Fn(f64, f64) -> JsValue>::new(move |offset, length| {
+        let result = js_sys::Object::new();
+        let data = buffer.slice_with_end(offset as u32, (offset + length) as u32);
+        let _ = js_sys::Reflect::set(&result, &JsValue::from_str("data"), &data);
+        js_sys::Promise::resolve(&result).into()
+    });
+    let key = JsValue::from_str(key);
+    let get_key = Closure::<dyn Fn() -> JsValue>::new(move || key.clone());
+    let source = js_sys::Object::new();
+    for (name, function) in [
+        ("getBytes", get_bytes.into_js_value()),
+        ("getKey", get_key.into_js_value()),
+    ] {
+        let _ = js_sys::Reflect::set(&source, &JsValue::from_str(name), &function);
     }
+    source.into()
 }
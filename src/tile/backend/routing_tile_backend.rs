@@ -0,0 +1,63 @@
+use super::http::HttpTileSource;
+use super::{Backend, Coord, FetchingError, Revalidation, TileFuture, Validators};
+use crate::tile::{routing_tile, ParsedTile};
+
+/// A tile in the routing tile format fetched via HTTP.
+pub struct Tile {
+    data: Vec<u8>,
+    validators: Validators,
+}
+
+impl super::Tile for Tile {
+    fn parse(&self) -> Result<ParsedTile, Box<dyn std::error::Error>> {
+        Ok(routing_tile::decode(&self.data)?)
+    }
+
+    fn get_validators(&self) -> Option<Validators> {
+        if self.validators == Validators::default() {
+            None
+        } else {
+            Some(self.validators.clone())
+        }
+    }
+}
+
+/// A backend fetching pre-built routing tiles from an XYZ URL template.
+///
+/// Routing tiles can be created from Mapbox Vector Tiles with `parseMVTTile`.
+pub struct RoutingTileBackend {
+    source: HttpTileSource,
+}
+
+impl RoutingTileBackend {
+    pub fn new(url_template: &str) -> Self {
+        RoutingTileBackend {
+            source: HttpTileSource::new(url_template),
+        }
+    }
+}
+
+impl Backend<Tile> for RoutingTileBackend {
+    fn get_tile<'a>(&'a self, coord: &'a Coord) -> TileFuture<'a, Tile> {
+        Box::pin(async move {
+            let (data, validators) =
+                self.source.fetch(coord, None).await?.ok_or_else(|| {
+                    FetchingError::RequestFailed("Unexpected 304 response".into())
+                })?;
+            Ok(Tile { data, validators })
+        })
+    }
+
+    fn revalidate<'a>(
+        &'a self,
+        coord: &'a Coord,
+        validators: &'a Validators,
+    ) -> TileFuture<'a, Revalidation<Tile>> {
+        Box::pin(async move {
+            Ok(match self.source.fetch(coord, Some(validators)).await? {
+                Some((data, validators)) => Revalidation::Modified(Tile { data, validators }),
+                None => Revalidation::NotModified,
+            })
+        })
+    }
+}
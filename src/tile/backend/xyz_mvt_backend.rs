@@ -0,0 +1,82 @@
+use super::http::HttpTileSource;
+use super::mvt_tile::parse_mvt_buffer;
+use super::{Backend, Coord, FetchingError, Revalidation, TileFuture, Validators};
+use crate::tile::ParsedTile;
+
+/// A Mapbox Vector Tile fetched via HTTP.
+pub struct Tile {
+    data: Vec<u8>,
+    coord: Coord,
+    validators: Validators,
+}
+
+impl Tile {
+    #[cfg_attr(not(feature = "native"), allow(dead_code))]
+    pub(crate) fn new(data: Vec<u8>, coord: Coord, validators: Validators) -> Self {
+        Tile {
+            data,
+            coord,
+            validators,
+        }
+    }
+}
+
+impl super::Tile for Tile {
+    fn parse(&self) -> Result<ParsedTile, Box<dyn std::error::Error>> {
+        Ok(parse_mvt_buffer(&self.data, &self.coord, false)?)
+    }
+
+    fn get_validators(&self) -> Option<Validators> {
+        if self.validators == Validators::default() {
+            None
+        } else {
+            Some(self.validators.clone())
+        }
+    }
+}
+
+/// A backend fetching Mapbox Vector Tiles from an XYZ URL template.
+pub struct XYZMVTBackend {
+    source: HttpTileSource,
+}
+
+impl XYZMVTBackend {
+    pub fn new(url_template: &str) -> Self {
+        XYZMVTBackend {
+            source: HttpTileSource::new(url_template),
+        }
+    }
+}
+
+impl Backend<Tile> for XYZMVTBackend {
+    fn get_tile<'a>(&'a self, coord: &'a Coord) -> TileFuture<'a, Tile> {
+        Box::pin(async move {
+            let (data, validators) =
+                self.source.fetch(coord, None).await?.ok_or_else(|| {
+                    FetchingError::RequestFailed("Unexpected 304 response".into())
+                })?;
+            Ok(Tile {
+                data,
+                coord: coord.clone(),
+                validators,
+            })
+        })
+    }
+
+    fn revalidate<'a>(
+        &'a self,
+        coord: &'a Coord,
+        validators: &'a Validators,
+    ) -> TileFuture<'a, Revalidation<Tile>> {
+        Box::pin(async move {
+            Ok(match self.source.fetch(coord, Some(validators)).await? {
+                Some((data, validators)) => Revalidation::Modified(Tile {
+                    data,
+                    coord: coord.clone(),
+                    validators,
+                }),
+                None => Revalidation::NotModified,
+            })
+        })
+    }
+}
@@ -1,23 +1,166 @@
-use crate::geo_types::Point;
+use crate::geo_types::projection::{mercator_to_lon_lat, HALF_CIRCUMFERENCE};
+use crate::geo_types::{Point, Rect};
+use crate::routing::RoutingError;
+#[cfg(all(target_arch = "wasm32", feature = "tilebelt"))]
 use std::convert::TryFrom;
 use wasm_bindgen::prelude::*;
 
 pub mod backend;
 
+pub mod parsed;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod pmtiles;
+pub use parsed::{ParseIssue, ParsedTile};
+
+pub mod region;
+pub use region::{RegionDownload, RegionEstimate};
+
+pub mod routing_tile;
+
+pub(crate) mod selection;
+
+pub mod store;
+pub use store::TileStore;
+
+#[cfg(all(feature = "writer", not(target_arch = "wasm32")))]
+pub mod writer;
+
 /// Coordinate of a tile.
+///
+/// In JS, the class is called `TileCoord`, e.g. to plan prefetching or to
+/// show which tiles are loaded.
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
+#[wasm_bindgen(js_name = TileCoord)]
 pub struct Coord {
+    /// Column of the tile.
+    #[wasm_bindgen(skip)]
     pub x: u32,
+    /// Row of the tile, counted from the north.
+    #[wasm_bindgen(skip)]
     pub y: u32,
+    /// Zoom level of the tile.
+    #[wasm_bindgen(skip)]
     pub z: u8,
 }
 
+#[wasm_bindgen(js_class = TileCoord)]
+impl Coord {
+    #[wasm_bindgen(constructor)]
+    /// Creates the coordinate of the tile `x`, `y` at zoom level `z`.
+    pub fn new(x: u32, y: u32, z: u8) -> Coord {
+        Coord { x, y, z }
+    }
+
+    /// Returns the column of the tile.
+    pub fn get_x(&self) -> u32 {
+        self.x
+    }
+
+    /// Returns the row of the tile, counted from the north.
+    pub fn get_y(&self) -> u32 {
+        self.y
+    }
+
+    /// Returns the zoom level of the tile.
+    pub fn get_z(&self) -> u8 {
+        self.z
+    }
+
+    #[wasm_bindgen(js_name = withNeighbours)]
+    /// Returns the tile and its (up to eight) neighbours.
+    ///
+    /// Neighbours wrap around the antimeridian in x. In y, there are no
+    /// neighbours beyond the poles. Every tile is returned only once, even at
+    /// low zoom levels where neighbours coincide.
+    pub fn with_neighbours(&self) -> Vec<Coord> {
+        self.with_radius(1)
+    }
+
+    /// Returns the tile at zoom level `z` containing this tile.
+    ///
+    /// Tiles at or above zoom level `z` are returned unchanged.
+    pub fn ancestor(&self, z: u8) -> Coord {
+        if z >= self.z {
+            return self.clone();
+        }
+        let shift = self.z - z;
+        Coord {
+            x: self.x >> shift,
+            y: self.y >> shift,
+            z,
+        }
+    }
+
+    /// Returns the bounds of the tile in longitude and latitude.
+    pub fn bbox(&self) -> Rect {
+        region::tile_bounds(self).into()
+    }
+}
+
+impl Coord {
+    /// Returns the tiles up to `radius` tiles away in x and y, including
+    /// this one, like [`Coord::with_neighbours`] for radius 1.
+    pub(crate) fn with_radius(&self, radius: u32) -> Vec<Coord> {
+        let radius = i64::from(radius);
+        let tiles = 1i64 << self.z;
+        let mut coords = Vec::new();
+        for dx in -radius..=radius {
+            let x = (i64::from(self.x) + dx).rem_euclid(tiles);
+            for dy in -radius..=radius {
+                let y = i64::from(self.y) + dy;
+                if !(0..tiles).contains(&y) {
+                    continue;
+                }
+                let coord = Coord {
+                    x: x as u32,
+                    y: y as u32,
+                    z: self.z,
+                };
+                if !coords.contains(&coord) {
+                    coords.push(coord);
+                }
+            }
+        }
+        coords
+    }
+
+    /// Returns the distance in tiles to the other tile, the larger of the
+    /// distances in x (around the antimeridian if shorter) and y, `None` for
+    /// tiles of different zoom levels.
+    pub(crate) fn distance(&self, other: &Coord) -> Option<u32> {
+        if self.z != other.z {
+            return None;
+        }
+        let dx = self.x.abs_diff(other.x);
+        let dx = dx.min((1u32 << self.z) - dx);
+        Some(dx.max(self.y.abs_diff(other.y)))
+    }
+
+    /// Returns the longitude and latitude of a position within the tile.
+    ///
+    /// `x` and `y` are measured from the top left corner of the tile, which
+    /// is `extent` units wide, as in Mapbox Vector Tiles.
+    pub fn position_to_lon_lat(&self, x: f64, y: f64, extent: f64) -> (f64, f64) {
+        let tiles = 2f64.powi(i32::from(self.z));
+        let x = (f64::from(self.x) + x / extent) / tiles;
+        let y = (f64::from(self.y) + y / extent) / tiles;
+        mercator_to_lon_lat(
+            (2.0 * x - 1.0) * HALF_CIRCUMFERENCE,
+            (1.0 - 2.0 * y) * HALF_CIRCUMFERENCE,
+        )
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "tilebelt"))]
 #[wasm_bindgen(module = "@mapbox/tilebelt")]
 extern "C" {
     fn pointToTile(x: f64, y: f64, z: u8) -> Vec<u32>;
 }
 
 /// Returns the coordinates of the tile that cover this point.
+#[cfg(all(target_arch = "wasm32", feature = "tilebelt"))]
+#[wasm_bindgen(js_name = pointToTileCoord)]
 pub fn point_to_tile_coord(point: &Point, z: u8) -> Coord {
     let ret = pointToTile(point.x(), point.y(), z);
     Coord {
@@ -26,3 +169,129 @@ pub fn point_to_tile_coord(point: &Point, z: u8) -> Coord {
         z: u8::try_from(ret[2]).unwrap(),
     }
 }
+
+/// Returns the coordinates of the tile that cover this point.
+#[cfg(not(all(target_arch = "wasm32", feature = "tilebelt")))]
+#[wasm_bindgen(js_name = pointToTileCoord)]
+pub fn point_to_tile_coord(point: &Point, z: u8) -> Coord {
+    let (x, y) = region::tile_index(point.x(), point.y(), z);
+    Coord { x, y, z }
+}
+
+#[wasm_bindgen(js_name = tilesCoveringBounds)]
+/// Returns the tiles at zoom level `z` intersecting the bounds [west, south,
+/// east, north], e.g. to build a custom download of offline regions.
+pub fn tiles_covering_bounds(bounds: Vec<f64>, z: u8) -> Result<Vec<Coord>, RoutingError> {
+    region::tiles_covering(&region::bounds_to_region(&bounds)?, z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coord(x: u32, y: u32, z: u8) -> Coord {
+        Coord { x, y, z }
+    }
+
+    #[test]
+    fn with_neighbours() {
+        let inner = coord(5, 5, 4).with_neighbours();
+        assert_eq!(inner.len(), 9);
+        assert!(inner.contains(&coord(4, 4, 4)));
+        assert!(inner.contains(&coord(6, 6, 4)));
+
+        // Wraps around the antimeridian.
+        let west = coord(0, 5, 4).with_neighbours();
+        assert_eq!(west.len(), 9);
+        assert!(west.contains(&coord(15, 5, 4)));
+        let east = coord(15, 5, 4).with_neighbours();
+        assert!(east.contains(&coord(0, 6, 4)));
+
+        // No neighbours beyond the poles.
+        let north = coord(0, 0, 4).with_neighbours();
+        assert_eq!(north.len(), 6);
+        assert!(north.contains(&coord(15, 1, 4)));
+        let south = coord(3, 15, 4).with_neighbours();
+        assert_eq!(south.len(), 6);
+        assert!(south.iter().all(|coord| coord.y >= 14));
+
+        // Neighbours coincide at low zoom levels.
+        assert_eq!(coord(0, 0, 0).with_neighbours(), vec![coord(0, 0, 0)]);
+        assert_eq!(coord(1, 0, 1).with_neighbours().len(), 4);
+    }
+
+    #[test]
+    fn point_to_tile_coord() {
+        assert_eq!(
+            super::point_to_tile_coord(&Point::new(8.68, 50.11), 14),
+            coord(8587, 5548, 14)
+        );
+        assert_eq!(
+            super::point_to_tile_coord(&Point::new(180.0, -90.0), 2),
+            coord(3, 3, 2)
+        );
+    }
+
+    #[test]
+    fn bbox() {
+        let bbox = coord(1, 0, 1).bbox();
+        assert!((bbox.west() - 0.0).abs() < 1e-9);
+        assert!((bbox.east() - 180.0).abs() < 1e-9);
+        assert!((bbox.south() - 0.0).abs() < 1e-9);
+        assert!((bbox.north() - 85.051_128_779_806_59).abs() < 1e-9);
+    }
+
+    #[test]
+    fn tiles_covering_bounds() {
+        assert_eq!(
+            super::tiles_covering_bounds(vec![1.0, 1.0, 2.0, 2.0], 1).unwrap(),
+            vec![coord(1, 0, 1)]
+        );
+        assert_eq!(
+            super::tiles_covering_bounds(vec![1.0, 1.0], 1).err(),
+            Some(RoutingError::InvalidRegion)
+        );
+    }
+
+    #[test]
+    fn distance() {
+        assert_eq!(coord(5, 5, 4).distance(&coord(7, 4, 4)), Some(2));
+        assert_eq!(coord(0, 5, 4).distance(&coord(15, 8, 4)), Some(3));
+        assert_eq!(coord(0, 0, 0).distance(&coord(0, 0, 0)), Some(0));
+        assert_eq!(coord(0, 0, 4).distance(&coord(0, 0, 5)), None);
+    }
+
+    #[test]
+    fn ancestor() {
+        assert_eq!(coord(37, 22, 6).ancestor(4), coord(9, 5, 4));
+        assert_eq!(coord(37, 22, 6).ancestor(0), coord(0, 0, 0));
+        assert_eq!(coord(37, 22, 6).ancestor(6), coord(37, 22, 6));
+        assert_eq!(coord(37, 22, 6).ancestor(8), coord(37, 22, 6));
+    }
+
+    #[test]
+    fn position_to_lon_lat() {
+        let assert_close = |actual: (f64, f64), expected: (f64, f64)| {
+            assert!((actual.0 - expected.0).abs() < 1e-9, "{:?}", actual);
+            assert!((actual.1 - expected.1).abs() < 1e-9, "{:?}", actual);
+        };
+        let world = coord(0, 0, 0);
+        assert_close(
+            world.position_to_lon_lat(2048.0, 2048.0, 4096.0),
+            (0.0, 0.0),
+        );
+        assert_close(
+            world.position_to_lon_lat(0.0, 4096.0, 4096.0),
+            (-180.0, -85.051_128_779_806_59),
+        );
+        assert_close(
+            coord(8587, 5548, 14).position_to_lon_lat(1024.0, 2048.0, 4096.0),
+            (8.684_692_382_812_5, 50.113_533_431_099_7),
+        );
+        let (lon, lat) = coord(8587, 5548, 14).position_to_lon_lat(1024.0, 2048.0, 4096.0);
+        assert_eq!(
+            super::point_to_tile_coord(&Point::new(lon, lat), 14),
+            coord(8587, 5548, 14)
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use crate::routing::{Connector, Segment, TurnRestriction};
+use crate::tile::routing_tile::{self, DecodingError};
+use crate::tile::Coord;
+use wasm_bindgen::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[wasm_bindgen]
+/// A feature which was skipped while parsing a tile.
+pub struct ParseIssue {
+    tile: Coord,
+    layer: String,
+    feature_id: String,
+    reason: String,
+}
+
+#[wasm_bindgen]
+impl ParseIssue {
+    /// Returns the coordinates of the tile as [z, x, y].
+    pub fn get_tile(&self) -> Vec<u32> {
+        vec![u32::from(self.tile.z), self.tile.x, self.tile.y]
+    }
+
+    /// Returns the name of the layer containing the feature.
+    pub fn get_layer(&self) -> String {
+        self.layer.clone()
+    }
+
+    /// Returns the ID of the feature, empty if the ID is missing.
+    pub fn get_feature_id(&self) -> String {
+        self.feature_id.clone()
+    }
+
+    /// Returns why the feature was skipped.
+    pub fn get_reason(&self) -> String {
+        self.reason.clone()
+    }
+}
+
+impl ParseIssue {
+    /// Creates the issue of the feature in the layer of the tile.
+    pub fn new(tile: &Coord, layer: &str, feature_id: &str, reason: &str) -> Self {
+        ParseIssue {
+            tile: tile.clone(),
+            layer: layer.into(),
+            feature_id: feature_id.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+#[wasm_bindgen]
+/// The segments and connectors parsed from a single tile.
+///
+/// Parsed tiles can be serialized to bytes (see [`routing_tile`]), so that
+/// parsing may happen in a Web Worker and the result is transferred back to the
+/// main thread.
+pub struct ParsedTile {
+    segments: Vec<Segment>,
+    connectors: Vec<Connector>,
+    restrictions: Vec<TurnRestriction>,
+    /// Features which were skipped while parsing.
+    issues: Vec<ParseIssue>,
+}
+
+#[wasm_bindgen]
+impl ParsedTile {
+    #[wasm_bindgen(js_name = segmentsLength)]
+    /// Returns number of parsed segments.
+    pub fn segments_len(&self) -> usize {
+        self.segments.len()
+    }
+
+    #[wasm_bindgen(js_name = connectorsLength)]
+    /// Returns number of parsed connectors.
+    pub fn connectors_len(&self) -> usize {
+        self.connectors.len()
+    }
+
+    #[wasm_bindgen(js_name = toBytes)]
+    /// Serializes the parsed tile in the routing tile format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        routing_tile::encode(self)
+    }
+
+    #[wasm_bindgen(js_name = fromBytes)]
+    /// Deserializes a parsed tile created by `toBytes`.
+    pub fn from_bytes_js(data: &[u8]) -> Result<ParsedTile, JsError> {
+        Ok(Self::from_bytes(data)?)
+    }
+}
+
+impl ParsedTile {
+    /// Creates a tile without features.
+    pub fn new() -> ParsedTile {
+        ParsedTile::default()
+    }
+
+    /// Adds a segment to the tile.
+    pub fn push_segment(&mut self, segment: Segment) {
+        self.segments.push(segment);
+    }
+
+    /// Adds a connector to the tile.
+    pub fn push_connector(&mut self, connector: Connector) {
+        self.connectors.push(connector);
+    }
+
+    /// Adds a turn restriction to the tile.
+    pub fn push_restriction(&mut self, restriction: TurnRestriction) {
+        self.restrictions.push(restriction);
+    }
+
+    /// Reports a feature which was skipped while parsing.
+    pub fn push_issue(&mut self, issue: ParseIssue) {
+        self.issues.push(issue);
+    }
+
+    /// Returns the features which were skipped while parsing.
+    ///
+    /// Issues are not part of the routing tile format.
+    pub fn get_issues(&self) -> &[ParseIssue] {
+        &self.issues
+    }
+
+    /// Returns the segments of the tile.
+    pub fn get_segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Returns the connectors of the tile.
+    pub fn get_connectors(&self) -> &[Connector] {
+        &self.connectors
+    }
+
+    /// Returns the turn restrictions of the tile.
+    pub fn get_restrictions(&self) -> &[TurnRestriction] {
+        &self.restrictions
+    }
+
+    /// Deserializes a parsed tile created by [`ParsedTile::to_bytes`].
+    pub fn from_bytes(data: &[u8]) -> Result<ParsedTile, DecodingError> {
+        routing_tile::decode(data)
+    }
+}
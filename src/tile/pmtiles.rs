@@ -0,0 +1,484 @@
+//! Reading of PMTiles v3 archives in Rust, used on native targets.
+//!
+//! See <https://github.com/protomaps/PMTiles/blob/main/spec/v3/spec.md>. The
+//! archive starts with a fixed size header, followed by the root directory.
+//! Directories map Hilbert curve tile IDs to ranges of tile data or to leaf
+//! directories.
+
+use crate::compression::{gzip_decompress, DecompressionError};
+use crate::tile::backend::FetchingError;
+use crate::tile::Coord;
+use std::future::Future;
+use thiserror::Error;
+
+/// Size of the header in bytes.
+pub(crate) const HEADER_SIZE: usize = 127;
+
+/// Maximum number of directories visited to find a tile.
+pub(crate) const MAX_DIRECTORY_DEPTH: usize = 4;
+
+/// Errors while reading a PMTiles archive.
+#[derive(Error, Debug)]
+pub enum PMTilesError {
+    /// The archive could not be read.
+    #[error("Could not read archive: {0}")]
+    Io(#[from] std::io::Error),
+    /// The archive could not be fetched.
+    #[error("Could not fetch archive: {0}")]
+    Fetching(#[from] FetchingError),
+    /// The archive is not a valid PMTiles v3 archive.
+    #[error("Invalid PMTiles archive: {0}")]
+    InvalidArchive(&'static str),
+    /// The archive uses a compression other than gzip.
+    #[error("Unsupported compression {0}")]
+    UnsupportedCompression(u8),
+    /// Compressed data could not be decompressed.
+    #[error("Invalid compressed data: {0}")]
+    Decompression(#[from] DecompressionError),
+}
+
+/// Compression of directories and tiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Compression {
+    Unknown,
+    None,
+    Gzip,
+    Other(u8),
+}
+
+impl From<u8> for Compression {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Compression::Unknown,
+            1 => Compression::None,
+            2 => Compression::Gzip,
+            other => Compression::Other(other),
+        }
+    }
+}
+
+impl Compression {
+    /// Decompresses the data.
+    pub(crate) fn decompress(self, data: Vec<u8>) -> Result<Vec<u8>, PMTilesError> {
+        match self {
+            Compression::Unknown | Compression::None => Ok(data),
+            Compression::Gzip => Ok(gzip_decompress(&data)?),
+            Compression::Other(other) => Err(PMTilesError::UnsupportedCompression(other)),
+        }
+    }
+}
+
+/// The header of a PMTiles archive.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Header {
+    pub root_offset: u64,
+    pub root_length: u64,
+    pub leaf_offset: u64,
+    pub tile_data_offset: u64,
+    pub internal_compression: Compression,
+    pub tile_compression: Compression,
+    pub min_zoom: u8,
+    pub max_zoom: u8,
+    /// Bounds as [west, south, east, north].
+    pub bounds: [f64; 4],
+}
+
+impl Header {
+    /// Parses the header from the first bytes of the archive.
+    pub(crate) fn parse(data: &[u8]) -> Result<Header, PMTilesError> {
+        if data.len() < HEADER_SIZE {
+            return Err(PMTilesError::InvalidArchive("header too short"));
+        }
+        if &data[0..7] != b"PMTiles" {
+            return Err(PMTilesError::InvalidArchive("missing magic number"));
+        }
+        if data[7] != 3 {
+            return Err(PMTilesError::InvalidArchive("unsupported version"));
+        }
+        let u64_at = |offset: usize| {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(&data[offset..offset + 8]);
+            u64::from_le_bytes(bytes)
+        };
+        let degrees_at = |offset: usize| {
+            let mut bytes = [0; 4];
+            bytes.copy_from_slice(&data[offset..offset + 4]);
+            f64::from(i32::from_le_bytes(bytes)) / 10_000_000.0
+        };
+        Ok(Header {
+            root_offset: u64_at(8),
+            root_length: u64_at(16),
+            leaf_offset: u64_at(40),
+            tile_data_offset: u64_at(56),
+            internal_compression: data[97].into(),
+            tile_compression: data[98].into(),
+            min_zoom: data[100],
+            max_zoom: data[101],
+            bounds: [
+                degrees_at(102),
+                degrees_at(106),
+                degrees_at(110),
+                degrees_at(114),
+            ],
+        })
+    }
+}
+
+/// An entry of a directory.
+///
+/// Entries with a run length of 0 point to leaf directories, all others to
+/// tile data used by `run_length` consecutive tile IDs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Entry {
+    pub tile_id: u64,
+    pub offset: u64,
+    pub length: u64,
+    pub run_length: u64,
+}
+
+/// Reads a varint at the position, advancing it.
+fn read_varint(data: &[u8], position: &mut usize) -> Result<u64, PMTilesError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data
+            .get(*position)
+            .ok_or(PMTilesError::InvalidArchive("truncated directory"))?;
+        *position += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(PMTilesError::InvalidArchive("varint too long"))
+}
+
+/// Appends the value as varint.
+#[cfg_attr(not(any(test, feature = "writer")), allow(dead_code))]
+fn write_varint(data: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        data.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    data.push(value as u8);
+}
+
+/// Serializes the entries, sorted by tile ID, as uncompressed directory.
+#[cfg_attr(not(any(test, feature = "writer")), allow(dead_code))]
+pub(crate) fn serialize_directory(entries: &[Entry]) -> Vec<u8> {
+    let mut data = Vec::new();
+    write_varint(&mut data, entries.len() as u64);
+    let mut last_id = 0;
+    for entry in entries {
+        write_varint(&mut data, entry.tile_id - last_id);
+        last_id = entry.tile_id;
+    }
+    for entry in entries {
+        write_varint(&mut data, entry.run_length);
+    }
+    for entry in entries {
+        write_varint(&mut data, entry.length);
+    }
+    for (index, entry) in entries.iter().enumerate() {
+        let previous = index.checked_sub(1).map(|index| &entries[index]);
+        match previous {
+            Some(previous) if previous.offset + previous.length == entry.offset => {
+                write_varint(&mut data, 0)
+            }
+            _ => write_varint(&mut data, entry.offset + 1),
+        }
+    }
+    data
+}
+
+/// Parses a decompressed directory.
+pub(crate) fn parse_directory(data: &[u8]) -> Result<Vec<Entry>, PMTilesError> {
+    let mut position = 0;
+    let count = read_varint(data, &mut position)? as usize;
+    if count > data.len() {
+        return Err(PMTilesError::InvalidArchive("invalid directory size"));
+    }
+    let mut entries = Vec::with_capacity(count);
+    let mut tile_id = 0;
+    for _ in 0..count {
+        tile_id = add_offset(tile_id, read_varint(data, &mut position)?)?;
+        entries.push(Entry {
+            tile_id,
+            offset: 0,
+            length: 0,
+            run_length: 0,
+        });
+    }
+    for entry in entries.iter_mut() {
+        entry.run_length = read_varint(data, &mut position)?;
+    }
+    for entry in entries.iter_mut() {
+        entry.length = read_varint(data, &mut position)?;
+    }
+    for index in 0..count {
+        let offset = read_varint(data, &mut position)?;
+        entries[index].offset = if offset == 0 && index > 0 {
+            add_offset(entries[index - 1].offset, entries[index - 1].length)?
+        } else {
+            offset
+                .checked_sub(1)
+                .ok_or(PMTilesError::InvalidArchive("invalid directory offset"))?
+        };
+    }
+    Ok(entries)
+}
+
+/// Adds numbers read from an archive, like offsets and lengths, which may
+/// overflow in invalid archives.
+pub(crate) fn add_offset(value: u64, offset: u64) -> Result<u64, PMTilesError> {
+    value
+        .checked_add(offset)
+        .ok_or(PMTilesError::InvalidArchive("offset out of range"))
+}
+
+/// Returns the ID of the tile on the Hilbert curve across all zoom levels.
+pub(crate) fn tile_id(coord: &Coord) -> u64 {
+    let base = ((1u64 << (2 * u32::from(coord.z))) - 1) / 3;
+    let (mut x, mut y) = (u64::from(coord.x), u64::from(coord.y));
+    let mut d = 0;
+    let mut s = (1u64 << coord.z) / 2;
+    while s > 0 {
+        let rx = u64::from(x & s > 0);
+        let ry = u64::from(y & s > 0);
+        d += s * s * ((3 * rx) ^ ry);
+        // Only the lower bits are relevant for the next steps.
+        x &= s - 1;
+        y &= s - 1;
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    base + d
+}
+
+/// Returns the coordinates of the tile with the ID, the inverse of [`tile_id`].
+pub(crate) fn tile_coord(tile_id: u64) -> Coord {
+    let mut z = 0;
+    let mut base = 0;
+    while z < 31 && base + (1u64 << (2 * z)) <= tile_id {
+        base += 1u64 << (2 * z);
+        z += 1;
+    }
+    let mut d = tile_id - base;
+    let (mut x, mut y) = (0u64, 0u64);
+    let mut s = 1u64;
+    while s < 1u64 << z {
+        let rx = 1 & (d / 2);
+        let ry = 1 & (d ^ rx);
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - x;
+                y = s - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        x += s * rx;
+        y += s * ry;
+        d /= 4;
+        s *= 2;
+    }
+    Coord {
+        x: x as u32,
+        y: y as u32,
+        z: z as u8,
+    }
+}
+
+/// Finds the entry containing the tile or the leaf directory to look into.
+pub(crate) fn find_entry(entries: &[Entry], tile_id: u64) -> Option<&Entry> {
+    let index = entries.partition_point(|entry| entry.tile_id <= tile_id);
+    let entry = entries.get(index.checked_sub(1)?)?;
+    if entry.tile_id == tile_id
+        || entry.run_length == 0
+        || tile_id - entry.tile_id < entry.run_length
+    {
+        Some(entry)
+    } else {
+        None
+    }
+}
+
+/// Walks the directories from the root to the entry of the tile's data,
+/// `None` if the archive doesn't contain the tile.
+///
+/// Leaf directories are read with `leaf_directory`, given the offset within
+/// the leaf section and the length of the directory.
+pub(crate) async fn find_tile<F, R>(
+    root: &[Entry],
+    tile_id: u64,
+    mut leaf_directory: F,
+) -> Result<Option<Entry>, PMTilesError>
+where
+    F: FnMut(u64, u64) -> R,
+    R: Future<Output = Result<Vec<Entry>, PMTilesError>>,
+{
+    let mut leaf;
+    let mut directory = root;
+    for _ in 0..MAX_DIRECTORY_DEPTH {
+        let entry = match find_entry(directory, tile_id) {
+            Some(entry) => entry.clone(),
+            None => return Ok(None),
+        };
+        if entry.run_length > 0 {
+            return Ok(Some(entry));
+        }
+        leaf = leaf_directory(entry.offset, entry.length).await?;
+        directory = &leaf;
+    }
+    Err(PMTilesError::InvalidArchive(
+        "directories nested too deeply",
+    ))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::future::ready;
+
+    /// Serializes the entries as uncompressed directory.
+    pub(crate) fn directory(entries: &[Entry]) -> Vec<u8> {
+        serialize_directory(entries)
+    }
+
+    /// Creates an archive header with uncompressed directories and tiles.
+    pub(crate) fn header(root_length: u64, leaf_offset: u64, tile_data_offset: u64) -> Vec<u8> {
+        let mut data = vec![0; HEADER_SIZE];
+        data[0..7].copy_from_slice(b"PMTiles");
+        data[7] = 3;
+        data[8..16].copy_from_slice(&(HEADER_SIZE as u64).to_le_bytes());
+        data[16..24].copy_from_slice(&root_length.to_le_bytes());
+        data[40..48].copy_from_slice(&leaf_offset.to_le_bytes());
+        data[56..64].copy_from_slice(&tile_data_offset.to_le_bytes());
+        data[97] = 1;
+        data[98] = 1;
+        data[101] = 14;
+        data[102..106].copy_from_slice(&(-1_800_000_000i32).to_le_bytes());
+        data[114..118].copy_from_slice(&850_000_000i32.to_le_bytes());
+        data
+    }
+
+    /// Creates a directory entry.
+    pub(crate) fn entry(tile_id: u64, offset: u64, length: u64, run_length: u64) -> Entry {
+        Entry {
+            tile_id,
+            offset,
+            length,
+            run_length,
+        }
+    }
+
+    #[test]
+    fn tile_id() {
+        let id = |z, x, y| super::tile_id(&Coord { x, y, z });
+        assert_eq!(id(0, 0, 0), 0);
+        assert_eq!(id(1, 0, 0), 1);
+        assert_eq!(id(1, 0, 1), 2);
+        assert_eq!(id(1, 1, 1), 3);
+        assert_eq!(id(1, 1, 0), 4);
+        assert_eq!(id(2, 0, 0), 5);
+        assert_eq!(id(3, 7, 0), 84);
+        assert_eq!(id(12, 3423, 1763), 19078479);
+    }
+
+    #[test]
+    fn tile_coord() {
+        for (z, x, y) in [
+            (0, 0, 0),
+            (1, 1, 0),
+            (3, 7, 0),
+            (12, 3423, 1763),
+            (14, 8587, 5548),
+        ] {
+            let coord = Coord { x, y, z };
+            assert_eq!(super::tile_coord(super::tile_id(&coord)), coord);
+        }
+    }
+
+    #[test]
+    fn parse_directory() {
+        let entries = vec![
+            entry(0, 0, 10, 1),
+            entry(5, 10, 20, 2),
+            entry(9, 100, 5, 1),
+            entry(20, 0, 50, 0),
+        ];
+        assert_eq!(
+            super::parse_directory(&directory(&entries)).unwrap(),
+            entries
+        );
+        assert!(super::parse_directory(&directory(&entries)[..5]).is_err());
+        // Tile IDs and offsets beyond the range of u64.
+        for entries in [
+            vec![entry(u64::MAX, 0, 10, 1), entry(u64::MAX, 0, 10, 1)],
+            vec![entry(0, u64::MAX - 1, 10, 1), entry(1, 0, 10, 1)],
+        ] {
+            let mut data = Vec::new();
+            write_varint(&mut data, entries.len() as u64);
+            for entry in &entries {
+                write_varint(&mut data, entry.tile_id);
+            }
+            for entry in &entries {
+                write_varint(&mut data, entry.run_length);
+            }
+            for entry in &entries {
+                write_varint(&mut data, entry.length);
+            }
+            write_varint(&mut data, entries[0].offset + 1);
+            write_varint(&mut data, 0);
+            assert!(matches!(
+                super::parse_directory(&data),
+                Err(PMTilesError::InvalidArchive(_))
+            ));
+        }
+
+        assert_eq!(find_entry(&entries, 0), Some(&entries[0]));
+        assert_eq!(find_entry(&entries, 3), None);
+        assert_eq!(find_entry(&entries, 6), Some(&entries[1]));
+        assert_eq!(find_entry(&entries, 7), None);
+        assert_eq!(find_entry(&entries, 25), Some(&entries[3]));
+    }
+
+    #[test]
+    fn find_tile() {
+        let root = [entry(1, 0, 10, 0), entry(5, 0, 1, 1)];
+        let leaf = vec![entry(1, 3, 2, 2)];
+        let find = |tile_id| {
+            block_on(super::find_tile(&root, tile_id, |offset, length| {
+                assert_eq!((offset, length), (0, 10));
+                ready(Ok(leaf.clone()))
+            }))
+            .unwrap()
+        };
+        assert_eq!(find(2), Some(leaf[0].clone()));
+        assert_eq!(find(3), None);
+        assert_eq!(find(5), Some(root[1].clone()));
+        assert_eq!(find(0), None);
+
+        // A leaf directory pointing to itself.
+        let cyclic = block_on(super::find_tile(&root, 1, |_, _| {
+            ready(Ok(vec![entry(1, 0, 10, 0)]))
+        }));
+        assert!(matches!(cyclic, Err(PMTilesError::InvalidArchive(_))));
+    }
+
+    #[test]
+    fn parse_header() {
+        let header = Header::parse(&header(10, 200, 300)).unwrap();
+        assert_eq!(header.root_offset, HEADER_SIZE as u64);
+        assert_eq!(header.leaf_offset, 200);
+        assert_eq!(header.tile_compression, Compression::None);
+        assert_eq!(header.max_zoom, 14);
+        assert_eq!(header.bounds, [-180.0, 0.0, 0.0, 85.0]);
+        assert!(Header::parse(b"PMTiles").is_err());
+    }
+}
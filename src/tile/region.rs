@@ -0,0 +1,180 @@
+use super::Coord;
+use crate::geo_types::projection::{lon_lat_to_mercator, HALF_CIRCUMFERENCE};
+use crate::geo_types::Rect;
+use crate::routing::RoutingError;
+use geo::{BoundingRect, Intersects};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+/// Estimated size of an offline region download.
+pub struct RegionEstimate {
+    tiles: usize,
+    bytes: f64,
+}
+
+#[wasm_bindgen]
+impl RegionEstimate {
+    /// Returns the number of tiles to download (for all sources).
+    pub fn get_tiles(&self) -> usize {
+        self.tiles
+    }
+
+    /// Returns the estimated number of bytes to store.
+    pub fn get_bytes(&self) -> f64 {
+        self.bytes
+    }
+}
+
+impl RegionEstimate {
+    pub(crate) fn new(tiles: usize, bytes: f64) -> Self {
+        RegionEstimate { tiles, bytes }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Default)]
+/// Result of an offline region download.
+pub struct RegionDownload {
+    pub(crate) tiles: usize,
+    pub(crate) failed: usize,
+    pub(crate) bytes: f64,
+}
+
+#[wasm_bindgen]
+impl RegionDownload {
+    /// Returns the number of stored tiles.
+    pub fn get_tiles(&self) -> usize {
+        self.tiles
+    }
+
+    /// Returns the number of tiles which could not be fetched.
+    pub fn get_failed(&self) -> usize {
+        self.failed
+    }
+
+    /// Returns the number of stored bytes.
+    pub fn get_bytes(&self) -> f64 {
+        self.bytes
+    }
+}
+
+/// Returns the region covered by the bounds [west, south, east, north].
+pub(crate) fn bounds_to_region(bounds: &[f64]) -> Result<geo::Polygon<f64>, RoutingError> {
+    match bounds {
+        [west, south, east, north] if west <= east && south <= north => {
+            Ok(geo::Rect::from(Rect::new(*west, *south, *east, *north)).to_polygon())
+        }
+        _ => Err(RoutingError::InvalidRegion),
+    }
+}
+
+/// Returns the region enclosed by the ring given as [x0, y0, x1, y1, ...].
+pub(crate) fn ring_to_region(ring: &[f64]) -> Result<geo::Polygon<f64>, RoutingError> {
+    if ring.len() < 6 || !ring.len().is_multiple_of(2) {
+        return Err(RoutingError::InvalidRegion);
+    }
+    let coords: Vec<geo::Coord<f64>> = ring
+        .chunks(2)
+        .map(|xy| geo::coord! { x: xy[0], y: xy[1] })
+        .collect();
+    Ok(geo::Polygon::new(coords.into(), vec![]))
+}
+
+/// Wraps an optional JS progress function of offline downloads.
+pub(crate) fn js_progress(progress: Option<js_sys::Function>) -> impl FnMut(usize, usize, f64) {
+    move |done, total, bytes| {
+        if let Some(progress) = &progress {
+            let _ = progress.call3(
+                &JsValue::NULL,
+                &JsValue::from(done as f64),
+                &JsValue::from(total as f64),
+                &JsValue::from(bytes),
+            );
+        }
+    }
+}
+
+/// Returns the x and y index of the tile at the given zoom containing the
+/// position.
+pub(crate) fn tile_index(lon: f64, lat: f64, z: u8) -> (u32, u32) {
+    let n = 2f64.powi(i32::from(z));
+    let (x, y) = lon_lat_to_mercator(lon, lat);
+    let x = (x / HALF_CIRCUMFERENCE + 1.0) / 2.0 * n;
+    let y = (1.0 - y / HALF_CIRCUMFERENCE) / 2.0 * n;
+    let clamp = |value: f64| value.floor().clamp(0.0, n - 1.0) as u32;
+    (clamp(x), clamp(y))
+}
+
+/// Returns the bounds of the tile.
+pub(crate) fn tile_bounds(coord: &Coord) -> geo::Rect<f64> {
+    let (west, north) = coord.position_to_lon_lat(0.0, 0.0, 1.0);
+    let (east, south) = coord.position_to_lon_lat(1.0, 1.0, 1.0);
+    geo::Rect::new(
+        geo::coord! { x: west, y: south },
+        geo::coord! { x: east, y: north },
+    )
+}
+
+/// Maximum number of tiles of a region at a single zoom level.
+pub const MAX_REGION_TILES: u64 = 100_000;
+
+/// Returns the coordinates of all tiles at zoom `z` intersecting the region.
+///
+/// Fails if the bounding box of the region spans more than
+/// [`MAX_REGION_TILES`] tiles.
+pub(crate) fn tiles_covering(
+    region: &geo::Polygon<f64>,
+    z: u8,
+) -> Result<Vec<Coord>, RoutingError> {
+    let bbox = match region.bounding_rect() {
+        Some(bbox) => bbox,
+        None => return Ok(vec![]),
+    };
+    let (min_x, min_y) = tile_index(bbox.min().x, bbox.max().y, z);
+    let (max_x, max_y) = tile_index(bbox.max().x, bbox.min().y, z);
+    let tiles = u64::from(max_x - min_x + 1) * u64::from(max_y - min_y + 1);
+    if tiles > MAX_REGION_TILES {
+        return Err(RoutingError::RegionTooLarge(tiles));
+    }
+    let mut coords = Vec::new();
+    for x in min_x..=max_x {
+        for y in min_y..=max_y {
+            let coord = Coord { x, y, z };
+            if tile_bounds(&coord).intersects(region) {
+                coords.push(coord);
+            }
+        }
+    }
+    Ok(coords)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiles_covering() {
+        assert_eq!(tile_index(0.0, 0.0, 1), (1, 1));
+        assert_eq!(tile_index(-180.0, 90.0, 2), (0, 0));
+        assert_eq!(tile_index(180.0, -90.0, 2), (3, 3));
+        assert_eq!(tile_index(13.4, 52.5, 14), (8801, 5374));
+
+        let bbox = bounds_to_region(&[-10.0, -10.0, 10.0, 10.0]).unwrap();
+        assert_eq!(super::tiles_covering(&bbox, 2).unwrap().len(), 4);
+        // A triangle in the lower left half of the bbox misses the upper right tile.
+        let triangle = ring_to_region(&[-10.0, -10.0, 5.0, -10.0, -10.0, 5.0]).unwrap();
+        let coords = super::tiles_covering(&triangle, 2).unwrap();
+        assert_eq!(coords.len(), 3);
+        assert!(!coords.contains(&Coord { x: 2, y: 1, z: 2 }));
+
+        let world = bounds_to_region(&[-180.0, -85.0, 180.0, 85.0]).unwrap();
+        assert_eq!(super::tiles_covering(&world, 8).unwrap().len(), 65536);
+        assert!(matches!(
+            super::tiles_covering(&world, 9),
+            Err(RoutingError::RegionTooLarge(262_144))
+        ));
+
+        assert!(bounds_to_region(&[10.0, 0.0, 0.0, 10.0]).is_err());
+        assert!(ring_to_region(&[0.0, 0.0, 1.0]).is_err());
+    }
+}
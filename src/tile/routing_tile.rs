@@ -0,0 +1,426 @@
+//! Compact binary format for routing tiles.
+//!
+//! A routing tile contains the connectors (nodes) and segments (edges) of a
+//! tile in a form which can be read much faster than a Mapbox Vector Tile.
+//! All numbers are little endian, counts and coordinates are stored as
+//! (zigzag encoded) varints:
+//!
+//! ```text
+//! magic "IBRT", version (u8), flags (u8), reserved (2 bytes)
+//! string count, strings (byte length + UTF-8)
+//! node count, nodes (id string index, longitude, latitude, tags)
+//! edge count, edges (id string index, direction flags (u8), length (f32),
+//!     coordinate count, coordinates (delta encoded longitude, latitude),
+//!     connector count, connector id string indices, tags)
+//! restriction count, restrictions (from, via and to id string indices,
+//!     only (u8))
+//! ```
+//!
+//! Tags are stored as count followed by key and value string indices, ordered
+//! by key. Version 1 tiles have no tags and version 2 tiles no restrictions,
+//! both are still read. The flags of an edge are 1 if it can only be
+//! travelled forward and 2 if only backward.
+//!
+//! Coordinates are stored as fixed point numbers with a precision of 1e-7
+//! degrees.
+
+use crate::geo_types::{LineString, Point};
+use crate::routing::{Connector, Direction, Segment, TurnRestriction};
+use crate::tile::ParsedTile;
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+use thiserror::Error;
+
+const MAGIC: &[u8; 4] = b"IBRT";
+
+/// Version of the format written by [`encode`].
+pub const VERSION: u8 = 3;
+
+/// Factor to convert degrees into fixed point coordinates.
+const PRECISION: f64 = 1e7;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum DecodingError {
+    #[error("Not a routing tile")]
+    InvalidMagic,
+    #[error("Unsupported routing tile version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Unexpected end of data")]
+    UnexpectedEnd,
+    #[error("Invalid UTF-8 string")]
+    InvalidString,
+    #[error("Invalid string reference {0}")]
+    InvalidReference(u64),
+    #[error("Coordinate out of range")]
+    InvalidCoordinate,
+}
+
+/// Encodes the parsed tile as routing tile.
+pub fn encode(tile: &ParsedTile) -> Vec<u8> {
+    let mut strings = StringTable::default();
+    for connector in tile.get_connectors() {
+        strings.intern(&connector.get_id());
+        strings.intern_tags(connector.tags());
+    }
+    for segment in tile.get_segments() {
+        strings.intern(&segment.get_id());
+        for connector_id in segment.get_connectors() {
+            strings.intern(connector_id);
+        }
+        strings.intern_tags(segment.tags());
+    }
+    for restriction in tile.get_restrictions() {
+        strings.intern(restriction.via());
+        strings.intern(restriction.from());
+        strings.intern(restriction.to());
+    }
+
+    let mut writer = Writer::default();
+    writer.buffer.extend_from_slice(MAGIC);
+    writer.buffer.extend_from_slice(&[VERSION, 0, 0, 0]);
+    writer.write_varint(strings.strings.len() as u64);
+    for string in &strings.strings {
+        writer.write_varint(string.len() as u64);
+        writer.buffer.extend_from_slice(string.as_bytes());
+    }
+    writer.write_varint(tile.get_connectors().len() as u64);
+    for connector in tile.get_connectors() {
+        let point = connector.get_point();
+        writer.write_varint(strings.get(&connector.get_id()));
+        writer.write_signed(to_fixed(point.x()));
+        writer.write_signed(to_fixed(point.y()));
+        writer.write_tags(&strings, connector.tags());
+    }
+    writer.write_varint(tile.get_segments().len() as u64);
+    for segment in tile.get_segments() {
+        writer.write_varint(strings.get(&segment.get_id()));
+        writer.buffer.push(match segment.get_direction() {
+            Direction::Both => 0,
+            Direction::Forward => 1,
+            Direction::Backward => 2,
+        });
+        writer
+            .buffer
+            .extend_from_slice(&(segment.get_length() as f32).to_le_bytes());
+        let geometry: geo::LineString<f64> = segment.get_geometry().into();
+        writer.write_varint(geometry.0.len() as u64);
+        let (mut last_x, mut last_y) = (0, 0);
+        for coord in geometry {
+            let (x, y) = (to_fixed(coord.x), to_fixed(coord.y));
+            writer.write_signed(x - last_x);
+            writer.write_signed(y - last_y);
+            last_x = x;
+            last_y = y;
+        }
+        writer.write_varint(segment.get_connectors().len() as u64);
+        for connector_id in segment.get_connectors() {
+            writer.write_varint(strings.get(connector_id));
+        }
+        writer.write_tags(&strings, segment.tags());
+    }
+    writer.write_varint(tile.get_restrictions().len() as u64);
+    for restriction in tile.get_restrictions() {
+        writer.write_varint(strings.get(restriction.from()));
+        writer.write_varint(strings.get(restriction.via()));
+        writer.write_varint(strings.get(restriction.to()));
+        writer.buffer.push(u8::from(restriction.is_only()));
+    }
+    writer.buffer
+}
+
+/// Decodes a routing tile created by [`encode`].
+pub fn decode(data: &[u8]) -> Result<ParsedTile, DecodingError> {
+    let mut reader = Reader { data, offset: 0 };
+    if reader.read_bytes(4)? != MAGIC {
+        return Err(DecodingError::InvalidMagic);
+    }
+    let header = reader.read_bytes(4)?;
+    let version = header[0];
+    if version == 0 || version > VERSION {
+        return Err(DecodingError::UnsupportedVersion(version));
+    }
+    let mut strings = Vec::new();
+    for _ in 0..reader.read_varint()? {
+        let len = reader.read_len()?;
+        let bytes = reader.read_bytes(len)?;
+        strings.push(String::from_utf8(bytes.to_vec()).map_err(|_| DecodingError::InvalidString)?);
+    }
+    let string = |index: u64| {
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| strings.get(index))
+            .cloned()
+            .ok_or(DecodingError::InvalidReference(index))
+    };
+    let read_tags = |reader: &mut Reader| -> Result<Vec<(String, String)>, DecodingError> {
+        if version < 2 {
+            return Ok(Vec::new());
+        }
+        (0..reader.read_varint()?)
+            .map(|_| {
+                Ok((
+                    string(reader.read_varint()?)?,
+                    string(reader.read_varint()?)?,
+                ))
+            })
+            .collect()
+    };
+    let mut tile = ParsedTile::new();
+    for _ in 0..reader.read_varint()? {
+        let id = string(reader.read_varint()?)?;
+        let x = from_fixed(reader.read_signed()?);
+        let y = from_fixed(reader.read_signed()?);
+        let mut connector = Connector::new(&id, &Point::new(x, y));
+        for (key, value) in read_tags(&mut reader)? {
+            connector.set_tag(&key, &value);
+        }
+        tile.push_connector(connector);
+    }
+    for _ in 0..reader.read_varint()? {
+        let id = string(reader.read_varint()?)?;
+        let flags = reader.read_bytes(1)?[0];
+        let length = f32::from_le_bytes(reader.read_bytes(4)?.try_into().unwrap());
+        let mut coords = Vec::new();
+        let (mut x, mut y) = (0, 0);
+        for _ in 0..reader.read_varint()? {
+            x = add_delta(x, reader.read_signed()?)?;
+            y = add_delta(y, reader.read_signed()?)?;
+            coords.push(geo::Coord {
+                x: from_fixed(x),
+                y: from_fixed(y),
+            });
+        }
+        let mut connectors = Vec::new();
+        for _ in 0..reader.read_varint()? {
+            connectors.push(string(reader.read_varint()?)?);
+        }
+        let geometry = LineString::from(geo::LineString::new(coords));
+        let mut segment = Segment::with_length(id, geometry, connectors, f64::from(length));
+        for (key, value) in read_tags(&mut reader)? {
+            segment.set_tag(&key, &value);
+        }
+        segment.set_direction(match flags {
+            1 => Direction::Forward,
+            2 => Direction::Backward,
+            _ => Direction::Both,
+        });
+        tile.push_segment(segment);
+    }
+    if version >= 3 {
+        for _ in 0..reader.read_varint()? {
+            let from = string(reader.read_varint()?)?;
+            let via = string(reader.read_varint()?)?;
+            let to = string(reader.read_varint()?)?;
+            tile.push_restriction(match reader.read_bytes(1)?[0] {
+                0 => TurnRestriction::new(from, via, to),
+                _ => TurnRestriction::only(from, via, to),
+            });
+        }
+    }
+    Ok(tile)
+}
+
+/// Adds the delta of a coordinate, which may overflow in invalid tiles.
+fn add_delta(value: i64, delta: i64) -> Result<i64, DecodingError> {
+    value
+        .checked_add(delta)
+        .ok_or(DecodingError::InvalidCoordinate)
+}
+
+fn to_fixed(value: f64) -> i64 {
+    (value * PRECISION).round() as i64
+}
+
+fn from_fixed(value: i64) -> f64 {
+    value as f64 / PRECISION
+}
+
+/// Table of strings which are referenced by index.
+#[derive(Default)]
+struct StringTable {
+    strings: Vec<String>,
+    indices: HashMap<String, u64>,
+}
+
+impl StringTable {
+    fn intern(&mut self, string: &str) {
+        if !self.indices.contains_key(string) {
+            self.indices
+                .insert(string.to_string(), self.strings.len() as u64);
+            self.strings.push(string.to_string());
+        }
+    }
+
+    fn intern_tags(&mut self, tags: &HashMap<String, String>) {
+        for (key, value) in tags {
+            self.intern(key);
+            self.intern(value);
+        }
+    }
+
+    fn get(&self, string: &str) -> u64 {
+        self.indices[string]
+    }
+}
+
+#[derive(Default)]
+struct Writer {
+    buffer: Vec<u8>,
+}
+
+impl Writer {
+    fn write_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buffer.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.buffer.push(value as u8);
+    }
+
+    fn write_signed(&mut self, value: i64) {
+        self.write_varint(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    /// Writes the tags ordered by key, so equal tiles are encoded equally.
+    fn write_tags(&mut self, strings: &StringTable, tags: &HashMap<String, String>) {
+        let mut tags: Vec<_> = tags.iter().collect();
+        tags.sort();
+        self.write_varint(tags.len() as u64);
+        for (key, value) in tags {
+            self.write_varint(strings.get(key));
+            self.write_varint(strings.get(value));
+        }
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], DecodingError> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset.saturating_add(len))
+            .ok_or(DecodingError::UnexpectedEnd)?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn read_varint(&mut self) -> Result<u64, DecodingError> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.read_bytes(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(DecodingError::UnexpectedEnd)
+    }
+
+    fn read_len(&mut self) -> Result<usize, DecodingError> {
+        usize::try_from(self.read_varint()?).or(Err(DecodingError::UnexpectedEnd))
+    }
+
+    fn read_signed(&mut self) -> Result<i64, DecodingError> {
+        let value = self.read_varint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo_types::coord::{coord, Coord};
+
+    #[test]
+    fn roundtrip() {
+        let mut tile = ParsedTile::new();
+        let mut connector = Connector::new("a", &Point::new(8.6821267, 50.1109221));
+        connector.set_tag("highway", "traffic_signals");
+        tile.push_connector(connector);
+        let mut segment = Segment::new(
+            "1".into(),
+            LineString::new(vec![
+                coord!(x: 8.6821267, y: 50.1109221),
+                coord!(x: -3.5, y: -4.25),
+            ]),
+            vec!["a".into(), "b".into()],
+        );
+        segment.set_tag("highway", "cycleway");
+        segment.set_tag("oneway", "yes");
+        // The direction is stored apart from the tags.
+        segment.set_direction(Direction::Backward);
+        tile.push_segment(segment);
+        let restriction = TurnRestriction::only("1".into(), "a".into(), "2".into());
+        tile.push_restriction(restriction.clone());
+        let decoded = decode(&encode(&tile)).unwrap();
+        assert_eq!(decoded.connectors_len(), 1);
+        assert_eq!(decoded.segments_len(), 1);
+        let connector = &decoded.get_connectors()[0];
+        assert_eq!(connector.get_id(), "a");
+        assert_eq!(connector.get_point().x(), 8.6821267);
+        assert_eq!(connector.get_point().y(), 50.1109221);
+        assert_eq!(connector.tag("highway"), Some("traffic_signals"));
+        let segment = &decoded.get_segments()[0];
+        assert_eq!(segment.get_id(), "1");
+        assert_eq!(segment.get_connectors(), &vec!["a", "b"]);
+        assert_eq!(segment.tags(), tile.get_segments()[0].tags());
+        assert_eq!(segment.get_direction(), Direction::Backward);
+        assert_eq!(decoded.get_restrictions(), &[restriction]);
+        assert!((segment.get_length() - tile.get_segments()[0].get_length()).abs() < 1e-4);
+        let geometry: geo::LineString<f64> = segment.get_geometry().into();
+        assert_eq!(geometry.0[0], coord!(x: 8.6821267, y: 50.1109221).into());
+        assert_eq!(geometry.0[1], coord!(x: -3.5, y: -4.25).into());
+    }
+
+    #[test]
+    fn decoding_errors() {
+        let mut tile = ParsedTile::new();
+        tile.push_connector(Connector::new("a", &Point::new(1.0, 2.0)));
+        let bytes = encode(&tile);
+        assert_eq!(
+            decode(&bytes[..bytes.len() - 1]).err(),
+            Some(DecodingError::UnexpectedEnd)
+        );
+        assert_eq!(decode(b"MVT?").err(), Some(DecodingError::InvalidMagic));
+        let mut future = bytes.clone();
+        future[4] = VERSION + 1;
+        assert_eq!(
+            decode(&future).err(),
+            Some(DecodingError::UnsupportedVersion(VERSION + 1))
+        );
+
+        // Coordinate deltas overflowing the previous coordinate.
+        let mut writer = Writer::default();
+        writer.buffer.extend_from_slice(MAGIC);
+        writer.buffer.extend_from_slice(&[VERSION, 0, 0, 0]);
+        for value in [1, 1, u64::from(b'a'), 0, 1, 0] {
+            writer.write_varint(value);
+        }
+        writer.buffer.extend_from_slice(&[0, 0, 0, 0, 0]);
+        writer.write_varint(2);
+        for delta in [i64::MAX, 0, 1, 0] {
+            writer.write_signed(delta);
+        }
+        assert_eq!(
+            decode(&writer.buffer).err(),
+            Some(DecodingError::InvalidCoordinate)
+        );
+    }
+
+    #[test]
+    fn decode_version_1() {
+        let bytes = [
+            b'I', b'B', b'R', b'T', 1, 0, 0, 0, // header
+            1, 1, b'a', // strings
+            1, 0, 2, 4, // node "a" at 1e-7, 2e-7
+            0, // edges
+        ];
+        let tile = decode(&bytes).unwrap();
+        assert_eq!(tile.get_connectors()[0].get_id(), "a");
+        assert!(tile.get_connectors()[0].tags().is_empty());
+    }
+}
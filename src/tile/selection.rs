@@ -0,0 +1,131 @@
+//! Strategies choosing the tiles loaded for a route search, see
+//! `RouteOptions.set_tile_selection`.
+
+use super::{point_to_tile_coord, Coord};
+use crate::geo_types::Point;
+use crate::routing::TileSelection;
+use std::collections::HashSet;
+
+/// Number of times the `Expanding` strategy grows its radius.
+const EXPANSIONS: u32 = 3;
+
+/// Chooses the tiles at the routing zoom to load for a route search.
+pub(crate) trait TileSelector {
+    /// Returns the tiles for the given attempt of a search from start to
+    /// stop, `None` if there is no further attempt. The first attempt is 0,
+    /// later attempts are made if the previous one found no route.
+    fn select(&self, start: &Point, stop: &Point, zoom: u8, attempt: u32) -> Option<Vec<Coord>>;
+}
+
+/// The tiles within a radius of the start tile.
+struct Around {
+    radius: u32,
+}
+
+impl TileSelector for Around {
+    fn select(&self, start: &Point, _stop: &Point, zoom: u8, attempt: u32) -> Option<Vec<Coord>> {
+        (attempt == 0).then(|| point_to_tile_coord(start, zoom).with_radius(self.radius))
+    }
+}
+
+/// The tiles within a radius of the straight line from start to stop.
+struct Corridor {
+    radius: u32,
+}
+
+impl TileSelector for Corridor {
+    fn select(&self, start: &Point, stop: &Point, zoom: u8, attempt: u32) -> Option<Vec<Coord>> {
+        if attempt > 0 {
+            return None;
+        }
+        let (first, last) = (
+            point_to_tile_coord(start, zoom),
+            point_to_tile_coord(stop, zoom),
+        );
+        // One sample per tile crossed, so no tile on the line is skipped.
+        let steps = first
+            .x
+            .abs_diff(last.x)
+            .max(first.y.abs_diff(last.y))
+            .max(1);
+        let samples = (0..=steps).map(|step| {
+            let fraction = f64::from(step) / f64::from(steps);
+            let point = Point::new(
+                start.x() + (stop.x() - start.x()) * fraction,
+                start.y() + (stop.y() - start.y()) * fraction,
+            );
+            point_to_tile_coord(&point, zoom)
+        });
+        Some(union(samples.map(|coord| coord.with_radius(self.radius))))
+    }
+}
+
+/// The tiles around start and stop, growing by one tile per attempt until a
+/// route is found.
+struct Expanding {
+    radius: u32,
+}
+
+impl TileSelector for Expanding {
+    fn select(&self, start: &Point, stop: &Point, zoom: u8, attempt: u32) -> Option<Vec<Coord>> {
+        if attempt > EXPANSIONS {
+            return None;
+        }
+        let radius = self.radius + attempt;
+        Some(union([start, stop].iter().map(|point| {
+            point_to_tile_coord(point, zoom).with_radius(radius)
+        })))
+    }
+}
+
+/// Returns the tiles of all lists in order, each only once.
+fn union(lists: impl Iterator<Item = Vec<Coord>>) -> Vec<Coord> {
+    let mut seen = HashSet::new();
+    lists
+        .flatten()
+        .filter(|coord| seen.insert(coord.clone()))
+        .collect()
+}
+
+/// Returns the selector of the strategy with the radius in tiles.
+pub(crate) fn selector(selection: TileSelection, radius: u32) -> Box<dyn TileSelector> {
+    match selection {
+        TileSelection::Around => Box::new(Around { radius }),
+        TileSelection::Corridor => Box::new(Corridor { radius }),
+        TileSelection::Expanding => Box::new(Expanding { radius }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select() {
+        let (start, stop) = (Point::new(0.1, 0.1), Point::new(3.0, 0.1));
+        let around = selector(TileSelection::Around, 1);
+        assert_eq!(around.select(&start, &stop, 8, 0).unwrap().len(), 9);
+        assert!(around.select(&start, &stop, 8, 1).is_none());
+
+        // Start and stop are two tiles apart.
+        let corridor = selector(TileSelection::Corridor, 0);
+        let tiles = corridor.select(&start, &stop, 8, 0).unwrap();
+        assert_eq!(tiles.len(), 3);
+        assert!(tiles.iter().all(|coord| coord.y == tiles[0].y));
+        assert_eq!(tiles[2].x - tiles[0].x, 2);
+        assert_eq!(
+            selector(TileSelection::Corridor, 1)
+                .select(&start, &stop, 8, 0)
+                .unwrap()
+                .len(),
+            15
+        );
+
+        let expanding = selector(TileSelection::Expanding, 0);
+        assert_eq!(expanding.select(&start, &stop, 8, 0).unwrap().len(), 2);
+        // The areas around start and stop overlap from radius 1.
+        assert_eq!(expanding.select(&start, &stop, 8, 1).unwrap().len(), 15);
+        assert_eq!(expanding.select(&start, &stop, 8, 3).unwrap().len(), 63);
+        assert!(expanding.select(&start, &stop, 8, 4).is_none());
+    }
+}
@@ -0,0 +1,118 @@
+use crate::debug::warn_log;
+use thiserror::Error;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{Cache, CacheStorage, Response};
+
+/// Prefix of the request URLs used as keys in the browser cache.
+const CACHE_URL_PREFIX: &str = "/ibre/tiles/";
+
+#[derive(Error, Debug)]
+pub(crate) enum StoreError {
+    #[error("Tile store is not available")]
+    Unavailable,
+    #[error("Could not access tile store")]
+    AccessFailed,
+}
+
+/// Persistent storage of encoded routing tiles, used for offline routing.
+pub enum TileStore {
+    /// Keeps the tiles in memory for testing.
+    #[cfg(test)]
+    Memory(std::cell::RefCell<std::collections::HashMap<String, Vec<u8>>>),
+    /// Stores the tiles in the browser's Cache API under the given name.
+    BrowserCache(String),
+}
+
+impl TileStore {
+    /// Creates a store keeping the tiles in memory.
+    #[cfg(test)]
+    pub fn memory() -> Self {
+        TileStore::Memory(Default::default())
+    }
+
+    /// Creates a store persisting the tiles in the browser cache of this name.
+    pub fn browser_cache(name: &str) -> Self {
+        TileStore::BrowserCache(name.into())
+    }
+
+    /// Returns the data stored under the key.
+    pub(crate) async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StoreError> {
+        match self {
+            #[cfg(test)]
+            TileStore::Memory(entries) => Ok(entries.borrow().get(key).cloned()),
+            TileStore::BrowserCache(name) => {
+                let cache = open_cache(name).await?;
+                let url = format!("{}{}", CACHE_URL_PREFIX, key);
+                let response = resolve(cache.match_with_str(&url)).await?;
+                if response.is_undefined() {
+                    return Ok(None);
+                }
+                let response: Response = response.dyn_into().or(Err(StoreError::AccessFailed))?;
+                let buffer =
+                    resolve(response.array_buffer().or(Err(StoreError::AccessFailed))?).await?;
+                Ok(Some(js_sys::Uint8Array::new(&buffer).to_vec()))
+            }
+        }
+    }
+
+    /// Stores the data under the key, replacing existing data.
+    pub(crate) async fn put(&self, key: &str, mut data: Vec<u8>) -> Result<(), StoreError> {
+        match self {
+            #[cfg(test)]
+            TileStore::Memory(entries) => {
+                entries.borrow_mut().insert(key.into(), data);
+                Ok(())
+            }
+            TileStore::BrowserCache(name) => {
+                let cache = open_cache(name).await?;
+                let url = format!("{}{}", CACHE_URL_PREFIX, key);
+                let response = Response::new_with_opt_u8_array(Some(&mut data))
+                    .or(Err(StoreError::AccessFailed))?;
+                resolve(cache.put_with_str(&url, &response)).await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Removes all data from the store.
+    pub(crate) async fn clear(&self) -> Result<(), StoreError> {
+        match self {
+            #[cfg(test)]
+            TileStore::Memory(entries) => {
+                entries.borrow_mut().clear();
+                Ok(())
+            }
+            TileStore::BrowserCache(name) => {
+                resolve(cache_storage()?.delete(name)).await?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Returns the global Cache API, which is missing e.g. in Node.js.
+fn cache_storage() -> Result<CacheStorage, StoreError> {
+    let caches = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("caches"))
+        .or(Err(StoreError::Unavailable))?;
+    if caches.is_object() {
+        Ok(caches.unchecked_into())
+    } else {
+        Err(StoreError::Unavailable)
+    }
+}
+
+async fn open_cache(name: &str) -> Result<Cache, StoreError> {
+    resolve(cache_storage()?.open(name))
+        .await?
+        .dyn_into()
+        .or(Err(StoreError::AccessFailed))
+}
+
+async fn resolve(promise: js_sys::Promise) -> Result<JsValue, StoreError> {
+    wasm_bindgen_futures::JsFuture::from(promise)
+        .await
+        .map_err(|err| {
+            warn_log!("Tile store error: {:?}", err);
+            StoreError::AccessFailed
+        })
+}
@@ -0,0 +1,491 @@
+//! Writing of routing networks as PMTiles archives of Mapbox Vector Tiles
+//! and of routing tiles.
+//!
+//! The input is a GeoJSON FeatureCollection of the network. Point features
+//! are connectors with an `id` property, LineString features are segments
+//! with an `id` and a `connector_ids` property listing the IDs of the
+//! connectors along the segment. Other properties of both are kept as tags,
+//! e.g. for profiles. The tiles contain the `connectors` and
+//! `segments` layers as read by the MVT backends.
+//!
+//! Every connector is written to the tile containing it. Segments are written
+//! unclipped to every tile their bounding box touches, so that each tile
+//! holds complete segments.
+//!
+//! Existing archives can be preprocessed into routing tiles, see
+//! [`write_routing_tiles`], which are faster to load than vector tiles.
+
+use super::backend::mvt_tile::parse_mvt_buffer;
+use super::backend::pmtiles_file_backend::PMTilesFileBackend;
+use super::pmtiles::{serialize_directory, tile_id, Entry, PMTilesError, HEADER_SIZE};
+use super::region::tile_index;
+use super::{routing_tile, Coord};
+use crate::geo_types::geojson::{GeoJsonError, Geometry};
+use crate::geo_types::projection::{lon_lat_to_mercator, HALF_CIRCUMFERENCE};
+use crate::routing::{Connector, Segment};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+use thiserror::Error;
+
+/// Extent of the MVT tile coordinates.
+const EXTENT: u32 = 4096;
+
+/// Maximum size of the root directory, so that header and root directory fit
+/// into the first 16 KiB of the archive as recommended by the specification.
+const MAX_ROOT_SIZE: usize = 16_384 - HEADER_SIZE;
+
+/// Errors while writing a network.
+#[derive(Error, Debug)]
+pub enum WriterError {
+    /// The input is not a valid GeoJSON FeatureCollection.
+    #[error("Invalid GeoJSON: {0}")]
+    InvalidGeoJson(#[from] GeoJsonError),
+    /// A feature lacks its ID or connector IDs.
+    #[error("Invalid feature {index}: {context}")]
+    InvalidFeature {
+        /// Index of the feature in the collection.
+        index: usize,
+        /// What is wrong with the feature.
+        context: &'static str,
+    },
+    /// The zoom level is beyond the zoom levels of PMTiles.
+    #[error("Unsupported zoom level {0}")]
+    UnsupportedZoom(u8),
+    /// The source archive could not be read.
+    #[error("Could not read archive: {0}")]
+    Archive(#[from] PMTilesError),
+    /// A tile of the source archive is no valid vector tile.
+    #[error("Invalid tile {0:?}")]
+    InvalidTile(Coord),
+    /// A tile could not be encoded.
+    #[error("Could not encode tile: {0}")]
+    Mvt(#[from] mvt::Error),
+    /// The archive could not be written.
+    #[error("Could not write archive: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Connectors and segments of a single tile.
+#[derive(Default)]
+struct TileContent<'a> {
+    connectors: Vec<&'a Connector>,
+    segments: Vec<&'a Segment>,
+}
+
+/// Writes the GeoJSON network as PMTiles archive with tiles at the zoom level.
+///
+/// Returns the number of written tiles.
+pub fn write_pmtiles<W: Write>(geojson: &str, zoom: u8, out: &mut W) -> Result<usize, WriterError> {
+    // Tile IDs of higher zoom levels overflow.
+    if zoom > 26 {
+        return Err(WriterError::UnsupportedZoom(zoom));
+    }
+    let (connectors, segments) = parse_network(geojson)?;
+    let mut tiles: BTreeMap<u64, (Coord, TileContent)> = BTreeMap::new();
+    for connector in &connectors {
+        let point = connector.get_point();
+        let (x, y) = tile_index(point.x(), point.y(), zoom);
+        tile(&mut tiles, Coord { x, y, z: zoom })
+            .connectors
+            .push(connector);
+    }
+    let mut bounds: Option<geo::Rect<f64>> = None;
+    for segment in &segments {
+        let bbox = match segment.get_geometry().bbox() {
+            Some(bbox) => geo::Rect::from(bbox),
+            None => continue,
+        };
+        let (min_x, min_y) = tile_index(bbox.min().x, bbox.max().y, zoom);
+        let (max_x, max_y) = tile_index(bbox.max().x, bbox.min().y, zoom);
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                tile(&mut tiles, Coord { x, y, z: zoom })
+                    .segments
+                    .push(segment);
+            }
+        }
+        bounds = Some(match bounds {
+            Some(bounds) => geo::Rect::new(
+                geo::coord! {
+                    x: bounds.min().x.min(bbox.min().x),
+                    y: bounds.min().y.min(bbox.min().y),
+                },
+                geo::coord! {
+                    x: bounds.max().x.max(bbox.max().x),
+                    y: bounds.max().y.max(bbox.max().y),
+                },
+            ),
+            None => bbox,
+        });
+    }
+
+    let mut entries = Vec::with_capacity(tiles.len());
+    let mut tile_data = Vec::new();
+    for (id, (coord, content)) in &tiles {
+        let data = encode_tile(coord, content)?;
+        entries.push(Entry {
+            tile_id: *id,
+            offset: tile_data.len() as u64,
+            length: data.len() as u64,
+            run_length: 1,
+        });
+        tile_data.extend(data);
+    }
+    let (root, leaves) = directories(&entries);
+    let metadata = serde_json::json!({
+        "vector_layers": [
+            {"id": "connectors", "fields": {"id": "String"}},
+            {"id": "segments", "fields": {"id": "String", "connector_ids": "String"}},
+        ],
+    })
+    .to_string();
+
+    let mut header = vec![0; HEADER_SIZE];
+    header[0..7].copy_from_slice(b"PMTiles");
+    header[7] = 3;
+    let mut offset = HEADER_SIZE as u64;
+    for (position, length) in [
+        (8, root.len()),
+        (24, metadata.len()),
+        (40, leaves.len()),
+        (56, tile_data.len()),
+    ] {
+        header[position..position + 8].copy_from_slice(&offset.to_le_bytes());
+        header[position + 8..position + 16].copy_from_slice(&(length as u64).to_le_bytes());
+        offset += length as u64;
+    }
+    for position in [72, 80, 88] {
+        header[position..position + 8].copy_from_slice(&(entries.len() as u64).to_le_bytes());
+    }
+    // Tiles are clustered, uncompressed Mapbox Vector Tiles.
+    header[96] = 1;
+    header[97] = 1;
+    header[98] = 1;
+    header[99] = 1;
+    header[100] = zoom;
+    header[101] = zoom;
+    let bounds = bounds.map_or([0.0; 4], |bounds| {
+        [
+            bounds.min().x,
+            bounds.min().y,
+            bounds.max().x,
+            bounds.max().y,
+        ]
+    });
+    let center = [(bounds[0] + bounds[2]) / 2.0, (bounds[1] + bounds[3]) / 2.0];
+    for (position, degrees) in [102, 106, 110, 114, 119, 123]
+        .iter()
+        .zip(bounds.iter().chain(center.iter()))
+    {
+        let value = (degrees * 10_000_000.0).round() as i32;
+        header[*position..position + 4].copy_from_slice(&value.to_le_bytes());
+    }
+    header[118] = zoom;
+
+    out.write_all(&header)?;
+    out.write_all(&root)?;
+    out.write_all(metadata.as_bytes())?;
+    out.write_all(&leaves)?;
+    out.write_all(&tile_data)?;
+    Ok(entries.len())
+}
+
+/// Converts all vector tiles of the archive into routing tiles.
+///
+/// The tiles are written to `{z}/{x}/{y}.ibrt` within the directory, to be
+/// served for the `RoutingTileRouter`. Invalid features are skipped with a
+/// warning. Returns the number of written tiles.
+pub fn write_routing_tiles<R: Read + Seek>(
+    archive: &PMTilesFileBackend<R>,
+    directory: &Path,
+) -> Result<usize, WriterError> {
+    let coords = archive.tile_coords()?;
+    for coord in &coords {
+        let data = archive
+            .read_tile(coord)?
+            .ok_or_else(|| WriterError::InvalidTile(coord.clone()))?;
+        let tile = parse_mvt_buffer(&data, coord, false)
+            .map_err(|_| WriterError::InvalidTile(coord.clone()))?;
+        let path = directory
+            .join(coord.z.to_string())
+            .join(coord.x.to_string());
+        fs::create_dir_all(&path)?;
+        fs::write(
+            path.join(format!("{}.ibrt", coord.y)),
+            routing_tile::encode(&tile),
+        )?;
+    }
+    Ok(coords.len())
+}
+
+/// Returns the content of the tile, adding the tile if it is missing.
+fn tile<'a, 'b>(
+    tiles: &'b mut BTreeMap<u64, (Coord, TileContent<'a>)>,
+    coord: Coord,
+) -> &'b mut TileContent<'a> {
+    &mut tiles
+        .entry(tile_id(&coord))
+        .or_insert_with(|| (coord, TileContent::default()))
+        .1
+}
+
+/// Parses the connectors and segments of the GeoJSON FeatureCollection.
+pub(crate) fn parse_network(geojson: &str) -> Result<(Vec<Connector>, Vec<Segment>), WriterError> {
+    let value: Value = serde_json::from_str(geojson).or(Err(GeoJsonError::InvalidJson))?;
+    let features = value
+        .get("features")
+        .and_then(Value::as_array)
+        .ok_or(GeoJsonError::InvalidGeometry("missing features"))?;
+    let mut connectors = Vec::new();
+    let mut segments = Vec::new();
+    for (index, feature) in features.iter().enumerate() {
+        let invalid = |context| WriterError::InvalidFeature { index, context };
+        let properties = feature.get("properties");
+        let id = match properties.and_then(|properties| properties.get("id")) {
+            Some(Value::String(id)) => id.clone(),
+            Some(Value::Number(id)) => id.to_string(),
+            _ => return Err(invalid("missing id")),
+        };
+        match Geometry::from_value(feature)? {
+            Geometry::Point(point) => {
+                let mut connector = Connector::new(&id, &point);
+                connector.set_properties_as_tags(properties);
+                connectors.push(connector);
+            }
+            Geometry::LineString(line) => {
+                let connector_ids = properties
+                    .and_then(|properties| properties.get("connector_ids"))
+                    .and_then(Value::as_array)
+                    .and_then(|ids| {
+                        ids.iter()
+                            .map(|id| id.as_str().map(String::from))
+                            .collect::<Option<Vec<_>>>()
+                    })
+                    .ok_or_else(|| invalid("missing or invalid connector_ids"))?;
+                let mut segment = Segment::new(id, line, connector_ids);
+                segment.set_properties_as_tags(properties);
+                segments.push(segment);
+            }
+        }
+    }
+    Ok((connectors, segments))
+}
+
+/// Returns the position of the longitude and latitude within the tile.
+///
+/// This is the inverse of [`Coord::position_to_lon_lat`].
+fn lon_lat_to_position(coord: &Coord, lon: f64, lat: f64) -> (f64, f64) {
+    let tiles = 2f64.powi(i32::from(coord.z));
+    let (x, y) = lon_lat_to_mercator(lon, lat);
+    let x = (x / HALF_CIRCUMFERENCE + 1.0) / 2.0 * tiles;
+    let y = (1.0 - y / HALF_CIRCUMFERENCE) / 2.0 * tiles;
+    let extent = f64::from(EXTENT);
+    (
+        (x - f64::from(coord.x)) * extent,
+        (y - f64::from(coord.y)) * extent,
+    )
+}
+
+/// Encodes the connectors and segments of the tile as MVT.
+fn encode_tile(coord: &Coord, content: &TileContent) -> Result<Vec<u8>, WriterError> {
+    let mut tile = mvt::Tile::new(EXTENT);
+    let mut layer = tile.create_layer("connectors");
+    for (index, connector) in content.connectors.iter().enumerate() {
+        let point = connector.get_point();
+        let (x, y) = lon_lat_to_position(coord, point.x(), point.y());
+        let geometry = mvt::GeomEncoder::new(mvt::GeomType::Point)
+            .point(x, y)?
+            .encode()?;
+        let mut feature = layer.into_feature(geometry);
+        feature.set_id(index as u64 + 1);
+        feature.add_tag_string("id", &connector.get_id());
+        let mut tags: Vec<_> = connector.tags().iter().collect();
+        tags.sort();
+        for (key, value) in tags {
+            feature.add_tag_string(key, value);
+        }
+        layer = feature.into_layer();
+    }
+    tile.add_layer(layer)?;
+    let mut layer = tile.create_layer("segments");
+    for (index, segment) in content.segments.iter().enumerate() {
+        let line = segment.get_geometry();
+        let mut encoder = mvt::GeomEncoder::new(mvt::GeomType::Linestring);
+        for index in 0..line.coordinate_count() {
+            let position = line.get_coord(index).expect("index within line string");
+            let (x, y) = lon_lat_to_position(coord, position.x(), position.y());
+            encoder = encoder.point(x, y)?;
+        }
+        let mut feature = layer.into_feature(encoder.encode()?);
+        feature.set_id(index as u64 + 1);
+        feature.add_tag_string("id", &segment.get_id());
+        feature.add_tag_string(
+            "connector_ids",
+            &serde_json::to_string(segment.get_connectors()).expect("serializable IDs"),
+        );
+        let mut tags: Vec<_> = segment.tags().iter().collect();
+        tags.sort();
+        for (key, value) in tags {
+            feature.add_tag_string(key, value);
+        }
+        layer = feature.into_layer();
+    }
+    tile.add_layer(layer)?;
+    Ok(tile.to_bytes()?)
+}
+
+/// Serializes the root directory and the leaf directories of the entries.
+///
+/// Entries are moved into leaf directories only if the root directory would
+/// not fit into the first 16 KiB of the archive.
+fn directories(entries: &[Entry]) -> (Vec<u8>, Vec<u8>) {
+    let root = serialize_directory(entries);
+    if root.len() <= MAX_ROOT_SIZE {
+        return (root, Vec::new());
+    }
+    let mut leaf_size = 4096;
+    loop {
+        let mut leaves = Vec::new();
+        let mut root_entries = Vec::new();
+        for chunk in entries.chunks(leaf_size) {
+            let leaf = serialize_directory(chunk);
+            root_entries.push(Entry {
+                tile_id: chunk[0].tile_id,
+                offset: leaves.len() as u64,
+                length: leaf.len() as u64,
+                run_length: 0,
+            });
+            leaves.extend(leaf);
+        }
+        let root = serialize_directory(&root_entries);
+        if root.len() <= MAX_ROOT_SIZE {
+            return (root, leaves);
+        }
+        leaf_size *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile::backend::pmtiles_file_backend::PMTilesFileBackend;
+    use crate::tile::backend::Backend;
+    use crate::tile::backend::Tile as _;
+    use futures::executor::block_on;
+    use std::io::Cursor;
+
+    const NETWORK: &str = r#"{"type": "FeatureCollection", "features": [
+        {"type": "Feature", "properties": {"id": "a", "highway": "traffic_signals"},
+         "geometry": {"type": "Point", "coordinates": [8.68, 50.11]}},
+        {"type": "Feature", "properties": {"id": "b"},
+         "geometry": {"type": "Point", "coordinates": [8.71, 50.12]}},
+        {"type": "Feature", "properties": {"id": "ab", "connector_ids": ["a", "b"], "highway": "primary", "lanes": 2},
+         "geometry": {"type": "LineString", "coordinates": [[8.68, 50.11], [8.71, 50.12]]}}
+    ]}"#;
+
+    #[test]
+    fn write_pmtiles() {
+        let mut data = Vec::new();
+        let count = super::write_pmtiles(NETWORK, 14, &mut data).unwrap();
+        let backend = PMTilesFileBackend::from_reader(Cursor::new(data)).unwrap();
+        assert_eq!(backend.get_min_zoom(), 14);
+        assert_eq!(backend.get_max_zoom(), 14);
+        let bounds = backend.get_bounds();
+        assert!((bounds[0] - 8.68).abs() < 1e-6 && (bounds[3] - 50.12).abs() < 1e-6);
+
+        let coord = Coord {
+            x: 8587,
+            y: 5548,
+            z: 14,
+        };
+        let tile = block_on(backend.get_tile(&coord)).unwrap().parse().unwrap();
+        assert_eq!(tile.connectors_len(), 1);
+        assert_eq!(tile.segments_len(), 1);
+        let point = tile.get_connectors()[0].get_point();
+        assert!((point.x() - 8.68).abs() < 1e-4 && (point.y() - 50.11).abs() < 1e-4);
+        assert_eq!(
+            tile.get_connectors()[0].get_tag("highway"),
+            Some("traffic_signals".into())
+        );
+        let segment = &tile.get_segments()[0];
+        assert_eq!(segment.get_id(), "ab");
+        assert_eq!(segment.get_connectors(), &["a", "b"]);
+        assert_eq!(segment.get_tag("highway"), Some("primary".into()));
+        assert_eq!(segment.get_tag("lanes"), Some("2".into()));
+        assert_eq!(segment.get_tag("id"), None);
+
+        // The segment is written to every tile it touches, the connectors only
+        // to the tile containing them.
+        assert_eq!(count, 2);
+        let coord = Coord { x: 8588, ..coord };
+        let tile = block_on(backend.get_tile(&coord)).unwrap().parse().unwrap();
+        assert_eq!(tile.connectors_len(), 1);
+        assert_eq!(tile.segments_len(), 1);
+    }
+
+    #[test]
+    fn write_routing_tiles() {
+        let mut data = Vec::new();
+        super::write_pmtiles(NETWORK, 14, &mut data).unwrap();
+        let archive = PMTilesFileBackend::from_reader(Cursor::new(data)).unwrap();
+        let directory = std::env::temp_dir().join(format!("ibre-writer-{}", std::process::id()));
+        assert_eq!(super::write_routing_tiles(&archive, &directory).unwrap(), 2);
+        let tile = routing_tile::decode(
+            &fs::read(directory.join("14").join("8587").join("5548.ibrt")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(tile.connectors_len(), 1);
+        assert_eq!(tile.get_segments()[0].get_connectors(), &["a", "b"]);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn invalid_network() {
+        let write = |geojson: &str| super::write_pmtiles(geojson, 14, &mut Vec::new());
+        assert!(matches!(write("{}"), Err(WriterError::InvalidGeoJson(_))));
+        assert!(matches!(
+            write(
+                r#"{"features": [{"type": "Feature", "properties": {},
+                "geometry": {"type": "Point", "coordinates": [0, 0]}}]}"#
+            ),
+            Err(WriterError::InvalidFeature { index: 0, .. })
+        ));
+        assert!(matches!(
+            write(
+                r#"{"features": [{"type": "Feature", "properties": {"id": 1},
+                "geometry": {"type": "LineString", "coordinates": [[0, 0], [1, 1]]}}]}"#
+            ),
+            Err(WriterError::InvalidFeature { index: 0, .. })
+        ));
+        assert!(matches!(
+            super::write_pmtiles(NETWORK, 30, &mut Vec::new()),
+            Err(WriterError::UnsupportedZoom(30))
+        ));
+    }
+
+    #[test]
+    fn leaf_directories() {
+        let entries: Vec<Entry> = (0..10_000)
+            .map(|index| Entry {
+                tile_id: index * 2,
+                offset: index * 10,
+                length: 10,
+                run_length: 1,
+            })
+            .collect();
+        let (root, leaves) = directories(&entries);
+        assert!(root.len() <= MAX_ROOT_SIZE);
+        let root = crate::tile::pmtiles::parse_directory(&root).unwrap();
+        assert_eq!(root.len(), 3);
+        assert!(root.iter().all(|entry| entry.run_length == 0));
+        let last = &root[2];
+        let leaf = &leaves[last.offset as usize..(last.offset + last.length) as usize];
+        assert_eq!(
+            crate::tile::pmtiles::parse_directory(leaf).unwrap(),
+            entries[8192..]
+        );
+    }
+}
@@ -0,0 +1,37 @@
+// Returns the current time in milliseconds since the UNIX epoch.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn now() -> f64 {
+    js_sys::Date::now()
+}
+
+// Returns the current time in milliseconds since the UNIX epoch.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn now() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs_f64() * 1000.0)
+        .unwrap_or(0.0)
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen::prelude::wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = performance, js_name = now)]
+    fn performance_now() -> f64;
+}
+
+// Returns a monotonic time in milliseconds to measure durations, on the web
+// the time since the page or worker started as used by the Performance API.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn monotonic() -> f64 {
+    performance_now()
+}
+
+// Returns a monotonic time in milliseconds to measure durations.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn monotonic() -> f64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+}
@@ -0,0 +1,434 @@
+//! Parsing of the GTFS files needed for routing.
+//!
+//! See <https://gtfs.org/schedule/reference/>. Only `stops.txt`, `trips.txt`,
+//! `stop_times.txt`, `calendar.txt` and `calendar_dates.txt` are read.
+
+use super::TransitError;
+use std::collections::HashMap;
+
+/// A stop of `stops.txt`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Stop {
+    pub id: String,
+    pub lon: f64,
+    pub lat: f64,
+}
+
+/// A row of `stop_times.txt`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct StopTime {
+    pub trip_id: String,
+    pub stop_id: String,
+    pub sequence: u32,
+    /// Arrival in seconds after midnight of the service day.
+    pub arrival: u32,
+    /// Departure in seconds after midnight of the service day.
+    pub departure: u32,
+}
+
+/// Parses `stops.txt`. Stops without coordinates, e.g. entrances without
+/// location, are skipped.
+pub(crate) fn parse_stops(data: &str) -> Result<Vec<Stop>, TransitError> {
+    let table = Table::parse("stops.txt", data)?;
+    let mut stops = Vec::new();
+    for (line, row) in table.rows() {
+        let (lon, lat) = match (row.get("stop_lon"), row.get("stop_lat")) {
+            (Some(lon), Some(lat)) if !lon.is_empty() && !lat.is_empty() => (lon, lat),
+            _ => continue,
+        };
+        let coordinate = |value: &str| {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| table.error(line, "invalid coordinate"))
+        };
+        stops.push(Stop {
+            id: row.require("stop_id")?.to_string(),
+            lon: coordinate(lon)?,
+            lat: coordinate(lat)?,
+        });
+    }
+    Ok(stops)
+}
+
+/// Parses `trips.txt` into the route and service IDs by trip ID.
+pub(crate) fn parse_trips(data: &str) -> Result<HashMap<String, (String, String)>, TransitError> {
+    let table = Table::parse("trips.txt", data)?;
+    let mut trips = HashMap::new();
+    for (_, row) in table.rows() {
+        trips.insert(
+            row.require("trip_id")?.to_string(),
+            (
+                row.require("route_id")?.to_string(),
+                row.require("service_id")?.to_string(),
+            ),
+        );
+    }
+    Ok(trips)
+}
+
+/// The days services run on, from `calendar.txt` and `calendar_dates.txt`.
+#[derive(Debug, Default)]
+pub(crate) struct Calendar {
+    /// Weekdays (Monday first), first and last date by service ID.
+    services: HashMap<String, ([bool; 7], u32, u32)>,
+    /// Whether the service is added (or removed) on the date.
+    exceptions: HashMap<(String, u32), bool>,
+}
+
+impl Calendar {
+    /// Returns whether the service runs on the date (`YYYYMMDD`).
+    pub fn runs(&self, service: &str, date: u32) -> bool {
+        if let Some(added) = self.exceptions.get(&(service.to_string(), date)) {
+            return *added;
+        }
+        self.services
+            .get(service)
+            .is_some_and(|(days, start, end)| {
+                (*start..=*end).contains(&date) && days[weekday(date)]
+            })
+    }
+}
+
+/// Parses `calendar.txt` and `calendar_dates.txt`, either of which may be
+/// missing.
+pub(crate) fn parse_calendar(
+    calendar: Option<&str>,
+    calendar_dates: Option<&str>,
+) -> Result<Calendar, TransitError> {
+    const DAYS: [&str; 7] = [
+        "monday",
+        "tuesday",
+        "wednesday",
+        "thursday",
+        "friday",
+        "saturday",
+        "sunday",
+    ];
+    let mut result = Calendar::default();
+    if let Some(data) = calendar {
+        let table = Table::parse("calendar.txt", data)?;
+        for (line, row) in table.rows() {
+            let date = |column: &str| {
+                parse_date(row.require(column)?.trim())
+                    .ok_or_else(|| table.error(line, "invalid date"))
+            };
+            let mut days = [false; 7];
+            for (day, name) in days.iter_mut().zip(DAYS) {
+                *day = match row.require(name)?.trim() {
+                    "1" => true,
+                    "0" => false,
+                    _ => return Err(table.error(line, "invalid weekday")),
+                };
+            }
+            result.services.insert(
+                row.require("service_id")?.to_string(),
+                (days, date("start_date")?, date("end_date")?),
+            );
+        }
+    }
+    if let Some(data) = calendar_dates {
+        let table = Table::parse("calendar_dates.txt", data)?;
+        for (line, row) in table.rows() {
+            let date = parse_date(row.require("date")?.trim())
+                .ok_or_else(|| table.error(line, "invalid date"))?;
+            let added = match row.require("exception_type")?.trim() {
+                "1" => true,
+                "2" => false,
+                _ => return Err(table.error(line, "invalid exception_type")),
+            };
+            result
+                .exceptions
+                .insert((row.require("service_id")?.to_string(), date), added);
+        }
+    }
+    Ok(result)
+}
+
+/// Parses a GTFS date (`YYYYMMDD`), checking the month and day.
+pub(crate) fn parse_date(value: &str) -> Option<u32> {
+    if value.len() != 8 || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let date = value.parse().ok()?;
+    is_valid_date(date).then_some(date)
+}
+
+/// Returns whether the date (`YYYYMMDD`) has a valid month and day.
+pub(crate) fn is_valid_date(date: u32) -> bool {
+    let (year, month, day) = (date / 10000, date / 100 % 100, date % 100);
+    year > 0 && (1..=12).contains(&month) && (1..=31).contains(&day)
+}
+
+/// Returns the weekday of the date (`YYYYMMDD`), 0 for Monday.
+fn weekday(date: u32) -> usize {
+    // Sakamoto's method, which counts from Sunday.
+    const OFFSETS: [u32; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+    let (mut year, month, day) = (date / 10000, date / 100 % 100, date % 100);
+    if month < 3 {
+        year -= 1;
+    }
+    let sunday_first =
+        (year + year / 4 - year / 100 + year / 400 + OFFSETS[month as usize - 1] + day) % 7;
+    (sunday_first as usize + 6) % 7
+}
+
+/// Parses `stop_times.txt`. A missing arrival or departure time is set to the
+/// other one; rows without both times (untimed stops) are skipped.
+pub(crate) fn parse_stop_times(data: &str) -> Result<Vec<StopTime>, TransitError> {
+    let table = Table::parse("stop_times.txt", data)?;
+    let mut stop_times = Vec::new();
+    for (line, row) in table.rows() {
+        let time = |column: &str| match row.get(column).map(str::trim) {
+            None | Some("") => Ok(None),
+            Some(value) => parse_time(value)
+                .map(Some)
+                .ok_or_else(|| table.error(line, "invalid time")),
+        };
+        let (arrival, departure) = match (time("arrival_time")?, time("departure_time")?) {
+            (Some(arrival), Some(departure)) => (arrival, departure),
+            (Some(time), None) | (None, Some(time)) => (time, time),
+            (None, None) => continue,
+        };
+        stop_times.push(StopTime {
+            trip_id: row.require("trip_id")?.to_string(),
+            stop_id: row.require("stop_id")?.to_string(),
+            sequence: row
+                .require("stop_sequence")?
+                .trim()
+                .parse()
+                .map_err(|_| table.error(line, "invalid stop_sequence"))?,
+            arrival,
+            departure,
+        });
+    }
+    Ok(stop_times)
+}
+
+/// Parses a GTFS time (`H:MM:SS`, may exceed 24 hours) into seconds.
+pub(crate) fn parse_time(value: &str) -> Option<u32> {
+    let mut parts = value.split(':').map(|part| part.parse::<u32>().ok());
+    let (hours, minutes, seconds) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() || minutes >= 60 || seconds >= 60 {
+        return None;
+    }
+    hours.checked_mul(3600)?.checked_add(minutes * 60 + seconds)
+}
+
+/// A CSV file with a header line.
+struct Table {
+    file: &'static str,
+    columns: HashMap<String, usize>,
+    records: Vec<(usize, Vec<String>)>,
+}
+
+/// A record of a table, whose fields are accessed by column name.
+struct Row<'a> {
+    table: &'a Table,
+    line: usize,
+    fields: &'a [String],
+}
+
+impl Table {
+    fn parse(file: &'static str, data: &str) -> Result<Table, TransitError> {
+        let mut records = parse_csv(data.trim_start_matches('\u{feff}')).into_iter();
+        let (_, header) = records.next().ok_or(TransitError::InvalidGtfs {
+            file,
+            line: 1,
+            context: "missing header",
+        })?;
+        let columns = header
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| (name.trim().to_string(), index))
+            .collect();
+        Ok(Table {
+            file,
+            columns,
+            records: records.collect(),
+        })
+    }
+
+    fn rows(&self) -> impl Iterator<Item = (usize, Row<'_>)> {
+        self.records.iter().map(move |(line, fields)| {
+            (
+                *line,
+                Row {
+                    table: self,
+                    line: *line,
+                    fields,
+                },
+            )
+        })
+    }
+
+    fn error(&self, line: usize, context: &'static str) -> TransitError {
+        TransitError::InvalidGtfs {
+            file: self.file,
+            line,
+            context,
+        }
+    }
+}
+
+impl<'a> Row<'a> {
+    /// Returns the field of the column, `None` if the column is missing.
+    fn get(&self, column: &str) -> Option<&'a str> {
+        let index = *self.table.columns.get(column)?;
+        Some(self.fields.get(index).map_or("", String::as_str))
+    }
+
+    /// Returns the non-empty field of the column.
+    fn require(&self, column: &str) -> Result<&'a str, TransitError> {
+        self.get(column)
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| self.table.error(self.line, "missing required field"))
+    }
+}
+
+/// Splits the CSV data into records of fields, each with its (1-based) line.
+///
+/// Fields may be quoted with `"` to contain commas, line breaks and escaped
+/// (doubled) quotes. Empty lines are skipped.
+fn parse_csv(data: &str) -> Vec<(usize, Vec<String>)> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = data.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                fields.push(std::mem::take(&mut field));
+                if fields.len() > 1 || !fields[0].is_empty() {
+                    records.push((record_line, std::mem::take(&mut fields)));
+                }
+                fields.clear();
+                line += 1;
+                record_line = line;
+            }
+            c => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    if !fields.is_empty() || !field.is_empty() {
+        fields.push(field);
+        records.push((record_line, fields));
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv() {
+        let records = parse_csv("a,b,c\r\n1,\"x, \"\"y\"\"\",\n\n2,\"multi\nline\",3");
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0], (1, vec!["a".into(), "b".into(), "c".into()]));
+        assert_eq!(
+            records[1],
+            (2, vec!["1".into(), "x, \"y\"".into(), "".into()])
+        );
+        assert_eq!(
+            records[2],
+            (4, vec!["2".into(), "multi\nline".into(), "3".into()])
+        );
+    }
+
+    #[test]
+    fn time() {
+        assert_eq!(parse_time("08:05:30"), Some(8 * 3600 + 5 * 60 + 30));
+        assert_eq!(parse_time("7:00:00"), Some(7 * 3600));
+        assert_eq!(parse_time("25:10:00"), Some(25 * 3600 + 600));
+        assert_eq!(parse_time("8:60:00"), None);
+        assert_eq!(parse_time("8:00"), None);
+        assert_eq!(parse_time("4294967:00:00"), None);
+    }
+
+    #[test]
+    fn files() {
+        let stops = parse_stops(
+            "\u{feff}stop_id,stop_name,stop_lat,stop_lon\nA,\"Main St, North\",50.1,8.6\nE,Entrance,,\n",
+        )
+        .unwrap();
+        assert_eq!(
+            stops,
+            vec![Stop {
+                id: "A".into(),
+                lon: 8.6,
+                lat: 50.1
+            }]
+        );
+        let stop_times = parse_stop_times(
+            "trip_id,arrival_time,departure_time,stop_id,stop_sequence\nT,08:00:00,08:01:00,A,1\nT,,,B,2\nT,08:10:00,,C,3\n",
+        )
+        .unwrap();
+        assert_eq!(stop_times.len(), 2);
+        assert_eq!(stop_times[0].departure, 8 * 3600 + 60);
+        assert_eq!(stop_times[1].departure, 8 * 3600 + 600);
+        assert_eq!(
+            parse_trips("route_id,service_id,trip_id\nR,S,T\n").unwrap()["T"],
+            ("R".into(), "S".into())
+        );
+        assert_eq!(
+            parse_stop_times(
+                "trip_id,arrival_time,departure_time,stop_id,stop_sequence\nT,8,8,A,1\n"
+            )
+            .err(),
+            Some(TransitError::InvalidGtfs {
+                file: "stop_times.txt",
+                line: 2,
+                context: "invalid time"
+            })
+        );
+        assert!(parse_stops("stop_id,stop_lat,stop_lon\n,1,2\n").is_err());
+    }
+
+    #[test]
+    fn calendar() {
+        assert_eq!(weekday(20240101), 0);
+        assert_eq!(weekday(20240229), 3);
+        assert_eq!(weekday(20241231), 1);
+        assert_eq!(parse_date("20240229"), Some(20240229));
+        assert_eq!(parse_date("20241301"), None);
+        assert_eq!(parse_date("2024011"), None);
+
+        let calendar = parse_calendar(
+            Some("service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\nweekdays,1,1,1,1,1,0,0,20240101,20241231\n"),
+            Some("service_id,date,exception_type\nweekdays,20240101,2\nextra,20240106,1\n"),
+        )
+        .unwrap();
+        assert!(calendar.runs("weekdays", 20240102));
+        assert!(!calendar.runs("weekdays", 20240106));
+        assert!(!calendar.runs("weekdays", 20250102));
+        // Holiday removed by an exception.
+        assert!(!calendar.runs("weekdays", 20240101));
+        assert!(calendar.runs("extra", 20240106));
+        assert!(!calendar.runs("extra", 20240107));
+        assert!(!calendar.runs("unknown", 20240102));
+
+        assert_eq!(
+            parse_calendar(None, Some("service_id,date,exception_type\nS,20240101,3\n")).err(),
+            Some(TransitError::InvalidGtfs {
+                file: "calendar_dates.txt",
+                line: 2,
+                context: "invalid exception_type"
+            })
+        );
+    }
+}
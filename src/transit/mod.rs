@@ -0,0 +1,729 @@
+//! Public transit routing on GTFS schedules.
+//!
+//! Journeys combine walking and transit legs and are searched with the
+//! Connection Scan Algorithm for the earliest arrival. Walking is estimated
+//! on the straight line at a constant speed, the road network is not used.
+//! Journeys are searched on a service day, on which only the trips of the
+//! services of the calendar run. Times are seconds after midnight of that day.
+
+mod gtfs;
+
+use crate::debug::{debug_log, span};
+use crate::geo_types::Point;
+use std::collections::HashMap;
+use thiserror::Error;
+use wasm_bindgen::prelude::*;
+
+/// Default walking speed in meters per second.
+const WALKING_SPEED: f64 = 1.4;
+
+/// Default maximum distance in meters walked to the first and from the last
+/// stop.
+const MAX_WALKING_DISTANCE: f64 = 1000.0;
+
+/// Default maximum distance in meters walked to change between stops.
+const MAX_TRANSFER_DISTANCE: f64 = 300.0;
+
+/// Meters per degree of latitude.
+const METERS_PER_DEGREE: f64 = 111_195.0;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+/// Errors while loading schedules or searching journeys.
+pub enum TransitError {
+    /// A GTFS file is malformed.
+    #[error("Invalid {file}, line {line}: {context}")]
+    InvalidGtfs {
+        /// Name of the file.
+        file: &'static str,
+        /// Line of the malformed record.
+        line: usize,
+        /// What is wrong with the record.
+        context: &'static str,
+    },
+    /// A stop time references a stop missing in `stops.txt`.
+    #[error("Unknown stop `{0}`")]
+    UnknownStop(String),
+    /// The service day is not a valid `YYYYMMDD` date.
+    #[error("Invalid date {0}")]
+    InvalidDate(u32),
+    /// The walking speed is not a positive number.
+    #[error("Invalid walking speed")]
+    InvalidWalkingSpeed,
+    /// The stop can't be reached from the start.
+    #[error("Could not find journey")]
+    NoJourney,
+}
+
+impl From<TransitError> for JsValue {
+    fn from(err: TransitError) -> JsValue {
+        JsError::new(&err.to_string()).into()
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How a leg of a journey is travelled.
+pub enum LegMode {
+    /// On foot, to, from or between stops.
+    Walk,
+    /// On a trip of the schedule.
+    Transit,
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+/// A part of a journey travelled in a single mode.
+pub struct JourneyLeg {
+    mode: LegMode,
+    trip_id: Option<String>,
+    route_id: Option<String>,
+    from_stop: Option<String>,
+    to_stop: Option<String>,
+    from: Point,
+    to: Point,
+    departure: u32,
+    arrival: u32,
+}
+
+#[wasm_bindgen]
+impl JourneyLeg {
+    /// Returns how the leg is travelled.
+    pub fn get_mode(&self) -> LegMode {
+        self.mode
+    }
+
+    /// Returns the GTFS trip of a transit leg.
+    pub fn get_trip_id(&self) -> Option<String> {
+        self.trip_id.clone()
+    }
+
+    /// Returns the GTFS route of a transit leg, if known.
+    pub fn get_route_id(&self) -> Option<String> {
+        self.route_id.clone()
+    }
+
+    /// Returns the stop the leg starts at, `undefined` at the start of the
+    /// journey.
+    pub fn get_from_stop(&self) -> Option<String> {
+        self.from_stop.clone()
+    }
+
+    /// Returns the stop the leg ends at, `undefined` at the end of the
+    /// journey.
+    pub fn get_to_stop(&self) -> Option<String> {
+        self.to_stop.clone()
+    }
+
+    /// Returns the location the leg starts at.
+    pub fn get_from(&self) -> Point {
+        self.from.clone()
+    }
+
+    /// Returns the location the leg ends at.
+    pub fn get_to(&self) -> Point {
+        self.to.clone()
+    }
+
+    /// Returns the departure in seconds after midnight.
+    pub fn get_departure(&self) -> u32 {
+        self.departure
+    }
+
+    /// Returns the arrival in seconds after midnight.
+    pub fn get_arrival(&self) -> u32 {
+        self.arrival
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+/// A journey of walking and transit legs.
+pub struct Journey {
+    legs: Vec<JourneyLeg>,
+    departure: u32,
+    arrival: u32,
+}
+
+#[wasm_bindgen]
+impl Journey {
+    /// Returns the legs in order of travel.
+    pub fn get_legs(&self) -> Vec<JourneyLeg> {
+        self.legs.clone()
+    }
+
+    /// Returns the requested departure in seconds after midnight.
+    pub fn get_departure(&self) -> u32 {
+        self.departure
+    }
+
+    /// Returns the arrival at the stop in seconds after midnight.
+    pub fn get_arrival(&self) -> u32 {
+        self.arrival
+    }
+}
+
+/// A ride between two consecutive stops of a trip.
+#[derive(Debug, Clone)]
+struct Connection {
+    from: usize,
+    to: usize,
+    departure: u32,
+    arrival: u32,
+    trip: usize,
+}
+
+/// A trip of the schedule.
+#[derive(Debug, Clone)]
+struct Trip {
+    id: String,
+    /// Route and service IDs, if the trip is in `trips.txt`.
+    route_id: Option<String>,
+    service_id: Option<String>,
+}
+
+/// How a stop was reached in a search.
+#[derive(Debug, Clone, Copy)]
+enum Reached {
+    /// By walking from the start.
+    Start,
+    /// By walking from another stop.
+    Walk { from: usize },
+    /// By a trip, boarded with the connection `enter` and left after the
+    /// connection `exit`.
+    Transit { enter: usize, exit: usize },
+}
+
+#[wasm_bindgen]
+/// A public transit network loaded from GTFS schedules.
+pub struct TransitNetwork {
+    stops: Vec<gtfs::Stop>,
+    trips: Vec<Trip>,
+    /// The days services run on, all trips run every day without.
+    calendar: Option<gtfs::Calendar>,
+    /// Connections ordered by departure.
+    connections: Vec<Connection>,
+    /// Stops within transfer distance and the walking time to them, by stop.
+    transfers: Vec<Vec<(usize, u32)>>,
+    walking_speed: f64,
+    max_walking_distance: f64,
+    max_transfer_distance: f64,
+}
+
+#[wasm_bindgen]
+impl TransitNetwork {
+    #[wasm_bindgen(js_name = fromGtfs)]
+    /// Creates the network from the contents of the GTFS files `stops.txt`,
+    /// `trips.txt` and `stop_times.txt`.
+    pub fn from_gtfs(
+        stops: &str,
+        trips: &str,
+        stop_times: &str,
+    ) -> Result<TransitNetwork, TransitError> {
+        let stops = gtfs::parse_stops(stops)?;
+        let trip_services = gtfs::parse_trips(trips)?;
+        let mut stop_times = gtfs::parse_stop_times(stop_times)?;
+        stop_times.sort_by(|a, b| {
+            a.trip_id
+                .cmp(&b.trip_id)
+                .then_with(|| a.sequence.cmp(&b.sequence))
+        });
+        let stop_indices: HashMap<&str, usize> = stops
+            .iter()
+            .enumerate()
+            .map(|(index, stop)| (stop.id.as_str(), index))
+            .collect();
+        let stop_index = |id: &str| {
+            stop_indices
+                .get(id)
+                .copied()
+                .ok_or_else(|| TransitError::UnknownStop(id.into()))
+        };
+        let mut trips = Vec::new();
+        let mut connections = Vec::new();
+        for pair in stop_times.windows(2) {
+            let (a, b) = (&pair[0], &pair[1]);
+            if trips.last().map(|trip: &Trip| &trip.id) != Some(&a.trip_id) {
+                let (route_id, service_id) = trip_services.get(&a.trip_id).cloned().unzip();
+                trips.push(Trip {
+                    id: a.trip_id.clone(),
+                    route_id,
+                    service_id,
+                });
+            }
+            if a.trip_id != b.trip_id {
+                continue;
+            }
+            connections.push(Connection {
+                from: stop_index(&a.stop_id)?,
+                to: stop_index(&b.stop_id)?,
+                departure: a.departure,
+                arrival: b.arrival.max(a.departure),
+                trip: trips.len() - 1,
+            });
+        }
+        connections.sort_by_key(|connection| (connection.departure, connection.arrival));
+        let mut network = TransitNetwork {
+            stops,
+            trips,
+            calendar: None,
+            connections,
+            transfers: Vec::new(),
+            walking_speed: WALKING_SPEED,
+            max_walking_distance: MAX_WALKING_DISTANCE,
+            max_transfer_distance: MAX_TRANSFER_DISTANCE,
+        };
+        network.update_transfers();
+        Ok(network)
+    }
+
+    #[wasm_bindgen(js_name = stopsLength)]
+    /// Returns the number of stops.
+    pub fn stops_len(&self) -> usize {
+        self.stops.len()
+    }
+
+    #[wasm_bindgen(js_name = connectionsLength)]
+    /// Returns the number of rides between consecutive stops of all trips.
+    pub fn connections_len(&self) -> usize {
+        self.connections.len()
+    }
+
+    /// Sets the walking speed in meters per second, 1.4 by default.
+    pub fn set_walking_speed(&mut self, speed: f64) -> Result<(), TransitError> {
+        if !speed.is_finite() || speed <= 0.0 {
+            return Err(TransitError::InvalidWalkingSpeed);
+        }
+        self.walking_speed = speed;
+        self.update_transfers();
+        Ok(())
+    }
+
+    /// Sets the maximum distance in meters walked to the first and from the
+    /// last stop, 1000 by default.
+    pub fn set_max_walking_distance(&mut self, distance: f64) {
+        self.max_walking_distance = distance;
+    }
+
+    /// Sets the maximum distance in meters walked to change between stops,
+    /// 300 by default.
+    pub fn set_max_transfer_distance(&mut self, distance: f64) {
+        self.max_transfer_distance = distance;
+        self.update_transfers();
+    }
+
+    #[wasm_bindgen(js_name = setCalendar)]
+    /// Sets the days services run on from the contents of the GTFS files
+    /// `calendar.txt` and `calendar_dates.txt`, either of which may be
+    /// missing. Without a calendar, all trips run every day.
+    pub fn set_calendar(
+        &mut self,
+        calendar: Option<String>,
+        calendar_dates: Option<String>,
+    ) -> Result<(), TransitError> {
+        self.calendar = Some(gtfs::parse_calendar(
+            calendar.as_deref(),
+            calendar_dates.as_deref(),
+        )?);
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = findJourney)]
+    /// Finds the journey from start to stop on the service day (`YYYYMMDD`),
+    /// leaving at the given time in seconds after midnight, which arrives
+    /// earliest.
+    ///
+    /// Trips of the previous service day running after midnight are not
+    /// considered.
+    pub fn find_journey(
+        &self,
+        start: &Point,
+        stop: &Point,
+        date: u32,
+        departure: u32,
+    ) -> Result<Journey, TransitError> {
+        let _span = span!("search journey");
+        if !gtfs::is_valid_date(date) {
+            return Err(TransitError::InvalidDate(date));
+        }
+        let running: Vec<bool> = self
+            .trips
+            .iter()
+            .map(|trip| match (&self.calendar, &trip.service_id) {
+                (Some(calendar), Some(service)) => calendar.runs(service, date),
+                _ => true,
+            })
+            .collect();
+        let infinity = u32::MAX;
+        let mut earliest = vec![infinity; self.stops.len()];
+        let mut reached = vec![None; self.stops.len()];
+        let mut egress = vec![None; self.stops.len()];
+        for (index, gtfs_stop) in self.stops.iter().enumerate() {
+            let location = stop_point(gtfs_stop);
+            if let Some(time) = self.walking_time(start, &location, self.max_walking_distance) {
+                earliest[index] = departure.saturating_add(time);
+                reached[index] = Some(Reached::Start);
+            }
+            egress[index] = self.walking_time(&location, stop, self.max_walking_distance);
+        }
+        // The best arrival at the stop and the last stop before, if any.
+        let mut best = self
+            .walking_time(start, stop, self.max_walking_distance)
+            .map(|time| (departure.saturating_add(time), None));
+        for (index, time) in earliest.iter().enumerate() {
+            if let Some(walk) = egress[index].filter(|_| *time != infinity) {
+                if best.is_none_or(|(arrival, _)| time.saturating_add(walk) < arrival) {
+                    best = Some((time.saturating_add(walk), Some(index)));
+                }
+            }
+        }
+
+        let mut boarded = vec![None; self.trips.len()];
+        let first = self
+            .connections
+            .partition_point(|connection| connection.departure < departure);
+        for (index, connection) in self.connections.iter().enumerate().skip(first) {
+            if best.is_some_and(|(arrival, _)| connection.departure > arrival) {
+                break;
+            }
+            if !running[connection.trip] {
+                continue;
+            }
+            if boarded[connection.trip].is_none() {
+                if earliest[connection.from] > connection.departure {
+                    continue;
+                }
+                boarded[connection.trip] = Some(index);
+            }
+            if connection.arrival >= earliest[connection.to] {
+                continue;
+            }
+            earliest[connection.to] = connection.arrival;
+            reached[connection.to] = Some(Reached::Transit {
+                enter: boarded[connection.trip].unwrap(),
+                exit: index,
+            });
+            let mut improved = vec![connection.to];
+            for (other, time) in &self.transfers[connection.to] {
+                let arrival = connection.arrival.saturating_add(*time);
+                if arrival < earliest[*other] {
+                    earliest[*other] = arrival;
+                    reached[*other] = Some(Reached::Walk {
+                        from: connection.to,
+                    });
+                    improved.push(*other);
+                }
+            }
+            for index in improved {
+                if let Some(walk) = egress[index] {
+                    let arrival = earliest[index].saturating_add(walk);
+                    if best.is_none_or(|(best, _)| arrival < best) {
+                        best = Some((arrival, Some(index)));
+                    }
+                }
+            }
+        }
+
+        let (arrival, last_stop) = best.ok_or(TransitError::NoJourney)?;
+        debug_log!("found journey arriving at {}", arrival);
+        let legs = match last_stop {
+            Some(last_stop) => self.legs(start, stop, last_stop, arrival, &earliest, &reached),
+            None => vec![self.walk_leg(start.clone(), None, stop.clone(), None, arrival)],
+        };
+        Ok(Journey {
+            legs: legs
+                .into_iter()
+                .filter(|leg| leg.mode == LegMode::Transit || leg.departure < leg.arrival)
+                .collect(),
+            departure,
+            arrival,
+        })
+    }
+}
+
+impl TransitNetwork {
+    /// Returns the walking time in seconds between the points, `None` if they
+    /// are further apart than the maximum distance.
+    fn walking_time(&self, from: &Point, to: &Point, max_distance: f64) -> Option<u32> {
+        let distance = from.distance_meters(to);
+        (distance <= max_distance).then(|| (distance / self.walking_speed).ceil() as u32)
+    }
+
+    /// Finds the stops within transfer distance of each stop.
+    fn update_transfers(&mut self) {
+        let mut order: Vec<usize> = (0..self.stops.len()).collect();
+        order.sort_by(|a, b| self.stops[*a].lat.total_cmp(&self.stops[*b].lat));
+        let max_latitude_difference = self.max_transfer_distance / METERS_PER_DEGREE;
+        let mut transfers = vec![Vec::new(); self.stops.len()];
+        for (position, a) in order.iter().enumerate() {
+            let from = stop_point(&self.stops[*a]);
+            // Only stops with a similar latitude may be close enough.
+            for b in &order[position + 1..] {
+                if self.stops[*b].lat - self.stops[*a].lat > max_latitude_difference {
+                    break;
+                }
+                let to = stop_point(&self.stops[*b]);
+                if let Some(time) = self.walking_time(&from, &to, self.max_transfer_distance) {
+                    transfers[*a].push((*b, time));
+                    transfers[*b].push((*a, time));
+                }
+            }
+        }
+        self.transfers = transfers;
+    }
+
+    /// Returns the legs of the journey reaching the stop via `last_stop`.
+    fn legs(
+        &self,
+        start: &Point,
+        stop: &Point,
+        last_stop: usize,
+        arrival: u32,
+        earliest: &[u32],
+        reached: &[Option<Reached>],
+    ) -> Vec<JourneyLeg> {
+        let mut legs = vec![JourneyLeg {
+            departure: earliest[last_stop],
+            ..self.walk_leg(
+                stop_point(&self.stops[last_stop]),
+                Some(last_stop),
+                stop.clone(),
+                None,
+                arrival,
+            )
+        }];
+        let mut current = last_stop;
+        // Every step leads to a stop reached earlier, the limit only guards
+        // against endless loops.
+        for _ in 0..=self.stops.len() {
+            let to = stop_point(&self.stops[current]);
+            match reached[current] {
+                Some(Reached::Transit { enter, exit }) => {
+                    let (enter, exit) = (&self.connections[enter], &self.connections[exit]);
+                    let trip = &self.trips[enter.trip];
+                    legs.push(JourneyLeg {
+                        mode: LegMode::Transit,
+                        trip_id: Some(trip.id.clone()),
+                        route_id: trip.route_id.clone(),
+                        from_stop: Some(self.stops[enter.from].id.clone()),
+                        to_stop: Some(self.stops[current].id.clone()),
+                        from: stop_point(&self.stops[enter.from]),
+                        to,
+                        departure: enter.departure,
+                        arrival: exit.arrival,
+                    });
+                    current = enter.from;
+                }
+                Some(Reached::Walk { from }) => {
+                    let leg = self.walk_leg(
+                        stop_point(&self.stops[from]),
+                        Some(from),
+                        to,
+                        Some(current),
+                        earliest[current],
+                    );
+                    legs.push(leg);
+                    current = from;
+                }
+                Some(Reached::Start) | None => {
+                    legs.push(self.walk_leg(
+                        start.clone(),
+                        None,
+                        to,
+                        Some(current),
+                        earliest[current],
+                    ));
+                    break;
+                }
+            }
+        }
+        legs.reverse();
+        legs
+    }
+
+    /// Returns a walking leg arriving at the given time.
+    fn walk_leg(
+        &self,
+        from: Point,
+        from_stop: Option<usize>,
+        to: Point,
+        to_stop: Option<usize>,
+        arrival: u32,
+    ) -> JourneyLeg {
+        let time = (from.distance_meters(&to) / self.walking_speed).ceil() as u32;
+        JourneyLeg {
+            mode: LegMode::Walk,
+            trip_id: None,
+            route_id: None,
+            from_stop: from_stop.map(|index| self.stops[index].id.clone()),
+            to_stop: to_stop.map(|index| self.stops[index].id.clone()),
+            from,
+            to,
+            departure: arrival.saturating_sub(time),
+            arrival,
+        }
+    }
+}
+
+/// Returns the location of the stop.
+fn stop_point(stop: &gtfs::Stop) -> Point {
+    Point::new(stop.lon, stop.lat)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STOPS: &str = "stop_id,stop_name,stop_lat,stop_lon
+A,West,0.0,0.0
+B,Center,0.0,0.1
+C,Center South,-0.002,0.1
+D,East,-0.002,0.2
+";
+
+    const TRIPS: &str = "route_id,service_id,trip_id
+1,daily,1-slow
+1,daily,1-fast
+2,daily,2
+";
+
+    const STOP_TIMES: &str = "trip_id,arrival_time,departure_time,stop_id,stop_sequence
+1-slow,08:00:00,08:00:00,A,1
+1-slow,08:30:00,08:30:00,B,2
+1-fast,08:10:00,08:10:00,A,1
+1-fast,08:20:00,08:20:00,B,2
+2,08:30:00,08:30:00,C,1
+2,08:40:00,08:40:00,D,2
+";
+
+    /// A Tuesday.
+    const DATE: u32 = 20240102;
+
+    fn network() -> TransitNetwork {
+        TransitNetwork::from_gtfs(STOPS, TRIPS, STOP_TIMES).unwrap()
+    }
+
+    #[test]
+    fn from_gtfs() {
+        let network = network();
+        assert_eq!(network.stops_len(), 4);
+        assert_eq!(network.connections_len(), 3);
+        // B and C are about 220 m apart.
+        assert_eq!(network.transfers[1].len(), 1);
+        assert_eq!(
+            TransitNetwork::from_gtfs(STOPS, TRIPS, "trip_id,arrival_time,departure_time,stop_id,stop_sequence\nT,8:00:00,8:00:00,X,1\nT,8:10:00,8:10:00,A,2").err(),
+            Some(TransitError::UnknownStop("X".into()))
+        );
+    }
+
+    #[test]
+    fn find_journey() {
+        let network = network();
+        let journey = network
+            .find_journey(
+                &Point::new(0.0, 0.001),
+                &Point::new(0.2, -0.003),
+                DATE,
+                8 * 3600,
+            )
+            .unwrap();
+        let legs = journey.get_legs();
+        let modes: Vec<_> = legs.iter().map(JourneyLeg::get_mode).collect();
+        assert_eq!(
+            modes,
+            vec![
+                LegMode::Walk,
+                LegMode::Transit,
+                LegMode::Walk,
+                LegMode::Transit,
+                LegMode::Walk
+            ]
+        );
+        // The faster trip is taken although it departs later.
+        assert_eq!(legs[1].get_trip_id().as_deref(), Some("1-fast"));
+        assert_eq!(legs[1].get_route_id().as_deref(), Some("1"));
+        assert_eq!(legs[1].get_departure(), 8 * 3600 + 600);
+        assert_eq!(legs[2].get_from_stop().as_deref(), Some("B"));
+        assert_eq!(legs[2].get_to_stop().as_deref(), Some("C"));
+        assert_eq!(legs[3].get_trip_id().as_deref(), Some("2"));
+        assert_eq!(legs[4].get_to_stop(), None);
+        assert!(journey.get_arrival() > 8 * 3600 + 2400);
+        assert!(legs
+            .windows(2)
+            .all(|pair| pair[0].get_arrival() <= pair[1].get_departure()));
+
+        // Missed the last trip.
+        assert_eq!(
+            network
+                .find_journey(
+                    &Point::new(0.0, 0.0),
+                    &Point::new(0.2, -0.002),
+                    DATE,
+                    9 * 3600
+                )
+                .err(),
+            Some(TransitError::NoJourney)
+        );
+    }
+
+    #[test]
+    fn calendar() {
+        let mut network = network();
+        network
+            .set_calendar(
+                Some("service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date\ndaily,1,1,1,1,1,0,0,20240101,20241231\n".into()),
+                Some("service_id,date,exception_type\ndaily,20240103,2\n".into()),
+            )
+            .unwrap();
+        let (start, stop) = (Point::new(0.0, 0.001), Point::new(0.2, -0.003));
+        assert!(network.find_journey(&start, &stop, DATE, 8 * 3600).is_ok());
+        // No service on Saturdays and on the removed date.
+        for date in [20240106, 20240103] {
+            assert_eq!(
+                network.find_journey(&start, &stop, date, 8 * 3600).err(),
+                Some(TransitError::NoJourney)
+            );
+        }
+        assert_eq!(
+            network
+                .find_journey(&start, &stop, 20241301, 8 * 3600)
+                .err(),
+            Some(TransitError::InvalidDate(20241301))
+        );
+    }
+
+    #[test]
+    fn walk_only() {
+        let network = network();
+        let journey = network
+            .find_journey(
+                &Point::new(0.0, 0.001),
+                &Point::new(0.0, 0.002),
+                DATE,
+                8 * 3600,
+            )
+            .unwrap();
+        let legs = journey.get_legs();
+        assert_eq!(legs.len(), 1);
+        assert_eq!(legs[0].get_mode(), LegMode::Walk);
+        assert_eq!(legs[0].get_departure(), 8 * 3600);
+        assert_eq!(journey.get_arrival(), 8 * 3600 + 80);
+    }
+
+    #[test]
+    fn walking_speed() {
+        let mut network = network();
+        for speed in [0.0, -1.4, f64::NAN, f64::INFINITY] {
+            assert_eq!(
+                network.set_walking_speed(speed),
+                Err(TransitError::InvalidWalkingSpeed)
+            );
+        }
+        network.set_walking_speed(0.7).unwrap();
+        let (start, stop) = (Point::new(0.0, 0.001), Point::new(0.0, 0.002));
+        let journey = network.find_journey(&start, &stop, DATE, 8 * 3600).unwrap();
+        assert_eq!(journey.get_arrival(), 8 * 3600 + 159);
+        // Arrivals after the end of the day saturate instead of overflowing.
+        let journey = network.find_journey(&start, &stop, DATE, u32::MAX).unwrap();
+        assert_eq!(journey.get_arrival(), u32::MAX);
+    }
+}